@@ -0,0 +1,64 @@
+// 比较不同缓冲区大小解压单个大条目的耗时：
+// cargo bench --bench extract
+use std::time::{Duration, Instant};
+
+use async_zip::{
+  Compression, ZipEntryBuilder, base::write::ZipFileWriter,
+};
+use korabli_mod_manager::mod_manager::{
+  DEFAULT_EXTRACT_BUFFER_SIZE, ModManager,
+};
+
+const ENTRY_SIZE: usize = 64 * 1024 * 1024;
+const ROUNDS: u32 = 5;
+const BUFFER_SIZES: [usize; 5] = [
+  8 * 1024,
+  64 * 1024,
+  DEFAULT_EXTRACT_BUFFER_SIZE,
+  1024 * 1024,
+  4 * 1024 * 1024,
+];
+
+#[tokio::main]
+async fn main() {
+  let root = std::env::temp_dir()
+    .join(format!("kmm-bench-extract-{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&root);
+  std::fs::create_dir_all(root.join("bin/1000/res_mods")).unwrap();
+
+  let mut writer = ZipFileWriter::new(Vec::new());
+  let content = (0..ENTRY_SIZE).map(|i| i as u8).collect::<Vec<_>>();
+  let entry = ZipEntryBuilder::new(
+    "large.bin".to_string().into(),
+    Compression::Stored,
+  );
+  writer.write_entry_whole(entry, &content).await.unwrap();
+  let archive = root.join("large.zip");
+  std::fs::write(&archive, writer.close().await.unwrap()).unwrap();
+  drop(content);
+
+  let mut mod_manager =
+    ModManager::try_from_game_dir(&root).await.unwrap();
+  mod_manager.ensure_records().await.unwrap();
+  for size in BUFFER_SIZES {
+    mod_manager.set_extract_buffer_size(size);
+    let mut total = Duration::ZERO;
+    for _ in 0..ROUNDS {
+      let started = Instant::now();
+      mod_manager
+        .install_zip_mod(&archive, "large", "1.0")
+        .await
+        .unwrap();
+      total += started.elapsed();
+    }
+    let average = total / ROUNDS;
+    println!(
+      "buffer {:>5} KiB: {:>8.1?} ({:.0} MiB/s)",
+      size / 1024,
+      average,
+      ENTRY_SIZE as f64 / 1024. / 1024. / average.as_secs_f64()
+    );
+  }
+
+  let _ = std::fs::remove_dir_all(&root);
+}
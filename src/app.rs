@@ -1,44 +1,120 @@
 use std::{
-  collections::{HashSet, VecDeque},
-  env::current_dir,
+  collections::{HashMap, HashSet, VecDeque},
+  path::{Path, PathBuf},
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use crate::mod_manager::{ModManager, Records};
-use crate::tasks::download::{Download, DownloadState};
-use crate::tasks::install::Install;
-use crate::tasks::uninstall::Uninstall;
+use crate::app::tasks::download::{Download, DownloadState};
+use crate::app::tasks::install::Install;
+use crate::app::tasks::operation::{Operation, Phase};
+use crate::app::tasks::uninstall::Uninstall;
+use crate::crash;
+use crate::format::{format_bytes, format_duration};
+use crate::http::init_client;
+use crate::mod_manager::{ModManager, Record, Records};
+use crate::paths::{
+  data_dir, pending_queue_path, settings_path, trash_dir,
+};
+use crate::tasks::{
+  TaskLog, TaskStatus, download::sweep_stale_downloads,
+};
+use crate::trash::{self, TrashEntry};
 use crate::{
-  data::registry::{Mod, Registry},
-  messages::Message,
+  data::{
+    batch::{
+      BatchItem, BatchPhase, BatchSummary, BatchTally, PendingAction,
+      ReleaseSnapshot, batch_progress, plan_batch,
+    },
+    filter::{ModFilter, matches_query},
+    health::HealthReport,
+    notification::{Notification, Severity},
+    pending::{PendingKind, PendingOperation, PendingQueue},
+    plan::{
+      BatchPlan, Installed, PlanItem, Target, build_plan,
+      is_up_to_date,
+    },
+    preview::ArchiveTree,
+    registry::{
+      LoadState, Mod, ModLocation, ModRelease, ModSource, Registry,
+      locate_mods, merge_mods,
+    },
+    scheduler::Scheduler,
+    settings::{Settings, SortKey, WindowGeometry},
+    settings_form::SettingsForm,
+    status::StatusLine,
+    window::visible_window,
+  },
+  i18n::{Locale, locale, set_locale, t},
+  messages::{
+    GameMsg, Message, RegistryMsg, SettingsMsg, TaskMsg, UiMsg,
+  },
 };
 use iced::{
-  Element, Font, Length, Task, Theme,
+  Color, Element, Event, Font, Length, Point, Size, Subscription,
+  Task, Theme,
   alignment::Vertical,
+  event, time,
   widget::{
-    button, checkbox, column, container, container::bordered_box,
-    image, progress_bar, row, text, text_input,
+    button, center, checkbox, column, container,
+    container::bordered_box, opaque, pick_list, progress_bar, row,
+    scrollable, stack, text, text_input,
   },
+  window,
 };
 use url::Url;
 
+#[cfg(feature = "control")]
+mod control;
+mod health;
+mod preview;
+mod settings;
+mod table;
+pub mod tasks;
+mod trash_view;
 mod update;
+mod watch;
 
 pub fn iced_main() -> iced::Result {
   let registries = VecDeque::new();
+  // 配置文件损坏时使用默认配置，启动后再提示
+  let (settings, settings_error) =
+    match Settings::load(&settings_path()) {
+      Ok(settings) => (settings, None),
+      Err(err) => (Settings::default(), Some(err)),
+    };
+  set_locale(settings.language.unwrap_or_else(Locale::detect));
+  init_client(&settings.http_options());
+  // 路径无效时PrepareModManager会失败，转而提示用户选择目录
+  let game_dir_path = settings.game_dir_path();
+  let (urls, invalid_urls) = settings.parsed_registry_urls();
 
   let init_task_batch = [
-    Task::done(Message::PrepareModManager {
-      game_dir_path: current_dir().expect("wtf current dir"),
-    }),
-    Task::done(Message::LoadRegistries {
-      urls: vec![
-        Url::parse("https://kmm.worker.zerodegress.ink/registry")
-          .expect("wtf web registry"),
-      ],
-    }),
-    Task::done(Message::QueueUpdateCurrentMods),
-    Task::done(Message::QueueUpdateRecords),
-  ];
+    Task::done(Message::Game(GameMsg::PrepareModManager {
+      game_dir_path: game_dir_path.to_owned(),
+    })),
+    Task::done(Message::Registry(RegistryMsg::LoadRegistries {
+      urls,
+    })),
+    Task::done(Message::Game(GameMsg::QueueUpdateCurrentMods)),
+    Task::done(Message::Game(GameMsg::QueueUpdateRecords)),
+    Task::future(sweep_orphaned_downloads()).discard(),
+    Task::future(purge_trash(settings.trash_retention_days))
+      .discard(),
+  ]
+  .into_iter()
+  .chain(settings_error.map(|err| {
+    Task::done(Message::Ui(UiMsg::Warning {
+      title: t!("settings.load_failed").to_string(),
+      text: t!("settings.load_failed_text", error = err),
+    }))
+  }))
+  .chain((!invalid_urls.is_empty()).then(|| {
+    Task::done(Message::Ui(UiMsg::Warning {
+      title: t!("settings.invalid_registry_urls").to_string(),
+      text: invalid_urls.join("\n"),
+    }))
+  }))
+  .collect::<Vec<_>>();
   let app = iced::application(App::title, App::update, App::view);
 
   let app = if cfg!(feature = "builtin-font") {
@@ -50,15 +126,17 @@ pub fn iced_main() -> iced::Result {
   app
     .default_font(Font::with_name("Source Han Sans CN"))
     .theme(App::theme)
-    .centered()
+    .subscription(App::subscription)
+    .window(window_settings(settings.window))
     .run_with(|| {
       (
         App {
-          game_dir: current_dir()
-            .expect("wtf current dir")
-            .to_string_lossy()
-            .to_string(),
+          game_dir: game_dir_path.to_string_lossy().to_string(),
+          game_dir_path,
           registries,
+          auto_update_pending: settings.auto_update,
+          settings,
+          crash_report: crash::unacknowledged_report(&data_dir()),
           ..Default::default()
         },
         Task::batch(init_task_batch),
@@ -68,167 +146,1920 @@ pub fn iced_main() -> iced::Result {
 
 #[derive(Debug, Default)]
 struct App {
+  // 输入框中的内容，点击应用后才会生效
   game_dir: String,
-  downloads: Vec<Download>,
-  installs: VecDeque<Install>,
+  game_dir_path: PathBuf,
+  game_dir_error: Option<String>,
+  // 自动检测到的游戏目录，None表示还没有检测过
+  detected_game_dirs: Option<Vec<PathBuf>>,
+  // 下载、检查、解压的模组，失败的停在出错的阶段
+  operations: Vec<Operation>,
   uninstalls: VecDeque<Uninstall>,
-  mod_manager: Option<ModManager>,
+  // 空闲的ModManager和等待它的操作
+  scheduler: Scheduler<ModManager>,
+  // 正在监视的res_mods，ModManager准备好之后才有
+  res_mods_path: Option<PathBuf>,
+  // 自己的操作最后一次改动res_mods的时间，之前的变化不算外部修改
+  files_changed_at: Option<Instant>,
   current_mods: HashSet<String>,
-  install_mods: HashSet<String>,
-  uninstall_mods: HashSet<String>,
+  pending_action: HashMap<String, PendingAction>,
+  // 已是最新也要重新安装的模组
+  force_reinstall: HashSet<String>,
+  settings: Settings,
+  // 最近一次更新涉及的模组及其所处阶段，成功后取消勾选
+  batch: HashMap<String, BatchPhase>,
+  batch_tally: Option<BatchTally>,
+  // 批次结束后等待用户关闭的汇总
+  batch_summary: Option<BatchSummary>,
   registries: VecDeque<Registry>,
   records: Records,
-  loading_registry: bool,
-  need_current_mods_update: bool,
-  need_records_update: bool,
+  // 当前游戏目录的安装记录已经读取过
+  records_loaded: bool,
+  // 安装记录是为其他构建写的：记录中的和当前的构建号
+  build_mismatch: Option<(u64, u64)>,
+  // 启动后还没有检查过自动更新
+  auto_update_pending: bool,
+  // 由安装记录生成，用来显示registry中已经没有的模组
+  local_mods: Vec<Mod>,
+  // 按配置顺序记录每个registry地址的加载状态
+  registry_loads: Vec<(Url, LoadState)>,
+  game_version: Option<String>,
+  compat_overrides: HashSet<String>,
+  selected_versions: HashMap<String, String>,
+  mod_index: Vec<String>,
+  // 经过筛选后实际显示的模组
+  filtered_index: Vec<String>,
+  mod_filter: ModFilter,
+  // 搜索框的内容，匹配id、名称和备注
+  mod_query: String,
+  // 等待用户确认的更新计划
+  plan: Option<BatchPlan>,
+  free_space: Option<u64>,
+  // 打开中的设置界面
+  settings_form: Option<SettingsForm>,
+  // 按id查找合并后的模组，随mod_index一起重建
+  mod_locations: HashMap<String, ModLocation>,
+  // 列表中每个模组来自哪个registry，随mod_index一起重建
+  mod_sources: HashMap<String, ModSource>,
+  // 已安装且registry中有新版本的模组，随mod_index一起重建
+  updatable_mods: HashSet<String>,
+  // 列表的滚动位置和视口高度，用来决定构建哪些行
+  list_offset: f32,
+  list_viewport: f32,
+  persisted_queue: PendingQueue,
+  show_tasks: bool,
+  task_history: Vec<TaskRecord>,
+  status: Option<StatusLine>,
+  // 还没写入配置的窗口大小和位置，停止变化一段时间后才保存
+  window_geometry: Option<WindowGeometry>,
+  window_generation: usize,
+  // 还没关闭的通知，新的在后面
+  notifications: Vec<Notification>,
+  notification_history: VecDeque<Notification>,
+  next_notification_id: usize,
+  show_notification_history: bool,
+  // 上次运行留下的、用户还没看过的崩溃报告
+  crash_report: Option<PathBuf>,
+  // 正在编辑备注的模组和输入框中的内容
+  note_edit: Option<(String, String)>,
+  preview: Option<PreviewDialog>,
+  // 最近一次健康检查的结果，关闭前一直显示
+  health: Option<HealthReport>,
+  // 打开的回收站中的各项，关闭后为None
+  trash: Option<Vec<TrashEntry>>,
+}
+
+// 正在查看内容的压缩包，version不为空时是等待确认安装的本地压缩包
+#[derive(Debug)]
+struct PreviewDialog {
+  id: String,
+  path: PathBuf,
+  version: Option<String>,
+  // 读取完成前为空
+  tree: Option<Result<ArchiveTree, String>>,
+  expanded: HashSet<PathBuf>,
+}
+
+// 已经结束的任务，保留到用户清除为止
+#[derive(Debug, Clone)]
+struct TaskRecord {
+  id: String,
+  operation: &'static str,
+  error: Option<String>,
+  log: TaskLog,
+}
+
+// 列表中一行对应的模组当前所处的状态
+#[derive(Clone, Copy)]
+enum RowState<'a> {
+  Idle,
+  Download(&'a Download),
+  Verify,
+  Install(&'a Install),
+  Uninstall(&'a Uninstall),
+  Failed(Option<&'a str>),
+  Done,
+  // 勾选了安装，但和已安装的相同，更新时会跳过
+  UpToDate,
+}
+
+impl RowState<'_> {
+  fn progress(&self) -> Option<f32> {
+    match self {
+      Self::Download(download) => match download.status() {
+        TaskStatus::Running { progress } => progress,
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+
+  fn label(&self) -> String {
+    match self {
+      Self::Idle => String::new(),
+      Self::Download(download) => match download.state() {
+        DownloadState::Ready => t!("row.queued").to_string(),
+        DownloadState::Running {
+          progress: Some(progress),
+          ..
+        } => t!(
+          "row.downloading",
+          percent = format!("{:.0}", progress * 100.)
+        ),
+        // 总大小未知时只能显示已下载的量
+        DownloadState::Running { progress: None, .. } => t!(
+          "mod.downloaded",
+          size = format_bytes(download.downloaded())
+        ),
+        DownloadState::Paused { bytes } => {
+          t!("mod.paused", size = format_bytes(*bytes))
+        }
+        // 下载完成后等待检查
+        DownloadState::Finished | DownloadState::Failed => {
+          t!("row.verifying").to_string()
+        }
+      },
+      Self::Verify => t!("row.verifying").to_string(),
+      Self::Install(install) => match install.status() {
+        TaskStatus::Queued => t!("row.queued").to_string(),
+        _ => t!("row.installing").to_string(),
+      },
+      Self::Uninstall(uninstall) => match uninstall.status() {
+        TaskStatus::Queued => t!("row.queued").to_string(),
+        _ => t!("row.uninstalling").to_string(),
+      },
+      Self::Failed(Some(error)) => t!("row.failed", error = error),
+      Self::Failed(None) => t!("task_status.failed").to_string(),
+      Self::Done => t!("row.done").to_string(),
+      Self::UpToDate => t!("row.up_to_date").to_string(),
+    }
+  }
+}
+
+// 可见范围前后多构建的行数，滚动时不会露出占位
+const LIST_OVERSCAN: usize = 5;
+
+const NOTIFICATION_HISTORY_LIMIT: usize = 50;
+
+const DEFAULT_WINDOW_SIZE: Size = Size::new(1024., 768.);
+// 再小模组列表就挤不下了
+const MIN_WINDOW_SIZE: Size = Size::new(800., 480.);
+
+fn window_settings(
+  geometry: Option<WindowGeometry>,
+) -> window::Settings {
+  let Some(geometry) = geometry else {
+    return window::Settings {
+      size: DEFAULT_WINDOW_SIZE,
+      position: window::Position::Centered,
+      min_size: Some(MIN_WINDOW_SIZE),
+      ..Default::default()
+    };
+  };
+  let size = Size::new(
+    geometry.width.max(MIN_WINDOW_SIZE.width),
+    geometry.height.max(MIN_WINDOW_SIZE.height),
+  );
+  window::Settings {
+    size,
+    // 标题栏跑到屏幕外的位置不恢复，其余的在窗口打开后再检查
+    position: geometry
+      .position
+      .filter(|(x, y)| *x > -size.width / 2. && *y >= 0.)
+      .map_or(window::Position::Centered, |(x, y)| {
+        window::Position::Specific(Point::new(x, y))
+      }),
+    min_size: Some(MIN_WINDOW_SIZE),
+    maximized: geometry.maximized,
+    ..Default::default()
+  }
 }
 
 impl App {
-  fn available_mods(&self) -> Vec<&str> {
+  // registries、安装记录或排序方式变化时重建，view中只读取缓存
+  fn refresh_mod_index(&mut self) {
+    let mut merged = merge_mods(
+      &self.registries,
+      &self.current_mods,
+      &self.local_mods,
+    );
+    let sources = merged
+      .iter_mut()
+      .map(|entry| {
+        (entry.modr.id.to_owned(), std::mem::take(&mut entry.source))
+      })
+      .collect::<HashMap<_, _>>();
+    let mut mods = merged
+      .into_iter()
+      .map(|entry| entry.modr)
+      .collect::<Vec<_>>();
+    let updatable = mods
+      .iter()
+      .filter(|modr| self.has_update(modr))
+      .map(|modr| modr.id.to_owned())
+      .collect::<HashSet<_>>();
+    let update_time = |modr: &Mod| {
+      self.records.records.get(&modr.id).map(|x| x.update_time)
+    };
+    mods.sort_by(|a, b| {
+      let ordering = match self.settings.sort_key {
+        SortKey::Name => {
+          a.name.to_lowercase().cmp(&b.name.to_lowercase())
+        }
+        SortKey::Installed => self
+          .current_mods
+          .contains(&a.id)
+          .cmp(&self.current_mods.contains(&b.id)),
+        SortKey::UpdateAvailable => {
+          updatable.contains(&a.id).cmp(&updatable.contains(&b.id))
+        }
+        SortKey::UpdateTime => update_time(a).cmp(&update_time(b)),
+      };
+      if self.settings.sort_descending {
+        ordering.reverse()
+      } else {
+        ordering
+      }
+      .then_with(|| a.id.cmp(&b.id))
+    });
+    self.mod_index =
+      mods.into_iter().map(|modr| modr.id.to_owned()).collect();
+    self.mod_locations =
+      locate_mods(&self.registries, &self.local_mods);
+    self.updatable_mods = updatable;
+    self.mod_sources = sources;
+    self.filtered_index = self.filtered_mods(self.mod_filter);
+  }
+
+  fn filter_matches(&self, filter: ModFilter, id: &str) -> bool {
+    let record = self.records.records.get(id);
+    filter.matches(
+      record.is_some(),
+      self.updatable_mods.contains(id),
+      self.request_mod(id).is_some(),
+    ) && matches_query(
+      &self.mod_query,
+      &[
+        id,
+        self.request_mod(id).map_or(id, |modr| modr.name.as_str()),
+        record.map_or("", Record::note),
+      ],
+    )
+  }
+
+  fn filtered_mods(&self, filter: ModFilter) -> Vec<String> {
+    self
+      .mod_index
+      .iter()
+      .filter(|id| self.filter_matches(filter, id))
+      .cloned()
+      .collect()
+  }
+
+  fn filter_button(&self, filter: ModFilter) -> Element<Message> {
+    let label = match filter {
+      ModFilter::All => t!("filter.all"),
+      ModFilter::Installed => t!("filter.installed"),
+      ModFilter::Updatable => t!("filter.updatable"),
+      ModFilter::NotInstalled => t!("filter.not_installed"),
+    };
+    let count = self
+      .mod_index
+      .iter()
+      .filter(|id| self.filter_matches(filter, id))
+      .count();
+    button(text(format!("{} ({})", label, count)).size(12))
+      .padding([2, 6])
+      .style(if self.mod_filter == filter {
+        button::primary
+      } else {
+        button::secondary
+      })
+      .on_press(Message::Ui(UiMsg::SelectModFilter(filter)))
+      .into()
+  }
+
+  // registry中的版本比安装记录中的新
+  fn has_update(&self, modr: &Mod) -> bool {
+    self.records.records.get(&modr.id).is_some_and(|record| {
+      modr.version.is_newer_than(&record.version)
+    })
+  }
+
+  fn sort_header(
+    &self,
+    label: &str,
+    key: SortKey,
+  ) -> Element<Message> {
+    let active = self.settings.sort_key == key;
+    let arrow = match (active, self.settings.sort_descending) {
+      (false, _) => "",
+      (true, false) => " ▲",
+      (true, true) => " ▼",
+    };
+    button(text(format!("{}{}", label, arrow)).size(12))
+      .padding([2, 6])
+      .style(if active {
+        button::primary
+      } else {
+        button::secondary
+      })
+      .on_press(Message::Settings(SettingsMsg::SortMods {
+        key,
+        // 再次点击切换方向；非名称列第一次点击时把有的排在前面
+        descending: if active {
+          !self.settings.sort_descending
+        } else {
+          key != SortKey::Name
+        },
+      }))
+      .into()
+  }
+
+  // 列表中的全部模组，已经去重并排好序
+  fn indexed_mods(&self) -> Vec<&Mod> {
+    self
+      .mod_index
+      .iter()
+      .filter_map(|id| self.listed_mod(id))
+      .collect()
+  }
+
+  // 经过筛选后显示的模组，顺序和列表一致
+  fn available_mods(&self) -> Vec<&Mod> {
+    self
+      .filtered_index
+      .iter()
+      .filter_map(|id| self.listed_mod(id))
+      .collect()
+  }
+
+  // 只构建视口附近的行，其余部分用固定高度的占位撑开，
+  // 所以不用分页，筛选后的全部模组都在一个列表里
+  fn mod_list(&self) -> Element<'_, Message> {
+    let heights = self
+      .filtered_index
+      .iter()
+      .map(|id| self.row_height(id))
+      .collect::<Vec<_>>();
+    let window = visible_window(
+      &heights,
+      self.list_offset,
+      self.list_viewport,
+      LIST_OVERSCAN,
+    );
+    column![]
+      .push(column![].height(Length::Fixed(window.before)))
+      .extend(
+        self.filtered_index[window.range]
+          .iter()
+          .map(|id| self.mod_row(id)),
+      )
+      .push(column![].height(Length::Fixed(window.after)))
+      .width(Length::Fill)
+      .into()
+  }
+
+  fn is_compatible(&self, modr: &Mod) -> bool {
+    self
+      .game_version
+      .as_deref()
+      .is_none_or(|version| modr.supports_game_version(version))
+  }
+
+  // 未手动选择版本时使用registry中的当前版本
+  // 已撤回、只有本地记录或不兼容且没有强制的模组不能勾选安装
+  fn is_installable(&self, id: &str) -> bool {
+    let (Some(modr), Some(_)) =
+      (self.listed_mod(id), self.request_mod(id))
+    else {
+      return false;
+    };
+    !modr.yanked
+      && (self.is_compatible(modr)
+        || self.compat_overrides.contains(id))
+  }
+
+  // 列表中满足条件且可以安装的模组
+  fn selectable_mods(
+    &self,
+    filter: impl Fn(&str) -> bool,
+  ) -> Vec<String> {
+    self
+      .available_mods()
+      .into_iter()
+      .filter(|modr| {
+        filter(&modr.id) && self.is_installable(&modr.id)
+      })
+      .map(|modr| modr.id.to_owned())
+      .collect()
+  }
+
+  fn selected_release(&self, modr: &Mod) -> ModRelease {
+    self
+      .selected_versions
+      .get(&modr.id)
+      .and_then(|version| modr.release(version))
+      .unwrap_or_else(|| modr.current_release())
+  }
+
+  // 只有registry当前版本的大小是已知的
+  fn release_size(
+    &self,
+    modr: &Mod,
+    release: &ModRelease,
+  ) -> Option<u64> {
+    (release.version == modr.version)
+      .then_some(modr.size)
+      .flatten()
+  }
+
+  // 已是最新的模组不再下载安装
+  fn update_batch(&self) -> Task<Message> {
+    let (install, uninstall) =
+      plan_batch(&self.pending_action, &self.current_mods);
+    let skipped = self.batch_plan().up_to_date_ids();
+    self.update_mods(
+      install
+        .into_iter()
+        .filter(|id| !skipped.contains(id))
+        .collect(),
+      uninstall
+        .into_iter()
+        .filter(|id| !skipped.contains(id))
+        .collect(),
+      skipped,
+    )
+  }
+
+  // 在确认的这一刻取下要安装的版本，之后排队的消息不再查registry
+  fn update_mods(
+    &self,
+    install: Vec<String>,
+    uninstall: Vec<String>,
+    skipped: Vec<String>,
+  ) -> Task<Message> {
+    let (releases, missing, warnings) =
+      self.release_snapshots(install);
+    Task::batch(
+      std::iter::once(Message::Task(TaskMsg::UpdateMods {
+        install: releases,
+        missing,
+        uninstall,
+        skipped,
+      }))
+      .chain(warnings)
+      .map(Task::done),
+    )
+  }
+
+  fn action_of(&self, id: &str) -> PendingAction {
+    self
+      .pending_action
+      .get(id)
+      .copied()
+      .unwrap_or(PendingAction::Keep)
+  }
+
+  fn pending_ids(&self, action: PendingAction) -> Vec<String> {
+    self
+      .pending_action
+      .iter()
+      .filter(|(_, pending)| **pending == action)
+      .map(|(id, _)| id.to_owned())
+      .collect()
+  }
+
+  // 保持不变的直接从表中去掉
+  fn set_action(&mut self, id: String, action: PendingAction) {
+    match action {
+      PendingAction::Keep => {
+        self.pending_action.remove(&id);
+      }
+      action => {
+        self.pending_action.insert(id.to_owned(), action);
+      }
+    }
+    if action != PendingAction::Install {
+      self.force_reinstall.remove(&id);
+    }
+  }
+
+  // 按钮的可用状态：没安装的不能卸载，registry中没有的不能安装
+  fn allows_action(&self, id: &str, action: PendingAction) -> bool {
+    !self.is_locked(id)
+      && match action {
+        PendingAction::Keep => true,
+        PendingAction::Install => self.is_installable(id),
+        PendingAction::Uninstall => self.current_mods.contains(id),
+      }
+  }
+
+  // 加载中的提示和加载失败的registry，全部完成时不显示
+  fn registry_status(&self) -> Option<Element<'_, Message>> {
+    let pending = self
+      .registry_loads
+      .iter()
+      .filter(|(_, state)| *state == LoadState::Pending)
+      .count();
+    let failed = self
+      .registry_loads
+      .iter()
+      .filter_map(|(url, state)| match state {
+        LoadState::Failed(error) => Some((url, error)),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    if pending == 0 && failed.is_empty() {
+      return None;
+    }
+    Some(
+      column![]
+        .extend((pending > 0).then(|| {
+          text(t!(
+            "registry.loading",
+            done = self.registry_loads.len() - pending,
+            total = self.registry_loads.len()
+          ))
+          .size(12)
+          .into()
+        }))
+        .extend(failed.into_iter().map(|(url, error)| {
+          row![]
+            .push(
+              text(t!(
+                "registry.source_failed",
+                source = self.settings.registry_label(url),
+                error = error
+              ))
+              .size(12)
+              .style(text::danger)
+              .width(Length::Fill),
+            )
+            .push(
+              button(text(t!("row.retry")).size(12))
+                .padding([2, 6])
+                .on_press(Message::Registry(
+                  RegistryMsg::RetryRegistry(url.to_owned()),
+                )),
+            )
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into()
+        }))
+        .spacing(5)
+        .into(),
+    )
+  }
+
+  // 配置中无效的地址在启动时已经提示过
+  fn registry_urls(&self) -> Vec<Url> {
+    self.settings.parsed_registry_urls().0
+  }
+
+  fn is_stale(&self, mod_manager: &ModManager) -> bool {
+    mod_manager.game_dir_path() != self.game_dir_path
+  }
+
+  // 正在解压或删除游戏目录中的文件
+  fn files_busy(&self) -> bool {
+    self
+      .operations
+      .iter()
+      .filter_map(Operation::install)
+      .any(|x| matches!(x.status(), TaskStatus::Running { .. }))
+      || self
+        .uninstalls
+        .iter()
+        .any(|x| matches!(x.status(), TaskStatus::Running { .. }))
+  }
+
+  // 有任务在进行时不允许再次提交，失败待处理的不算
+  fn batch_in_flight(&self) -> bool {
+    self.task_count() > 0
+  }
+
+  // 只推进属于当前批次且处于`from`阶段的模组
+  fn advance_batch(
+    &mut self,
+    id: &str,
+    from: BatchPhase,
+    to: BatchPhase,
+  ) -> bool {
+    match self.batch.get_mut(id) {
+      Some(phase) if *phase == from => {
+        *phase = to;
+      }
+      _ => return false,
+    }
+    if to == BatchPhase::Failed {
+      let error = self
+        .task_history
+        .iter()
+        .rev()
+        .find(|record| record.id == id)
+        .and_then(|record| record.error.as_deref())
+        .unwrap_or_default();
+      if let Some(tally) = self.batch_tally.as_mut() {
+        tally.add_failure(id, error);
+      }
+    }
+    // 整个批次结束时汇总结果
+    if self.batch.values().all(BatchPhase::is_finished) {
+      let failed = self
+        .batch
+        .values()
+        .filter(|phase| **phase == BatchPhase::Failed)
+        .count();
+      let summary = t!(
+        "status.batch_done",
+        succeeded = self.batch.len() - failed,
+        failed = failed
+      );
+      self.set_status(if failed == 0 {
+        StatusLine::Success(summary)
+      } else {
+        StatusLine::Error(summary)
+      });
+      self.batch_summary = self
+        .batch_tally
+        .as_ref()
+        .map(|tally| tally.summary(self.batch.len(), Instant::now()));
+    }
+    true
+  }
+
+  // 依次查找进行中的任务，都没有时看最近一次批次的结果
+  fn row_state(&self, id: &str) -> RowState {
+    if self.failed_task_error(id).is_some() {
+      return RowState::Failed(None);
+    }
+    if let Some(operation) = self.operation(id) {
+      return match operation.phase() {
+        Phase::Download => operation
+          .download()
+          .map_or(RowState::Idle, RowState::Download),
+        Phase::Verify => RowState::Verify,
+        Phase::Extract => operation
+          .install()
+          .map_or(RowState::Idle, RowState::Install),
+      };
+    }
+    if let Some(uninstall) =
+      self.uninstalls.iter().find(|x| x.id() == id)
+    {
+      return RowState::Uninstall(uninstall);
+    }
+    match self.batch.get(id) {
+      Some(BatchPhase::Failed) => RowState::Failed(
+        self
+          .task_history
+          .iter()
+          .rev()
+          .find(|record| record.id == id)
+          .and_then(|record| record.error.as_deref()),
+      ),
+      Some(BatchPhase::Done) => RowState::Done,
+      _ if self.action_of(id) == PendingAction::Install
+        && !self.force_reinstall.contains(id)
+        && self.is_up_to_date(id) =>
+      {
+        RowState::UpToDate
+      }
+      _ => RowState::Idle,
+    }
+  }
+
+  // 用完尝试次数、等待用户重试或忽略的任务
+  fn failed_task_error(&self, id: &str) -> Option<&str> {
+    let operation = self
+      .operation(id)
+      .filter(|x| x.is_failed())
+      .map(|x| x.error());
+    let uninstall = || {
+      self
+        .uninstalls
+        .iter()
+        .find(|x| x.id() == id && x.is_failed())
+        .map(|x| x.error())
+    };
+    operation
+      .or_else(uninstall)
+      .map(|error| error.unwrap_or_default())
+  }
+
+  // 已经下载好、还没开始解压或解压失败的压缩包可以查看内容
+  fn previewable_archive(&self, id: &str) -> Option<&Path> {
+    self
+      .operation(id)
+      .filter(|x| {
+        x.phase() == Phase::Extract
+          && (x.is_failed() || x.status() == TaskStatus::Queued)
+      })
+      .map(|x| x.path())
+  }
+
+  fn operation(&self, id: &str) -> Option<&Operation> {
+    self.operations.iter().find(|x| x.id() == id)
+  }
+
+  // registry中还有安装记录里的版本才能重新安装
+  fn can_repair(&self, id: &str) -> bool {
+    !self.batch_in_flight()
+      && self.records.records.get(id).is_some_and(|record| {
+        self
+          .request_mod(id)
+          .and_then(|modr| modr.any_release(&record.version))
+          .is_some()
+      })
+  }
+
+  // 正在进行的批次中的模组不允许修改勾选
+  fn is_locked(&self, id: &str) -> bool {
+    self.batch_in_flight()
+      && self.batch.get(id).is_some_and(|phase| !phase.is_finished())
+  }
+
+  fn batch_items(&self) -> Vec<BatchItem> {
     self
-      .registries
+      .batch
       .iter()
-      .flat_map(|registry| registry.mods.keys().map(|id| id.as_str()))
-      .collect::<Vec<_>>()
+      .map(|(id, phase)| {
+        let status = match phase {
+          BatchPhase::Uninstalling => self
+            .uninstalls
+            .iter()
+            .find(|x| x.id() == id)
+            .map(|x| x.status()),
+          BatchPhase::Downloading | BatchPhase::Installing => {
+            self.operation(id).map(|x| x.status())
+          }
+          BatchPhase::Done | BatchPhase::Failed => None,
+        };
+        BatchItem {
+          phase: *phase,
+          size: self.request_mod(id).and_then(|modr| {
+            self.release_size(modr, &self.selected_release(modr))
+          }),
+          progress: status.and_then(|status| status.progress()),
+        }
+      })
+      .collect()
+  }
+
+  fn batch_progress(&self) -> Option<Element<Message>> {
+    if self.batch.is_empty() || !self.batch_in_flight() {
+      return None;
+    }
+    let finished = self
+      .batch
+      .values()
+      .filter(|phase| phase.is_finished())
+      .count();
+    Some(
+      row![]
+        .push(
+          progress_bar(
+            0.0..=100.,
+            batch_progress(&self.batch_items()) * 100.,
+          )
+          .length(Length::Fixed(150.)),
+        )
+        .push(text(t!(
+          "batch.finished",
+          finished = finished,
+          total = self.batch.len()
+        )))
+        .spacing(10)
+        .align_y(Vertical::Center)
+        .into(),
+    )
+  }
+
+  fn estimated_download(&self) -> String {
+    download_size_label(self.batch_plan().download_size())
+  }
+
+  fn batch_plan(&self) -> BatchPlan {
+    let (install, uninstall) =
+      plan_batch(&self.pending_action, &self.current_mods);
+    build_plan(
+      &install,
+      &uninstall,
+      |id| self.installed_version(id),
+      |id| self.target_version(id),
+      &self.force_reinstall,
+      self.free_space,
+    )
+  }
+
+  // 版本和选中的相同时才检查文件是否都还在
+  fn installed_version(&self, id: &str) -> Option<Installed> {
+    let record = self.records.records.get(id)?;
+    let same = self
+      .target_version(id)
+      .is_some_and(|target| target.version == record.version);
+    Some(Installed {
+      version: record.version.clone(),
+      sha256: record.archive_sha256().map(str::to_owned),
+      intact: same
+        && self.res_mods_path.as_ref().is_some_and(|res_mods| {
+          record.files.iter().all(|file| res_mods.join(file).exists())
+        }),
+    })
+  }
+
+  fn target_version(&self, id: &str) -> Option<Target> {
+    let modr = self.request_mod(id)?;
+    let release = self.selected_release(modr);
+    Some(Target {
+      size: self.release_size(modr, &release),
+      version: release.version,
+      sha256: release.sha256,
+    })
   }
 
+  // 不考虑强制重装，只比较已安装的和选中的版本
+  fn is_up_to_date(&self, id: &str) -> bool {
+    self
+      .installed_version(id)
+      .zip(self.target_version(id))
+      .is_some_and(|(installed, target)| {
+        is_up_to_date(&installed, &target)
+      })
+  }
+
+  fn plan_dialog<'a>(
+    &'a self,
+    plan: &'a BatchPlan,
+  ) -> Element<'a, Message> {
+    let section = |title: &'static str, items: &'a [PlanItem]| {
+      column![]
+        .push(text(format!("{} ({})", title, items.len())))
+        .extend(items.iter().map(|item| {
+          text(format!(
+            "{}  {}{}{}{}",
+            item.id,
+            item.from.as_deref().unwrap_or_default(),
+            if item.from.is_some() && item.to.is_some() {
+              " → "
+            } else {
+              ""
+            },
+            item.to.as_deref().unwrap_or_default(),
+            item
+              .size
+              .map(|size| format!(" ({})", format_bytes(size)))
+              .unwrap_or_default(),
+          ))
+          .size(12)
+          .into()
+        }))
+        .spacing(2)
+    };
+    container(
+      column![]
+        .push(text(t!("plan.title")).size(18))
+        .push(
+          scrollable(
+            column![]
+              .push(section(t!("plan.installs"), &plan.installs))
+              .push(section(t!("plan.upgrades"), &plan.upgrades))
+              .push(section(t!("plan.uninstalls"), &plan.uninstalls))
+              .extend((!plan.up_to_date.is_empty()).then(|| {
+                section(t!("plan.up_to_date"), &plan.up_to_date)
+                  .into()
+              }))
+              .extend((!plan.missing.is_empty()).then(|| {
+                text(t!(
+                  "mods.not_found_text",
+                  ids = plan.missing.join(", ")
+                ))
+                .style(text::danger)
+                .into()
+              }))
+              .spacing(10),
+          )
+          .height(Length::Fixed(300.)),
+        )
+        .push(text(t!(
+          "plan.download_size",
+          size = download_size_label(plan.download_size())
+        )))
+        .extend(plan.space_shortage().map(|(required, available)| {
+          text(t!(
+            "plan.no_space",
+            required = format_bytes(required),
+            available = format_bytes(available)
+          ))
+          .style(text::danger)
+          .into()
+        }))
+        .push(
+          container(
+            row![]
+              .push(
+                button(t!("plan.cancel"))
+                  .style(button::secondary)
+                  .on_press(Message::Task(TaskMsg::CancelBatch)),
+              )
+              .push(
+                button(t!("plan.confirm")).on_press_maybe(
+                  (!plan.is_empty()
+                    && plan.space_shortage().is_none())
+                  .then_some(Message::Task(TaskMsg::ConfirmBatch)),
+                ),
+              )
+              .spacing(10),
+          )
+          .align_right(Length::Fill),
+        )
+        .spacing(10),
+    )
+    .style(bordered_box)
+    .padding(20)
+    .width(Length::Fixed(500.))
+    .into()
+  }
+
+  fn summary_dialog<'a>(
+    &'a self,
+    summary: &'a BatchSummary,
+  ) -> Element<'a, Message> {
+    container(
+      column![]
+        .push(text(t!("summary.title")).size(18))
+        .push(text(t!(
+          "status.batch_done",
+          succeeded = summary.succeeded,
+          failed = summary.failures.len()
+        )))
+        .push(text(t!(
+          "summary.details",
+          size = format_bytes(summary.downloaded),
+          elapsed = format_duration(summary.elapsed)
+        )))
+        .extend((!summary.failures.is_empty()).then(|| {
+          scrollable(
+            column![]
+              .extend(summary.failures.iter().map(|(id, error)| {
+                text(t!(
+                  "error.subject_reason",
+                  subject = id,
+                  error = error
+                ))
+                .size(12)
+                .style(text::danger)
+                .into()
+              }))
+              .spacing(2),
+          )
+          .height(Length::Fixed(200.))
+          .into()
+        }))
+        .push(
+          container(
+            row![]
+              .push(
+                button(t!("summary.copy_failures"))
+                  .style(button::secondary)
+                  .on_press_maybe(
+                    (!summary.failures.is_empty()).then_some(
+                      Message::Task(TaskMsg::CopyBatchFailures),
+                    ),
+                  ),
+              )
+              .push(button(t!("summary.close")).on_press(
+                Message::Task(TaskMsg::DismissBatchSummary),
+              ))
+              .spacing(10),
+          )
+          .align_right(Length::Fill),
+        )
+        .spacing(10),
+    )
+    .style(bordered_box)
+    .padding(20)
+    .width(Length::Fixed(500.))
+    .into()
+  }
+
+  // 总大小未知时只显示速度
+  fn download_speed(&self, id: &str) -> Option<String> {
+    let download = self.operation(id)?.download()?;
+    if !matches!(download.state(), DownloadState::Running { .. }) {
+      return None;
+    }
+    let speed =
+      format!("{}/s", format_bytes(download.speed()? as u64));
+    Some(match download.eta() {
+      Some(eta) => {
+        t!(
+          "download.speed_eta",
+          speed = speed,
+          eta = format_duration(eta)
+        )
+      }
+      None => speed,
+    })
+  }
+
+  // 下载中的模组也算作待安装，恢复时如果压缩包不完整会重新下载；
+  // 已经失败的任务不恢复
+  fn pending_queue(&self) -> PendingQueue {
+    let installs = self
+      .operations
+      .iter()
+      .filter(|operation| !operation.is_failed())
+      .map(|operation| PendingOperation {
+        kind: PendingKind::Install,
+        id: operation.id().to_owned(),
+        version: Some(operation.version().to_string()),
+        path: (operation.phase() != Phase::Download)
+          .then(|| operation.path().to_path_buf()),
+      });
+    let uninstalls = self
+      .uninstalls
+      .iter()
+      .filter(|uninstall| !uninstall.is_failed())
+      .map(|uninstall| PendingOperation {
+        kind: PendingKind::Uninstall,
+        id: uninstall.id().to_owned(),
+        version: None,
+        path: None,
+      });
+    PendingQueue {
+      operations: uninstalls.chain(installs).collect(),
+    }
+  }
+
+  fn record_task(
+    &mut self,
+    id: &str,
+    operation: &'static str,
+    error: Option<String>,
+    log: TaskLog,
+  ) {
+    self.task_history.push(TaskRecord {
+      id: id.to_string(),
+      operation,
+      error,
+      log,
+    });
+  }
+
+  // 失败待处理的任务显示在各自的行里，不计入
+  fn task_count(&self) -> usize {
+    self.operations.iter().filter(|x| !x.is_failed()).count()
+      + self.uninstalls.iter().filter(|x| !x.is_failed()).count()
+  }
+
+  fn queued_count(&self) -> usize {
+    self
+      .operations
+      .iter()
+      .map(|x| x.status())
+      .chain(self.uninstalls.iter().map(|x| x.status()))
+      .filter(|status| matches!(status, TaskStatus::Queued))
+      .count()
+  }
+
+  fn set_status(&mut self, status: StatusLine) {
+    if self
+      .status
+      .as_ref()
+      .is_none_or(|current| current.replaced_by(&status))
+    {
+      self.status = Some(status);
+    }
+  }
+
+  fn status_bar(&self) -> Element<Message> {
+    row![]
+      .extend(self.status.as_ref().map(|status| {
+        let line = text(status.text()).size(12);
+        if status.is_error() {
+          line.style(text::danger)
+        } else {
+          line
+        }
+        .width(Length::Fill)
+        .into()
+      }))
+      .extend(self.status.as_ref().filter(|s| s.is_error()).map(
+        |_| {
+          button(text(t!("status.dismiss")).size(12))
+            .padding([2, 6])
+            .on_press(Message::Ui(UiMsg::DismissStatus))
+            .into()
+        },
+      ))
+      .extend(self.free_space.map(|free_space| {
+        text(t!("status.free_space", size = format_bytes(free_space)))
+          .size(12)
+          .style(text::secondary)
+          .into()
+      }))
+      .push(
+        text(t!("status.queued", count = self.queued_count()))
+          .size(12)
+          .style(text::secondary),
+      )
+      .spacing(10)
+      .align_y(Vertical::Center)
+      .into()
+  }
+
+  fn notify(
+    &mut self,
+    severity: Severity,
+    title: String,
+    text: String,
+  ) {
+    let notification = Notification {
+      id: self.next_notification_id,
+      severity,
+      title,
+      text,
+      created: Instant::now(),
+    };
+    self.next_notification_id += 1;
+    if self.notification_history.len() >= NOTIFICATION_HISTORY_LIMIT {
+      self.notification_history.pop_front();
+    }
+    self.notification_history.push_back(notification.to_owned());
+    self.notifications.push(notification);
+  }
+
+  fn toasts(&self) -> Element<Message> {
+    column![]
+      .extend(self.notifications.iter().map(|notification| {
+        container(
+          row![]
+            .push(
+              column![]
+                .push(text(notification.title.as_str()).style(
+                  match notification.severity {
+                    Severity::Info => text::success,
+                    Severity::Warning => text::danger,
+                  },
+                ))
+                .push(text(notification.text.as_str()).size(12))
+                .width(Length::Fill),
+            )
+            .push(
+              button(text(t!("status.dismiss")).size(12))
+                .padding([2, 6])
+                .on_press(Message::Ui(UiMsg::DismissNotification(
+                  notification.id,
+                ))),
+            )
+            .spacing(10)
+            .align_y(Vertical::Center),
+        )
+        .style(bordered_box)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+      }))
+      .spacing(5)
+      .into()
+  }
+
+  fn notification_panel(&self) -> Element<Message> {
+    container(
+      column![]
+        .push(text(t!("notification.history")))
+        .push(
+          scrollable(
+            column![]
+              .extend(self.notification_history.iter().rev().map(
+                |notification| {
+                  row![]
+                    .push(
+                      text(notification.title.as_str())
+                        .style(match notification.severity {
+                          Severity::Info => text::success,
+                          Severity::Warning => text::danger,
+                        })
+                        .width(Length::Fixed(200.)),
+                    )
+                    .push(text(notification.text.replace('\n', " ")))
+                    .spacing(10)
+                    .into()
+                },
+              ))
+              .spacing(5),
+          )
+          .height(Length::Fixed(150.)),
+        )
+        .spacing(5),
+    )
+    .style(bordered_box)
+    .padding(10)
+    .width(Length::Fill)
+    .into()
+  }
+
+  fn task_panel(&self) -> Element<Message> {
+    // 失败的任务已经记在历史里
+    let running = self
+      .operations
+      .iter()
+      .filter(|x| !x.is_failed())
+      .map(|x| {
+        task_row(x.id(), x.label(), x.status(), x.error(), None)
+      })
+      .chain(self.uninstalls.iter().filter(|x| !x.is_failed()).map(
+        |x| {
+          task_row(
+            x.id(),
+            t!("task.uninstall"),
+            x.status(),
+            x.error(),
+            None,
+          )
+        },
+      ));
+    let history = self.task_history.iter().enumerate().rev().map(
+      |(index, record)| {
+        task_row(
+          &record.id,
+          record.operation,
+          if record.error.is_some() {
+            TaskStatus::Failed
+          } else {
+            TaskStatus::Finished
+          },
+          record.error.as_deref(),
+          record
+            .error
+            .is_some()
+            .then_some(Message::Ui(UiMsg::ShowTaskLog(index))),
+        )
+      },
+    );
+    container(
+      column![]
+        .push(
+          row![]
+            .push(text(t!("task.panel")).width(Length::Fill))
+            .push(
+              button(text(t!("task.clear_finished")).size(12))
+                .padding([2, 6])
+                .on_press_maybe(
+                  (!self.task_history.is_empty()).then_some(
+                    Message::Task(TaskMsg::ClearTaskHistory),
+                  ),
+                ),
+            )
+            .align_y(Vertical::Center),
+        )
+        .push(
+          scrollable(
+            column![].extend(running.chain(history)).spacing(5),
+          )
+          .height(Length::Fixed(150.)),
+        )
+        .spacing(5),
+    )
+    .style(bordered_box)
+    .padding(10)
+    .width(Length::Fill)
+    .into()
+  }
+
+  // 拖进窗口的每个文件都会单独产生一个事件
+  fn subscription(&self) -> Subscription<Message> {
+    let events = event::listen_with(|event, _, id| match event {
+      Event::Window(window::Event::FileDropped(path)) => {
+        Some(Message::Task(TaskMsg::AddLocalArchive(path)))
+      }
+      Event::Window(window::Event::Opened { .. }) => {
+        Some(Message::Ui(UiMsg::WindowOpened(id)))
+      }
+      // 最小化时会收到零大小和极远的坐标，不能记下来
+      Event::Window(window::Event::Resized(size))
+        if size.width > 0. && size.height > 0. =>
+      {
+        Some(Message::Ui(UiMsg::WindowChanged {
+          id,
+          size: Some(size),
+          position: None,
+        }))
+      }
+      Event::Window(window::Event::Moved(position))
+        if position.x > -10000. && position.y > -10000. =>
+      {
+        Some(Message::Ui(UiMsg::WindowChanged {
+          id,
+          size: None,
+          position: Some(position),
+        }))
+      }
+      _ => None,
+    });
+    // 只有等着自动消失的通知时才需要计时
+    let expiry = self
+      .notifications
+      .iter()
+      .any(|x| x.severity == Severity::Info)
+      .then(|| {
+        time::every(Duration::from_secs(1))
+          .map(|_| Message::Ui(UiMsg::ExpireNotifications))
+      });
+    let watch =
+      self.res_mods_path.to_owned().map(watch::subscription);
+    #[cfg(feature = "control")]
+    let events =
+      Subscription::batch([events, control::subscription()]);
+    Subscription::batch(
+      [events].into_iter().chain(expiry).chain(watch),
+    )
+  }
+
+  // 设置界面中选择的主题立即预览
   fn theme(&self) -> Theme {
-    Theme::Nord
+    let name = self
+      .settings_form
+      .as_ref()
+      .map_or(&self.settings.theme, |form| &form.theme);
+    Theme::ALL
+      .iter()
+      .find(|theme| theme.to_string() == *name)
+      .cloned()
+      .unwrap_or(Theme::Nord)
   }
 
   fn title(&self) -> String {
-    "战舰世界莱服模组管理器".to_string()
+    t!("app.title").to_string()
   }
 
   fn view(&self) -> Element<Message> {
     let element: Element<_> = column![]
-      .push(text("战舰世界莱服模组管理器"))
       .push(
         row![]
-          .push(text("游戏根目录"))
+          .push(text(t!("app.title")).width(Length::Fill))
+          .push(text(t!("language")))
+          .push(pick_list(Locale::ALL, Some(locale()), |locale| {
+            Message::Settings(SettingsMsg::SelectLanguage(locale))
+          }))
           .push(
-            text_input("游戏根目录", &self.game_dir), // .on_input(Message::GameDirInput),
+            button(t!("settings.title"))
+              .on_press(Message::Settings(SettingsMsg::OpenSettings)),
           )
+          .spacing(10)
           .align_y(Vertical::Center),
       )
-      .push(
+      .extend(self.crash_report.as_ref().map(|report| {
         container(
-          column![]
-            .extend(self.available_mods().iter().map(|modid| {
-              let modid = modid.to_owned();
-              let Some(modr) = self.request_mod(modid) else {
-                return row![].into();
-              };
-              row![]
-                .push(checkbox("", self.current_mods.contains(modid)))
-                .push(image(""))
-                .push(text(modid).width(Length::Fixed(100.)))
+          row![]
+            .push(
+              column![]
+                .push(text(t!("crash.title")).style(text::danger))
                 .push(
-                  text(modr.name.as_str()).width(Length::Fixed(100.)),
-                )
-                .push(
-                  text(format!(
-                    "{}->{}",
-                    self
-                      .records
-                      .records
-                      .get(modid)
-                      .map(|x| x.version.to_owned())
-                      .unwrap_or_default(),
-                    modr.version
+                  text(t!(
+                    "crash.text",
+                    path = report.to_string_lossy()
                   ))
-                  .width(Length::Fixed(100.)),
-                )
-                .push(
-                  progress_bar(0.0..=100., {
-                    if let Some(download) =
-                      self.downloads.iter().find(|x| x.id() == modid)
-                    {
-                      match download.state() {
-                        DownloadState::Running {
-                          progress, ..
-                        } => progress * 100.,
-                        _ => 100.,
-                      }
-                    } else {
-                      100.
-                    }
-                  })
-                  .length(Length::Fixed(200.)),
+                  .size(12),
                 )
-                .push(
-                  checkbox(
-                    "安装/更新",
-                    self.install_mods.contains(modid),
-                  )
-                  .on_toggle(|flag| {
-                    if flag {
-                      Message::AddInstallMod {
-                        id: modid.to_string(),
-                      }
-                    } else {
-                      Message::RemoveInstallMod {
-                        id: modid.to_string(),
-                      }
-                    }
-                  }),
-                )
-                .push(
-                  checkbox(
-                    "卸载",
-                    self.uninstall_mods.contains(modid),
-                  )
-                  .on_toggle(|flag| {
-                    if flag {
-                      Message::AddUninstallMod {
-                        id: modid.to_string(),
-                      }
-                    } else {
-                      Message::RemoveUninstallMod {
-                        id: modid.to_string(),
-                      }
-                    }
-                  }),
-                )
-                .spacing(5)
-                .width(Length::Fill)
-                .align_y(Vertical::Center)
-                .into()
-            }))
-            .width(Length::Fill)
-            .height(Length::Fill),
+                .width(Length::Fill),
+            )
+            .push(
+              button(text(t!("crash.open")).size(12))
+                .padding([2, 6])
+                .on_press(Message::Ui(UiMsg::OpenCrashReport)),
+            )
+            .push(
+              button(text(t!("crash.ignore")).size(12))
+                .padding([2, 6])
+                .style(button::secondary)
+                .on_press(Message::Ui(UiMsg::DismissCrashReport)),
+            )
+            .spacing(10)
+            .align_y(Vertical::Center),
         )
         .style(bordered_box)
         .padding(10)
         .width(Length::Fill)
+        .into()
+      }))
+      .push(self.toasts())
+      .push(
+        row![]
+          .extend((!self.settings.games.is_empty()).then(|| {
+            // 切换和手动修改目录走同一个流程
+            pick_list(
+              self.settings.games.as_slice(),
+              self
+                .settings
+                .game_install(&self.game_dir_path)
+                .cloned(),
+              |game| Message::Game(GameMsg::PickGameDir(game.path)),
+            )
+            .placeholder(t!("game_dir.saved"))
+            .into()
+          }))
+          .push(text(t!("game_dir.label")))
+          .push(
+            text_input(t!("game_dir.label"), &self.game_dir)
+              .on_input(|input| {
+                Message::Game(GameMsg::GameDirInput(input))
+              })
+              .on_submit(Message::Game(GameMsg::ApplyGameDir)),
+          )
+          .push(
+            button(t!("game_dir.browse"))
+              .on_press(Message::Game(GameMsg::BrowseGameDir)),
+          )
+          .push(
+            button(t!("game_dir.apply")).on_press_maybe(
+              (Path::new(self.game_dir.trim()) != self.game_dir_path)
+                .then_some(Message::Game(GameMsg::ApplyGameDir)),
+            ),
+          )
+          .spacing(10)
+          .align_y(Vertical::Center),
+      )
+      .extend(
+        self
+          .game_dir_error
+          .as_deref()
+          .map(|error| text(error).style(text::danger).into()),
+      )
+      .extend(self.build_mismatch.map(|(recorded, build)| {
+        row![]
+          .push(
+            text(t!(
+              "records.build_mismatch_text",
+              recorded = recorded,
+              build = build
+            ))
+            .size(12)
+            .style(text::danger)
+            .width(Length::Fill),
+          )
+          .push(
+            button(text(t!("toolbar.health_check")).size(12))
+              .padding([2, 6])
+              .on_press(Message::Game(GameMsg::CheckHealth)),
+          )
+          .push(
+            button(text(t!("records.stamp_build")).size(12))
+              .padding([2, 6])
+              .on_press_maybe(
+                (!self.files_busy())
+                  .then_some(Message::Game(GameMsg::StampBuild)),
+              ),
+          )
+          .spacing(10)
+          .align_y(Vertical::Center)
+          .into()
+      }))
+      .extend(
+        self
+          .detected_game_dirs
+          .as_ref()
+          .filter(|game_dirs| !game_dirs.is_empty())
+          .map(|game_dirs| {
+            row![]
+              .push(text(t!("game_dir.detected")))
+              .extend(game_dirs.iter().map(|game_dir| {
+                button(text(game_dir.to_string_lossy()))
+                  .on_press(Message::Game(GameMsg::PickGameDir(
+                    game_dir.to_owned(),
+                  )))
+                  .into()
+              }))
+              .spacing(10)
+              .align_y(Vertical::Center)
+              .into()
+          }),
+      )
+      .push(
+        row![]
+          .push(text(t!("sort.label")).size(12))
+          .push(self.sort_header(t!("sort.name"), SortKey::Name))
+          .push(
+            self
+              .sort_header(t!("sort.installed"), SortKey::Installed),
+          )
+          .push(self.sort_header(
+            t!("sort.update_available"),
+            SortKey::UpdateAvailable,
+          ))
+          .push(
+            self.sort_header(
+              t!("sort.update_time"),
+              SortKey::UpdateTime,
+            ),
+          )
+          .spacing(5)
+          .align_y(Vertical::Center),
+      )
+      .push(
+        row![]
+          .push(text(t!("filter.label")).size(12))
+          .extend(
+            ModFilter::ALL
+              .into_iter()
+              .map(|filter| self.filter_button(filter)),
+          )
+          .push(
+            text_input(t!("filter.search"), &self.mod_query)
+              .on_input(|query| {
+                Message::Ui(UiMsg::ModQueryChanged(query))
+              })
+              .size(12)
+              .width(Length::Fixed(200.)),
+          )
+          .spacing(5)
+          .align_y(Vertical::Center),
+      )
+      .push(
+        row![]
+          .push(
+            button(text(t!("select.updatable")).size(12))
+              .padding([2, 6])
+              .on_press_maybe(
+                (!self.batch_in_flight())
+                  .then_some(Message::Ui(UiMsg::SelectUpdatable)),
+              ),
+          )
+          .push(
+            button(text(t!("select.not_installed")).size(12))
+              .padding([2, 6])
+              .on_press_maybe(
+                (!self.batch_in_flight())
+                  .then_some(Message::Ui(UiMsg::SelectNotInstalled)),
+              ),
+          )
+          .push(
+            button(text(t!("select.clear")).size(12))
+              .padding([2, 6])
+              .on_press_maybe(
+                (!self.batch_in_flight()
+                  && !self.pending_action.is_empty())
+                .then_some(Message::Ui(UiMsg::ClearSelection)),
+              ),
+          )
+          .push(
+            button(text(t!("select.import")).size(12))
+              .padding([2, 6])
+              .on_press_maybe(
+                (!self.batch_in_flight())
+                  .then_some(Message::Ui(UiMsg::ImportSelection)),
+              ),
+          )
+          .spacing(5)
+          .align_y(Vertical::Center),
+      )
+      .extend(self.registry_status())
+      .push(
+        container(column![].push(self.mod_table_header()).push(
+          scrollable(self.mod_list()).on_scroll(|viewport| {
+            Message::Ui(UiMsg::ModListScrolled {
+              offset: viewport.absolute_offset().y,
+              height: viewport.bounds().height,
+            })
+          }),
+        ))
+        .style(bordered_box)
+        .padding(10)
+        .width(Length::Fill)
         .height(Length::Fill),
       )
+      .extend(self.show_tasks.then(|| self.task_panel()))
+      .extend(
+        self
+          .show_notification_history
+          .then(|| self.notification_panel()),
+      )
       .push(
         container(
-          button("更新模组").on_press(Message::UpdateMods {
-            install: self.install_mods.iter().cloned().collect(),
-            uninstall: self
-              .install_mods
-              .iter()
-              .cloned()
-              .chain(self.uninstall_mods.iter().cloned())
-              .collect(),
-          }),
+          row![]
+            .push(
+              button(text(t!(
+                "toolbar.tasks",
+                count = self.task_count()
+              )))
+              .on_press(Message::Ui(UiMsg::ToggleTaskPanel)),
+            )
+            .push(button(t!("notification.history")).on_press(
+              Message::Ui(UiMsg::ToggleNotificationHistory),
+            ))
+            .push(
+              button(t!("toolbar.install_local")).on_press(
+                Message::Task(TaskMsg::InstallLocalArchive),
+              ),
+            )
+            .push(button(t!("toolbar.reload_registries")).on_press(
+              Message::Registry(RegistryMsg::ReloadRegistries),
+            ))
+            .push(
+              button(t!("toolbar.share"))
+                .on_press(Message::Ui(UiMsg::ShareInstalledMods)),
+            )
+            .push(
+              button(t!("toolbar.health_check"))
+                .on_press(Message::Game(GameMsg::CheckHealth)),
+            )
+            .push(
+              button(t!("toolbar.trash"))
+                .on_press(Message::Game(GameMsg::ShowTrash)),
+            )
+            .push(
+              checkbox(
+                t!("toolbar.cache_archives"),
+                self.settings.cache_archives,
+              )
+              .on_toggle(|flag| {
+                Message::Settings(SettingsMsg::ToggleArchiveCache(
+                  flag,
+                ))
+              }),
+            )
+            .extend(cfg!(debug_assertions).then(|| {
+              button(t!("toolbar.generate_registry"))
+                .on_press(Message::Registry(
+                  RegistryMsg::GenerateRegistry,
+                ))
+                .into()
+            }))
+            .extend(self.batch_progress())
+            .extend(
+              self
+                .pending_action
+                .values()
+                .any(|action| *action == PendingAction::Install)
+                .then(|| {
+                  text(t!(
+                    "toolbar.estimated_download",
+                    size = self.estimated_download()
+                  ))
+                  .into()
+                }),
+            )
+            .push(
+              button(text(t!(
+                "toolbar.update_all",
+                count = self.updatable_mods.len()
+              )))
+              .on_press_maybe(
+                (!self.batch_in_flight()
+                  && !self.updatable_mods.is_empty())
+                .then_some(Message::Task(TaskMsg::UpdateAll)),
+              ),
+            )
+            .push(text(t!(
+              "toolbar.selected",
+              install =
+                self.pending_ids(PendingAction::Install).len(),
+              uninstall =
+                self.pending_ids(PendingAction::Uninstall).len()
+            )))
+            .push(
+              button(t!("toolbar.update_mods")).on_press_maybe(
+                (!self.batch_in_flight())
+                  .then_some(Message::Task(TaskMsg::PreviewBatch)),
+              ),
+            )
+            .push(
+              button(t!("toolbar.launch_game")).on_press_maybe(
+                (!self.files_busy())
+                  .then_some(Message::Game(GameMsg::LaunchGame)),
+              ),
+            )
+            .spacing(10)
+            .align_y(Vertical::Center),
         )
         .align_right(Length::Fill),
       )
+      .push(self.status_bar())
       .spacing(10)
       .padding(20)
       .into();
 
-    element
+    // 对话框打开时挡住后面的界面
+    if let Some(preview) = &self.preview {
+      return modal(element, self.preview_dialog(preview));
+    }
+    if let Some(report) = &self.health {
+      return modal(element, self.health_dialog(report));
+    }
+    if let Some(entries) = &self.trash {
+      return modal(element, self.trash_dialog(entries));
+    }
+    match (&self.plan, &self.settings_form, &self.batch_summary) {
+      (Some(plan), _, _) => modal(element, self.plan_dialog(plan)),
+      (None, Some(form), _) => {
+        modal(element, self.settings_dialog(form))
+      }
+      (None, None, Some(summary)) => {
+        modal(element, self.summary_dialog(summary))
+      }
+      (None, None, None) => element,
+    }
     // .explain(Color::BLACK)
   }
 
+  // registry中的模组，只有安装记录的不算
   fn request_mod(&self, id: &str) -> Option<&Mod> {
     self
-      .registries
-      .iter()
-      .find_map(|registry| registry.mods.get(id))
+      .request_registry(id)
+      .and_then(|registry| registry.mods.get(id))
+  }
+
+  fn request_registry(&self, id: &str) -> Option<&Registry> {
+    match self.mod_locations.get(id)? {
+      ModLocation::Registry(pos) => self.registries.get(*pos),
+      ModLocation::Local(_) => None,
+    }
+  }
+
+  // registry为已安装的版本声明的sha256
+  fn declared_sha256(&self, id: &str) -> Option<String> {
+    let record = self.records.records.get(id)?;
+    self.request_mod(id)?.any_release(&record.version)?.sha256
+  }
+
+  // 选中版本实际的下载地址，相对地址按所在的registry解析
+  fn release_url(
+    &self,
+    id: &str,
+  ) -> Option<(ModRelease, Result<Url, url::ParseError>)> {
+    let registry = self.request_registry(id)?;
+    let modr = registry.mods.get(id)?;
+    let release = self.selected_release(modr);
+    let url = registry.resolve_url(&release.url);
+    Some((release, url))
+  }
+
+  // 取下registry中选中版本的下载信息，返回找不到的模组id；
+  // 地址无效的模组换成警告
+  fn release_snapshots(
+    &self,
+    install: Vec<String>,
+  ) -> (Vec<ReleaseSnapshot>, Vec<String>, Vec<Message>) {
+    let mut releases = Vec::new();
+    let mut missing = Vec::new();
+    let mut warnings = Vec::new();
+    for id in install {
+      let (Some(modr), Some((release, url))) =
+        (self.request_mod(&id), self.release_url(&id))
+      else {
+        missing.push(id);
+        continue;
+      };
+      match url {
+        Ok(url) => releases.push(ReleaseSnapshot {
+          id: modr.id.to_owned(),
+          url,
+          size: self.release_size(modr, &release),
+          sha256: release.sha256,
+          version: release.version,
+          ty: modr.ty.to_owned(),
+        }),
+        Err(err) => warnings.push(Message::Ui(UiMsg::Warning {
+          title: t!("download.invalid_url").to_string(),
+          text: format!("{}: {} ({})", id, release.url, err),
+        })),
+      }
+    }
+    (releases, missing, warnings)
   }
+
+  // 列表中显示的模组，registry中没有时取安装记录
+  fn listed_mod(&self, id: &str) -> Option<&Mod> {
+    match self.mod_locations.get(id)? {
+      ModLocation::Registry(pos) => {
+        self.registries.get(*pos)?.mods.get(id)
+      }
+      ModLocation::Local(pos) => self.local_mods.get(*pos),
+    }
+  }
+}
+
+// 清理上次异常退出残留的下载文件，待恢复的任务还要用的除外
+async fn sweep_orphaned_downloads() {
+  let Ok(pending_queue) =
+    PendingQueue::load(&pending_queue_path()).await
+  else {
+    return;
+  };
+  let keep = pending_queue
+    .operations
+    .into_iter()
+    .filter_map(|operation| operation.path)
+    .collect();
+  if let Err(err) =
+    sweep_stale_downloads(Duration::from_secs(60), &keep).await
+  {
+    tracing::warn!(%err, "failed to sweep downloads");
+  }
+}
+
+// 清理超过保留天数的回收站内容，关掉安全删除前留下的也一样
+async fn purge_trash(retention_days: u32) {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |elapsed| elapsed.as_secs());
+  let max_age =
+    Duration::from_secs(u64::from(retention_days) * 24 * 60 * 60);
+  if let Err(err) =
+    trash::purge_older_than(&trash_dir(), now, max_age).await
+  {
+    tracing::warn!(%err, "failed to purge trash");
+  }
+}
+
+fn modal<'a>(
+  base: Element<'a, Message>,
+  dialog: Element<'a, Message>,
+) -> Element<'a, Message> {
+  stack![
+    base,
+    opaque(center(dialog).style(|theme| {
+      container::Style {
+        background: Some(
+          Color {
+            a: 0.6,
+            ..Color::BLACK
+          }
+          .into(),
+        ),
+        ..container::transparent(theme)
+      }
+    }))
+  ]
+  .into()
+}
+
+fn download_size_label((known, unknown): (u64, usize)) -> String {
+  match (known, unknown) {
+    (_, 0) => format_bytes(known),
+    (0, _) => t!("size.unknown").to_string(),
+    _ => t!("size.partially_unknown", known = format_bytes(known)),
+  }
+}
+
+fn task_row<'a>(
+  id: &'a str,
+  operation: &'a str,
+  status: TaskStatus,
+  error: Option<&'a str>,
+  details: Option<Message>,
+) -> Element<'a, Message> {
+  row![]
+    .push(text(id).width(Length::Fixed(150.)))
+    .push(text(operation).width(Length::Fixed(40.)))
+    .push(
+      text(status.label())
+        .style(match status {
+          TaskStatus::Failed => text::danger,
+          TaskStatus::Finished => text::success,
+          _ => text::default,
+        })
+        .width(Length::Fixed(60.)),
+    )
+    .push(match status {
+      TaskStatus::Running {
+        progress: Some(progress),
+      } => Element::from(
+        progress_bar(0.0..=100., progress * 100.)
+          .length(Length::Fixed(120.)),
+      ),
+      _ => row![].width(Length::Fixed(120.)).into(),
+    })
+    .push(text(error.unwrap_or_default()).style(text::danger))
+    .extend(details.map(|details| {
+      button(text(t!("task.details")).size(12))
+        .padding([2, 6])
+        .on_press(details)
+        .into()
+    }))
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
 }
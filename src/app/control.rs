@@ -0,0 +1,49 @@
+use std::net::Ipv4Addr;
+
+use futures::{Stream, StreamExt, future, stream};
+use iced::Subscription;
+use tokio::{net::TcpListener, sync::mpsc};
+
+use crate::{
+  control::{Call, DEFAULT_PORT, load_or_create_token, serve},
+  messages::Message,
+  paths::control_token_path,
+};
+
+// 控制接口收到的请求转成消息，由update统一处理
+pub(super) fn subscription() -> Subscription<Message> {
+  Subscription::run(calls)
+}
+
+fn calls() -> impl Stream<Item = Message> {
+  stream::once(listen())
+    .filter_map(future::ready)
+    .flat_map(|mut calls| {
+      stream::poll_fn(move |cx| calls.poll_recv(cx))
+    })
+    .map(Message::Control)
+}
+
+// 端口被占用等情况只记录日志，界面照常使用
+async fn listen() -> Option<mpsc::UnboundedReceiver<Call>> {
+  let token = load_or_create_token(&control_token_path())
+    .await
+    .inspect_err(
+      |err| tracing::warn!(%err, "failed to prepare control token"),
+    )
+    .ok()?;
+  let listener =
+    TcpListener::bind((Ipv4Addr::LOCALHOST, DEFAULT_PORT))
+      .await
+      .inspect_err(
+        |err| tracing::warn!(%err, "failed to start control server"),
+      )
+      .ok()?;
+  let (calls, receiver) = mpsc::unbounded_channel();
+  tokio::spawn(async move {
+    if let Err(err) = serve(listener, token, calls).await {
+      tracing::warn!(%err, "control server stopped");
+    }
+  });
+  Some(receiver)
+}
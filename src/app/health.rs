@@ -0,0 +1,175 @@
+use super::App;
+
+use std::path::PathBuf;
+
+use iced::{
+  Element, Length,
+  alignment::Vertical,
+  widget::{
+    button, column, container, container::bordered_box, row,
+    scrollable, text,
+  },
+};
+
+use crate::{
+  data::health::{HealthReport, ModHealth, ModReport},
+  i18n::t,
+  messages::{GameMsg, Message},
+};
+
+// 每类文件最多列出的数量，其余只显示个数
+const FILE_LIMIT: usize = 20;
+
+fn file_list<'a>(files: &'a [PathBuf]) -> Element<'a, Message> {
+  column![]
+    .extend(
+      files
+        .iter()
+        .take(FILE_LIMIT)
+        .map(|file| text(file.to_string_lossy()).size(12).into()),
+    )
+    .extend((files.len() > FILE_LIMIT).then(|| {
+      text(t!("health.more_files", count = files.len() - FILE_LIMIT))
+        .size(12)
+        .style(text::secondary)
+        .into()
+    }))
+    .spacing(2)
+    .padding([0, 10])
+    .into()
+}
+
+// 没有记录的一类文件，可以一起收编
+fn unrecorded_section<'a>(
+  title: String,
+  hint: String,
+  files: &'a [PathBuf],
+) -> Option<Element<'a, Message>> {
+  (!files.is_empty()).then(|| {
+    column![]
+      .push(
+        row![]
+          .push(text(title).width(Length::Fill))
+          .push(
+            button(text(t!("health.adopt")).size(12))
+              .padding([2, 6])
+              .on_press(Message::Game(GameMsg::AdoptFiles(
+                files.to_vec(),
+              ))),
+          )
+          .spacing(10)
+          .align_y(Vertical::Center),
+      )
+      .push(text(hint).size(12).style(text::secondary))
+      .push(file_list(files))
+      .spacing(5)
+      .into()
+  })
+}
+
+impl App {
+  fn mod_health_row<'a>(
+    &'a self,
+    report: &'a ModReport,
+  ) -> Element<'a, Message> {
+    let id = report.id.as_str();
+    let (status, files, action) = match &report.health {
+      ModHealth::Intact => return column![].into(),
+      ModHealth::Missing(files) => (
+        t!("health.missing", count = files.len()),
+        Some(files),
+        button(text(t!("health.repair")).size(12)).on_press_maybe(
+          self.can_repair(id).then(|| {
+            Message::Game(GameMsg::RepairMod { id: id.to_owned() })
+          }),
+        ),
+      ),
+      ModHealth::AllMissing => (
+        t!("health.all_missing").to_string(),
+        None,
+        button(text(t!("health.forget")).size(12)).on_press(
+          Message::Game(GameMsg::ForgetRecord { id: id.to_owned() }),
+        ),
+      ),
+    };
+    column![]
+      .push(
+        row![]
+          .push(
+            text(t!(
+              "error.subject_reason",
+              subject = format!("{id} {}", report.version),
+              error = status
+            ))
+            .style(text::danger)
+            .width(Length::Fill),
+          )
+          .push(action.padding([2, 6]))
+          .spacing(10)
+          .align_y(Vertical::Center),
+      )
+      .extend(files.map(|files| file_list(files)))
+      .spacing(5)
+      .into()
+  }
+
+  pub(super) fn health_dialog<'a>(
+    &'a self,
+    report: &'a HealthReport,
+  ) -> Element<'a, Message> {
+    let content: Element<'a, Message> = if report.is_healthy() {
+      text(t!("health.healthy")).into()
+    } else {
+      scrollable(
+        column![]
+          .extend(
+            report
+              .broken_mods()
+              .map(|mod_report| self.mod_health_row(mod_report)),
+          )
+          .extend(unrecorded_section(
+            t!("health.orphans", count = report.orphans.len()),
+            t!("health.orphans_hint").to_string(),
+            &report.orphans,
+          ))
+          .extend(unrecorded_section(
+            t!("health.backups", count = report.backups.len()),
+            t!("health.leftovers_hint").to_string(),
+            &report.backups,
+          ))
+          .extend(unrecorded_section(
+            t!("health.temp_files", count = report.temp_files.len()),
+            t!("health.leftovers_hint").to_string(),
+            &report.temp_files,
+          ))
+          .spacing(10)
+          .padding([0, 10]),
+      )
+      .height(Length::Fixed(360.))
+      .into()
+    };
+    container(
+      column![]
+        .push(text(t!("health.title")).size(18))
+        .push(text(t!(
+          "health.summary",
+          mods = report.mods.len(),
+          broken = report.broken_mods().count()
+        )))
+        .push(content)
+        .push(
+          container(
+            button(t!("preview.close"))
+              .style(button::secondary)
+              .on_press(Message::Game(GameMsg::CloseHealthReport)),
+          )
+          .align_right(Length::Fill),
+        )
+        .spacing(10),
+    )
+    .style(bordered_box)
+    .padding(20)
+    .width(Length::Fixed(600.))
+    .into()
+  }
+}
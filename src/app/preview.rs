@@ -0,0 +1,177 @@
+use super::{App, PreviewDialog};
+
+use iced::{
+  Element, Length, Padding,
+  alignment::Vertical,
+  widget::{
+    button, column, container, container::bordered_box, row,
+    scrollable, text,
+  },
+};
+
+use crate::{
+  data::preview::{Flag, TreeNode},
+  format::format_bytes,
+  i18n::t,
+  messages::{Message, TaskMsg, UiMsg},
+};
+
+// 每一级缩进的宽度
+const INDENT: f32 = 16.;
+
+fn flag_text(flag: &Flag) -> String {
+  match flag {
+    Flag::Conflict(Some(owner)) => {
+      t!("preview.conflict_mod", id = owner)
+    }
+    Flag::Conflict(None) => t!("preview.conflict").to_string(),
+    Flag::Escapes(name) => t!("preview.escapes", name = name),
+    Flag::Executable => t!("preview.executable").to_string(),
+  }
+}
+
+fn node_row<'a>(
+  depth: usize,
+  node: &'a TreeNode,
+  expanded: bool,
+) -> Element<'a, Message> {
+  let name: Element<'a, Message> = if node.dir {
+    button(
+      text(format!(
+        "{} {}/",
+        if expanded { "▾" } else { "▸" },
+        node.name
+      ))
+      .size(12),
+    )
+    .padding([0, 4])
+    .style(button::text)
+    .on_press(Message::Ui(UiMsg::TogglePreviewDir(
+      node.path.to_owned(),
+    )))
+    .into()
+  } else {
+    text(node.name.as_str()).size(12).into()
+  };
+  let label = row![]
+    .push(name)
+    .extend(node.flags.iter().map(|flag| {
+      text(flag_text(flag)).size(12).style(text::danger).into()
+    }))
+    // 折叠的目录里有需要留意的条目
+    .extend((node.dir && !expanded && node.flagged).then(|| {
+      text(t!("preview.flagged"))
+        .size(12)
+        .style(text::danger)
+        .into()
+    }))
+    .spacing(5)
+    .align_y(Vertical::Center)
+    .width(Length::Fill);
+  row![]
+    .push(label)
+    .push(
+      text(format_bytes(node.size))
+        .size(12)
+        .style(text::secondary),
+    )
+    .spacing(5)
+    .padding(Padding {
+      left: depth as f32 * INDENT,
+      ..Padding::ZERO
+    })
+    .align_y(Vertical::Center)
+    .into()
+}
+
+impl App {
+  pub(super) fn preview_dialog<'a>(
+    &'a self,
+    preview: &'a PreviewDialog,
+  ) -> Element<'a, Message> {
+    let content: Element<'a, Message> = match &preview.tree {
+      None => text(t!("preview.loading")).into(),
+      Some(Err(error)) => text(t!("error.reason", error = error))
+        .style(text::danger)
+        .into(),
+      Some(Ok(tree)) => column![]
+        .push(text(t!(
+          "preview.summary",
+          files = tree.files,
+          size = format_bytes(tree.size)
+        )))
+        .extend((tree.conflicts > 0).then(|| {
+          text(t!("preview.conflicts", count = tree.conflicts))
+            .style(text::danger)
+            .into()
+        }))
+        .extend((tree.suspicious > 0).then(|| {
+          text(t!("preview.suspicious", count = tree.suspicious))
+            .style(text::danger)
+            .into()
+        }))
+        .push(
+          scrollable(
+            column![]
+              .extend(
+                tree.visible(&preview.expanded).into_iter().map(
+                  |(depth, node)| {
+                    node_row(
+                      depth,
+                      node,
+                      preview.expanded.contains(&node.path),
+                    )
+                  },
+                ),
+              )
+              .spacing(2)
+              .padding([0, 10]),
+          )
+          .height(Length::Fixed(360.)),
+        )
+        .spacing(5)
+        .into(),
+    };
+    let close =
+      |install| Message::Task(TaskMsg::ClosePreview { install });
+    let buttons = match preview.version {
+      // 本地压缩包看过内容后再决定是否安装
+      Some(_) => row![]
+        .push(
+          button(t!("plan.cancel"))
+            .style(button::secondary)
+            .on_press(close(false)),
+        )
+        .push(button(t!("preview.install")).on_press(close(true))),
+      None => row![].push(
+        button(t!("preview.close"))
+          .style(button::secondary)
+          .on_press(close(false)),
+      ),
+    };
+    container(
+      column![]
+        .push(
+          text(t!("preview.title", id = preview.id.as_str()))
+            .size(18),
+        )
+        .extend(preview.version.as_ref().map(|version| {
+          text(t!(
+            "local.confirm_text",
+            id = preview.id.as_str(),
+            version = version
+          ))
+          .into()
+        }))
+        .push(content)
+        .push(
+          container(buttons.spacing(10)).align_right(Length::Fill),
+        )
+        .spacing(10),
+    )
+    .style(bordered_box)
+    .padding(20)
+    .width(Length::Fixed(600.))
+    .into()
+  }
+}
@@ -0,0 +1,304 @@
+use super::App;
+
+use iced::{
+  Element, Length, Theme,
+  alignment::Vertical,
+  widget::{
+    button, checkbox, column, container, container::bordered_box,
+    pick_list, row, scrollable, text, text_input,
+  },
+};
+
+use crate::{
+  data::settings_form::{SettingsEdit, SettingsForm, UrlError},
+  i18n::{Locale, t},
+  messages::{Message, SettingsMsg},
+};
+
+// 回收站保留天数的选项
+const RETENTION_DAYS: [u32; 5] = [1, 3, 7, 14, 30];
+// 超时秒数和重试次数的选项
+const CONNECT_TIMEOUTS: [u64; 5] = [5, 10, 15, 30, 60];
+const IDLE_TIMEOUTS: [u64; 5] = [15, 30, 60, 120, 300];
+const DOWNLOAD_RETRIES: [u32; 5] = [0, 1, 3, 5, 10];
+
+fn section<'a>(
+  title: &'static str,
+  content: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+  column![]
+    .push(text(title).size(16))
+    .push(content)
+    .spacing(5)
+    .into()
+}
+
+// 左边是说明，右边是选项
+fn option_row<'a>(
+  label: &'static str,
+  picker: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+  row![]
+    .push(text(label))
+    .push(picker)
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
+
+impl App {
+  pub(super) fn settings_dialog<'a>(
+    &'a self,
+    form: &'a SettingsForm,
+  ) -> Element<'a, Message> {
+    let edit =
+      |edit| Message::Settings(SettingsMsg::EditSettings(edit));
+    let appearance = column![]
+      .push(
+        row![]
+          .push(text(t!("settings.theme")).width(Length::Fixed(100.)))
+          .push(pick_list(
+            Theme::ALL,
+            Theme::ALL
+              .iter()
+              .find(|theme| theme.to_string() == form.theme)
+              .cloned(),
+            move |theme| edit(SettingsEdit::Theme(theme.to_string())),
+          ))
+          .spacing(10)
+          .align_y(Vertical::Center),
+      )
+      .push(
+        row![]
+          .push(text(t!("language")).width(Length::Fixed(100.)))
+          .push(
+            pick_list(Locale::ALL, form.language, move |language| {
+              edit(SettingsEdit::Language(Some(language)))
+            })
+            .placeholder(t!("settings.system_language")),
+          )
+          .push(
+            button(text(t!("settings.system_language")).size(12))
+              .padding([2, 6])
+              .style(button::secondary)
+              .on_press_maybe(
+                form
+                  .language
+                  .is_some()
+                  .then(|| edit(SettingsEdit::Language(None))),
+              ),
+          )
+          .spacing(10)
+          .align_y(Vertical::Center),
+      )
+      .spacing(5);
+    let registries = column![]
+      .extend(form.registry_urls.iter().enumerate().map(
+        |(index, url)| {
+          column![]
+            .push(
+              row![]
+                .push(text_input("https://", url).on_input(
+                  move |url| {
+                    edit(SettingsEdit::RegistryUrl(index, url))
+                  },
+                ))
+                .push(
+                  button(text(t!("settings.remove")).size(12))
+                    .padding([2, 6])
+                    .style(button::secondary)
+                    .on_press(edit(SettingsEdit::RemoveRegistryUrl(
+                      index,
+                    ))),
+                )
+                .spacing(5)
+                .align_y(Vertical::Center),
+            )
+            .extend(form.errors.get(&index).map(|error| {
+              text(match error {
+                UrlError::Invalid(reason) => {
+                  t!("settings.invalid_url", reason = reason)
+                }
+                UrlError::UnsupportedScheme(scheme) => {
+                  t!("registry.unsupported_scheme", scheme = scheme)
+                }
+              })
+              .size(12)
+              .style(text::danger)
+              .into()
+            }))
+            .spacing(2)
+            .into()
+        },
+      ))
+      .push(
+        button(text(t!("settings.add_registry")).size(12))
+          .padding([2, 6])
+          .on_press(edit(SettingsEdit::AddRegistryUrl)),
+      )
+      .spacing(5);
+    let games = column![]
+      .extend(form.games.iter().enumerate().map(
+        |(index, (name, path))| {
+          row![]
+            .push(
+              text_input(t!("settings.game_name"), name)
+                .on_input(move |name| {
+                  edit(SettingsEdit::GameName(index, name))
+                })
+                .width(Length::Fixed(120.)),
+            )
+            .push(text_input(t!("game_dir.label"), path).on_input(
+              move |path| edit(SettingsEdit::GamePath(index, path)),
+            ))
+            .push(
+              button(text(t!("settings.remove")).size(12))
+                .padding([2, 6])
+                .style(button::secondary)
+                .on_press(edit(SettingsEdit::RemoveGame(index))),
+            )
+            .spacing(5)
+            .align_y(Vertical::Center)
+            .into()
+        },
+      ))
+      .push(
+        button(text(t!("settings.add_game")).size(12))
+          .padding([2, 6])
+          .on_press(edit(SettingsEdit::AddGame(
+            self.game_dir_path.to_owned(),
+          ))),
+      )
+      .push(
+        checkbox(
+          t!("settings.allow_external_res_mods"),
+          form.allow_external_res_mods,
+        )
+        .on_toggle(move |flag| {
+          edit(SettingsEdit::AllowExternalResMods(flag))
+        }),
+      )
+      .spacing(5);
+    let downloads = column![]
+      .push(
+        checkbox(t!("toolbar.cache_archives"), form.cache_archives)
+          .on_toggle(move |flag| {
+            edit(SettingsEdit::CacheArchives(flag))
+          }),
+      )
+      .push(option_row(
+        t!("settings.idle_timeout"),
+        pick_list(
+          IDLE_TIMEOUTS,
+          Some(form.idle_timeout_secs),
+          move |secs| edit(SettingsEdit::IdleTimeout(secs)),
+        ),
+      ))
+      .push(option_row(
+        t!("settings.download_retries"),
+        pick_list(
+          DOWNLOAD_RETRIES,
+          Some(form.download_retries),
+          move |retries| edit(SettingsEdit::DownloadRetries(retries)),
+        ),
+      ))
+      .push(option_row(
+        t!("settings.connect_timeout"),
+        pick_list(
+          CONNECT_TIMEOUTS,
+          Some(form.connect_timeout_secs),
+          move |secs| edit(SettingsEdit::ConnectTimeout(secs)),
+        ),
+      ))
+      .push(
+        checkbox(
+          t!("settings.allow_insecure_redirects"),
+          form.allow_insecure_redirects,
+        )
+        .on_toggle(move |flag| {
+          edit(SettingsEdit::AllowInsecureRedirects(flag))
+        }),
+      )
+      .push(
+        text(t!("settings.network_restart_hint"))
+          .size(12)
+          .style(text::secondary),
+      )
+      .spacing(5);
+    let startup = column![]
+      .push(
+        checkbox(t!("settings.auto_update"), form.auto_update)
+          .on_toggle(move |flag| {
+            edit(SettingsEdit::AutoUpdate(flag))
+          }),
+      )
+      .push(
+        checkbox(
+          t!("settings.auto_update_ask"),
+          form.auto_update_ask,
+        )
+        .on_toggle_maybe(form.auto_update.then_some(
+          move |flag| edit(SettingsEdit::AutoUpdateAsk(flag)),
+        )),
+      )
+      .spacing(5);
+    let uninstall = column![]
+      .push(
+        checkbox(t!("settings.safe_delete"), form.safe_delete)
+          .on_toggle(move |flag| {
+            edit(SettingsEdit::SafeDelete(flag))
+          }),
+      )
+      .push(
+        row![]
+          .push(text(t!("settings.trash_retention_days")))
+          .push(pick_list(
+            RETENTION_DAYS,
+            Some(form.trash_retention_days),
+            move |days| edit(SettingsEdit::TrashRetentionDays(days)),
+          ))
+          .spacing(10)
+          .align_y(Vertical::Center),
+      )
+      .spacing(5);
+    container(
+      column![]
+        .push(text(t!("settings.title")).size(18))
+        .push(
+          scrollable(
+            column![]
+              .push(section(t!("settings.appearance"), appearance))
+              .push(section(t!("settings.registries"), registries))
+              .push(section(t!("settings.games"), games))
+              .push(section(t!("settings.downloads"), downloads))
+              .push(section(t!("settings.startup"), startup))
+              .push(section(t!("settings.uninstall"), uninstall))
+              .spacing(15),
+          )
+          .height(Length::Fixed(400.)),
+        )
+        .push(
+          container(
+            row![]
+              .push(
+                button(t!("plan.cancel"))
+                  .style(button::secondary)
+                  .on_press(Message::Settings(
+                    SettingsMsg::CancelSettings,
+                  )),
+              )
+              .push(button(t!("settings.apply")).on_press(
+                Message::Settings(SettingsMsg::ApplySettings),
+              ))
+              .spacing(10),
+          )
+          .align_right(Length::Fill),
+        )
+        .spacing(10),
+    )
+    .style(bordered_box)
+    .padding(20)
+    .width(Length::Fixed(600.))
+    .into()
+  }
+}
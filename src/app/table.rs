@@ -0,0 +1,569 @@
+use super::{App, RowState};
+
+use iced::{
+  Element, Length,
+  alignment::Vertical,
+  widget::{
+    button, checkbox, column, container, image, pick_list,
+    progress_bar, row, text, text_input, tooltip,
+  },
+};
+
+use crate::{
+  app::tasks::download::DownloadState,
+  data::batch::PendingAction,
+  format::{ellipsize, format_timestamp},
+  i18n::{locale, t},
+  messages::{GameMsg, Message, TaskMsg, UiMsg},
+};
+
+// 表头和每一行共用的列宽，名称列占据剩下的空间
+const STATUS_WIDTH: f32 = 110.;
+const ICON_WIDTH: f32 = 40.;
+const ID_WIDTH: f32 = 120.;
+const VERSION_WIDTH: f32 = 200.;
+const PROGRESS_WIDTH: f32 = 260.;
+const ACTIONS_WIDTH: f32 = 420.;
+// 名称列放不下时截断，完整名称在悬停提示中
+const NAME_MAX_CHARS: usize = 24;
+// 行高按显示的内容算出并固定下来，列表只构建可见的行
+const ROW_BASE_HEIGHT: f32 = 56.;
+const ROW_LINE_HEIGHT: f32 = 18.;
+const NOTE_INPUT_HEIGHT: f32 = 32.;
+const FAILURE_HEIGHT: f32 = 32.;
+
+impl App {
+  pub(super) fn mod_table_header(&self) -> Element<Message> {
+    let cell = |label: &'static str, width: Length| {
+      text(label).size(12).style(text::secondary).width(width)
+    };
+    row![]
+      .push(cell(t!("table.status"), Length::Fixed(STATUS_WIDTH)))
+      .push(cell(t!("table.icon"), Length::Fixed(ICON_WIDTH)))
+      .push(cell(t!("table.id"), Length::Fixed(ID_WIDTH)))
+      .push(cell(t!("table.name"), Length::Fill))
+      .push(cell(t!("table.version"), Length::Fixed(VERSION_WIDTH)))
+      .push(cell(t!("table.progress"), Length::Fixed(PROGRESS_WIDTH)))
+      .push(cell(t!("table.actions"), Length::Fixed(ACTIONS_WIDTH)))
+      .spacing(5)
+      .padding([0, 10])
+      .into()
+  }
+
+  // 保持/安装/卸载三选一，不允许的操作直接禁用
+  fn action_selector<'a>(
+    &'a self,
+    modid: &'a str,
+  ) -> Element<'a, Message> {
+    let current = self.action_of(modid);
+    row![]
+      .extend(PendingAction::ALL.into_iter().map(|action| {
+        let label = match action {
+          PendingAction::Keep => t!("mod.keep"),
+          PendingAction::Install => t!("mod.install"),
+          PendingAction::Uninstall => t!("mod.uninstall"),
+        };
+        button(text(label).size(12))
+          .padding([2, 6])
+          .style(if action == current {
+            button::primary
+          } else {
+            button::secondary
+          })
+          .on_press_maybe(self.allows_action(modid, action).then(
+            || {
+              Message::Ui(UiMsg::SetPendingAction {
+                id: modid.to_string(),
+                action,
+              })
+            },
+          ))
+          .into()
+      }))
+      .spacing(2)
+      .into()
+  }
+
+  // 截短显示安装时的sha256，和registry声明的不一致时标出来
+  fn archive_hash<'a>(
+    &self,
+    modid: &'a str,
+    hash: &'a str,
+  ) -> Element<'a, Message> {
+    let mismatch = self
+      .declared_sha256(modid)
+      .is_some_and(|declared| !declared.eq_ignore_ascii_case(hash));
+    row![]
+      .push(
+        text(t!("mod.archive_sha256", hash = ellipsize(hash, 12)))
+          .size(12)
+          .style(if mismatch {
+            text::danger
+          } else {
+            text::secondary
+          }),
+      )
+      .extend(mismatch.then(|| {
+        text(t!("mod.checksum_mismatch"))
+          .size(12)
+          .style(text::danger)
+          .into()
+      }))
+      .push(
+        button(text(t!("mod.copy_hash")).size(10))
+          .padding([1, 4])
+          .style(button::text)
+          .on_press(Message::Ui(UiMsg::CopyArchiveHash {
+            id: modid.to_string(),
+          })),
+      )
+      .spacing(5)
+      .align_y(Vertical::Center)
+      .into()
+  }
+
+  // 来源registry，被覆盖的同名条目一起列出
+  fn source_label(&self, modid: &str) -> Option<String> {
+    let source = self.mod_sources.get(modid)?;
+    let label = self.settings.registry_label(source.source.as_ref()?);
+    Some(if source.shadowed.is_empty() {
+      t!("mod.source", source = label)
+    } else {
+      t!(
+        "mod.source_shadowing",
+        source = label,
+        shadowed = source
+          .shadowed
+          .iter()
+          .map(|url| self.settings.registry_label(url))
+          .collect::<Vec<_>>()
+          .join(", ")
+      )
+    })
+  }
+
+  // 已安装的模组才有备注，编辑中的显示输入框
+  fn note_editor<'a>(
+    &'a self,
+    modid: &'a str,
+    note: &'a str,
+  ) -> Element<'a, Message> {
+    match &self.note_edit {
+      Some((id, input)) if id == modid => row![]
+        .push(
+          text_input(t!("note.placeholder"), input)
+            .on_input(|note| Message::Game(GameMsg::NoteInput(note)))
+            .on_submit(Message::Game(GameMsg::SaveNote))
+            .size(12),
+        )
+        .push(
+          button(text(t!("note.save")).size(12))
+            .padding([2, 6])
+            .on_press(Message::Game(GameMsg::SaveNote)),
+        )
+        .push(
+          button(text(t!("plan.cancel")).size(12))
+            .padding([2, 6])
+            .style(button::secondary)
+            .on_press(Message::Game(GameMsg::CancelNote)),
+        )
+        .spacing(5)
+        .align_y(Vertical::Center)
+        .into(),
+      _ => row![]
+        .extend((!note.is_empty()).then(|| {
+          text(t!("note.label", note = note)).size(12).into()
+        }))
+        .push(
+          button(
+            text(if note.is_empty() {
+              t!("note.add")
+            } else {
+              t!("note.edit")
+            })
+            .size(10),
+          )
+          .padding([1, 4])
+          .style(button::text)
+          .on_press(Message::Game(GameMsg::EditNote {
+            id: modid.to_string(),
+          })),
+        )
+        .spacing(5)
+        .align_y(Vertical::Center)
+        .into(),
+    }
+  }
+
+  pub(super) fn row_height(&self, modid: &str) -> f32 {
+    let Some(modr) = self.listed_mod(modid) else {
+      return 0.;
+    };
+    let record = self.records.records.get(modid);
+    let lines = [
+      self
+        .mod_sources
+        .get(modid)
+        .is_some_and(|source| source.source.is_some()),
+      record.is_some(),
+      modr.yanked,
+    ]
+    .into_iter()
+    .filter(|line| *line)
+    .count();
+    let note = match (record, &self.note_edit) {
+      (None, _) => 0.,
+      (Some(_), Some((id, _))) if id == modid => NOTE_INPUT_HEIGHT,
+      (Some(_), _) => ROW_LINE_HEIGHT,
+    };
+    let failure = if self.failed_task_error(modid).is_some() {
+      FAILURE_HEIGHT
+    } else {
+      0.
+    };
+    ROW_BASE_HEIGHT + lines as f32 * ROW_LINE_HEIGHT + note + failure
+  }
+
+  pub(super) fn mod_row<'a>(
+    &'a self,
+    modid: &'a str,
+  ) -> Element<'a, Message> {
+    let Some(modr) = self.listed_mod(modid) else {
+      return row![].into();
+    };
+    // 只有安装记录的模组没法重新下载，只能卸载
+    let local_only = self.request_mod(modid).is_none();
+    let compatible = self.is_compatible(modr);
+    let state = self.row_state(modid);
+    let name = text(ellipsize(&modr.name, NAME_MAX_CHARS)).style(
+      if compatible {
+        text::default
+      } else {
+        text::secondary
+      },
+    );
+    let cells = row![]
+      .push(
+        row![]
+          .push(checkbox("", self.current_mods.contains(modid)))
+          .extend(local_only.then(|| {
+            text(t!("mod.local_only"))
+              .size(12)
+              .style(text::secondary)
+              .into()
+          }))
+          .extend(self.updatable_mods.contains(modid).then(|| {
+            text(t!("mod.update_available"))
+              .size(12)
+              .style(text::success)
+              .into()
+          }))
+          .spacing(5)
+          .align_y(Vertical::Center)
+          .width(Length::Fixed(STATUS_WIDTH)),
+      )
+      .push(container(image("")).width(Length::Fixed(ICON_WIDTH)))
+      .push(
+        column![]
+          .push(text(modid))
+          .push(
+            row![]
+              .push(
+                button(text(t!("mod.copy_id")).size(10))
+                  .padding([1, 4])
+                  .style(button::text)
+                  .on_press(Message::Ui(UiMsg::CopyModId {
+                    id: modid.to_string(),
+                  })),
+              )
+              .push(
+                button(text(t!("mod.copy_url")).size(10))
+                  .padding([1, 4])
+                  .style(button::text)
+                  .on_press_maybe((!local_only).then(|| {
+                    Message::Ui(UiMsg::CopyModUrl {
+                      id: modid.to_string(),
+                    })
+                  })),
+              )
+              .push(
+                button(text(t!("mod.install_log")).size(10))
+                  .padding([1, 4])
+                  .style(button::text)
+                  .on_press(Message::Ui(UiMsg::OpenInstallLog {
+                    id: modid.to_string(),
+                  })),
+              ),
+          )
+          .width(Length::Fixed(ID_WIDTH)),
+      )
+      .push(
+        column![]
+          .push(if modr.name.chars().count() > NAME_MAX_CHARS {
+            Element::from(tooltip(
+              name,
+              text(modr.name.as_str()),
+              tooltip::Position::Bottom,
+            ))
+          } else {
+            name.into()
+          })
+          .push(
+            text(modr.author.as_deref().unwrap_or_default())
+              .size(12)
+              .style(text::secondary),
+          )
+          .extend(self.source_label(modid).map(|label| {
+            text(label).size(12).style(text::secondary).into()
+          }))
+          .extend(self.records.records.get(modid).map(|record| {
+            row![]
+              .push(
+                text(t!(
+                  "mod.installed_info",
+                  version = record.version,
+                  files = record.files.len(),
+                  date =
+                    format_timestamp(record.update_time, locale())
+                      .unwrap_or_else(
+                        || t!("mod.date_unknown").to_string()
+                      )
+                ))
+                .size(12)
+                .style(text::secondary),
+              )
+              .extend(
+                record
+                  .archive_sha256()
+                  .map(|hash| self.archive_hash(modid, hash)),
+              )
+              .spacing(5)
+              .align_y(Vertical::Center)
+              .into()
+          }))
+          .extend(
+            self
+              .records
+              .records
+              .get(modid)
+              .map(|record| self.note_editor(modid, record.note())),
+          )
+          .extend(modr.yanked.then(|| {
+            text(format!(
+              "{}{}{}",
+              t!("mod.yanked"),
+              if modr.deprecation_note.is_empty() {
+                String::new()
+              } else {
+                format!(": {}", modr.deprecation_note)
+              },
+              modr
+                .replaced_by
+                .as_ref()
+                .map(|id| t!("mod.replaced_by", id = id))
+                .unwrap_or_default()
+            ))
+            .size(12)
+            .style(text::danger)
+            .into()
+          }))
+          .width(Length::Fill),
+      )
+      .push(
+        container({
+          let recorded_version = self
+            .records
+            .records
+            .get(modid)
+            .map(|x| x.version.to_owned())
+            .unwrap_or_default();
+          let releases = modr.releases();
+          let selected = self.selected_release(modr);
+          if releases.len() > 1 {
+            row![]
+              .push(text(format!("{}->", recorded_version)))
+              .push(
+                pick_list(
+                  releases
+                    .into_iter()
+                    .map(|release| release.version)
+                    .collect::<Vec<_>>(),
+                  Some(selected.version),
+                  |version| {
+                    Message::Ui(UiMsg::SelectModVersion {
+                      id: modid.to_string(),
+                      version: version.to_string(),
+                    })
+                  },
+                )
+                .width(Length::Fill),
+              )
+              .align_y(Vertical::Center)
+          } else {
+            row![].push(text(format!(
+              "{}->{}",
+              recorded_version, selected.version
+            )))
+          }
+        })
+        .width(Length::Fixed(VERSION_WIDTH)),
+      )
+      .push(
+        column![]
+          .push(
+            row![]
+              .extend(state.progress().map(|progress| {
+                progress_bar(0.0..=100., progress * 100.)
+                  .length(Length::Fixed(100.))
+                  .into()
+              }))
+              .push(text(state.label()).size(12).style(match state {
+                RowState::Failed(_) => text::danger,
+                RowState::Done | RowState::UpToDate => text::success,
+                _ => text::default,
+              }))
+              .spacing(5)
+              .align_y(Vertical::Center),
+          )
+          .extend(self.download_speed(modid).map(|speed| {
+            text(speed).size(12).style(text::secondary).into()
+          }))
+          .width(Length::Fixed(PROGRESS_WIDTH)),
+      )
+      .push(
+        row![]
+          .push(
+            button(text(t!("mod.homepage")).size(12))
+              .padding([2, 6])
+              .on_press_maybe(
+                modr
+                  .homepage_url()
+                  .map(|url| Message::Ui(UiMsg::OpenUrl(url))),
+              ),
+          )
+          .extend(match state {
+            RowState::Download(download) => match download.state() {
+              DownloadState::Running { .. } => Some(
+                button(text(t!("mod.pause")).size(12))
+                  .padding([2, 6])
+                  .on_press(Message::Task(TaskMsg::PauseDownload {
+                    id: modid.to_string(),
+                  }))
+                  .into(),
+              ),
+              DownloadState::Paused { .. } => Some(
+                button(text(t!("mod.resume")).size(12))
+                  .padding([2, 6])
+                  .on_press(Message::Task(TaskMsg::ResumeDownload {
+                    id: modid.to_string(),
+                  }))
+                  .into(),
+              ),
+              _ => None,
+            },
+            RowState::Verify | RowState::Install(_) => Some(
+              button(text(t!("mod.cancel_install")).size(12))
+                .padding([2, 6])
+                .on_press(Message::Task(TaskMsg::CancelInstall {
+                  id: modid.to_string(),
+                }))
+                .into(),
+            ),
+            _ => None,
+          })
+          .extend(self.previewable_archive(modid).map(|path| {
+            button(text(t!("mod.preview")).size(12))
+              .padding([2, 6])
+              .on_press(Message::Task(TaskMsg::PreviewArchive {
+                id: modid.to_string(),
+                path: path.to_path_buf(),
+                version: None,
+              }))
+              .into()
+          }))
+          .extend((!compatible).then(|| {
+            row![]
+              .push(text(t!("mod.incompatible")).style(text::danger))
+              .push(
+                checkbox(
+                  t!("mod.force"),
+                  self.compat_overrides.contains(modid),
+                )
+                .on_toggle(|flag| {
+                  if flag {
+                    Message::Ui(UiMsg::AddCompatOverride {
+                      id: modid.to_string(),
+                    })
+                  } else {
+                    Message::Ui(UiMsg::RemoveCompatOverride {
+                      id: modid.to_string(),
+                    })
+                  }
+                }),
+              )
+              .spacing(5)
+              .align_y(Vertical::Center)
+              .into()
+          }))
+          .extend(
+            (self.action_of(modid) == PendingAction::Install
+              && self.is_up_to_date(modid))
+            .then(|| {
+              checkbox(
+                t!("mod.force_reinstall"),
+                self.force_reinstall.contains(modid),
+              )
+              .on_toggle(|force| {
+                Message::Ui(UiMsg::SetForceReinstall {
+                  id: modid.to_string(),
+                  force,
+                })
+              })
+              .into()
+            }),
+          )
+          .push(self.action_selector(modid))
+          .spacing(5)
+          .align_y(Vertical::Center)
+          .width(Length::Fixed(ACTIONS_WIDTH)),
+      )
+      .spacing(5)
+      .width(Length::Fill)
+      .align_y(Vertical::Center);
+    // 失败的任务在行下方显示原因，由用户决定重试还是忽略
+    let row = column![]
+      .push(cells)
+      .extend(self.failed_task_error(modid).map(|error| {
+        row![]
+          .push(
+            text(t!("row.failed", error = error))
+              .size(12)
+              .style(text::danger)
+              .width(Length::Fill),
+          )
+          .push(
+            button(text(t!("row.retry")).size(12))
+              .padding([2, 6])
+              .on_press(Message::Task(TaskMsg::RetryTask {
+                id: modid.to_string(),
+              })),
+          )
+          .push(
+            button(text(t!("row.ignore")).size(12))
+              .padding([2, 6])
+              .on_press(Message::Task(TaskMsg::DismissFailedTask {
+                id: modid.to_string(),
+              })),
+          )
+          .spacing(5)
+          .padding([0, 10])
+          .align_y(Vertical::Center)
+          .into()
+      }))
+      .spacing(2);
+    container(row)
+      .height(Length::Fixed(self.row_height(modid)))
+      .clip(true)
+      .into()
+  }
+}
@@ -0,0 +1,38 @@
+// 下载、安装和卸载在界面中的状态，以及驱动它们的iced任务
+pub mod download;
+pub mod install;
+pub mod operation;
+pub mod uninstall;
+
+use futures::{StreamExt, channel::mpsc, future};
+use iced::task::{Straw, sipper};
+
+use crate::tasks::TaskEvent;
+
+// 核心任务通过回调汇报事件，这里转成iced的Straw
+pub fn forward_events<T, E, F, Fut>(
+  run: F,
+) -> impl Straw<T, TaskEvent, E>
+where
+  F: FnOnce(Box<dyn FnMut(TaskEvent) + Send>) -> Fut + Send + 'static,
+  Fut: Future<Output = Result<T, E>> + Send,
+  T: Send + 'static,
+  E: Send + 'static,
+{
+  sipper(move |mut progress| async move {
+    let (sender, mut receiver) = mpsc::unbounded();
+    let forward = async move {
+      while let Some(event) = receiver.next().await {
+        progress.send(event).await;
+      }
+    };
+    let (res, ()) = future::join(
+      run(Box::new(move |event| {
+        let _ = sender.unbounded_send(event);
+      })),
+      forward,
+    )
+    .await;
+    res
+  })
+}
@@ -0,0 +1,263 @@
+use std::{
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+use iced::{
+  Task,
+  task::{self, Straw},
+};
+use url::Url;
+
+use super::forward_events;
+use crate::{
+  data::{
+    progress::{Progress, SpeedMeter},
+    version::ModVersion,
+  },
+  error::Error,
+  i18n::t,
+  paths::archive_cache_dir,
+  tasks::{
+    TaskEvent, TaskLog, TaskStatus,
+    download::{
+      DownloadRequest, DownloadTimeouts, DownloadedFile, RetryPolicy,
+      cached_download_path, download,
+    },
+  },
+};
+
+#[derive(Debug, Clone)]
+pub struct Download {
+  url: Url,
+  id: String,
+  version: ModVersion,
+  size: Option<u64>,
+  sha256: Option<String>,
+  path: PathBuf,
+  retry_policy: RetryPolicy,
+  timeouts: DownloadTimeouts,
+  state: DownloadState,
+  last_progress: Progress,
+  speed_meter: SpeedMeter,
+  last_error: Option<String>,
+  log: TaskLog,
+}
+
+#[derive(Debug, Clone)]
+pub enum DownloadState {
+  Running {
+    progress: Option<f32>,
+    _task_handle: task::Handle,
+  },
+  // 保留已下载的部分，继续时从这里接着下载
+  Paused {
+    bytes: u64,
+  },
+  Finished,
+  Failed,
+  Ready,
+}
+
+#[derive(Debug, Clone)]
+pub enum DownloadUpdate {
+  Downloading(Progress),
+  Log(String),
+  Finished(Result<DownloadedFile, Error>),
+}
+
+impl Download {
+  pub fn new(
+    id: String,
+    version: ModVersion,
+    url: Url,
+    size: Option<u64>,
+    sha256: Option<String>,
+    retry_policy: RetryPolicy,
+    timeouts: DownloadTimeouts,
+  ) -> Self {
+    Self {
+      path: cached_download_path(&id, &version, &url),
+      url,
+      id,
+      version,
+      size,
+      sha256,
+      retry_policy,
+      timeouts,
+      state: DownloadState::Ready,
+      last_progress: Progress {
+        current: 0,
+        max: None,
+      },
+      speed_meter: SpeedMeter::default(),
+      last_error: None,
+      log: TaskLog::default(),
+    }
+  }
+
+  pub fn with_cache(mut self, cache: bool) -> Self {
+    if let (true, Some(file_name)) = (cache, self.path.file_name()) {
+      self.path = archive_cache_dir().join(file_name);
+    }
+    self
+  }
+
+  pub fn state(&self) -> &DownloadState {
+    &self.state
+  }
+
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn version(&self) -> &ModVersion {
+    &self.version
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  // 字节/秒
+  pub fn speed(&self) -> Option<f64> {
+    self.speed_meter.speed()
+  }
+
+  pub fn log(&self) -> &TaskLog {
+    &self.log
+  }
+
+  pub fn error(&self) -> Option<&str> {
+    self.last_error.as_deref()
+  }
+
+  pub fn is_failed(&self) -> bool {
+    matches!(self.state, DownloadState::Failed)
+  }
+
+  pub fn downloaded(&self) -> u64 {
+    self.last_progress.current
+  }
+
+  // 总大小未知时为None
+  pub fn eta(&self) -> Option<Duration> {
+    self.speed_meter.eta(self.last_progress)
+  }
+
+  pub fn start(&mut self) -> Task<DownloadUpdate> {
+    match self.state {
+      DownloadState::Failed
+      | DownloadState::Ready
+      | DownloadState::Finished => self.spawn(0),
+      DownloadState::Running { .. }
+      | DownloadState::Paused { .. } => Task::none(),
+    }
+  }
+
+  pub fn status(&self) -> TaskStatus {
+    match &self.state {
+      DownloadState::Ready => TaskStatus::Queued,
+      DownloadState::Running { progress, .. } => {
+        TaskStatus::Running {
+          progress: *progress,
+        }
+      }
+      DownloadState::Paused { .. } => TaskStatus::Paused,
+      DownloadState::Failed => TaskStatus::Failed,
+      DownloadState::Finished => TaskStatus::Finished,
+    }
+  }
+
+  // 中止下载任务，但保留已下载的部分
+  pub fn pause(&mut self) {
+    if let DownloadState::Running { .. } = self.state {
+      self.state = DownloadState::Paused {
+        bytes: self.last_progress.current,
+      };
+      self.log.push(t!(
+        "task_log.paused",
+        bytes = self.last_progress.current
+      ));
+    }
+  }
+
+  pub fn resume(&mut self) -> Task<DownloadUpdate> {
+    match self.state {
+      DownloadState::Paused { bytes } => self.spawn(bytes),
+      _ => Task::none(),
+    }
+  }
+
+  fn spawn(&mut self, resume_from: u64) -> Task<DownloadUpdate> {
+    let (task, handle) = Task::sip(
+      download_to(DownloadRequest {
+        url: self.url.to_owned(),
+        path: self.path.to_owned(),
+        expected_size: self.size,
+        sha256: self.sha256.to_owned(),
+        retry_policy: self.retry_policy,
+        timeouts: self.timeouts,
+        resume_from,
+      }),
+      |event| match event {
+        TaskEvent::Progress(progress) => {
+          DownloadUpdate::Downloading(progress)
+        }
+        TaskEvent::Log(line) => DownloadUpdate::Log(line),
+      },
+      DownloadUpdate::Finished,
+    )
+    .abortable();
+    self.log.push(if resume_from > 0 {
+      t!("task_log.resume", offset = resume_from, url = self.url)
+    } else {
+      t!("task_log.start", url = self.url)
+    });
+
+    self.state = DownloadState::Running {
+      progress: Some(0.),
+      _task_handle: handle.abort_on_drop(),
+    };
+    self.speed_meter = SpeedMeter::default();
+
+    task
+  }
+
+  pub fn update(&mut self, update: DownloadUpdate) {
+    if let DownloadState::Running { progress, .. } = &mut self.state {
+      match update {
+        DownloadUpdate::Downloading(new_progress) => {
+          self.last_progress = new_progress;
+          self
+            .speed_meter
+            .record(Instant::now(), new_progress.current);
+          *progress = new_progress.fraction();
+        }
+        DownloadUpdate::Log(line) => self.log.push(line),
+        DownloadUpdate::Finished(res) => {
+          self.state = match res {
+            Ok(file) => {
+              self.log.push(t!(
+                "task_log.downloaded",
+                path = file.path.display()
+              ));
+              DownloadState::Finished
+            }
+            Err(err) => {
+              self.log.error(&err);
+              self.last_error = Some(err.to_string());
+              DownloadState::Failed
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+fn download_to(
+  request: DownloadRequest,
+) -> impl Straw<DownloadedFile, TaskEvent, Error> {
+  forward_events(move |on_event| download(request, on_event))
+}
@@ -0,0 +1,261 @@
+use std::path::{Path, PathBuf};
+
+use iced::{
+  Task,
+  task::{self, Straw},
+};
+
+use super::forward_events;
+use crate::{
+  data::{
+    archive::ArchiveType, progress::Progress, version::ModVersion,
+  },
+  error::Error,
+  i18n::t,
+  mod_manager::{ModManager, Record},
+  tasks::{
+    TaskEvent, TaskLog, TaskStatus,
+    install::{discard_archive, install},
+  },
+};
+
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct Install {
+  id: String,
+  path: PathBuf,
+  version: ModVersion,
+  state: InstallState,
+  ty: Option<ArchiveType>,
+  attempts: u32,
+  last_error: Option<String>,
+  log: TaskLog,
+}
+
+#[derive(Debug, Clone)]
+pub enum InstallState {
+  Running {
+    progress: Option<f32>,
+    task_handle: task::Handle,
+    // 取消时任务连同其中的ModManager一起被丢弃，需要留一份归还
+    mod_manager: ModManager,
+  },
+  Failed,
+  Finished,
+  Ready,
+}
+
+#[derive(Debug, Clone)]
+pub enum InstallUpdate {
+  Running(Progress),
+  Log(String),
+  // 成功时带上写入的安装记录，读取不到时为None
+  Finished((Result<Option<Record>, Error>, ModManager)),
+}
+
+impl Install {
+  pub fn new(
+    id: &str,
+    path: &Path,
+    version: &ModVersion,
+    ty: Option<ArchiveType>,
+  ) -> Self {
+    Self {
+      id: id.to_string(),
+      path: path.to_path_buf(),
+      version: version.to_owned(),
+      state: InstallState::Ready,
+      ty,
+      attempts: 0,
+      last_error: None,
+      log: TaskLog::default(),
+    }
+  }
+
+  pub fn log(&self) -> &TaskLog {
+    &self.log
+  }
+
+  pub fn status(&self) -> TaskStatus {
+    match &self.state {
+      InstallState::Ready => TaskStatus::Queued,
+      InstallState::Running { progress, .. } => TaskStatus::Running {
+        progress: *progress,
+      },
+      InstallState::Failed => TaskStatus::Failed,
+      InstallState::Finished => TaskStatus::Finished,
+    }
+  }
+
+  // 最近一次失败的原因，重试中的任务也会保留
+  pub fn error(&self) -> Option<&str> {
+    self.last_error.as_deref()
+  }
+
+  // 失败的任务会被重新排队，直到用完尝试次数
+  pub fn can_start(&self) -> bool {
+    match self.state {
+      InstallState::Ready => true,
+      InstallState::Failed => self.attempts < MAX_ATTEMPTS,
+      _ => false,
+    }
+  }
+
+  // 用完尝试次数，留在队列里等用户重试或忽略
+  pub fn is_failed(&self) -> bool {
+    matches!(self.state, InstallState::Failed) && !self.can_start()
+  }
+
+  pub fn ty(&self) -> Option<ArchiveType> {
+    self.ty.to_owned()
+  }
+
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn version(&self) -> &ModVersion {
+    &self.version
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn start(
+    &mut self,
+    mod_manager: ModManager,
+  ) -> Task<InstallUpdate> {
+    match self.state {
+      InstallState::Failed
+      | InstallState::Finished
+      | InstallState::Ready => {
+        let (task, handle) = Task::sip(
+          install_mod(
+            self.id.to_owned(),
+            self.path.to_owned(),
+            self.version.to_owned(),
+            self.ty.to_owned(),
+            mod_manager.to_owned(),
+          ),
+          |event| match event {
+            TaskEvent::Progress(progress) => {
+              InstallUpdate::Running(progress)
+            }
+            TaskEvent::Log(line) => InstallUpdate::Log(line),
+          },
+          |res| {
+            InstallUpdate::Finished(match res {
+              Ok((record, mod_manager)) => (Ok(record), mod_manager),
+              Err((err, mod_manager)) => (Err(err), mod_manager),
+            })
+          },
+        )
+        .abortable();
+        self.attempts += 1;
+        self.log.push(t!(
+          "task_log.install_attempt",
+          attempt = self.attempts,
+          path = self.path.display()
+        ));
+        self.state = InstallState::Running {
+          progress: Some(0.),
+          task_handle: handle,
+          mod_manager,
+        };
+        task
+      }
+      InstallState::Running { .. } => Task::none(),
+    }
+  }
+
+  // 中止正在进行的安装并清理已经解压出来的文件和压缩包
+  pub fn cancel(&mut self) -> Task<ModManager> {
+    let InstallState::Running {
+      task_handle,
+      mod_manager,
+      ..
+    } = &self.state
+    else {
+      return Task::future(discard_archive(self.path.to_owned()))
+        .discard();
+    };
+    task_handle.abort();
+    self.log.push(t!("task.cancelled"));
+    let mut mod_manager = mod_manager.to_owned();
+    let id = self.id.to_owned();
+    let path = self.path.to_owned();
+    self.state = InstallState::Ready;
+    Task::future(async move {
+      if let Err(err) = mod_manager
+        .rollback_zip_mod(path.as_path(), id.as_str())
+        .await
+      {
+        tracing::warn!(%id, %err, "failed to roll back install");
+      }
+      discard_archive(path).await;
+      mod_manager
+    })
+  }
+
+  pub fn update(&mut self, update: InstallUpdate) {
+    if let InstallState::Running { progress, .. } = &mut self.state {
+      match update {
+        InstallUpdate::Running(new_progress) => {
+          *progress = new_progress.fraction();
+        }
+        InstallUpdate::Log(line) => self.log.push(line),
+        InstallUpdate::Finished((res, ..)) => {
+          self.state = match res {
+            Ok(_) => {
+              self.log.push(t!("task_log.installed"));
+              InstallState::Finished
+            }
+            Err(err) => {
+              self.log.error(&err);
+              self.last_error = Some(err.to_string());
+              InstallState::Failed
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+fn install_mod(
+  id: String,
+  path: PathBuf,
+  version: ModVersion,
+  ty: Option<ArchiveType>,
+  mut mod_manager: ModManager,
+) -> impl Straw<(Option<Record>, ModManager), TaskEvent, (Error, ModManager)>
+{
+  forward_events(move |on_event| async move {
+    // 安装失败时保留缓存中的压缩包，重试时不用重新下载
+    match install(
+      &mut mod_manager,
+      &id,
+      &version,
+      &path,
+      ty,
+      on_event,
+    )
+    .await
+    {
+      Ok(_) => {
+        discard_archive(path).await;
+        let record = mod_manager
+          .record(&id)
+          .await
+          .inspect_err(|err| {
+            tracing::warn!(%id, %err, "failed to read record");
+          })
+          .unwrap_or_default();
+        Ok((record, mod_manager))
+      }
+      Err(err) => Err((err, mod_manager)),
+    }
+  })
+}
@@ -0,0 +1,554 @@
+// 一个模组从下载、检查到解压的完整流程。失败时停在出错的阶段，
+// 重试时从这个阶段继续，已经下载好的压缩包不用重新下载
+use std::path::{Path, PathBuf};
+
+use iced::Task;
+
+use super::{
+  download::{Download, DownloadUpdate},
+  install::{Install, InstallUpdate},
+};
+use crate::{
+  data::{archive::ArchiveType, version::ModVersion},
+  error::Error,
+  i18n::t,
+  mod_manager::ModManager,
+  tasks::{
+    TaskLog, TaskStatus,
+    install::{discard_archive, verify_archive},
+  },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+  Download,
+  // 确定格式并检查压缩包是否完整
+  Verify,
+  // 解压到res_mods，需要等调度器交出ModManager
+  Extract,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VerifyState {
+  Ready,
+  Running,
+  Failed,
+}
+
+#[derive(Debug, Clone)]
+pub enum OperationUpdate {
+  Download(DownloadUpdate),
+  // 成功时是确定下来的格式
+  Verified(Result<ArchiveType, Error>),
+  Install(InstallUpdate),
+}
+
+#[derive(Debug, Clone)]
+pub struct Operation {
+  id: String,
+  version: ModVersion,
+  // 压缩包的位置，下载完成前是下载的目标
+  path: PathBuf,
+  // 检查时的格式提示，来自registry或下载的文件名
+  ty: Option<ArchiveType>,
+  phase: Phase,
+  // 本地压缩包没有下载阶段
+  download: Option<Download>,
+  verify: VerifyState,
+  verify_error: Option<String>,
+  // 检查通过后才有
+  install: Option<Install>,
+  // 已经结束的阶段的日志
+  log: TaskLog,
+}
+
+impl Operation {
+  // 从registry下载，`ty`是registry声明的格式
+  pub fn new(download: Download, ty: Option<ArchiveType>) -> Self {
+    Self {
+      id: download.id().to_owned(),
+      version: download.version().to_owned(),
+      path: download.path().to_path_buf(),
+      ty,
+      phase: Phase::Download,
+      download: Some(download),
+      verify: VerifyState::Ready,
+      verify_error: None,
+      install: None,
+      log: TaskLog::default(),
+    }
+  }
+
+  // 已经在本地的压缩包，从检查开始
+  pub fn from_archive(
+    id: &str,
+    version: &ModVersion,
+    path: &Path,
+    ty: Option<ArchiveType>,
+  ) -> Self {
+    Self {
+      id: id.to_string(),
+      version: version.to_owned(),
+      path: path.to_path_buf(),
+      ty,
+      phase: Phase::Verify,
+      download: None,
+      verify: VerifyState::Ready,
+      verify_error: None,
+      install: None,
+      log: TaskLog::default(),
+    }
+  }
+
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn version(&self) -> &ModVersion {
+    &self.version
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn phase(&self) -> Phase {
+    self.phase
+  }
+
+  // 只在下载阶段有
+  pub fn download(&self) -> Option<&Download> {
+    self
+      .download
+      .as_ref()
+      .filter(|_| self.phase == Phase::Download)
+  }
+
+  // 只在解压阶段有
+  pub fn install(&self) -> Option<&Install> {
+    self
+      .install
+      .as_ref()
+      .filter(|_| self.phase == Phase::Extract)
+  }
+
+  pub fn label(&self) -> &'static str {
+    match self.phase {
+      Phase::Download => t!("task.download"),
+      Phase::Verify => t!("task.verify"),
+      Phase::Extract => t!("task.install"),
+    }
+  }
+
+  pub fn status(&self) -> TaskStatus {
+    if let Some(download) = self.download() {
+      return download.status();
+    }
+    if let Some(install) = self.install() {
+      return install.status();
+    }
+    match self.verify {
+      VerifyState::Ready => TaskStatus::Queued,
+      VerifyState::Running => TaskStatus::Running { progress: None },
+      VerifyState::Failed => TaskStatus::Failed,
+    }
+  }
+
+  // 当前阶段最近一次失败的原因
+  pub fn error(&self) -> Option<&str> {
+    match self.phase {
+      Phase::Download => self.download().and_then(Download::error),
+      Phase::Verify => self.verify_error.as_deref(),
+      Phase::Extract => self.install().and_then(Install::error),
+    }
+  }
+
+  // 停在出错的阶段，等用户重试或忽略
+  pub fn is_failed(&self) -> bool {
+    match self.phase {
+      Phase::Download => {
+        self.download().is_some_and(Download::is_failed)
+      }
+      Phase::Verify => self.verify == VerifyState::Failed,
+      Phase::Extract => {
+        self.install().is_some_and(Install::is_failed)
+      }
+    }
+  }
+
+  // 解压阶段等待或需要重新开始，调度器交出ModManager时检查
+  pub fn can_extract(&self) -> bool {
+    self.install().is_some_and(Install::can_start)
+  }
+
+  pub fn downloaded(&self) -> u64 {
+    self.download.as_ref().map_or(0, Download::downloaded)
+  }
+
+  // 各阶段的日志按顺序接在一起
+  pub fn log(&self) -> TaskLog {
+    let mut log = self.log.to_owned();
+    if let Some(current) = self
+      .download()
+      .map(Download::log)
+      .or_else(|| self.install().map(Install::log))
+    {
+      log.append(current);
+    }
+    log
+  }
+
+  // 开始下载或检查；解压由App在拿到ModManager后调用extract
+  pub fn start(&mut self) -> Task<OperationUpdate> {
+    match self.phase {
+      Phase::Download => match self.download.as_mut() {
+        Some(download) => {
+          download.start().map(OperationUpdate::Download)
+        }
+        None => Task::none(),
+      },
+      Phase::Verify if self.verify != VerifyState::Running => {
+        self.verify = VerifyState::Running;
+        self
+          .log
+          .push(t!("task_log.verifying", path = self.path.display()));
+        let path = self.path.to_owned();
+        let ty = self.ty.to_owned();
+        Task::perform(
+          async move { verify_archive(&path, ty).await },
+          |res| OperationUpdate::Verified(res.map_err(Error::from)),
+        )
+      }
+      Phase::Verify | Phase::Extract => Task::none(),
+    }
+  }
+
+  pub fn extract(
+    &mut self,
+    mod_manager: ModManager,
+  ) -> Task<OperationUpdate> {
+    match self
+      .install
+      .as_mut()
+      .filter(|_| self.phase == Phase::Extract)
+    {
+      Some(install) => {
+        install.start(mod_manager).map(OperationUpdate::Install)
+      }
+      None => Task::none(),
+    }
+  }
+
+  pub fn pause(&mut self) {
+    if let Some(download) = self
+      .download
+      .as_mut()
+      .filter(|_| self.phase == Phase::Download)
+    {
+      download.pause();
+    }
+  }
+
+  pub fn resume(&mut self) -> Task<OperationUpdate> {
+    match self
+      .download
+      .as_mut()
+      .filter(|_| self.phase == Phase::Download)
+    {
+      Some(download) => {
+        download.resume().map(OperationUpdate::Download)
+      }
+      None => Task::none(),
+    }
+  }
+
+  // 取消检查或解压，解压中的还要回滚并归还ModManager
+  pub fn cancel(&mut self) -> Task<ModManager> {
+    match self
+      .install
+      .as_mut()
+      .filter(|_| self.phase == Phase::Extract)
+    {
+      Some(install) => install.cancel(),
+      None => {
+        Task::future(discard_archive(self.path.to_owned())).discard()
+      }
+    }
+  }
+
+  // 从失败的阶段重新开始。下载失败或压缩包已经不在时返回false，
+  // 只能按registry中的信息重新下载
+  pub fn retry(&mut self) -> bool {
+    if self.phase == Phase::Download || !self.path.is_file() {
+      return false;
+    }
+    self.log.push(t!("task_log.retry"));
+    match self.phase {
+      Phase::Verify => {
+        self.verify = VerifyState::Ready;
+        self.verify_error = None;
+      }
+      _ => {
+        if let Some(install) = self.install.take() {
+          self.log.append(install.log());
+          self.install = Some(Install::new(
+            &self.id,
+            &self.path,
+            &self.version,
+            install.ty(),
+          ));
+        }
+      }
+    }
+    true
+  }
+
+  // 当前阶段结束时进入下一个阶段，失败时停在原地
+  pub fn update(&mut self, update: OperationUpdate) {
+    match update {
+      OperationUpdate::Download(update) => {
+        let Some(download) = self
+          .download
+          .as_mut()
+          .filter(|_| self.phase == Phase::Download)
+        else {
+          return;
+        };
+        download.update(update.to_owned());
+        // 暂停后迟到的结果不算完成
+        if let (
+          DownloadUpdate::Finished(Ok(file)),
+          TaskStatus::Finished,
+        ) = (update, download.status())
+        {
+          self.log.append(download.log());
+          // registry没有声明格式时用文件名作为提示
+          self.ty = self.ty.take().or_else(|| {
+            file
+              .file_name
+              .as_deref()
+              .and_then(ArchiveType::from_file_name)
+          });
+          self.path = file.path;
+          self.phase = Phase::Verify;
+        }
+      }
+      OperationUpdate::Verified(res) => {
+        if self.phase != Phase::Verify
+          || self.verify != VerifyState::Running
+        {
+          return;
+        }
+        match res {
+          Ok(ty) => {
+            self.log.push(t!("task_log.archive_type", ty = ty));
+            self.install = Some(Install::new(
+              &self.id,
+              &self.path,
+              &self.version,
+              Some(ty),
+            ));
+            self.phase = Phase::Extract;
+          }
+          Err(err) => {
+            self.log.error(&err);
+            self.verify_error = Some(err.to_string());
+            self.verify = VerifyState::Failed;
+          }
+        }
+      }
+      OperationUpdate::Install(update) => {
+        if let Some(install) = self
+          .install
+          .as_mut()
+          .filter(|_| self.phase == Phase::Extract)
+        {
+          install.update(update);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tasks::download::DownloadedFile;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "kmm-operation-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  fn archive(name: &str) -> PathBuf {
+    let path = temp_dir(name).join("example.zip");
+    std::fs::write(&path, "not really a zip").unwrap();
+    path
+  }
+
+  fn downloading() -> Operation {
+    let download = Download::new(
+      "example".to_string(),
+      "1.0".into(),
+      "http://127.0.0.1:9/example.zip".parse().unwrap(),
+      None,
+      None,
+      Default::default(),
+      Default::default(),
+    );
+    let mut operation = Operation::new(download, None);
+    let _ = operation.start();
+    operation
+  }
+
+  fn verifying(path: &Path) -> Operation {
+    let mut operation =
+      Operation::from_archive("example", &"1.0".into(), path, None);
+    let _ = operation.start();
+    operation
+  }
+
+  fn invalid() -> Error {
+    Error::NotFound("example".to_string())
+  }
+
+  async fn mod_manager(name: &str) -> ModManager {
+    let dir = temp_dir(name);
+    std::fs::create_dir_all(dir.join("bin/1000/res_mods")).unwrap();
+    ModManager::try_from_game_dir(&dir).await.unwrap()
+  }
+
+  #[test]
+  fn download_failure_needs_redownload() {
+    let mut operation = downloading();
+    assert_eq!(
+      operation.status(),
+      TaskStatus::Running { progress: Some(0.) }
+    );
+    operation.update(OperationUpdate::Download(
+      DownloadUpdate::Finished(Err(invalid())),
+    ));
+    assert_eq!(operation.phase(), Phase::Download);
+    assert!(operation.is_failed());
+    assert!(operation.error().is_some());
+    assert!(!operation.retry());
+  }
+
+  #[test]
+  fn download_moves_on_to_verify() {
+    let path = archive("downloaded");
+    let mut operation = downloading();
+    operation.update(OperationUpdate::Download(
+      DownloadUpdate::Finished(Ok(DownloadedFile {
+        path: path.to_owned(),
+        file_name: Some("example.zip".to_string()),
+      })),
+    ));
+    assert_eq!(operation.phase(), Phase::Verify);
+    assert_eq!(operation.path(), path);
+    assert_eq!(operation.ty, Some(ArchiveType::Zip));
+    assert_eq!(operation.status(), TaskStatus::Queued);
+    assert!(operation.download().is_none());
+    let _ = operation.start();
+    assert_eq!(
+      operation.status(),
+      TaskStatus::Running { progress: None }
+    );
+  }
+
+  // 检查失败后重新检查同一个文件，不用重新下载
+  #[test]
+  fn verify_failure_resumes_verify() {
+    let path = archive("verify-failed");
+    let mut operation = verifying(&path);
+    operation.update(OperationUpdate::Verified(Err(invalid())));
+    assert_eq!(operation.phase(), Phase::Verify);
+    assert!(operation.is_failed());
+    assert!(operation.retry());
+    assert!(!operation.is_failed());
+    assert_eq!(operation.status(), TaskStatus::Queued);
+    assert_eq!(operation.phase(), Phase::Verify);
+
+    // 压缩包被删掉后只能重新下载
+    let _ = operation.start();
+    operation.update(OperationUpdate::Verified(Err(invalid())));
+    std::fs::remove_file(&path).unwrap();
+    assert!(!operation.retry());
+    assert!(operation.is_failed());
+  }
+
+  #[test]
+  fn late_verify_result_is_ignored() {
+    let path = archive("verify-late");
+    let mut operation =
+      Operation::from_archive("example", &"1.0".into(), &path, None);
+    operation.update(OperationUpdate::Verified(Ok(ArchiveType::Zip)));
+    assert_eq!(operation.phase(), Phase::Verify);
+  }
+
+  #[test]
+  fn verified_archive_waits_for_manager() {
+    let path = archive("verified");
+    let mut operation = verifying(&path);
+    operation.update(OperationUpdate::Verified(Ok(ArchiveType::Zip)));
+    assert_eq!(operation.phase(), Phase::Extract);
+    assert_eq!(operation.status(), TaskStatus::Queued);
+    assert!(operation.can_extract());
+    assert_eq!(
+      operation.install().unwrap().ty(),
+      Some(ArchiveType::Zip)
+    );
+  }
+
+  // 解压失败先自动重试，用完次数后停下，重试时仍从解压开始
+  #[tokio::test]
+  async fn extract_failure_resumes_extract() {
+    let path = archive("extract-failed");
+    let mod_manager = mod_manager("extract-failed-game").await;
+    let mut operation = verifying(&path);
+    operation.update(OperationUpdate::Verified(Ok(ArchiveType::Zip)));
+    while operation.can_extract() {
+      let _ = operation.extract(mod_manager.to_owned());
+      assert!(matches!(
+        operation.status(),
+        TaskStatus::Running { .. }
+      ));
+      operation.update(OperationUpdate::Install(
+        InstallUpdate::Finished((
+          Err(invalid()),
+          mod_manager.to_owned(),
+        )),
+      ));
+    }
+    assert!(operation.is_failed());
+    assert_eq!(operation.phase(), Phase::Extract);
+    // 检查阶段的日志保留在同一个任务里
+    let verifying = t!("task_log.verifying", path = path.display());
+    assert!(operation.log().to_string().contains(&verifying));
+
+    assert!(operation.retry());
+    assert_eq!(operation.phase(), Phase::Extract);
+    assert!(operation.can_extract());
+    assert!(!operation.is_failed());
+  }
+
+  #[tokio::test]
+  async fn extract_success_finishes() {
+    let path = archive("extract-done");
+    let mod_manager = mod_manager("extract-done-game").await;
+    let mut operation = verifying(&path);
+    operation.update(OperationUpdate::Verified(Ok(ArchiveType::Zip)));
+    let _ = operation.extract(mod_manager.to_owned());
+    operation.update(OperationUpdate::Install(
+      InstallUpdate::Finished((Ok(None), mod_manager)),
+    ));
+    assert_eq!(operation.status(), TaskStatus::Finished);
+    assert!(!operation.can_extract());
+  }
+}
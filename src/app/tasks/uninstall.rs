@@ -0,0 +1,179 @@
+use iced::{
+  Task,
+  task::{self, Straw, sipper},
+};
+
+use crate::{
+  data::progress::Progress,
+  error::Error,
+  i18n::t,
+  mod_manager::ModManager,
+  tasks::{TaskEvent, TaskLog, TaskStatus},
+};
+
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct Uninstall {
+  id: String,
+  state: UninstallState,
+  attempts: u32,
+  last_error: Option<String>,
+  log: TaskLog,
+}
+
+#[derive(Debug, Clone)]
+pub enum UninstallState {
+  Running {
+    progress: Option<f32>,
+    _task_handle: task::Handle,
+  },
+  Failed,
+  Finished,
+  Ready,
+}
+
+#[derive(Debug, Clone)]
+pub enum UninstallUpdate {
+  Running(Progress),
+  Log(String),
+  Finished((Result<(), Error>, ModManager)),
+}
+
+impl Uninstall {
+  pub fn new(id: &str) -> Self {
+    Self {
+      id: id.to_string(),
+      state: UninstallState::Ready,
+      attempts: 0,
+      last_error: None,
+      log: TaskLog::default(),
+    }
+  }
+
+  pub fn log(&self) -> &TaskLog {
+    &self.log
+  }
+
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn status(&self) -> TaskStatus {
+    match &self.state {
+      UninstallState::Ready => TaskStatus::Queued,
+      UninstallState::Running { progress, .. } => {
+        TaskStatus::Running {
+          progress: *progress,
+        }
+      }
+      UninstallState::Failed => TaskStatus::Failed,
+      UninstallState::Finished => TaskStatus::Finished,
+    }
+  }
+
+  // 最近一次失败的原因，重试中的任务也会保留
+  pub fn error(&self) -> Option<&str> {
+    self.last_error.as_deref()
+  }
+
+  // 失败的任务会被重新排队，直到用完尝试次数
+  pub fn can_start(&self) -> bool {
+    match self.state {
+      UninstallState::Ready => true,
+      UninstallState::Failed => self.attempts < MAX_ATTEMPTS,
+      _ => false,
+    }
+  }
+
+  // 用完尝试次数，留在队列里等用户重试或忽略
+  pub fn is_failed(&self) -> bool {
+    matches!(self.state, UninstallState::Failed) && !self.can_start()
+  }
+
+  pub fn start(
+    &mut self,
+    mod_manager: ModManager,
+  ) -> Task<UninstallUpdate> {
+    match self.state {
+      UninstallState::Failed
+      | UninstallState::Finished
+      | UninstallState::Ready => {
+        let (task, handle) = Task::sip(
+          uninstall_mod(self.id.to_owned(), mod_manager),
+          |event| match event {
+            TaskEvent::Progress(progress) => {
+              UninstallUpdate::Running(progress)
+            }
+            TaskEvent::Log(line) => UninstallUpdate::Log(line),
+          },
+          |res| match res {
+            Err((err, mod_manager)) => {
+              UninstallUpdate::Finished((Err(err), mod_manager))
+            }
+            Ok(mod_manager) => {
+              UninstallUpdate::Finished((Ok(()), mod_manager))
+            }
+          },
+        )
+        .abortable();
+        self.attempts += 1;
+        self.log.push(t!(
+          "task_log.uninstall_attempt",
+          attempt = self.attempts
+        ));
+        self.state = UninstallState::Running {
+          progress: Some(0.),
+          _task_handle: handle,
+        };
+        task
+      }
+      UninstallState::Running { .. } => Task::none(),
+    }
+  }
+
+  pub fn update(&mut self, update: UninstallUpdate) {
+    if let UninstallState::Running { progress, .. } = &mut self.state
+    {
+      match update {
+        UninstallUpdate::Running(new_progress) => {
+          *progress = new_progress.fraction();
+        }
+        UninstallUpdate::Log(line) => self.log.push(line),
+        UninstallUpdate::Finished((res, ..)) => {
+          self.state = match res {
+            Ok(()) => {
+              self.log.push(t!("task_log.uninstalled"));
+              UninstallState::Finished
+            }
+            Err(err) => {
+              self.log.error(&err);
+              self.last_error = Some(err.to_string());
+              UninstallState::Failed
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+fn uninstall_mod(
+  id: String,
+  mut mod_manager: ModManager,
+) -> impl Straw<ModManager, TaskEvent, (Error, ModManager)> {
+  sipper(async move |mut progress| {
+    let removed = mod_manager
+      .uninstall_mod(&id)
+      .await
+      .map_err(|err| (Error::from(err), mod_manager.to_owned()))?;
+    if !removed {
+      progress
+        .send(TaskEvent::Log(
+          t!("task_log.not_installed").to_string(),
+        ))
+        .await;
+    }
+    Ok(mod_manager)
+  })
+}
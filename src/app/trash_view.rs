@@ -0,0 +1,108 @@
+use super::App;
+
+use iced::{
+  Element, Length,
+  alignment::Vertical,
+  widget::{
+    button, column, container, container::bordered_box, row,
+    scrollable, text,
+  },
+};
+
+use crate::{
+  format::{format_bytes, format_timestamp},
+  i18n::{locale, t},
+  messages::{GameMsg, Message},
+  trash::TrashEntry,
+};
+
+impl App {
+  fn trash_row<'a>(
+    &'a self,
+    entry: &'a TrashEntry,
+  ) -> Element<'a, Message> {
+    // 同一模组已经装回去时只能先卸载
+    let installed = self.records.records.contains_key(&entry.id);
+    row![]
+      .push(
+        column![]
+          .push(text(format!("{} {}", entry.id, entry.version)))
+          .push(
+            text(format!(
+              "{}  {}",
+              format_timestamp(entry.trashed_at, locale())
+                .unwrap_or_default(),
+              format_bytes(entry.size)
+            ))
+            .size(12)
+            .style(text::secondary),
+          )
+          .width(Length::Fill),
+      )
+      .push(
+        button(text(t!("trash.restore")).size(12))
+          .padding([2, 6])
+          .on_press_maybe((!installed).then(|| {
+            Message::Game(GameMsg::RestoreTrashed(
+              entry.path.to_owned(),
+            ))
+          })),
+      )
+      .spacing(10)
+      .align_y(Vertical::Center)
+      .into()
+  }
+
+  pub(super) fn trash_dialog<'a>(
+    &'a self,
+    entries: &'a [TrashEntry],
+  ) -> Element<'a, Message> {
+    let content: Element<'a, Message> = if entries.is_empty() {
+      text(t!("trash.empty_hint")).into()
+    } else {
+      scrollable(
+        column![]
+          .extend(entries.iter().map(|entry| self.trash_row(entry)))
+          .spacing(10)
+          .padding([0, 10]),
+      )
+      .height(Length::Fixed(360.))
+      .into()
+    };
+    let size = entries.iter().map(|entry| entry.size).sum();
+    container(
+      column![]
+        .push(text(t!("trash.title")).size(18))
+        .push(text(t!("trash.hint")).size(12).style(text::secondary))
+        .push(content)
+        .push(
+          container(
+            row![]
+              .push(
+                button(text(t!(
+                  "trash.empty",
+                  size = format_bytes(size)
+                )))
+                .style(button::danger)
+                .on_press_maybe(
+                  (!entries.is_empty())
+                    .then_some(Message::Game(GameMsg::EmptyTrash)),
+                ),
+              )
+              .push(
+                button(t!("preview.close"))
+                  .style(button::secondary)
+                  .on_press(Message::Game(GameMsg::CloseTrash)),
+              )
+              .spacing(10),
+          )
+          .align_right(Length::Fill),
+        )
+        .spacing(10),
+    )
+    .style(bordered_box)
+    .padding(20)
+    .width(Length::Fixed(600.))
+    .into()
+  }
+}
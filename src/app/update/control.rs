@@ -0,0 +1,89 @@
+use crate::app::App;
+use crate::app::tasks::operation::Phase;
+
+use serde_json::{Value, json};
+
+use crate::{
+  control::{Call, Method},
+  error::Error,
+  messages::{Message, TaskMsg},
+};
+use iced::Task;
+
+impl App {
+  pub(super) fn handle_control(
+    &mut self,
+    call: Call,
+  ) -> Task<Message> {
+    match call.method.to_owned() {
+      Method::ListInstalled => {
+        let mut installed = self
+          .records
+          .records
+          .iter()
+          .map(|(id, record)| {
+            json!({ "id": id, "version": record.version })
+          })
+          .collect::<Vec<_>>();
+        installed
+          .sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+        call.reply(Ok(Value::Array(installed)));
+        Task::none()
+      }
+      Method::ListAvailable => {
+        let available = self
+          .indexed_mods()
+          .into_iter()
+          .filter(|modr| self.request_mod(&modr.id).is_some())
+          .map(|modr| {
+            json!({
+              "id": modr.id,
+              "name": modr.name,
+              "version": modr.version,
+            })
+          })
+          .collect();
+        call.reply(Ok(Value::Array(available)));
+        Task::none()
+      }
+      // 和在界面中勾选安装一样，从registry下载选中的版本
+      Method::Install { id } => {
+        let (requests, missing) =
+          self.download_requests(vec![id.to_owned()]);
+        if !missing.is_empty() {
+          call.reply(Err(Error::NotFound(id).to_string()));
+          return Task::none();
+        }
+        call.reply(Ok(json!({ "queued": id })));
+        Task::batch(requests.into_iter().map(Task::done))
+      }
+      Method::Uninstall { id } => {
+        if !self.records.records.contains_key(&id) {
+          call.reply(Err(Error::NotInstalled(id).to_string()));
+          return Task::none();
+        }
+        call.reply(Ok(json!({ "queued": id })));
+        Task::done(Message::Task(TaskMsg::UninstallMod { id }))
+      }
+      Method::Status => {
+        call.reply(Ok(json!({
+          "game_dir": self.game_dir_path,
+          "game_version": self.game_version,
+          "registries": self.registries.len(),
+          "downloads": self
+            .operations
+            .iter()
+            .filter(|x| x.phase() == Phase::Download)
+            .count(),
+          "installs": self
+            .operations
+            .iter()
+            .filter(|x| x.phase() != Phase::Download)
+            .count(),
+          "uninstalls": self.uninstalls.len(),
+        })));
+        Task::none()
+      }
+    }
+  }
+}
@@ -0,0 +1,800 @@
+use crate::app::App;
+
+use std::{collections::HashSet, path::PathBuf, time::Instant};
+
+use crate::detect;
+use crate::format::format_bytes;
+use crate::i18n::t;
+use crate::launch;
+use crate::mod_manager::{ModManager, Records};
+use crate::paths::{logs_dir, pending_queue_path, trash_dir};
+use crate::trash;
+use crate::{
+  data::{
+    batch::PendingAction, health::ADOPTED_ID, pending::PendingQueue,
+    registry::Mod, scheduler::Operation, settings::Settings,
+    status::StatusLine,
+  },
+  messages::{GameMsg, Message, SettingsMsg, TaskMsg, UiMsg},
+};
+use iced::Task;
+
+impl App {
+  pub(super) fn handle_game(
+    &mut self,
+    message: GameMsg,
+  ) -> Task<Message> {
+    match message {
+      GameMsg::UpdateRecords { mod_manager } => Task::perform(
+        async move {
+          let records =
+            mod_manager.records().await.unwrap_or_default();
+          (mod_manager, records)
+        },
+        |(mod_manager, records)| {
+          Message::Game(GameMsg::RecordsUpdated {
+            mod_manager,
+            records,
+          })
+        },
+      ),
+      GameMsg::QueueUpdateRecords => {
+        let next = self.scheduler.enqueue(Operation::RefreshRecords);
+        self.dispatch(next)
+      }
+      GameMsg::RecordsUpdated {
+        mod_manager,
+        records,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        self.local_mods = records
+          .records
+          .iter()
+          .map(|(id, record)| Mod::local(id, &record.version))
+          .collect();
+        // 刷新记录时当前模组的刷新已经合并进来
+        self.current_mods = records.records.keys().cloned().collect();
+        // 每次读取记录都会检查，变化时才提示
+        let build = mod_manager.build();
+        let mismatch = records
+          .build_mismatch(build)
+          .map(|recorded| (recorded, build));
+        let notice = mismatch
+          .filter(|_| mismatch != self.build_mismatch)
+          .map(|(recorded, build)| {
+            Task::done(Message::Ui(UiMsg::Warning {
+              title: t!("records.build_mismatch").to_string(),
+              text: t!(
+                "records.build_mismatch_text",
+                recorded = recorded,
+                build = build
+              ),
+            }))
+          });
+        self.build_mismatch = mismatch;
+        self.records = records;
+        self.records_loaded = true;
+        self.refresh_mod_index();
+        // 安装和卸载之后都会刷新记录，顺便更新剩余空间
+        Task::batch(notice.into_iter().chain([
+          Task::done(Message::Game(GameMsg::ModManagerReady {
+            mod_manager,
+          })),
+          Task::done(Message::Task(TaskMsg::RefreshFreeSpace)),
+          self.auto_update(),
+        ]))
+      }
+      GameMsg::CurrentModsUpdated {
+        mod_manager,
+        current_mods,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        self.current_mods = current_mods;
+        self.refresh_mod_index();
+        Task::done(Message::Game(GameMsg::ModManagerReady {
+          mod_manager,
+        }))
+      }
+      GameMsg::UpdateCurrentMods { mod_manager } => {
+        let old_current_mods = self.current_mods.to_owned();
+        Task::perform(
+          async move {
+            let records = mod_manager.records().await;
+            (
+              mod_manager,
+              records.map(|records| {
+                records
+                  .records
+                  .keys()
+                  .cloned()
+                  .collect::<HashSet<_>>()
+              }),
+            )
+          },
+          move |(mod_manager, current_mods)| {
+            Message::Game(GameMsg::CurrentModsUpdated {
+              mod_manager,
+              current_mods: current_mods
+                .unwrap_or(old_current_mods.to_owned()),
+            })
+          },
+        )
+      }
+      GameMsg::QueueUpdateCurrentMods => {
+        let next =
+          self.scheduler.enqueue(Operation::RefreshCurrentMods);
+        self.dispatch(next)
+      }
+      GameMsg::PrepareModManager { game_dir_path } => {
+        let allow_external = self.settings.allow_external_res_mods;
+        Task::perform(
+          async move {
+            let res = async {
+              let mut mod_manager =
+                ModManager::try_from_game_dir_with(
+                  game_dir_path.as_path(),
+                  allow_external,
+                )
+                .await?;
+              mod_manager.ensure_records().await?;
+              mod_manager.set_log_dir(logs_dir());
+              let game_version = mod_manager.game_version().await;
+              Ok::<_, crate::mod_manager::Error>((
+                mod_manager,
+                game_version,
+              ))
+            }
+            .await;
+            (game_dir_path, res)
+          },
+          |(game_dir_path, res)| match res {
+            Ok((mod_manager, game_version)) => {
+              Message::Game(GameMsg::ModManagerPrepared {
+                mod_manager,
+                game_version,
+              })
+            }
+            Err(err) => Message::Game(GameMsg::ModManagerFailed {
+              game_dir_path,
+              error: err.to_string(),
+            }),
+          },
+        )
+      }
+      GameMsg::ModManagerFailed {
+        game_dir_path,
+        error,
+      } => {
+        if game_dir_path != self.game_dir_path {
+          return Task::none();
+        }
+        self.game_dir_error =
+          Some(t!("game_dir.unmanageable", error = error));
+        // 第一次找不到游戏时到常见位置找一找
+        if self.detected_game_dirs.is_some() {
+          return Task::none();
+        }
+        self.detected_game_dirs = Some(Vec::new());
+        Task::perform(detect::detect_game_dirs(), |dirs| {
+          Message::Game(GameMsg::GameDirsDetected(dirs))
+        })
+      }
+      GameMsg::GameDirsDetected(game_dirs) => {
+        if game_dirs.is_empty() {
+          return Task::done(Message::Game(GameMsg::BrowseGameDir));
+        }
+        self.detected_game_dirs = Some(game_dirs);
+        Task::none()
+      }
+      GameMsg::PickGameDir(game_dir_path) => {
+        self.detected_game_dirs = Some(Vec::new());
+        self.game_dir = game_dir_path.to_string_lossy().to_string();
+        Task::done(Message::Game(GameMsg::ApplyGameDir))
+      }
+      GameMsg::BrowseGameDir => {
+        let Ok(Some(dir)) = native_dialog::FileDialog::new()
+          .set_title(t!("game_dir.pick"))
+          .set_location(&self.game_dir)
+          .show_open_single_dir()
+        else {
+          return Task::none();
+        };
+        // 在选择框中确认过的目录直接应用
+        self.game_dir = dir.to_string_lossy().to_string();
+        Task::done(Message::Game(GameMsg::ApplyGameDir))
+      }
+      GameMsg::ApplyGameDir => {
+        let game_dir_path = PathBuf::from(self.game_dir.trim());
+        // 正在解压或删除文件时不能切换
+        if self.files_busy() {
+          self.game_dir_error = Some(t!("game_dir.busy").to_string());
+          return Task::none();
+        }
+        if !game_dir_path.is_dir() {
+          self.game_dir_error =
+            Some(t!("game_dir.not_found").to_string());
+          return Task::none();
+        }
+        self.switch_game_dir(game_dir_path)
+      }
+      GameMsg::ModManagerPrepared {
+        mod_manager,
+        game_version,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        self.game_version = Some(game_version);
+        self.res_mods_path =
+          Some(mod_manager.res_mods_path().to_path_buf());
+        // 能正常打开的目录才记下来
+        let settings = Settings {
+          game_dir: Some(self.game_dir_path.to_owned()),
+          ..self.settings.to_owned()
+        };
+        Task::batch([
+          Task::done(Message::Game(GameMsg::ModManagerReady {
+            mod_manager,
+          })),
+          Task::perform(
+            async { PendingQueue::load(&pending_queue_path()).await },
+            |res| match res {
+              Ok(pending_queue) => Message::Task(
+                TaskMsg::PendingQueueLoaded(pending_queue),
+              ),
+              Err(err) => Message::Ui(UiMsg::Warning {
+                title: t!("pending.load_failed").to_string(),
+                text: t!("error.reason", error = err),
+              }),
+            },
+          ),
+          Task::done(Message::Settings(
+            SettingsMsg::SettingsChanged(settings),
+          )),
+        ])
+      }
+      GameMsg::AddCurrentMod { id } => {
+        self.current_mods.insert(id);
+        self.refresh_mod_index();
+        Task::none()
+      }
+      // 不重新读取整个安装记录，刚结束的操作只改动了这一条
+      GameMsg::RecordChanged { id, record } => {
+        self.local_mods.retain(|modr| modr.id != id);
+        match record {
+          Some(record) => {
+            self.local_mods.push(Mod::local(&id, &record.version));
+            self.current_mods.insert(id.to_owned());
+            self.records.records.insert(id, record);
+          }
+          None => {
+            self.current_mods.remove(&id);
+            self.records.records.remove(&id);
+          }
+        }
+        self.refresh_mod_index();
+        Task::done(Message::Task(TaskMsg::RefreshFreeSpace))
+      }
+      GameMsg::GameDirInput(game_dir) => {
+        self.game_dir = game_dir;
+        Task::none()
+      }
+      GameMsg::EditNote { id } => {
+        let note = self
+          .records
+          .records
+          .get(&id)
+          .map(|record| record.note().to_string())
+          .unwrap_or_default();
+        self.note_edit = Some((id, note));
+        Task::none()
+      }
+      GameMsg::NoteInput(input) => {
+        if let Some((_, note)) = self.note_edit.as_mut() {
+          *note = input;
+        }
+        Task::none()
+      }
+      GameMsg::SaveNote => {
+        let Some((id, note)) = self.note_edit.take() else {
+          return Task::none();
+        };
+        let note = note.trim().to_string();
+        // 先更新界面上的记录，写入完成后再重新读取
+        let Some(record) = self.records.records.get_mut(&id) else {
+          return Task::none();
+        };
+        record.metadata.get_or_insert_default().note =
+          note.to_owned();
+        let next =
+          self.scheduler.enqueue(Operation::SaveNote { id, note });
+        self.dispatch(next)
+      }
+      GameMsg::CancelNote => {
+        self.note_edit = None;
+        Task::none()
+      }
+      GameMsg::NoteSaved {
+        mod_manager,
+        result,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        // 写入失败时重新读取记录，撤销界面上的修改
+        let warning = result.err().map(|err| {
+          self.scheduler.enqueue(Operation::RefreshRecords);
+          Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("note.save_failed").to_string(),
+            text: err,
+          }))
+        });
+        Task::batch(
+          warning
+            .into_iter()
+            .chain([self.release_mod_manager(mod_manager, false)]),
+        )
+      }
+      GameMsg::CheckHealth => {
+        self.set_status(StatusLine::Progress(
+          t!("status.health_checking").to_string(),
+        ));
+        let next = self.scheduler.enqueue(Operation::CheckHealth);
+        self.dispatch(next)
+      }
+      GameMsg::HealthChecked {
+        mod_manager,
+        result,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        let warning = match result {
+          Ok(report) => {
+            self.set_status(StatusLine::Success(
+              t!("status.health_checked").to_string(),
+            ));
+            self.health = Some(report);
+            None
+          }
+          Err(err) => {
+            self.set_status(StatusLine::Error(
+              t!("health.failed").to_string(),
+            ));
+            Some(Task::done(Message::Ui(UiMsg::Warning {
+              title: t!("health.failed").to_string(),
+              text: t!("error.reason", error = err),
+            })))
+          }
+        };
+        Task::batch(
+          warning
+            .into_iter()
+            .chain([self.release_mod_manager(mod_manager, false)]),
+        )
+      }
+      GameMsg::CloseHealthReport => {
+        self.health = None;
+        Task::none()
+      }
+      // 和手动选择旧版本一样走批量更新，开着缓存时不用重新下载
+      GameMsg::RepairMod { id } => {
+        let Some(record) = self.records.records.get(&id) else {
+          return Task::none();
+        };
+        if !self.can_repair(&id) {
+          return Task::none();
+        }
+        self
+          .selected_versions
+          .insert(id.to_owned(), record.version.to_string());
+        self.set_action(id.to_owned(), PendingAction::Install);
+        self.health = None;
+        self.update_mods(vec![id], Vec::new(), Vec::new())
+      }
+      // 卸载时不存在的文件会跳过，只剩删除记录
+      GameMsg::ForgetRecord { id } => {
+        self.health = None;
+        Task::done(Message::Task(TaskMsg::UninstallMod { id }))
+      }
+      GameMsg::AdoptFiles(files) => {
+        let next = self.scheduler.enqueue(Operation::AdoptFiles {
+          id: ADOPTED_ID.to_string(),
+          files,
+        });
+        self.dispatch(next)
+      }
+      GameMsg::FilesAdopted {
+        mod_manager,
+        result,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        let warning = match result {
+          // 重新检查，报告中去掉已经收编的文件
+          Ok(()) => {
+            self.scheduler.enqueue(Operation::CheckHealth);
+            None
+          }
+          Err(err) => Some(Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("health.adopt_failed").to_string(),
+            text: t!("error.reason", error = err),
+          }))),
+        };
+        self.scheduler.enqueue(Operation::RefreshCurrentMods);
+        self.scheduler.enqueue(Operation::RefreshRecords);
+        Task::batch(
+          warning
+            .into_iter()
+            .chain([self.release_mod_manager(mod_manager, false)]),
+        )
+      }
+      GameMsg::StampBuild => {
+        let next = self.scheduler.enqueue(Operation::StampBuild);
+        self.dispatch(next)
+      }
+      GameMsg::BuildStamped {
+        mod_manager,
+        result,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        let warning = result.err().map(|err| {
+          Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("records.stamp_failed").to_string(),
+            text: t!("error.reason", error = err),
+          }))
+        });
+        self.scheduler.enqueue(Operation::RefreshRecords);
+        Task::batch(
+          warning
+            .into_iter()
+            .chain([self.release_mod_manager(mod_manager, false)]),
+        )
+      }
+      GameMsg::ShowTrash => Task::perform(
+        async { trash::list(&trash_dir()).await },
+        |entries| Message::Game(GameMsg::TrashListed(entries)),
+      ),
+      GameMsg::TrashListed(entries) => {
+        self.trash = Some(entries);
+        Task::none()
+      }
+      GameMsg::CloseTrash => {
+        self.trash = None;
+        Task::none()
+      }
+      GameMsg::EmptyTrash => Task::perform(
+        async {
+          trash::empty(&trash_dir())
+            .await
+            .map_err(|err| err.to_string())
+        },
+        |result| Message::Game(GameMsg::TrashEmptied(result)),
+      ),
+      GameMsg::TrashEmptied(result) => {
+        let warning = match result {
+          Ok(size) => {
+            self.set_status(StatusLine::Success(t!(
+              "status.trash_emptied",
+              size = format_bytes(size)
+            )));
+            None
+          }
+          Err(err) => Some(Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("trash.empty_failed").to_string(),
+            text: t!("error.reason", error = err),
+          }))),
+        };
+        // 删掉一部分后失败的，列表也要更新
+        Task::batch(
+          warning
+            .into_iter()
+            .chain([Task::done(Message::Game(GameMsg::ShowTrash))]),
+        )
+      }
+      GameMsg::RestoreTrashed(path) => {
+        let next =
+          self.scheduler.enqueue(Operation::RestoreTrashed(path));
+        self.dispatch(next)
+      }
+      GameMsg::TrashRestored {
+        mod_manager,
+        result,
+      } => {
+        if self.is_stale(&mod_manager) {
+          return Task::none();
+        }
+        let warning = match result {
+          Ok(id) => {
+            self.set_status(StatusLine::Success(t!(
+              "status.trash_restored",
+              id = id
+            )));
+            None
+          }
+          Err(err) => Some(Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("trash.restore_failed").to_string(),
+            text: t!("error.reason", error = err),
+          }))),
+        };
+        self.scheduler.enqueue(Operation::RefreshCurrentMods);
+        self.scheduler.enqueue(Operation::RefreshRecords);
+        let reload = self
+          .trash
+          .is_some()
+          .then(|| Task::done(Message::Game(GameMsg::ShowTrash)));
+        Task::batch(
+          warning
+            .into_iter()
+            .chain(reload)
+            .chain([self.release_mod_manager(mod_manager, false)]),
+        )
+      }
+      // 客户端会读取模组文件，安装/卸载进行中时不启动
+      GameMsg::LaunchGame => {
+        if self.files_busy() {
+          return Task::none();
+        }
+        Task::perform(
+          launch::launch(self.game_dir_path.to_owned()),
+          |res| match res {
+            Ok(launcher) => Message::Game(GameMsg::GameLaunched(
+              launcher.to_string(),
+            )),
+            Err((launcher, err)) => Message::Ui(UiMsg::Warning {
+              title: t!("launch.failed").to_string(),
+              text: t!(
+                "error.subject_reason",
+                subject = launcher,
+                error = err
+              ),
+            }),
+          },
+        )
+      }
+      GameMsg::GameLaunched(launcher) => {
+        self.set_status(StatusLine::Success(t!(
+          "status.launched",
+          launcher = launcher
+        )));
+        Task::none()
+      }
+      // 切换目录后旧目录的ModManager直接丢弃
+      GameMsg::ModManagerReady { mod_manager }
+        if self.is_stale(&mod_manager) =>
+      {
+        Task::none()
+      }
+      GameMsg::ModManagerReady { mod_manager } => {
+        self.release_mod_manager(mod_manager, false)
+      }
+      // 自己的操作进行中或结束前产生的变化不算，旧目录的也不算
+      GameMsg::ResModsChangedExternally { path, changed_at } => {
+        let own = self
+          .scheduler
+          .running()
+          .is_some_and(Operation::writes_files)
+          || self.files_changed_at.is_some_and(|at| changed_at <= at);
+        if own || self.res_mods_path.as_ref() != Some(&path) {
+          return Task::none();
+        }
+        tracing::info!(?path, "res_mods changed externally");
+        Task::batch([
+          Task::done(Message::Game(GameMsg::QueueUpdateCurrentMods)),
+          Task::done(Message::Game(GameMsg::QueueUpdateRecords)),
+        ])
+      }
+    }
+  }
+}
+
+impl App {
+  // 切换游戏目录：旧目录的状态和排队中的任务全部作废。
+  // 旧的ModManager可能还在某个任务手里，之后归还时会因为
+  // 目录不一致被is_stale丢弃，不会替换掉新的
+  fn switch_game_dir(
+    &mut self,
+    game_dir_path: PathBuf,
+  ) -> Task<Message> {
+    self.game_dir = game_dir_path.to_string_lossy().to_string();
+    self.game_dir_error = None;
+    self.game_dir_path = game_dir_path.to_owned();
+    self.scheduler.clear();
+    self.res_mods_path = None;
+    self.files_changed_at = None;
+    self.operations.clear();
+    self.uninstalls.clear();
+    self.batch.clear();
+    self.batch_tally = None;
+    self.batch_summary = None;
+    self.current_mods.clear();
+    self.pending_action.clear();
+    self.force_reinstall.clear();
+    self.selected_versions.clear();
+    self.compat_overrides.clear();
+    self.plan = None;
+    self.note_edit = None;
+    self.health = None;
+    self.records = Records::default();
+    self.records_loaded = false;
+    self.build_mismatch = None;
+    self.local_mods.clear();
+    self.game_version = None;
+    self.refresh_mod_index();
+    Task::batch([
+      Task::done(Message::Game(GameMsg::PrepareModManager {
+        game_dir_path,
+      })),
+      Task::done(Message::Game(GameMsg::QueueUpdateCurrentMods)),
+      Task::done(Message::Game(GameMsg::QueueUpdateRecords)),
+    ])
+  }
+
+  // ModManager交还给调度器，需要时重试刚才的操作。
+  // 刚结束的操作改动过res_mods的话记下时间
+  pub(super) fn release_mod_manager(
+    &mut self,
+    mod_manager: ModManager,
+    retry: bool,
+  ) -> Task<Message> {
+    if self
+      .scheduler
+      .running()
+      .is_some_and(Operation::writes_files)
+    {
+      self.files_changed_at = Some(Instant::now());
+    }
+    let next =
+      self.scheduler.on_operation_finished(mod_manager, retry);
+    self.dispatch(next)
+  }
+
+  // 执行调度器取出的操作。失败的任务留在队列中等用户处理，
+  // 已经被移除或不能开始的跳过，把ModManager交还给调度器
+  pub(super) fn dispatch(
+    &mut self,
+    mut next: Option<(Operation, ModManager)>,
+  ) -> Task<Message> {
+    while let Some((operation, mut mod_manager)) = next {
+      match operation {
+        Operation::Uninstall(id) => {
+          if let Some(uninstall) = self
+            .uninstalls
+            .iter_mut()
+            .find(|x| x.id() == id && x.can_start())
+          {
+            mod_manager.set_trash_dir(
+              self.settings.safe_delete.then(trash_dir),
+            );
+            return uninstall.start(mod_manager).map(move |update| {
+              Message::Task(TaskMsg::UninstallModUpdated {
+                id: id.to_owned(),
+                update,
+              })
+            });
+          }
+        }
+        Operation::Install(id) => {
+          if let Some(operation) = self
+            .operations
+            .iter_mut()
+            .find(|x| x.id() == id && x.can_extract())
+          {
+            return operation.extract(mod_manager).map(
+              move |update| {
+                Message::Task(TaskMsg::OperationUpdated {
+                  id: id.to_owned(),
+                  update,
+                })
+              },
+            );
+          }
+        }
+        Operation::SaveNote { id, note } => {
+          return Task::perform(
+            async move {
+              let result = mod_manager
+                .set_note(&id, &note)
+                .await
+                .map_err(|err| err.to_string());
+              (mod_manager, result)
+            },
+            |(mod_manager, result)| {
+              Message::Game(GameMsg::NoteSaved {
+                mod_manager,
+                result,
+              })
+            },
+          );
+        }
+        Operation::AdoptFiles { id, files } => {
+          return Task::perform(
+            async move {
+              let result = mod_manager
+                .adopt_files(&id, &files)
+                .await
+                .map_err(|err| err.to_string());
+              (mod_manager, result)
+            },
+            |(mod_manager, result)| {
+              Message::Game(GameMsg::FilesAdopted {
+                mod_manager,
+                result,
+              })
+            },
+          );
+        }
+        Operation::RestoreTrashed(path) => {
+          return Task::perform(
+            async move {
+              let result = mod_manager
+                .restore_trashed(&path)
+                .await
+                .map_err(|err| err.to_string());
+              (mod_manager, result)
+            },
+            |(mod_manager, result)| {
+              Message::Game(GameMsg::TrashRestored {
+                mod_manager,
+                result,
+              })
+            },
+          );
+        }
+        Operation::CheckHealth => {
+          return Task::perform(
+            async move {
+              let result = mod_manager
+                .health_check()
+                .await
+                .map_err(|err| err.to_string());
+              (mod_manager, result)
+            },
+            |(mod_manager, result)| {
+              Message::Game(GameMsg::HealthChecked {
+                mod_manager,
+                result,
+              })
+            },
+          );
+        }
+        Operation::StampBuild => {
+          return Task::perform(
+            async move {
+              let result = mod_manager
+                .stamp_build()
+                .await
+                .map_err(|err| err.to_string());
+              (mod_manager, result)
+            },
+            |(mod_manager, result)| {
+              Message::Game(GameMsg::BuildStamped {
+                mod_manager,
+                result,
+              })
+            },
+          );
+        }
+        Operation::RefreshCurrentMods => {
+          return Task::done(Message::Game(
+            GameMsg::UpdateCurrentMods { mod_manager },
+          ));
+        }
+        Operation::RefreshRecords => {
+          return Task::done(Message::Game(GameMsg::UpdateRecords {
+            mod_manager,
+          }));
+        }
+      }
+      next = self.scheduler.on_manager_available(mod_manager);
+    }
+    Task::none()
+  }
+}
@@ -0,0 +1,148 @@
+use crate::app::App;
+
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::http::client;
+use crate::i18n::t;
+use crate::{
+  data::{
+    registry::{self, LoadState, Registry, refresh_from_url},
+    status::StatusLine,
+  },
+  messages::{Message, RegistryMsg, UiMsg},
+};
+use futures::stream::FuturesOrdered;
+use iced::Task;
+use url::Url;
+
+impl App {
+  pub(super) fn handle_registries(
+    &mut self,
+    message: RegistryMsg,
+  ) -> Task<Message> {
+    match message {
+      RegistryMsg::GenerateRegistry => {
+        let Ok(Some(dir)) = native_dialog::FileDialog::new()
+          .set_title(t!("generate_registry.pick_dir"))
+          .show_open_single_dir()
+        else {
+          return Task::none();
+        };
+        Task::perform(
+          async move {
+            let path = dir.join("registry.json");
+            Registry::generate(dir.as_path())
+              .await?
+              .save(&path)
+              .await?;
+            Ok::<_, registry::Error>(path)
+          },
+          |res| match res {
+            Ok(path) => Message::Ui(UiMsg::Info {
+              title: t!("generate_registry.done").to_string(),
+              text: path.to_string_lossy().to_string(),
+            }),
+            Err(err) => Message::Ui(UiMsg::Warning {
+              title: t!("generate_registry.failed").to_string(),
+              text: t!("error.reason", error = err),
+            }),
+          },
+        )
+      }
+      RegistryMsg::RegistryLoaded { url, result } => {
+        // 重新加载后已经不再等待的结果直接丢弃
+        let Some(state) = self
+          .registry_loads
+          .iter_mut()
+          .find(|(pending, state)| {
+            *pending == url && *state == LoadState::Pending
+          })
+          .map(|(_, state)| state)
+        else {
+          return Task::none();
+        };
+        let mut registry = match result {
+          Ok(registry) => {
+            *state = LoadState::Loaded;
+            *registry
+          }
+          Err(error) => {
+            *state = LoadState::Failed(error);
+            return self.auto_update();
+          }
+        };
+        let invalid_homepages = registry.sanitize_homepages();
+        self.set_status(StatusLine::Success(t!(
+          "status.registry_loaded",
+          source = registry
+            .base_url
+            .as_ref()
+            .map_or_else(|| "data".to_string(), Url::to_string)
+        )));
+        self
+          .registries
+          .retain(|registry| registry.source.as_ref() != Some(&url));
+        self.registries.push_front(registry);
+        self.refresh_mod_index();
+        let warning = (!invalid_homepages.is_empty()).then(|| {
+          Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("registry.invalid_homepages").to_string(),
+            text: invalid_homepages.join(", "),
+          }))
+        });
+        Task::batch(warning.into_iter().chain([self.auto_update()]))
+      }
+      RegistryMsg::RetryRegistry(url) => {
+        match self
+          .registry_loads
+          .iter_mut()
+          .find(|(failed, _)| *failed == url)
+        {
+          Some((_, state @ LoadState::Failed(_))) => {
+            *state = LoadState::Pending;
+          }
+          _ => return Task::none(),
+        }
+        Task::future(load(url, None))
+      }
+      RegistryMsg::ReloadRegistries => {
+        Task::done(Message::Registry(RegistryMsg::LoadRegistries {
+          urls: self.registry_urls(),
+        }))
+      }
+      RegistryMsg::LoadRegistries { urls: url } => {
+        // 保留旧的registry用于增量更新
+        let mut cached = self
+          .registries
+          .drain(..)
+          .filter_map(|registry| {
+            Some((registry.base_url.to_owned()?, registry))
+          })
+          .collect::<HashMap<_, _>>();
+        self.refresh_mod_index();
+        self.registry_loads = url
+          .iter()
+          .map(|url| (url.to_owned(), LoadState::Pending))
+          .collect();
+        Task::stream(FuturesOrdered::from_iter(url.into_iter().map(
+          |url| {
+            let cached = cached.remove(&url);
+            load(url, cached)
+          },
+        )))
+      }
+    }
+  }
+}
+
+// 加载结果转成消息，失败时带上显示给用户的原因
+async fn load(url: Url, cached: Option<Registry>) -> Message {
+  let result = refresh_from_url(&url, cached, client()).await;
+  Message::Registry(RegistryMsg::RegistryLoaded {
+    url,
+    result: result
+      .map(Box::new)
+      .map_err(|err| Error::from(err).to_string()),
+  })
+}
@@ -0,0 +1,113 @@
+use crate::app::App;
+
+use crate::i18n::{Locale, set_locale};
+use crate::paths::settings_path;
+use crate::{
+  data::{
+    settings::Settings,
+    settings_form::{SettingsEdit, SettingsForm},
+  },
+  messages::{GameMsg, Message, RegistryMsg, SettingsMsg},
+};
+use iced::Task;
+
+impl App {
+  pub(super) fn handle_settings(
+    &mut self,
+    message: SettingsMsg,
+  ) -> Task<Message> {
+    match message {
+      SettingsMsg::ToggleArchiveCache(cache_archives) => Task::done(
+        Message::Settings(SettingsMsg::SettingsChanged(Settings {
+          cache_archives,
+          ..self.settings.to_owned()
+        })),
+      ),
+      SettingsMsg::SelectLanguage(language) => Task::done(
+        Message::Settings(SettingsMsg::SettingsChanged(Settings {
+          language: Some(language),
+          ..self.settings.to_owned()
+        })),
+      ),
+      SettingsMsg::OpenSettings => {
+        self.settings_form =
+          Some(SettingsForm::from_settings(&self.settings));
+        Task::none()
+      }
+      SettingsMsg::EditSettings(edit) => {
+        let Some(form) = self.settings_form.as_mut() else {
+          return Task::none();
+        };
+        // 语言和主题一样立即生效，取消时再恢复
+        if let SettingsEdit::Language(language) = &edit {
+          set_locale(language.unwrap_or_else(Locale::detect));
+        }
+        form.update(edit);
+        Task::none()
+      }
+      SettingsMsg::ApplySettings => {
+        let Some(form) = self.settings_form.as_mut() else {
+          return Task::none();
+        };
+        let settings = match form.apply(&self.settings) {
+          Ok(settings) => settings,
+          Err(errors) => {
+            form.errors = errors;
+            return Task::none();
+          }
+        };
+        self.settings_form = None;
+        let reload =
+          settings.registry_urls != self.settings.registry_urls;
+        // 重新打开游戏目录，按新的设置检查res_mods的位置
+        let reopen = settings.allow_external_res_mods
+          != self.settings.allow_external_res_mods;
+        let (urls, _) = settings.parsed_registry_urls();
+        Task::done(Message::Settings(SettingsMsg::SettingsChanged(
+          settings,
+        )))
+        .chain(if reload {
+          Task::done(Message::Registry(RegistryMsg::LoadRegistries {
+            urls,
+          }))
+        } else {
+          Task::none()
+        })
+        .chain(if reopen {
+          Task::done(Message::Game(GameMsg::ApplyGameDir))
+        } else {
+          Task::none()
+        })
+      }
+      SettingsMsg::CancelSettings => {
+        self.settings_form = None;
+        set_locale(
+          self.settings.language.unwrap_or_else(Locale::detect),
+        );
+        Task::none()
+      }
+      SettingsMsg::SortMods { key, descending } => Task::done(
+        Message::Settings(SettingsMsg::SettingsChanged(Settings {
+          sort_key: key,
+          sort_descending: descending,
+          ..self.settings.to_owned()
+        })),
+      ),
+      // 所有设置的修改都经过这里写入配置文件
+      SettingsMsg::SettingsChanged(settings) => {
+        if settings == self.settings {
+          return Task::none();
+        }
+        self.settings = settings.to_owned();
+        set_locale(settings.language.unwrap_or_else(Locale::detect));
+        self.refresh_mod_index();
+        Task::future(async move {
+          if let Err(err) = settings.save(&settings_path()).await {
+            tracing::warn!(%err, "failed to save settings");
+          }
+        })
+        .discard()
+      }
+    }
+  }
+}
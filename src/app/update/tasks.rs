@@ -0,0 +1,1519 @@
+use crate::app::{App, PreviewDialog};
+
+use std::{collections::HashSet, path::PathBuf, time::Instant};
+
+use crate::app::tasks::download::{Download, DownloadUpdate};
+use crate::app::tasks::install::InstallUpdate;
+use crate::app::tasks::operation::{self, OperationUpdate, Phase};
+use crate::app::tasks::uninstall::{Uninstall, UninstallUpdate};
+use crate::error::Error;
+use crate::format::format_bytes;
+use crate::i18n::t;
+use crate::mod_manager::{Records, find_conflicts, list_zip_entries};
+use crate::paths::{
+  available_space, downloads_dir, pending_queue_path,
+};
+use crate::tasks::install::{self, discard_archive};
+use crate::{
+  data::{
+    archive::ArchiveType,
+    batch::{BatchPhase, BatchTally, PendingAction},
+    pending::{PendingKind, PendingQueue, Resume},
+    preview::ArchiveTree,
+    registry::{self, LoadState},
+    scheduler::Operation,
+    status::StatusLine,
+  },
+  messages::{GameMsg, Message, TaskMsg, UiMsg},
+};
+use iced::{Task, clipboard};
+
+// 从队列中取出的失败任务
+enum FailedTask {
+  Operation(operation::Operation),
+  Uninstall,
+}
+
+impl App {
+  pub(super) fn handle_tasks(
+    &mut self,
+    message: TaskMsg,
+  ) -> Task<Message> {
+    match message {
+      TaskMsg::RefreshFreeSpace => Task::perform(
+        available_space(self.game_dir_path.to_owned()),
+        |space| Message::Task(TaskMsg::FreeSpaceUpdated(space)),
+      ),
+      TaskMsg::FreeSpaceUpdated(free_space) => {
+        self.free_space = free_space;
+        Task::none()
+      }
+      TaskMsg::PendingQueueLoaded(pending_queue) => {
+        if pending_queue.operations.is_empty() {
+          return Task::none();
+        }
+        let resume = native_dialog::MessageDialog::new()
+          .set_title(t!("pending.resume_title"))
+          .set_text(
+            t!(
+              "pending.resume_text",
+              count = pending_queue.operations.len()
+            )
+            .as_str(),
+          )
+          .set_type(native_dialog::MessageType::Info)
+          .show_confirm()
+          .unwrap_or_default();
+        if !resume {
+          return Task::future(async {
+            PendingQueue::default().save(&pending_queue_path()).await
+          })
+          .discard();
+        }
+        let mut redownload = Vec::new();
+        let mut tasks = Vec::new();
+        for operation in pending_queue.operations {
+          match operation.resume() {
+            Resume::Uninstall => {
+              tasks.push(Task::done(Message::Task(
+                TaskMsg::UninstallMod { id: operation.id },
+              )));
+            }
+            // 格式按文件内容判断，registry可能已经变了
+            Resume::Install(path) => {
+              tasks.push(Task::done(Message::Task(
+                TaskMsg::InstallMod {
+                  path,
+                  ty: None,
+                  version: operation
+                    .version
+                    .unwrap_or_default()
+                    .into(),
+                  id: operation.id,
+                },
+              )));
+            }
+            Resume::Redownload => {
+              if let Some(version) = operation.version {
+                self
+                  .selected_versions
+                  .insert(operation.id.to_owned(), version);
+              }
+              self.set_action(
+                operation.id.to_owned(),
+                PendingAction::Install,
+              );
+              if self.request_mod(&operation.id).is_some() {
+                redownload.push(operation.id);
+              }
+            }
+          }
+        }
+        if !redownload.is_empty() {
+          tasks.push(self.update_mods(
+            redownload,
+            Vec::new(),
+            Vec::new(),
+          ));
+        }
+        Task::batch(tasks)
+      }
+      // 只勾选有新版本的模组，之后和手动更新走同一个流程
+      TaskMsg::UpdateAll => {
+        self.pending_action = self
+          .updatable_mods
+          .iter()
+          .map(|id| (id.to_owned(), PendingAction::Install))
+          .collect();
+        for id in self.updatable_mods.iter() {
+          self.selected_versions.remove(id);
+        }
+        self.checked_update_batch()
+      }
+      TaskMsg::PreviewBatch => {
+        self.plan = Some(self.batch_plan());
+        Task::none()
+      }
+      TaskMsg::ConfirmBatch => {
+        self.plan = None;
+        if self.batch_in_flight() {
+          return Task::none();
+        }
+        self.checked_update_batch()
+      }
+      TaskMsg::CancelBatch => {
+        self.plan = None;
+        Task::none()
+      }
+      TaskMsg::UpdateMods {
+        install,
+        missing,
+        uninstall,
+        skipped,
+      } => {
+        for release in install.iter() {
+          self.force_reinstall.remove(&release.id);
+        }
+        for id in missing.iter() {
+          self.force_reinstall.remove(id);
+        }
+        // registry中已经不存在的和已是最新的模组跳过，并取消勾选
+        for id in missing.iter().chain(skipped.iter()) {
+          self.pending_action.remove(id);
+        }
+        if !skipped.is_empty() {
+          self.set_status(StatusLine::Success(t!(
+            "status.up_to_date_skipped",
+            ids = skipped.join(", ")
+          )));
+        }
+        self.batch_tally = Some(BatchTally::new(Instant::now()));
+        self.batch_summary = None;
+        self.batch = uninstall
+          .iter()
+          .map(|id| (id.to_owned(), BatchPhase::Uninstalling))
+          .chain(
+            self
+              .pending_ids(PendingAction::Install)
+              .into_iter()
+              .map(|id| (id, BatchPhase::Downloading)),
+          )
+          .collect();
+        Task::batch(
+          uninstall
+            .into_iter()
+            .map(|id| {
+              Task::done(Message::Task(TaskMsg::UninstallMod { id }))
+            })
+            .chain(install.into_iter().map(|release| {
+              Task::done(Message::Task(TaskMsg::GetMod(release)))
+            }))
+            .chain((!missing.is_empty()).then(|| {
+              Task::done(Message::Ui(UiMsg::Warning {
+                title: t!("mods.not_found").to_string(),
+                text: t!(
+                  "mods.not_found_text",
+                  ids = missing.join(", ")
+                ),
+              }))
+            })),
+        )
+      }
+      TaskMsg::GetMod(release) => {
+        self.take_failed_task(&release.id);
+        // registry声明的格式作为检查时的提示，没有声明时看文件名
+        let ty = release
+          .ty
+          .filter(|ty| !matches!(ty, ArchiveType::Unknown(_)));
+        let download = Download::new(
+          release.id,
+          release.version,
+          release.url,
+          release.size,
+          release.sha256,
+          self.settings.retry_policy(),
+          self.settings.download_timeouts(),
+        )
+        .with_cache(self.settings.cache_archives);
+        self.start_operation(operation::Operation::new(download, ty))
+      }
+      TaskMsg::PauseDownload { id } => {
+        if let Some(operation) =
+          self.operations.iter_mut().find(|x| x.id() == id)
+        {
+          operation.pause();
+        }
+        Task::none()
+      }
+      TaskMsg::ResumeDownload { id } => {
+        let Some(operation) =
+          self.operations.iter_mut().find(|x| x.id() == id)
+        else {
+          return Task::none();
+        };
+        let task = operation.resume();
+        operation_task(id, task)
+      }
+      TaskMsg::InstallMod {
+        path,
+        id,
+        version,
+        ty,
+      } => {
+        self.take_failed_task(&id);
+        self.start_operation(operation::Operation::from_archive(
+          id.as_str(),
+          &version,
+          path.as_path(),
+          ty,
+        ))
+      }
+      TaskMsg::OperationUpdated { id, update } => {
+        let Some(operation) =
+          self.operations.iter_mut().find(|x| x.id() == id)
+        else {
+          return Task::none();
+        };
+        operation.update(update.to_owned());
+        match update {
+          OperationUpdate::Download(update) => {
+            self.download_updated(id, update)
+          }
+          OperationUpdate::Verified(res) => self.verified(id, res),
+          OperationUpdate::Install(update) => {
+            self.install_updated(id, update)
+          }
+        }
+      }
+      TaskMsg::InstallLocalArchive => {
+        let Ok(Some(source)) = native_dialog::FileDialog::new()
+          .set_title(t!("local.pick"))
+          .add_filter(t!("local.filter"), &["zip", "7z", "gz", "tgz"])
+          .show_open_single_file()
+        else {
+          return Task::none();
+        };
+        Task::done(Message::Task(TaskMsg::AddLocalArchive(source)))
+      }
+      TaskMsg::AddLocalArchive(source) => Task::perform(
+        prepare_local_archive(source),
+        |res| match res {
+          Ok((path, id, version)) => {
+            Message::Task(TaskMsg::LocalArchiveReady {
+              path,
+              id,
+              version,
+            })
+          }
+          Err(message) => message,
+        },
+      ),
+      // 在预览中确认后才安装
+      TaskMsg::LocalArchiveReady { path, id, version } => {
+        Task::done(Message::Task(TaskMsg::PreviewArchive {
+          id,
+          path,
+          version: Some(version),
+        }))
+      }
+      TaskMsg::PreviewArchive { id, path, version } => {
+        let task = Task::perform(
+          preview_archive(
+            path.to_owned(),
+            self.res_mods_path.to_owned(),
+            self.records.to_owned(),
+            id.to_owned(),
+          ),
+          {
+            let path = path.to_owned();
+            move |result| {
+              Message::Task(TaskMsg::ArchivePreviewed {
+                path,
+                result: result.map(Box::new),
+              })
+            }
+          },
+        );
+        self.preview = Some(PreviewDialog {
+          id,
+          path,
+          version,
+          tree: None,
+          expanded: HashSet::new(),
+        });
+        task
+      }
+      TaskMsg::ArchivePreviewed { path, result } => {
+        let Some(preview) = self
+          .preview
+          .as_mut()
+          .filter(|preview| preview.path == path)
+        else {
+          return Task::none();
+        };
+        if let Ok(tree) = result.as_ref() {
+          preview.expanded = tree.flagged_dirs();
+        }
+        preview.tree = Some(result.map(|tree| *tree));
+        Task::none()
+      }
+      TaskMsg::ClosePreview { install } => {
+        let Some(PreviewDialog {
+          id,
+          path,
+          version: Some(version),
+          ..
+        }) = self.preview.take()
+        else {
+          return Task::none();
+        };
+        if !install {
+          return Task::future(tokio::fs::remove_file(path))
+            .discard();
+        }
+        Task::done(Message::Task(TaskMsg::InstallMod {
+          path,
+          id,
+          version: version.into(),
+          ty: None,
+        }))
+      }
+      TaskMsg::ClearTaskHistory => {
+        self.task_history.clear();
+        Task::none()
+      }
+      TaskMsg::CopyBatchFailures => {
+        let Some(summary) = self.batch_summary.as_ref() else {
+          return Task::none();
+        };
+        let list = summary.failure_list();
+        self.set_status(StatusLine::Success(t!(
+          "status.copied",
+          text = t!(
+            "summary.failures_copied",
+            count = summary.failures.len()
+          )
+        )));
+        clipboard::write(list)
+      }
+      TaskMsg::DismissBatchSummary => {
+        self.batch_summary = None;
+        Task::none()
+      }
+      TaskMsg::RetryTask { id } => {
+        let Some(failed) = self.take_failed_task(&id) else {
+          return Task::none();
+        };
+        // 压缩包还在时从失败的阶段继续，否则从下载开始
+        let (phase, task) = match failed {
+          FailedTask::Uninstall => (
+            BatchPhase::Uninstalling,
+            Task::done(Message::Task(TaskMsg::UninstallMod {
+              id: id.to_owned(),
+            })),
+          ),
+          FailedTask::Operation(mut operation)
+            if operation.retry() =>
+          {
+            (BatchPhase::Installing, self.start_operation(operation))
+          }
+          FailedTask::Operation(_) => {
+            let (requests, missing) =
+              self.download_requests(vec![id.to_owned()]);
+            if !missing.is_empty() {
+              return Task::done(Message::Ui(UiMsg::Warning {
+                title: t!("mods.not_found").to_string(),
+                text: t!("mods.not_found_text", ids = id),
+              }));
+            }
+            (
+              BatchPhase::Downloading,
+              Task::batch(requests.into_iter().map(Task::done)),
+            )
+          }
+        };
+        if let Some(current) = self.batch.get_mut(&id) {
+          *current = phase;
+        }
+        if let Some(tally) = self.batch_tally.as_mut() {
+          tally.clear_failure(&id);
+        }
+        task
+      }
+      TaskMsg::DismissFailedTask { id } => {
+        self.batch.remove(&id);
+        if let Some(tally) = self.batch_tally.as_mut() {
+          tally.clear_failure(&id);
+        }
+        // 下载失败时不完整的文件已经删掉了
+        match self.take_failed_task(&id) {
+          Some(FailedTask::Operation(operation))
+            if operation.phase() != Phase::Download =>
+          {
+            Task::future(discard_archive(operation.path().to_owned()))
+              .discard()
+          }
+          _ => Task::none(),
+        }
+      }
+      TaskMsg::CancelInstall { id } => {
+        // 排队中的直接移除，解压中的还要归还ModManager
+        let Some(pos) = self
+          .operations
+          .iter()
+          .position(|x| x.id() == id && x.phase() != Phase::Download)
+        else {
+          return Task::none();
+        };
+        let mut operation = self.operations.remove(pos);
+        self.scheduler.cancel(&Operation::Install(id.to_owned()));
+        let task = operation.cancel().map(|mod_manager| {
+          Message::Game(GameMsg::ModManagerReady { mod_manager })
+        });
+        self.record_task(
+          &id,
+          operation.label(),
+          Some(t!("task.cancelled").to_string()),
+          operation.log(),
+        );
+        self.advance_batch(
+          &id,
+          BatchPhase::Installing,
+          BatchPhase::Failed,
+        );
+        task
+      }
+      TaskMsg::UninstallMod { id } => {
+        self.take_failed_task(&id);
+        let uninstall = Uninstall::new(id.as_str());
+        self.uninstalls.push_back(uninstall);
+        let next = self.scheduler.enqueue(Operation::Uninstall(id));
+        self.dispatch(next)
+      }
+      TaskMsg::UninstallModUpdated { id, update } => {
+        if let Some(uninstall) =
+          self.uninstalls.iter_mut().find(|x| x.id() == id.as_str())
+        {
+          uninstall.update(update.to_owned());
+          match update {
+            UninstallUpdate::Running(_) | UninstallUpdate::Log(_) => {
+              self.set_status(StatusLine::Progress(t!(
+                "status.uninstalling",
+                id = id
+              )));
+              Task::none()
+            }
+            UninstallUpdate::Finished((res, mod_manager)) => {
+              match res {
+                // 还有尝试次数时留在队列里，由调度器重新开始
+                Err(err) if uninstall.can_start() => {
+                  tracing::warn!(%id, %err, "uninstall failed, retrying");
+                  self.release_mod_manager(mod_manager, true)
+                }
+                Err(err) => {
+                  let log = uninstall.log().to_owned();
+                  self.record_task(
+                    &id,
+                    t!("task.uninstall"),
+                    Some(err.to_string()),
+                    log,
+                  );
+                  self.advance_batch(
+                    &id,
+                    BatchPhase::Uninstalling,
+                    BatchPhase::Failed,
+                  );
+                  Task::batch([
+                    Task::done(Message::Game(
+                      GameMsg::ModManagerReady { mod_manager },
+                    )),
+                    Task::done(Message::Ui(UiMsg::Warning {
+                      title: t!("uninstall.failed").to_string(),
+                      text: t!(
+                        "error.subject_reason",
+                        subject = id,
+                        error = err
+                      ),
+                    })),
+                  ])
+                }
+                Ok(()) => {
+                  let log = uninstall.log().to_owned();
+                  if let Some(pos) =
+                    self.uninstalls.iter().position(|x| x.id() == id)
+                  {
+                    self.uninstalls.remove(pos);
+                  }
+                  self.record_task(
+                    &id,
+                    t!("task.uninstall"),
+                    None,
+                    log,
+                  );
+                  self.set_status(StatusLine::Success(t!(
+                    "status.uninstalled",
+                    id = id
+                  )));
+                  if self.advance_batch(
+                    &id,
+                    BatchPhase::Uninstalling,
+                    BatchPhase::Done,
+                  ) {
+                    self.pending_action.remove(&id);
+                  }
+                  Task::batch([
+                    Task::done(Message::Game(
+                      GameMsg::ModManagerReady { mod_manager },
+                    )),
+                    Task::done(Message::Game(
+                      GameMsg::RecordChanged {
+                        id: id.to_string(),
+                        record: None,
+                      },
+                    )),
+                  ])
+                }
+              }
+            }
+          }
+        } else {
+          Task::none()
+        }
+      }
+    }
+  }
+}
+
+impl App {
+  // 启动后registry和安装记录都就绪时检查一次，出错只会出现在
+  // 批次的汇总里，不影响正常使用
+  pub(super) fn auto_update(&mut self) -> Task<Message> {
+    let Some(count) = self.take_auto_update() else {
+      return Task::none();
+    };
+    if self.settings.auto_update_ask
+      && !native_dialog::MessageDialog::new()
+        .set_title(t!("auto_update.title"))
+        .set_text(t!("auto_update.text", count = count).as_str())
+        .set_type(native_dialog::MessageType::Info)
+        .show_confirm()
+        .unwrap_or_default()
+    {
+      return Task::none();
+    }
+    self.set_status(StatusLine::Progress(t!(
+      "status.auto_update",
+      count = count
+    )));
+    Task::done(Message::Task(TaskMsg::UpdateAll))
+  }
+
+  // 就绪前返回None；之后只检查一次，有可更新的模组时返回数量。
+  // 所有registry都加载失败时视为离线，已有任务或勾选时不打扰
+  fn take_auto_update(&mut self) -> Option<usize> {
+    let settled = !self.registry_loads.is_empty()
+      && self
+        .registry_loads
+        .iter()
+        .all(|(_, state)| *state != LoadState::Pending);
+    if !self.auto_update_pending || !self.records_loaded || !settled {
+      return None;
+    }
+    self.auto_update_pending = false;
+    let idle =
+      !self.batch_in_flight() && self.pending_action.is_empty();
+    (!self.registries.is_empty()
+      && idle
+      && !self.updatable_mods.is_empty())
+    .then_some(self.updatable_mods.len())
+  }
+
+  // 空间不够时不开始，免得解压到一半失败
+  fn checked_update_batch(&self) -> Task<Message> {
+    match self.batch_plan().space_shortage() {
+      Some((required, available)) => {
+        Task::done(Message::Ui(UiMsg::Warning {
+          title: t!("plan.no_space_title").to_string(),
+          text: t!(
+            "plan.no_space",
+            required = format_bytes(required),
+            available = format_bytes(available)
+          ),
+        }))
+      }
+      None => self.update_batch(),
+    }
+  }
+
+  // 重新提交或忽略前先把失败的任务移出队列
+  fn take_failed_task(&mut self, id: &str) -> Option<FailedTask> {
+    if let Some(pos) = self
+      .operations
+      .iter()
+      .position(|x| x.id() == id && x.is_failed())
+    {
+      return Some(FailedTask::Operation(
+        self.operations.remove(pos),
+      ));
+    }
+    let pos = self
+      .uninstalls
+      .iter()
+      .position(|x| x.id() == id && x.is_failed())?;
+    self.uninstalls.remove(pos).map(|_| FailedTask::Uninstall)
+  }
+
+  // 为每个要安装的模组生成下载请求，返回找不到的模组id
+  pub(super) fn download_requests(
+    &self,
+    install: Vec<String>,
+  ) -> (Vec<Message>, Vec<String>) {
+    let (releases, missing, warnings) =
+      self.release_snapshots(install);
+    let requests = releases
+      .into_iter()
+      .map(|release| Message::Task(TaskMsg::GetMod(release)))
+      .chain(warnings)
+      .collect();
+    (requests, missing)
+  }
+
+  // 加入队列并开始当前阶段，已经到解压阶段的交给调度器
+  fn start_operation(
+    &mut self,
+    mut operation: operation::Operation,
+  ) -> Task<Message> {
+    let id = operation.id().to_owned();
+    let extract = operation.phase() == Phase::Extract;
+    let task = operation.start();
+    self.operations.push(operation);
+    let next = if extract {
+      self.scheduler.enqueue(Operation::Install(id.to_owned()))
+    } else {
+      None
+    };
+    Task::batch([operation_task(id, task), self.dispatch(next)])
+  }
+
+  fn download_updated(
+    &mut self,
+    id: String,
+    update: DownloadUpdate,
+  ) -> Task<Message> {
+    match update {
+      DownloadUpdate::Downloading(progress) => {
+        self.set_status(StatusLine::Progress(
+          match progress.fraction() {
+            Some(fraction) => t!(
+              "status.downloading",
+              id = id,
+              percent = format!("{:.0}", fraction * 100.)
+            ),
+            None => t!(
+              "status.downloading_bytes",
+              id = id,
+              size = format_bytes(progress.current)
+            ),
+          },
+        ));
+        Task::none()
+      }
+      DownloadUpdate::Log(_) => Task::none(),
+      // 失败的下载留在列表中，等用户重试或忽略
+      DownloadUpdate::Finished(Err(err)) => {
+        let Some(operation) = self.operation(&id) else {
+          return Task::none();
+        };
+        let path = operation.path().to_path_buf();
+        let log = operation.log();
+        self.record_task(
+          &id,
+          t!("task.download"),
+          Some(err.to_string()),
+          log,
+        );
+        self.advance_batch(
+          &id,
+          BatchPhase::Downloading,
+          BatchPhase::Failed,
+        );
+        Task::batch([
+          Task::future(tokio::fs::remove_file(path)).discard(),
+          Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("download.failed").to_string(),
+            text: t!("download.failed_text", id = id, error = err),
+          })),
+        ])
+      }
+      // 下载完成后接着检查压缩包
+      DownloadUpdate::Finished(Ok(_)) => {
+        let Some(operation) = self
+          .operations
+          .iter_mut()
+          .find(|x| x.id() == id && x.phase() == Phase::Verify)
+        else {
+          return Task::none();
+        };
+        let downloaded = operation.downloaded();
+        let task = operation.start();
+        if let Some(tally) = self
+          .batch_tally
+          .as_mut()
+          .filter(|_| self.batch.contains_key(&id))
+        {
+          tally.add_download(downloaded);
+        }
+        self.advance_batch(
+          &id,
+          BatchPhase::Downloading,
+          BatchPhase::Installing,
+        );
+        self.set_status(StatusLine::Progress(t!(
+          "status.verifying",
+          id = id
+        )));
+        operation_task(id, task)
+      }
+    }
+  }
+
+  // 检查失败时压缩包留着，重试时不用重新下载
+  fn verified(
+    &mut self,
+    id: String,
+    res: Result<ArchiveType, Error>,
+  ) -> Task<Message> {
+    match res {
+      Err(err) => {
+        let Some(operation) = self.operation(&id) else {
+          return Task::none();
+        };
+        let log = operation.log();
+        self.record_task(
+          &id,
+          t!("task.verify"),
+          Some(err.to_string()),
+          log,
+        );
+        self.advance_batch(
+          &id,
+          BatchPhase::Installing,
+          BatchPhase::Failed,
+        );
+        let title = if matches!(
+          err.root(),
+          Error::Install(install::Error::UnknownArchive(_))
+        ) {
+          t!("archive.unknown_format")
+        } else {
+          t!("install.failed")
+        };
+        Task::done(Message::Ui(UiMsg::Warning {
+          title: title.to_string(),
+          text: t!("error.subject_reason", subject = id, error = err),
+        }))
+      }
+      // 升级和重新安装直接装在旧版本上，旧版本不再包含的文件
+      // 在解压时删掉，安装记录中的备注也保留下来
+      Ok(_) => {
+        let next = self.scheduler.enqueue(Operation::Install(id));
+        self.dispatch(next)
+      }
+    }
+  }
+
+  fn install_updated(
+    &mut self,
+    id: String,
+    update: InstallUpdate,
+  ) -> Task<Message> {
+    let Some(operation) = self.operation(&id) else {
+      return Task::none();
+    };
+    let version = operation.version().to_owned();
+    let can_extract = operation.can_extract();
+    let log = operation.log();
+    match update {
+      InstallUpdate::Running(_) | InstallUpdate::Log(_) => {
+        self.set_status(StatusLine::Progress(t!(
+          "status.installing",
+          id = id
+        )));
+        Task::none()
+      }
+      InstallUpdate::Finished((res, mod_manager)) => {
+        match res {
+          // 还有尝试次数时留在队列里，由调度器重新开始
+          Err(err) if can_extract => {
+            tracing::warn!(%id, %err, "install failed, retrying");
+            self.release_mod_manager(mod_manager, true)
+          }
+          // 压缩包留着，用户重试时不用重新下载
+          Err(err) => {
+            self.record_task(
+              &id,
+              t!("task.install"),
+              Some(err.to_string()),
+              log,
+            );
+            self.advance_batch(
+              &id,
+              BatchPhase::Installing,
+              BatchPhase::Failed,
+            );
+            Task::batch([
+              Task::done(Message::Game(GameMsg::ModManagerReady {
+                mod_manager,
+              })),
+              Task::done(Message::Ui(UiMsg::Warning {
+                title: t!("install.failed").to_string(),
+                text: t!(
+                  "error.subject_reason",
+                  subject = id,
+                  error = err
+                ),
+              })),
+            ])
+          }
+          Ok(record) => {
+            self.operations.retain(|x| x.id() != id);
+            self.record_task(&id, t!("task.install"), None, log);
+            self.set_status(StatusLine::Success(t!(
+              "status.installed",
+              id = id,
+              version = version
+            )));
+            // 失败的保持勾选，方便重试
+            if self.advance_batch(
+              &id,
+              BatchPhase::Installing,
+              BatchPhase::Done,
+            ) {
+              self.pending_action.remove(&id);
+            }
+            // 读取不到刚写入的记录时再完整刷新一次
+            let refresh = match record {
+              Some(record) => vec![Task::done(Message::Game(
+                GameMsg::RecordChanged {
+                  id: id.to_string(),
+                  record: Some(record),
+                },
+              ))],
+              None => vec![
+                Task::done(Message::Game(GameMsg::AddCurrentMod {
+                  id: id.to_string(),
+                })),
+                Task::done(Message::Game(
+                  GameMsg::QueueUpdateRecords,
+                )),
+              ],
+            };
+            Task::batch(
+              [Task::done(Message::Game(GameMsg::ModManagerReady {
+                mod_manager,
+              }))]
+              .into_iter()
+              .chain(refresh),
+            )
+          }
+        }
+      }
+    }
+  }
+}
+
+fn operation_task(
+  id: String,
+  task: Task<OperationUpdate>,
+) -> Task<Message> {
+  task.map(move |update| {
+    Message::Task(TaskMsg::OperationUpdated {
+      id: id.to_owned(),
+      update,
+    })
+  })
+}
+
+// 安装成功后压缩包会被删除，所以先复制一份到缓存目录
+async fn prepare_local_archive(
+  source: PathBuf,
+) -> Result<(PathBuf, String, String), Message> {
+  let file_name = source
+    .file_name()
+    .unwrap_or_default()
+    .to_string_lossy()
+    .to_string();
+  let supported = source.is_file()
+    && (ArchiveType::from_file_name(&file_name).is_some()
+      || ArchiveType::sniff_file(&source)
+        .await
+        .is_ok_and(|ty| ty.is_some()));
+  if !supported {
+    return Err(Message::Ui(UiMsg::Warning {
+      title: t!("local.unsupported").to_string(),
+      text: t!("local.unsupported_text", path = source.display()),
+    }));
+  }
+  let (id, version) = registry::identify_archive(&source).await;
+  let path = downloads_dir().join(sanitize_filename::sanitize(
+    format!("local-{}", file_name),
+  ));
+  async {
+    tokio::fs::create_dir_all(downloads_dir()).await?;
+    tokio::fs::copy(&source, &path).await
+  }
+  .await
+  .map_err(|err| {
+    Message::Ui(UiMsg::Warning {
+      title: t!("local.read_failed").to_string(),
+      text: t!(
+        "error.subject_reason",
+        subject = source.display(),
+        error = err
+      ),
+    })
+  })?;
+  Ok((path, id, version))
+}
+
+// 只能预览zip，冲突按当前的安装记录检查
+async fn preview_archive(
+  path: PathBuf,
+  res_mods_path: Option<PathBuf>,
+  records: Records,
+  id: String,
+) -> Result<ArchiveTree, String> {
+  let ty = ArchiveType::sniff_file(&path)
+    .await
+    .map_err(|err| Error::from(err).to_string())?;
+  if ty != Some(ArchiveType::Zip) {
+    return Err(t!("preview.unsupported").to_string());
+  }
+  let entries = list_zip_entries(&path)
+    .await
+    .map_err(|err| Error::from(err).to_string())?;
+  let conflicts = res_mods_path
+    .map(|res_mods_path| {
+      find_conflicts(&res_mods_path, &records, &id, &entries)
+    })
+    .unwrap_or_default();
+  Ok(ArchiveTree::build(&entries, &conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::data::{
+    batch::ReleaseSnapshot, filter::ModFilter,
+    pending::PendingOperation,
+  };
+  use crate::error::Error;
+  use crate::tasks::{TaskStatus, download::DownloadedFile};
+
+  fn get_mod(id: &str) -> TaskMsg {
+    TaskMsg::GetMod(ReleaseSnapshot {
+      id: id.to_string(),
+      url: "http://127.0.0.1:9/mod.zip".parse().unwrap(),
+      version: "1.0".into(),
+      size: None,
+      sha256: None,
+      ty: None,
+    })
+  }
+
+  #[test]
+  fn pause_and_resume_download() {
+    let mut app = App::default();
+    let _ = app.handle_tasks(get_mod("example"));
+    assert!(matches!(
+      app.operations[0].status(),
+      TaskStatus::Running { .. }
+    ));
+
+    let _ = app.handle_tasks(TaskMsg::PauseDownload {
+      id: "example".to_string(),
+    });
+    assert_eq!(app.operations[0].status(), TaskStatus::Paused);
+
+    let _ = app.handle_tasks(TaskMsg::ResumeDownload {
+      id: "example".to_string(),
+    });
+    assert!(matches!(
+      app.operations[0].status(),
+      TaskStatus::Running { .. }
+    ));
+  }
+
+  #[test]
+  fn failed_download_can_be_dismissed() {
+    let mut app = App::default();
+    let _ = app.handle_tasks(get_mod("example"));
+    let _ = app.handle_tasks(TaskMsg::OperationUpdated {
+      id: "example".to_string(),
+      update: OperationUpdate::Download(DownloadUpdate::Finished(
+        Err(Error::NotFound("example".to_string())),
+      )),
+    });
+    assert!(app.operations[0].is_failed());
+    assert!(app.failed_task_error("example").is_some());
+
+    let _ = app.handle_tasks(TaskMsg::DismissFailedTask {
+      id: "example".to_string(),
+    });
+    assert!(app.operations.is_empty());
+  }
+
+  // 检查通过后没有ModManager时只排队，不会开始
+  #[test]
+  fn tasks_wait_for_mod_manager() {
+    let mut app = App::default();
+    let _ = app.handle_tasks(TaskMsg::InstallMod {
+      path: PathBuf::from("example.zip"),
+      id: "example".to_string(),
+      version: "1.0".into(),
+      ty: None,
+    });
+    let _ = app.handle_tasks(TaskMsg::UninstallMod {
+      id: "other".to_string(),
+    });
+    assert_eq!(app.operations[0].phase(), Phase::Verify);
+    let _ = app.handle_tasks(TaskMsg::OperationUpdated {
+      id: "example".to_string(),
+      update: OperationUpdate::Verified(Ok(ArchiveType::Zip)),
+    });
+    assert_eq!(app.operations[0].phase(), Phase::Extract);
+    assert_eq!(app.operations[0].status(), TaskStatus::Queued);
+    assert_eq!(app.uninstalls[0].status(), TaskStatus::Queued);
+  }
+
+  #[test]
+  fn queued_tasks_are_persisted() {
+    let mut app = App::default();
+    let _ = app.update(Message::Task(TaskMsg::UninstallMod {
+      id: "example".to_string(),
+    }));
+    assert_eq!(
+      app.persisted_queue.operations,
+      vec![PendingOperation {
+        kind: PendingKind::Uninstall,
+        id: "example".to_string(),
+        version: None,
+        path: None,
+      }]
+    );
+  }
+
+  #[test]
+  fn batch_preview_can_be_cancelled() {
+    let mut app = App::default();
+    let _ = app.handle_tasks(TaskMsg::PreviewBatch);
+    assert!(app.plan.is_some());
+    let _ = app.handle_tasks(TaskMsg::CancelBatch);
+    assert!(app.plan.is_none());
+  }
+
+  // registry中有2.0，已安装1.0
+  fn updatable_app() -> App {
+    let mut app = App {
+      auto_update_pending: true,
+      records_loaded: true,
+      ..App::default()
+    };
+    app.registry_loads = vec![(
+      "http://127.0.0.1:9/registry".parse().unwrap(),
+      LoadState::Loaded,
+    )];
+    app.registries.push_back(registry::Registry {
+      mods: [(
+        "example".to_string(),
+        registry::Mod::local("example", "2.0"),
+      )]
+      .into(),
+      ..Default::default()
+    });
+    app.records.records.insert(
+      "example".to_string(),
+      crate::mod_manager::Record {
+        metadata: None,
+        update_time: 0,
+        version: "1.0".into(),
+        files: Vec::new(),
+      },
+    );
+    app.refresh_mod_index();
+    app
+  }
+
+  // 多个registry中的同一模组只出现一次，取靠前的registry中的；
+  // 名字相同时按id排，顺序不随HashMap变化
+  #[test]
+  fn available_mods_are_sorted_and_unique() {
+    let mut app = updatable_app();
+    let mut shadowed = registry::Mod::local("example", "1.5");
+    shadowed.name = "Shadowed".to_string();
+    app.registries.push_back(registry::Registry {
+      mods: [
+        ("example".to_string(), shadowed),
+        ("b".to_string(), registry::Mod::local("b", "1.0")),
+      ]
+      .into(),
+      ..Default::default()
+    });
+    for id in ["c", "a"] {
+      let mut modr = registry::Mod::local(id, "1.0");
+      modr.name = "Same".to_string();
+      app.local_mods.push(modr);
+    }
+    app.local_mods.push(registry::Mod::local("b", "0.1"));
+    app.refresh_mod_index();
+
+    let ids = |mods: Vec<&registry::Mod>| -> Vec<String> {
+      mods.into_iter().map(|modr| modr.id.to_owned()).collect()
+    };
+    assert_eq!(
+      ids(app.available_mods()),
+      vec!["b", "example", "a", "c"]
+    );
+    assert_eq!(ids(app.indexed_mods()), ids(app.available_mods()));
+    assert_eq!(app.listed_mod("example").unwrap().version, "2.0");
+    assert_eq!(app.listed_mod("b").unwrap().version, "1.0");
+    assert!(app.request_mod("a").is_none());
+    assert_eq!(app.listed_mod("a").unwrap().name, "Same");
+  }
+
+  #[test]
+  fn auto_update_runs_once_when_ready() {
+    let mut app = updatable_app();
+    app.records_loaded = false;
+    assert_eq!(app.take_auto_update(), None);
+    app.records_loaded = true;
+    app.registry_loads[0].1 = LoadState::Pending;
+    assert_eq!(app.take_auto_update(), None);
+    app.registry_loads[0].1 = LoadState::Loaded;
+    assert_eq!(app.take_auto_update(), Some(1));
+    assert_eq!(app.take_auto_update(), None);
+  }
+
+  #[test]
+  fn auto_update_is_skipped_offline() {
+    let mut app = updatable_app();
+    app.registries.clear();
+    app.registry_loads[0].1 =
+      LoadState::Failed("offline".to_string());
+    app.refresh_mod_index();
+    assert_eq!(app.take_auto_update(), None);
+    assert!(!app.auto_update_pending);
+  }
+
+  #[test]
+  fn auto_update_keeps_existing_selection() {
+    let mut app = updatable_app();
+    app.set_action("other".to_string(), PendingAction::Uninstall);
+    assert_eq!(app.take_auto_update(), None);
+    assert!(!app.auto_update_pending);
+  }
+
+  // 只有registry中还有记录的版本时才能重新安装修复
+  #[test]
+  fn repair_reinstalls_recorded_version() {
+    let mut app = updatable_app();
+    app.health = Some(Default::default());
+    assert!(!app.can_repair("example"));
+    let _ = app.update(Message::Game(GameMsg::RepairMod {
+      id: "example".to_string(),
+    }));
+    assert!(app.health.is_some());
+    assert!(app.pending_action.is_empty());
+
+    app.records.records.get_mut("example").unwrap().version =
+      "2.0".into();
+    assert!(app.can_repair("example"));
+    let _ = app.update(Message::Game(GameMsg::RepairMod {
+      id: "example".to_string(),
+    }));
+    assert!(app.health.is_none());
+    assert_eq!(app.selected_versions["example"], "2.0");
+    assert_eq!(app.action_of("example"), PendingAction::Install);
+  }
+
+  // 确认后registry重新加载成了别的版本，排队中的下载和之后的
+  // 安装仍然用确认时取下的版本、地址和格式
+  #[test]
+  fn batch_keeps_snapshot_across_registry_reload() {
+    let mut app = updatable_app();
+    let modr = app.registries[0].mods.get_mut("example").unwrap();
+    modr.url = "http://127.0.0.1:9/example-2.0.zip".to_string();
+    modr.ty = Some(ArchiveType::Zip);
+    let (releases, missing, warnings) = app.release_snapshots(vec![
+      "example".to_string(),
+      "gone".to_string(),
+    ]);
+    assert_eq!(missing, ["gone"]);
+    assert!(warnings.is_empty());
+    assert_eq!(releases[0].version, "2.0");
+    assert_eq!(releases[0].ty, Some(ArchiveType::Zip));
+
+    app.registries.clear();
+    let mut reloaded = registry::Mod::local("example", "3.0");
+    reloaded.url = "http://127.0.0.1:9/example-3.0.zip".to_string();
+    app.registries.push_back(registry::Registry {
+      mods: [("example".to_string(), reloaded)].into(),
+      ..Default::default()
+    });
+    app.refresh_mod_index();
+
+    let _ = app.update(Message::Task(TaskMsg::UpdateMods {
+      install: releases.to_owned(),
+      missing,
+      uninstall: Vec::new(),
+      skipped: Vec::new(),
+    }));
+    let _ = app
+      .update(Message::Task(TaskMsg::GetMod(releases[0].to_owned())));
+    let operation = app.operation("example").unwrap();
+    assert_eq!(operation.version(), "2.0");
+    assert!(
+      operation
+        .path()
+        .to_string_lossy()
+        .contains("example-2.0.zip")
+    );
+    assert_eq!(
+      app.persisted_queue.operations[0].version.as_deref(),
+      Some("2.0")
+    );
+  }
+
+  fn upgrade_example(app: &mut App) {
+    app
+      .pending_action
+      .insert("example".to_string(), PendingAction::Install);
+    let (releases, _, _) =
+      app.release_snapshots(vec!["example".to_string()]);
+    let _ = app.handle_tasks(TaskMsg::UpdateMods {
+      install: releases.to_owned(),
+      missing: Vec::new(),
+      uninstall: Vec::new(),
+      skipped: Vec::new(),
+    });
+    let _ = app.handle_tasks(TaskMsg::GetMod(releases[0].to_owned()));
+  }
+
+  fn download_finished(app: &mut App, res: Result<PathBuf, Error>) {
+    let _ = app.handle_tasks(TaskMsg::OperationUpdated {
+      id: "example".to_string(),
+      update: OperationUpdate::Download(DownloadUpdate::Finished(
+        res.map(|path| DownloadedFile {
+          path,
+          file_name: Some("example.zip".to_string()),
+        }),
+      )),
+    });
+  }
+
+  fn set_note(app: &mut App, note: &str) {
+    app.records.records.get_mut("example").unwrap().metadata =
+      Some(crate::mod_manager::Metadata {
+        note: note.to_string(),
+        archive_sha256: None,
+      });
+  }
+
+  // 升级时不卸载旧版本，检查通过后直接在旧版本上解压，
+  // 安装记录和其中的备注一直都在
+  #[test]
+  fn upgrade_installs_over_record() {
+    let mut app = updatable_app();
+    set_note(&mut app, "keep me");
+    upgrade_example(&mut app);
+    download_finished(&mut app, Ok(PathBuf::from("example.zip")));
+    assert_eq!(app.operations[0].phase(), Phase::Verify);
+    assert!(app.uninstalls.is_empty());
+
+    let _ = app.handle_tasks(TaskMsg::OperationUpdated {
+      id: "example".to_string(),
+      update: OperationUpdate::Verified(Ok(ArchiveType::Zip)),
+    });
+    assert_eq!(app.operations[0].phase(), Phase::Extract);
+    assert!(app.uninstalls.is_empty());
+    assert!(
+      app
+        .scheduler
+        .is_queued(&Operation::Install("example".to_string()))
+    );
+    assert!(
+      !app
+        .scheduler
+        .is_queued(&Operation::Uninstall("example".to_string()))
+    );
+    assert_eq!(app.records.records["example"].note(), "keep me");
+  }
+
+  // 下载或检查失败时已安装的版本保持不动
+  #[test]
+  fn failed_upgrade_keeps_installed_version() {
+    let mut app = updatable_app();
+    upgrade_example(&mut app);
+    download_finished(
+      &mut app,
+      Err(Error::NotFound("example".to_string())),
+    );
+    assert!(app.operations[0].is_failed());
+    assert!(app.uninstalls.is_empty());
+
+    let mut app = updatable_app();
+    upgrade_example(&mut app);
+    download_finished(&mut app, Ok(PathBuf::from("example.zip")));
+    let _ = app.handle_tasks(TaskMsg::OperationUpdated {
+      id: "example".to_string(),
+      update: OperationUpdate::Verified(Err(Error::NotFound(
+        "example.zip".to_string(),
+      ))),
+    });
+    assert!(app.operations[0].is_failed());
+    assert!(app.uninstalls.is_empty());
+    assert!(app.records.records.contains_key("example"));
+  }
+
+  // 搜索框也匹配备注
+  #[test]
+  fn notes_are_searchable() {
+    let mut app = updatable_app();
+    set_note(&mut app, "Pretty ships");
+    app.refresh_mod_index();
+    let _ = app.update(Message::Ui(UiMsg::ModQueryChanged(
+      "ships PRETTY".to_string(),
+    )));
+    assert_eq!(app.filtered_index, ["example"]);
+    let _ = app.update(Message::Ui(UiMsg::ModQueryChanged(
+      "ugly".to_string(),
+    )));
+    assert!(app.filtered_index.is_empty());
+    let _ =
+      app.update(Message::Ui(UiMsg::ModQueryChanged(String::new())));
+    assert_eq!(app.filtered_index, ["example"]);
+  }
+
+  fn install_record(app: &mut App, id: &str, version: &str) {
+    app.current_mods.insert(id.to_string());
+    app.records.records.insert(
+      id.to_string(),
+      crate::mod_manager::Record {
+        metadata: None,
+        update_time: 0,
+        version: version.into(),
+        files: Vec::new(),
+      },
+    );
+  }
+
+  fn filtered(app: &mut App, filter: ModFilter) -> Vec<String> {
+    let _ = app.update(Message::Ui(UiMsg::SelectModFilter(filter)));
+    let mut ids = app.filtered_index.to_owned();
+    ids.sort();
+    ids
+  }
+
+  // example可以更新，current已是最新，fresh还没安装，
+  // manual只在本地有安装记录
+  #[test]
+  fn quick_filters() {
+    let mut app = updatable_app();
+    let registry = &mut app.registries[0].mods;
+    for id in ["current", "fresh"] {
+      registry
+        .insert(id.to_string(), registry::Mod::local(id, "1.0"));
+    }
+    install_record(&mut app, "current", "1.0");
+    install_record(&mut app, "manual", "0.1");
+    app.local_mods.push(registry::Mod::local("manual", "0.1"));
+    app.refresh_mod_index();
+
+    assert_eq!(
+      filtered(&mut app, ModFilter::All),
+      ["current", "example", "fresh", "manual"]
+    );
+    assert_eq!(
+      filtered(&mut app, ModFilter::Installed),
+      ["current", "example", "manual"]
+    );
+    assert_eq!(filtered(&mut app, ModFilter::Updatable), ["example"]);
+    assert_eq!(
+      filtered(&mut app, ModFilter::NotInstalled),
+      ["fresh"]
+    );
+  }
+
+  fn selected(app: &App) -> Vec<String> {
+    let mut ids: Vec<_> =
+      app.pending_action.keys().cloned().collect();
+    ids.sort();
+    ids
+  }
+
+  // 批量勾选跳过已撤回和不兼容的模组，强制兼容的除外
+  #[test]
+  fn bulk_selection_skips_disabled_mods() {
+    let mut app = updatable_app();
+    app.game_version = Some("13.5.0".to_string());
+    install_record(&mut app, "example", "1.0");
+    install_record(&mut app, "stale", "1.0");
+    let mut mods = vec![
+      registry::Mod::local("stale", "2.0"),
+      registry::Mod::local("yanked", "1.0"),
+      registry::Mod::local("old", "1.0"),
+      registry::Mod::local("forced", "1.0"),
+      registry::Mod::local("fresh", "1.0"),
+    ];
+    mods[0].yanked = true;
+    mods[1].yanked = true;
+    for modr in &mut mods[2..4] {
+      modr.game_versions = vec!["13.4".to_string()];
+    }
+    app.compat_overrides.insert("forced".to_string());
+    for modr in mods {
+      app.registries[0].mods.insert(modr.id.to_owned(), modr);
+    }
+    app.refresh_mod_index();
+
+    let mut selectable = app.selectable_mods(|_| true);
+    selectable.sort();
+    assert_eq!(selectable, ["example", "forced", "fresh"]);
+
+    let _ = app.update(Message::Ui(UiMsg::SelectUpdatable));
+    assert_eq!(selected(&app), ["example"]);
+    let _ = app.update(Message::Ui(UiMsg::SelectNotInstalled));
+    assert_eq!(selected(&app), ["example", "forced", "fresh"]);
+    let _ = app.update(Message::Ui(UiMsg::ClearSelection));
+    assert!(selected(&app).is_empty());
+  }
+
+  // 排序只在重建索引时做一次，切换筛选和搜索只按索引顺序过滤
+  #[test]
+  fn filtering_keeps_index_order() {
+    let mut app = App::default();
+    let mut registry = registry::Registry::default();
+    for i in 0..5000 {
+      let id = format!("mod{:04}", i);
+      let mut modr = registry::Mod::local(&id, "1.0");
+      modr.name = format!("Mod {}", (i * 7919) % 5000);
+      registry.mods.insert(id.to_owned(), modr);
+      if i % 3 == 0 {
+        install_record(&mut app, &id, "1.0");
+      }
+    }
+    app.registries.push_back(registry);
+    app.refresh_mod_index();
+    let index = app.mod_index.to_owned();
+    assert_eq!(index.len(), 5000);
+    assert_eq!(app.filtered_index, index);
+
+    // 没有重建索引时改排序不影响筛选结果的顺序
+    app.settings.sort_descending = !app.settings.sort_descending;
+    let _ = app.update(Message::Ui(UiMsg::SelectModFilter(
+      ModFilter::Installed,
+    )));
+    let installed: Vec<_> = index
+      .iter()
+      .filter(|id| app.current_mods.contains(*id))
+      .cloned()
+      .collect();
+    assert_eq!(installed.len(), 1667);
+    assert_eq!(app.filtered_index, installed);
+    let _ = app.update(Message::Ui(UiMsg::ModQueryChanged(
+      "mod 1".to_string(),
+    )));
+    assert!(!app.filtered_index.is_empty());
+    assert!(app.filtered_index.len() < installed.len());
+    assert!(
+      app.filtered_index.iter().all(|id| installed.contains(id))
+    );
+    let positions: Vec<_> = app
+      .filtered_index
+      .iter()
+      .map(|id| index.iter().position(|x| x == id).unwrap())
+      .collect();
+    assert!(positions.is_sorted());
+    assert_eq!(app.mod_index, index);
+  }
+}
@@ -0,0 +1,461 @@
+use crate::app::{App, DEFAULT_WINDOW_SIZE};
+
+use std::time::{Duration, Instant};
+
+use crate::crash;
+use crate::format::format_bytes;
+use crate::i18n::t;
+use crate::install_log::latest_log;
+use crate::paths::logs_dir;
+use crate::{
+  data::{
+    batch::PendingAction,
+    import::{match_selection, parse_selection},
+    notification::Severity,
+    settings::{Settings, WindowGeometry},
+    share::{LINK_WARN_LEN, share_link, share_registry},
+    status::StatusLine,
+  },
+  messages::{Message, SettingsMsg, UiMsg},
+};
+use iced::{Point, Task, clipboard, window};
+
+// 拖动或缩放窗口时会连续产生事件，停下来之后再保存
+const WINDOW_SAVE_DELAY: Duration = Duration::from_millis(500);
+
+impl App {
+  pub(super) fn handle_ui(
+    &mut self,
+    message: UiMsg,
+  ) -> Task<Message> {
+    match message {
+      UiMsg::Warning { title, text } => {
+        self.set_status(StatusLine::Error(format!(
+          "{}: {}",
+          title,
+          text.replace('\n', " ")
+        )));
+        self.notify(Severity::Warning, title, text);
+        Task::none()
+      }
+      UiMsg::Info { title, text } => {
+        self.set_status(StatusLine::Success(title.to_owned()));
+        self.notify(Severity::Info, title, text);
+        Task::none()
+      }
+      UiMsg::DismissNotification(id) => {
+        self.notifications.retain(|x| x.id != id);
+        Task::none()
+      }
+      UiMsg::ExpireNotifications => {
+        let now = Instant::now();
+        self.notifications.retain(|x| !x.is_expired(now));
+        Task::none()
+      }
+      UiMsg::ToggleNotificationHistory => {
+        self.show_notification_history =
+          !self.show_notification_history;
+        Task::none()
+      }
+      UiMsg::OpenUrl(url) => {
+        match open::that_detached(url.as_str()) {
+          Ok(()) => Task::none(),
+          Err(err) => Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("open_url.failed").to_string(),
+            text: format!("{}: {}", url, err),
+          })),
+        }
+      }
+      // 打开或忽略后都不再提示这份报告
+      UiMsg::OpenCrashReport | UiMsg::DismissCrashReport => {
+        let Some(report) = self.crash_report.take() else {
+          return Task::none();
+        };
+        if let Err(err) = crash::acknowledge(&report) {
+          tracing::warn!(%err, "failed to acknowledge crash report");
+        }
+        if !matches!(message, UiMsg::OpenCrashReport) {
+          return Task::none();
+        }
+        match open::that_detached(&report) {
+          Ok(()) => Task::none(),
+          Err(err) => Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("open_url.failed").to_string(),
+            text: format!("{}: {}", report.display(), err),
+          })),
+        }
+      }
+      UiMsg::SelectModVersion { id, version } => {
+        self.selected_versions.insert(id, version);
+        Task::none()
+      }
+      UiMsg::AddCompatOverride { id } => {
+        self.compat_overrides.insert(id);
+        Task::none()
+      }
+      UiMsg::RemoveCompatOverride { id } => {
+        self.compat_overrides.remove(&id);
+        if !self.is_installable(&id)
+          && self.action_of(&id) == PendingAction::Install
+        {
+          self.set_action(id, PendingAction::Keep);
+        }
+        Task::none()
+      }
+      UiMsg::DismissStatus => {
+        self.status = None;
+        Task::none()
+      }
+      UiMsg::ModListScrolled { offset, height } => {
+        self.list_offset = offset;
+        self.list_viewport = height;
+        Task::none()
+      }
+      UiMsg::SelectModFilter(filter) => {
+        self.mod_filter = filter;
+        self.filtered_index = self.filtered_mods(filter);
+        Task::none()
+      }
+      UiMsg::ModQueryChanged(query) => {
+        self.mod_query = query;
+        self.filtered_index = self.filtered_mods(self.mod_filter);
+        Task::none()
+      }
+      UiMsg::SetPendingAction { id, action } => {
+        if self.allows_action(&id, action) {
+          self.set_action(id, action);
+        }
+        Task::none()
+      }
+      UiMsg::SetForceReinstall { id, force } => {
+        if force {
+          self.force_reinstall.insert(id);
+        } else {
+          self.force_reinstall.remove(&id);
+        }
+        Task::none()
+      }
+      UiMsg::SelectUpdatable => {
+        for id in
+          self.selectable_mods(|id| self.updatable_mods.contains(id))
+        {
+          self.set_action(id, PendingAction::Install);
+        }
+        Task::none()
+      }
+      UiMsg::SelectNotInstalled => {
+        for id in
+          self.selectable_mods(|id| !self.current_mods.contains(id))
+        {
+          self.set_action(id, PendingAction::Install);
+        }
+        Task::none()
+      }
+      UiMsg::ClearSelection => {
+        self.pending_action.clear();
+        self.force_reinstall.clear();
+        Task::none()
+      }
+      UiMsg::ImportSelection => {
+        let Ok(Some(path)) = native_dialog::FileDialog::new()
+          .set_title(t!("import.pick"))
+          .add_filter(t!("import.filter"), &["inl", "ini", "txt"])
+          .show_open_single_file()
+        else {
+          return Task::none();
+        };
+        // 旧的安装器不一定用UTF-8保存，读不出的字符替换掉
+        Task::perform(
+          async move {
+            tokio::fs::read(&path)
+              .await
+              .map(|data| String::from_utf8_lossy(&data).into_owned())
+              .map_err(|err| {
+                t!(
+                  "error.subject_reason",
+                  subject = path.display(),
+                  error = err
+                )
+              })
+          },
+          |res| match res {
+            Ok(content) => {
+              Message::Ui(UiMsg::SelectionLoaded(content))
+            }
+            Err(text) => Message::Ui(UiMsg::Warning {
+              title: t!("import.read_failed").to_string(),
+              text,
+            }),
+          },
+        )
+      }
+      // 只勾选，确认前用户还可以检查和修改
+      UiMsg::SelectionLoaded(content) => {
+        let entries = parse_selection(&content);
+        if entries.is_empty() {
+          return Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("import.empty").to_string(),
+            text: String::new(),
+          }));
+        }
+        let result = match_selection(
+          &entries,
+          self
+            .indexed_mods()
+            .into_iter()
+            .map(|modr| (modr.id.as_str(), modr.name.as_str())),
+        );
+        let (selected, blocked): (Vec<_>, Vec<_>) =
+          result.ids.into_iter().partition(|id| {
+            self.allows_action(id, PendingAction::Install)
+          });
+        let count = selected.len();
+        for id in selected {
+          self.set_action(id, PendingAction::Install);
+        }
+        let title = t!("import.done", count = count);
+        let text = [
+          (!result.unmatched.is_empty()).then(|| {
+            t!(
+              "import.unmatched",
+              names = result.unmatched.join(", ")
+            )
+          }),
+          (!blocked.is_empty())
+            .then(|| t!("import.blocked", ids = blocked.join(", "))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        // 有没匹配上的时候需要用户留意，不自动消失
+        Task::done(Message::Ui(if text.is_empty() {
+          UiMsg::Info {
+            title,
+            text: String::new(),
+          }
+        } else {
+          UiMsg::Warning {
+            title,
+            text: text.join("\n"),
+          }
+        }))
+      }
+      // 窗口不在任何显示器上时（例如显示器已断开）移回原点
+      UiMsg::WindowOpened(id) => {
+        window::monitor_size(id).then(move |monitor| match monitor {
+          Some(_) => Task::none(),
+          None => window::move_to(id, Point::ORIGIN),
+        })
+      }
+      UiMsg::WindowChanged { id, size, position } => {
+        let mut geometry = self
+          .window_geometry
+          .or(self.settings.window)
+          .unwrap_or(WindowGeometry {
+            width: DEFAULT_WINDOW_SIZE.width,
+            height: DEFAULT_WINDOW_SIZE.height,
+            position: None,
+            maximized: false,
+          });
+        if let Some(size) = size {
+          geometry.width = size.width;
+          geometry.height = size.height;
+        }
+        if let Some(position) = position {
+          geometry.position = Some((position.x, position.y));
+        }
+        self.window_geometry = Some(geometry);
+        self.window_generation += 1;
+        let generation = self.window_generation;
+        Task::perform(
+          tokio::time::sleep(WINDOW_SAVE_DELAY),
+          move |()| {
+            Message::Ui(UiMsg::WindowSettled { id, generation })
+          },
+        )
+      }
+      UiMsg::WindowSettled { id, generation } => {
+        if generation != self.window_generation {
+          return Task::none();
+        }
+        window::get_maximized(id).map(|maximized| {
+          Message::Ui(UiMsg::WindowMaximized(maximized))
+        })
+      }
+      UiMsg::WindowMaximized(maximized) => {
+        let Some(pending) = self.window_geometry.take() else {
+          return Task::none();
+        };
+        // 最大化后的大小不记录，还原时用之前的
+        let geometry = if maximized {
+          WindowGeometry {
+            maximized,
+            ..self.settings.window.unwrap_or(pending)
+          }
+        } else {
+          WindowGeometry {
+            maximized,
+            ..pending
+          }
+        };
+        Task::done(Message::Settings(SettingsMsg::SettingsChanged(
+          Settings {
+            window: Some(geometry),
+            ..self.settings.to_owned()
+          },
+        )))
+      }
+      UiMsg::ToggleTaskPanel => {
+        self.show_tasks = !self.show_tasks;
+        Task::none()
+      }
+      UiMsg::ShowTaskLog(index) => {
+        let Some(record) = self.task_history.get(index) else {
+          return Task::none();
+        };
+        let path = logs_dir().join(sanitize_filename::sanitize(
+          format!("{}-{}.log", record.id, index),
+        ));
+        let content = format!(
+          "{} {}\n{}\n\n{}",
+          record.operation,
+          record.id,
+          record.error.as_deref().unwrap_or_default(),
+          record.log
+        );
+        Task::future(async move {
+          tokio::fs::create_dir_all(logs_dir()).await?;
+          tokio::fs::write(&path, content).await?;
+          open::that_detached(&path)
+        })
+        .then(|res: std::io::Result<()>| match res {
+          Ok(()) => Task::none(),
+          Err(err) => Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("task.log_failed").to_string(),
+            text: t!("error.reason", error = err),
+          })),
+        })
+      }
+      UiMsg::TogglePreviewDir(path) => {
+        let Some(preview) = self.preview.as_mut() else {
+          return Task::none();
+        };
+        if !preview.expanded.remove(&path) {
+          preview.expanded.insert(path);
+        }
+        Task::none()
+      }
+      UiMsg::CopyArchiveHash { id } => {
+        let Some(hash) = self
+          .records
+          .records
+          .get(&id)
+          .and_then(|record| record.archive_sha256())
+          .map(str::to_string)
+        else {
+          return Task::none();
+        };
+        self.set_status(StatusLine::Success(t!(
+          "status.copied",
+          text = hash
+        )));
+        clipboard::write(hash)
+      }
+      UiMsg::CopyModId { id } => {
+        self.set_status(StatusLine::Success(t!(
+          "status.copied",
+          text = id
+        )));
+        clipboard::write(id)
+      }
+      UiMsg::ShareInstalledMods => {
+        let (shared, skipped) = share_registry(
+          self.registries.make_contiguous(),
+          self.records.records.iter().map(|(id, record)| {
+            (
+              id.as_str(),
+              record.version.as_str(),
+              record.archive_sha256(),
+            )
+          }),
+        );
+        if shared.mods.is_empty() {
+          return Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("share.nothing").to_string(),
+            text: String::new(),
+          }));
+        }
+        let link = match share_link(&shared) {
+          Ok(link) => link,
+          Err(err) => {
+            return Task::done(Message::Ui(UiMsg::Warning {
+              title: t!("share.failed").to_string(),
+              text: t!("error.reason", error = err),
+            }));
+          }
+        };
+        let title = t!("share.copied", count = shared.mods.len());
+        let text = [
+          (!skipped.is_empty())
+            .then(|| t!("share.skipped", ids = skipped.join(", "))),
+          (link.len() > LINK_WARN_LEN).then(|| {
+            t!(
+              "share.too_long",
+              size = format_bytes(link.len() as u64)
+            )
+          }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        Task::batch([
+          clipboard::write(link),
+          Task::done(Message::Ui(if text.is_empty() {
+            UiMsg::Info {
+              title,
+              text: String::new(),
+            }
+          } else {
+            UiMsg::Warning {
+              title,
+              text: text.join("\n"),
+            }
+          })),
+        ])
+      }
+      // 复制的是解析后实际用来下载的地址
+      UiMsg::CopyModUrl { id } => match self.release_url(&id) {
+        Some((_, Ok(url))) => {
+          self.set_status(StatusLine::Success(t!(
+            "status.copied",
+            text = url
+          )));
+          clipboard::write(url.to_string())
+        }
+        Some((release, Err(err))) => {
+          Task::done(Message::Ui(UiMsg::Warning {
+            title: t!("download.invalid_url").to_string(),
+            text: format!("{}: {} ({})", id, release.url, err),
+          }))
+        }
+        None => Task::none(),
+      },
+      UiMsg::OpenInstallLog { id } => Task::future(async move {
+        latest_log(&logs_dir(), &id)
+          .await
+          .map(|path| open::that_detached(&path))
+      })
+      .then(|res| match res {
+        Some(Ok(())) => Task::none(),
+        Some(Err(err)) => Task::done(Message::Ui(UiMsg::Warning {
+          title: t!("task.log_failed").to_string(),
+          text: t!("error.reason", error = err),
+        })),
+        None => Task::done(Message::Ui(UiMsg::Warning {
+          title: t!("task.log_failed").to_string(),
+          text: t!("mod.no_install_log").to_string(),
+        })),
+      }),
+    }
+  }
+}
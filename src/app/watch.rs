@@ -0,0 +1,108 @@
+use std::{
+  path::PathBuf,
+  time::{Duration, Instant},
+};
+
+use futures::{SinkExt, Stream, channel::mpsc::Sender};
+use iced::{Subscription, stream};
+use notify::{EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::messages::{GameMsg, Message};
+
+// 解压时会连续产生大量事件，安静这么久之后才通知一次
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+// 以目录为键，切换游戏目录或不再需要时旧的监视随之停止
+pub(super) fn subscription(
+  res_mods: PathBuf,
+) -> Subscription<Message> {
+  Subscription::run_with(res_mods, changes)
+}
+
+fn changes(
+  res_mods: &PathBuf,
+) -> impl Stream<Item = Message> + use<> {
+  let res_mods = res_mods.to_owned();
+  stream::channel(1, |mut output: Sender<Message>| async move {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(
+      move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| {
+          !matches!(event.kind, EventKind::Access(_))
+        }) {
+          let _ = sender.send(Instant::now());
+        }
+      },
+    );
+    // 监视失败只影响自动刷新，记录日志即可
+    let mut watcher = match watcher {
+      Ok(watcher) => watcher,
+      Err(err) => {
+        tracing::warn!(%err, "failed to create res_mods watcher");
+        return;
+      }
+    };
+    if let Err(err) =
+      watcher.watch(&res_mods, RecursiveMode::Recursive)
+    {
+      tracing::warn!(%err, ?res_mods, "failed to watch res_mods");
+      return;
+    }
+    while let Some(mut changed_at) = receiver.recv().await {
+      while let Ok(Some(at)) =
+        tokio::time::timeout(DEBOUNCE, receiver.recv()).await
+      {
+        changed_at = at;
+      }
+      let message =
+        Message::Game(GameMsg::ResModsChangedExternally {
+          path: res_mods.to_owned(),
+          changed_at,
+        });
+      if output.send(message).await.is_err() {
+        break;
+      }
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use futures::StreamExt;
+
+  // 连续的多次改动合并成一条消息
+  #[tokio::test]
+  async fn changes_are_debounced() {
+    let dir = std::env::temp_dir()
+      .join(format!("kmm-watch-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    let mut changes = Box::pin(changes(&dir));
+    let next = tokio::spawn(async move {
+      let message = changes.next().await;
+      let more =
+        tokio::time::timeout(DEBOUNCE * 2, changes.next()).await;
+      (message, more.is_err())
+    });
+    // 等监视开始
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    for name in ["a.txt", "b.txt", "c.txt"] {
+      tokio::fs::write(dir.join(name), name).await.unwrap();
+    }
+    let (message, quiet) =
+      tokio::time::timeout(Duration::from_secs(10), next)
+        .await
+        .unwrap()
+        .unwrap();
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    assert!(matches!(
+      message,
+      Some(Message::Game(GameMsg::ResModsChangedExternally {
+        path,
+        ..
+      })) if path == dir
+    ));
+    assert!(quiet);
+  }
+}
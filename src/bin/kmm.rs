@@ -0,0 +1,414 @@
+// 不启动界面的命令行入口，供脚本和自动化使用
+use std::{
+  collections::HashSet, io::Write, path::PathBuf, process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use korabli_mod_manager::{
+  data::{
+    registry::{Mod, Registry, load_from_url, merge_mods},
+    settings::Settings,
+  },
+  error::{Error, Operation, ResultExt},
+  format::format_bytes,
+  http::{client, init_client},
+  i18n::{Locale, set_locale},
+  mod_manager::{ModManager, Records},
+  paths::{archive_cache_dir, logs_dir, settings_path},
+  t,
+  tasks::{
+    TaskEvent,
+    download::{DownloadRequest, cached_download_path, download},
+    install::{discard_archive, install},
+  },
+};
+use serde::Serialize;
+use url::Url;
+
+// 部分操作失败时的退出码，整体无法进行时为2
+const EXIT_PARTIAL: u8 = 1;
+const EXIT_FATAL: u8 = 2;
+
+#[derive(Debug, Parser)]
+#[command(name = "kmm", version)]
+/// 不打开界面管理模组
+struct Cli {
+  /// 游戏目录，默认使用设置中的目录，再没有时使用当前目录
+  #[arg(long, global = true)]
+  game_dir: Option<PathBuf>,
+  /// registry地址，可以重复指定，靠前的优先；默认依次使用设置、
+  /// KMM_REGISTRY_URLS环境变量和内置的地址
+  #[arg(long = "registry", global = true)]
+  registries: Vec<Url>,
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+  /// 列出registry和安装记录中的模组
+  List {
+    /// 以JSON格式输出
+    #[arg(long)]
+    json: bool,
+  },
+  /// 安装或升级模组，`id@version`安装指定版本
+  Install {
+    #[arg(required = true)]
+    ids: Vec<String>,
+  },
+  /// 卸载模组
+  Uninstall {
+    #[arg(required = true)]
+    ids: Vec<String>,
+  },
+  /// 把有新版本的已安装模组升级到最新版本
+  Update,
+}
+
+#[derive(Debug, Serialize)]
+struct ListEntry<'a> {
+  id: &'a str,
+  name: &'a str,
+  installed: Option<&'a str>,
+  available: Option<&'a str>,
+  update: bool,
+}
+
+struct Context {
+  settings: Settings,
+  game_dir: PathBuf,
+  registry_urls: Vec<Url>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+  tracing_subscriber::fmt()
+    .with_writer(std::io::stderr)
+    .init();
+  let cli = Cli::parse();
+  match run(cli).await {
+    Ok(true) => ExitCode::SUCCESS,
+    Ok(false) => ExitCode::from(EXIT_PARTIAL),
+    Err(err) => {
+      eprintln!("{}", t!("cli.fatal", error = err));
+      ExitCode::from(EXIT_FATAL)
+    }
+  }
+}
+
+// 全部成功时返回true
+async fn run(cli: Cli) -> Result<bool, Error> {
+  // 配置文件损坏时和界面一样使用默认配置，只做提示
+  let (settings, settings_error) =
+    match Settings::load(&settings_path()) {
+      Ok(settings) => (settings, None),
+      Err(err) => (Settings::default(), Some(err)),
+    };
+  set_locale(settings.language.unwrap_or_else(Locale::detect));
+  if let Some(err) = settings_error {
+    eprintln!("{}", t!("cli.settings_invalid", error = err));
+  }
+  init_client(&settings.http_options());
+  // 设置中写错的地址跳过，和界面一样只做提示
+  let registry_urls = if cli.registries.is_empty() {
+    let (urls, invalid) = settings.parsed_registry_urls();
+    for url in invalid {
+      eprintln!("{}", t!("cli.invalid_registry", url = url));
+    }
+    urls
+  } else {
+    cli.registries
+  };
+  let game_dir =
+    cli.game_dir.unwrap_or_else(|| settings.game_dir_path());
+  let context = Context {
+    settings,
+    game_dir,
+    registry_urls,
+  };
+  let mut mod_manager = ModManager::try_from_game_dir_with(
+    &context.game_dir,
+    context.settings.allow_external_res_mods,
+  )
+  .await?;
+  mod_manager.ensure_records().await?;
+  mod_manager.set_log_dir(logs_dir());
+  match cli.command {
+    Command::List { json } => {
+      list(&context, &mod_manager, json).await
+    }
+    Command::Install { ids } => {
+      install_mods(&context, &mut mod_manager, &ids).await
+    }
+    Command::Uninstall { ids } => {
+      uninstall_mods(&mut mod_manager, &ids).await
+    }
+    Command::Update => update(&context, &mut mod_manager).await,
+  }
+}
+
+// 按顺序加载，失败的registry跳过并在返回值中标记
+async fn load_registries(urls: &[Url]) -> (Vec<Registry>, bool) {
+  let mut registries = Vec::new();
+  let mut ok = true;
+  for url in urls {
+    match load_from_url(url, client()).await {
+      Ok(registry) => registries.push(registry),
+      Err(error) => {
+        ok = false;
+        eprintln!(
+          "{}",
+          t!(
+            "cli.registry_failed",
+            url = url,
+            error = Error::from(error)
+          )
+        );
+      }
+    }
+  }
+  (registries, ok)
+}
+
+async fn list(
+  context: &Context,
+  mod_manager: &ModManager,
+  json: bool,
+) -> Result<bool, Error> {
+  let (registries, ok) =
+    load_registries(&context.registry_urls).await;
+  let records = mod_manager.records().await?;
+  let installed = records.records.keys().cloned().collect();
+  let local = records
+    .records
+    .iter()
+    .map(|(id, record)| Mod::local(id, &record.version))
+    .collect::<Vec<_>>();
+  let mut merged = merge_mods(&registries, &installed, &local);
+  merged.sort_by(|a, b| a.modr.id.cmp(&b.modr.id));
+  let entries = merged
+    .iter()
+    .map(|entry| {
+      let installed = records
+        .records
+        .get(&entry.modr.id)
+        .map(|record| &record.version);
+      let available =
+        entry.source.source.is_some().then_some(&entry.modr.version);
+      ListEntry {
+        id: &entry.modr.id,
+        name: &entry.modr.name,
+        installed: installed.map(|version| version.as_str()),
+        available: available.map(|version| version.as_str()),
+        update: matches!(
+          (installed, available),
+          (Some(installed), Some(available))
+            if available.is_newer_than(installed)
+        ),
+      }
+    })
+    .collect::<Vec<_>>();
+  let mut stdout = std::io::stdout().lock();
+  if json {
+    serde_json::to_writer_pretty(&mut stdout, &entries)
+      .map_err(std::io::Error::from)?;
+    let _ = writeln!(stdout);
+  } else {
+    for entry in entries {
+      let _ = writeln!(
+        stdout,
+        "{}\t{}\t{}\t{}{}",
+        entry.id,
+        entry.name,
+        entry.installed.unwrap_or("-"),
+        entry.available.unwrap_or("-"),
+        if entry.update { "\t*" } else { "" },
+      );
+    }
+  }
+  Ok(ok)
+}
+
+async fn install_mods(
+  context: &Context,
+  mod_manager: &mut ModManager,
+  ids: &[String],
+) -> Result<bool, Error> {
+  let (registries, mut ok) =
+    load_registries(&context.registry_urls).await;
+  for id in ids {
+    let (id, version) = id
+      .split_once('@')
+      .map_or((id.as_str(), None), |(id, version)| {
+        (id, Some(version))
+      });
+    let res =
+      install_mod(context, mod_manager, &registries, id, version)
+        .await
+        .context(Operation::Install, id);
+    ok &= report(res);
+  }
+  Ok(ok)
+}
+
+async fn uninstall_mods(
+  mod_manager: &mut ModManager,
+  ids: &[String],
+) -> Result<bool, Error> {
+  let mut ok = true;
+  for id in ids {
+    let res = match mod_manager.uninstall_mod(id).await {
+      Ok(true) => Ok(t!("cli.uninstalled", id = id)),
+      Ok(false) => Err(Error::NotInstalled(id.to_owned())),
+      Err(err) => Err(err.into()),
+    }
+    .context(Operation::Uninstall, id);
+    ok &= report(res);
+  }
+  Ok(ok)
+}
+
+async fn update(
+  context: &Context,
+  mod_manager: &mut ModManager,
+) -> Result<bool, Error> {
+  let (registries, mut ok) =
+    load_registries(&context.registry_urls).await;
+  let records = mod_manager.records().await?;
+  let updatable = updatable(&registries, &records);
+  if updatable.is_empty() {
+    eprintln!("{}", t!("cli.up_to_date"));
+  }
+  for id in updatable {
+    let res =
+      install_mod(context, mod_manager, &registries, &id, None)
+        .await
+        .context(Operation::Update, &id);
+    ok &= report(res);
+  }
+  Ok(ok)
+}
+
+// 已安装且registry中有更高版本的模组，按id排序
+fn updatable(
+  registries: &[Registry],
+  records: &Records,
+) -> Vec<String> {
+  let installed: HashSet<String> =
+    records.records.keys().cloned().collect();
+  let mut ids = merge_mods(registries, &installed, [])
+    .into_iter()
+    .filter(|entry| {
+      records.records.get(&entry.modr.id).is_some_and(|record| {
+        entry.modr.version.is_newer_than(&record.version)
+      })
+    })
+    .map(|entry| entry.modr.id.to_owned())
+    .collect::<Vec<_>>();
+  ids.sort();
+  ids
+}
+
+fn report(res: Result<String, Error>) -> bool {
+  match res {
+    Ok(line) => {
+      eprintln!("{}", line);
+      true
+    }
+    Err(err) => {
+      eprintln!("{}", err);
+      false
+    }
+  }
+}
+
+// 下载并安装单个模组，成功时返回汇报给用户的内容
+async fn install_mod(
+  context: &Context,
+  mod_manager: &mut ModManager,
+  registries: &[Registry],
+  id: &str,
+  version: Option<&str>,
+) -> Result<String, Error> {
+  // 与合并列表相同，靠前的registry优先
+  let (registry, modr) = registries
+    .iter()
+    .find_map(|registry| {
+      registry.mods.get(id).map(|modr| (registry, modr))
+    })
+    .ok_or_else(|| Error::NotFound(id.to_owned()))?;
+  let release = match version {
+    Some(version) => modr
+      .release(version)
+      .or_else(|| {
+        (version == modr.version).then(|| modr.current_release())
+      })
+      .ok_or_else(|| {
+        Error::NotFound(format!("{}@{}", id, version))
+      })?,
+    None => modr.current_release(),
+  };
+  let url = registry.resolve_url(&release.url)?;
+  let mut path = cached_download_path(id, &release.version, &url);
+  if let (true, Some(file_name)) =
+    (context.settings.cache_archives, path.file_name())
+  {
+    path = archive_cache_dir().join(file_name);
+  }
+  eprintln!(
+    "{}",
+    t!("cli.downloading", id = id, version = release.version)
+  );
+  let request = DownloadRequest {
+    url,
+    path,
+    // 只有当前版本的大小是已知的
+    expected_size: (release.version == modr.version)
+      .then_some(modr.size)
+      .flatten(),
+    sha256: release.sha256.to_owned(),
+    retry_policy: context.settings.retry_policy(),
+    timeouts: context.settings.download_timeouts(),
+    resume_from: 0,
+  };
+  let file = download(request, print_event(id)).await;
+  eprintln!();
+  let file = file?;
+  install(
+    mod_manager,
+    id,
+    &release.version,
+    &file.path,
+    modr.ty.to_owned(),
+    |event| tracing::debug!(?event),
+  )
+  .await?;
+  discard_archive(file.path).await;
+  Ok(t!("cli.installed", id = id, version = release.version))
+}
+
+// 进度写在stderr的同一行，日志交给tracing
+fn print_event(id: &str) -> impl FnMut(TaskEvent) {
+  let id = id.to_owned();
+  let mut last = None;
+  move |event| match event {
+    TaskEvent::Progress(progress) => {
+      let percent =
+        progress.fraction().map(|fraction| (fraction * 100.) as u32);
+      if percent.is_some() && percent == last {
+        return;
+      }
+      last = percent;
+      let total =
+        progress.max.map_or_else(|| "?".to_string(), format_bytes);
+      eprint!(
+        "\r{}: {} / {}",
+        id,
+        format_bytes(progress.current),
+        total
+      );
+    }
+    TaskEvent::Log(line) => tracing::info!(%id, "{}", line),
+  }
+}
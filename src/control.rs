@@ -0,0 +1,207 @@
+// 本机的控制接口：每行一个JSON请求，每行返回一个JSON响应。
+// 请求必须带上数据目录中令牌文件的内容，其他进程读不到该文件就无法操作
+use std::{net::SocketAddr, path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+  fs,
+  io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+  net::{TcpListener, TcpStream},
+  sync::mpsc,
+};
+
+pub const DEFAULT_PORT: u16 = 47815;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error("Io: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Random: {0}")]
+  Random(#[from] getrandom::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(
+  tag = "method",
+  content = "params",
+  rename_all = "snake_case"
+)]
+pub enum Method {
+  ListInstalled,
+  ListAvailable,
+  Install { id: String },
+  Uninstall { id: String },
+  Status,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Request {
+  // 原样放回响应中，用于对应请求
+  #[serde(default)]
+  pub id: Value,
+  pub token: String,
+  #[serde(flatten)]
+  pub method: Method,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Response {
+  pub id: Value,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub result: Option<Value>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+impl Response {
+  fn new(id: Value, result: Result<Value, String>) -> Self {
+    match result {
+      Ok(result) => Self {
+        id,
+        result: Some(result),
+        error: None,
+      },
+      Err(error) => Self {
+        id,
+        result: None,
+        error: Some(error),
+      },
+    }
+  }
+}
+
+// 交给应用处理的请求，处理完后用reply返回结果
+#[derive(Debug, Clone)]
+pub struct Call {
+  pub method: Method,
+  reply: mpsc::UnboundedSender<Result<Value, String>>,
+}
+
+impl Call {
+  pub fn reply(self, result: Result<Value, String>) {
+    let _ = self.reply.send(result);
+  }
+}
+
+// 令牌文件不存在时生成新的，unix下只有当前用户可读
+pub async fn load_or_create_token(
+  path: &Path,
+) -> Result<String, Error> {
+  match fs::read_to_string(path).await {
+    Ok(token) if !token.trim().is_empty() => {
+      return Ok(token.trim().to_string());
+    }
+    Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+      return Err(err.into());
+    }
+    _ => {}
+  }
+  let mut bytes = [0; 32];
+  getrandom::fill(&mut bytes)?;
+  let token = hex::encode(bytes);
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).await?;
+  }
+  let mut options = fs::OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+  #[cfg(unix)]
+  options.mode(0o600);
+  options
+    .open(path)
+    .await?
+    .write_all(token.as_bytes())
+    .await?;
+  Ok(token)
+}
+
+// 只接受本机的连接，每个连接中的请求按顺序处理
+pub async fn serve(
+  listener: TcpListener,
+  token: String,
+  calls: mpsc::UnboundedSender<Call>,
+) -> std::io::Result<()> {
+  let token = Arc::new(token);
+  loop {
+    let (stream, addr) = listener.accept().await?;
+    if !addr.ip().is_loopback() {
+      tracing::warn!(%addr, "rejected remote control connection");
+      continue;
+    }
+    tokio::spawn(handle_connection(
+      stream,
+      addr,
+      token.to_owned(),
+      calls.to_owned(),
+    ));
+  }
+}
+
+async fn handle_connection(
+  stream: TcpStream,
+  addr: SocketAddr,
+  token: Arc<String>,
+  calls: mpsc::UnboundedSender<Call>,
+) {
+  let (reader, mut writer) = stream.into_split();
+  let mut lines = BufReader::new(reader).lines();
+  while let Ok(Some(line)) = lines.next_line().await {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let response = respond(&line, &token, &calls).await;
+    let Ok(mut line) = serde_json::to_vec(&response) else {
+      return;
+    };
+    line.push(b'\n');
+    if writer.write_all(&line).await.is_err() {
+      tracing::debug!(%addr, "control connection closed");
+      return;
+    }
+  }
+}
+
+async fn respond(
+  line: &str,
+  token: &str,
+  calls: &mpsc::UnboundedSender<Call>,
+) -> Response {
+  let request: Request = match serde_json::from_str(line) {
+    Ok(request) => request,
+    Err(err) => {
+      return Response::new(
+        Value::Null,
+        Err(format!("invalid request: {}", err)),
+      );
+    }
+  };
+  if !token_matches(&request.token, token) {
+    return Response::new(
+      request.id,
+      Err("unauthorized".to_string()),
+    );
+  }
+  let (reply, mut result) = mpsc::unbounded_channel();
+  let call = Call {
+    method: request.method,
+    reply,
+  };
+  if calls.send(call).is_err() {
+    return Response::new(request.id, Err("unavailable".to_string()));
+  }
+  let result = result
+    .recv()
+    .await
+    .unwrap_or_else(|| Err("no reply".to_string()));
+  Response::new(request.id, result)
+}
+
+// 逐字节比较完，不因提前返回泄露匹配的长度
+fn token_matches(given: &str, token: &str) -> bool {
+  given.len() == token.len()
+    && given
+      .bytes()
+      .zip(token.bytes())
+      .fold(0, |diff, (a, b)| diff | (a ^ b))
+      == 0
+}
@@ -0,0 +1,172 @@
+use std::{
+  backtrace::Backtrace,
+  collections::VecDeque,
+  fs,
+  io::{self, Write},
+  panic::PanicHookInfo,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use chrono::{DateTime, Local};
+
+// 崩溃报告中附带的最近日志行数
+const LOG_LINES: usize = 200;
+const REPORT_PREFIX: &str = "crash-";
+const REPORT_SUFFIX: &str = ".txt";
+// 记录用户看过的最新一份报告
+const ACKNOWLEDGED: &str = "crash-acknowledged";
+
+static RECENT_LOGS: Mutex<VecDeque<String>> =
+  Mutex::new(VecDeque::new());
+
+// 日志照常写到标准输出，同时留下最近的几行给崩溃报告
+pub struct LogWriter;
+
+impl Write for LogWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if let Ok(mut logs) = RECENT_LOGS.lock() {
+      logs.extend(
+        String::from_utf8_lossy(buf).lines().map(str::to_string),
+      );
+      while logs.len() > LOG_LINES {
+        logs.pop_front();
+      }
+    }
+    io::stdout().write_all(buf)?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    io::stdout().flush()
+  }
+}
+
+// 时间戳按字典序排列就是先后顺序
+pub fn report_name(time: DateTime<Local>) -> String {
+  format!(
+    "{}{}{}",
+    REPORT_PREFIX,
+    time.format("%Y%m%d-%H%M%S"),
+    REPORT_SUFFIX
+  )
+}
+
+fn is_report(name: &str) -> bool {
+  name.starts_with(REPORT_PREFIX) && name.ends_with(REPORT_SUFFIX)
+}
+
+// 在默认的处理之前写下报告，写入失败也不影响原本的输出
+pub fn install_panic_hook(dir: PathBuf) {
+  let default = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    if let Err(err) = write_report(&dir, info) {
+      eprintln!("failed to write crash report: {}", err);
+    }
+    default(info);
+  }));
+}
+
+fn write_report(dir: &Path, info: &PanicHookInfo) -> io::Result<()> {
+  let logs = RECENT_LOGS
+    .lock()
+    .map(|logs| logs.iter().cloned().collect::<Vec<_>>().join("\n"))
+    .unwrap_or_default();
+  fs::create_dir_all(dir)?;
+  fs::write(
+    dir.join(report_name(Local::now())),
+    format!(
+      "version: {}\n\n{}\n\nbacktrace:\n{}\n\nrecent logs:\n{}\n",
+      env!("CARGO_PKG_VERSION"),
+      info,
+      Backtrace::force_capture(),
+      logs
+    ),
+  )
+}
+
+// 比已确认的报告更新的那一份，没有时为None
+pub fn unacknowledged_report(dir: &Path) -> Option<PathBuf> {
+  let acknowledged =
+    fs::read_to_string(dir.join(ACKNOWLEDGED)).unwrap_or_default();
+  fs::read_dir(dir)
+    .ok()?
+    .filter_map(Result::ok)
+    .map(|entry| entry.file_name().to_string_lossy().to_string())
+    .filter(|name| is_report(name))
+    .max()
+    .filter(|name| name.as_str() > acknowledged.trim())
+    .map(|name| dir.join(name))
+}
+
+// 之后只提示比这份更新的报告
+pub fn acknowledge(report: &Path) -> io::Result<()> {
+  let (Some(dir), Some(name)) = (report.parent(), report.file_name())
+  else {
+    return Ok(());
+  };
+  fs::write(dir.join(ACKNOWLEDGED), name.to_string_lossy().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "kmm-crash-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  fn report(dir: &Path, month: u32, day: u32) -> PathBuf {
+    let time =
+      Local.with_ymd_and_hms(2026, month, day, 8, 5, 9).unwrap();
+    let path = dir.join(report_name(time));
+    fs::write(&path, "panicked").unwrap();
+    path
+  }
+
+  #[test]
+  fn name_sorts_by_time() {
+    let time = Local.with_ymd_and_hms(2026, 3, 7, 8, 5, 9).unwrap();
+    assert_eq!(report_name(time), "crash-20260307-080509.txt");
+    assert!(is_report(&report_name(time)));
+    assert!(
+      report_name(time)
+        < report_name(time + chrono::Duration::seconds(1))
+    );
+    assert!(!is_report(ACKNOWLEDGED));
+    assert!(!is_report("crash-20260307-080509.log"));
+  }
+
+  #[test]
+  fn newest_report_until_acknowledged() {
+    let dir = temp_dir("acknowledge");
+    assert_eq!(unacknowledged_report(&dir), None);
+    report(&dir, 1, 2);
+    let newest = report(&dir, 11, 20);
+    report(&dir, 3, 4);
+    // 不是报告的文件不算
+    fs::write(dir.join("crash-notes.log"), "").unwrap();
+    assert_eq!(unacknowledged_report(&dir), Some(newest.to_owned()));
+
+    acknowledge(&newest).unwrap();
+    assert_eq!(unacknowledged_report(&dir), None);
+    // 之后的崩溃照常提示
+    let later = report(&dir, 12, 1);
+    assert_eq!(unacknowledged_report(&dir), Some(later));
+    let _ = fs::remove_dir_all(&dir);
+  }
+
+  #[test]
+  fn missing_dir_has_no_report() {
+    let dir = temp_dir("missing").join("crash");
+    assert_eq!(unacknowledged_report(&dir), None);
+  }
+}
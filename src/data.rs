@@ -1,2 +1,18 @@
+pub mod archive;
+pub mod batch;
+pub mod filter;
+pub mod health;
+pub mod import;
+pub mod notification;
+pub mod pending;
+pub mod plan;
+pub mod preview;
 pub mod progress;
 pub mod registry;
+pub mod scheduler;
+pub mod settings;
+pub mod settings_form;
+pub mod share;
+pub mod status;
+pub mod version;
+pub mod window;
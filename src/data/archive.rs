@@ -0,0 +1,231 @@
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use tokio::{
+  fs,
+  io::{AsyncReadExt, AsyncSeekExt},
+};
+
+// EOCD记录22字节，后面最多跟65535字节的注释
+const EOCD_SEARCH_LEN: u64 = 22 + 0xFFFF;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArchiveType {
+  Zip,
+  TarGz,
+  SevenZ,
+  File,
+  Dir,
+  Unknown(String),
+}
+
+impl ArchiveType {
+  pub fn as_str(&self) -> &str {
+    match self {
+      Self::Zip => "zip",
+      Self::TarGz => "tar.gz",
+      Self::SevenZ => "7z",
+      Self::File => "file",
+      Self::Dir => "dir",
+      Self::Unknown(ty) => ty.as_str(),
+    }
+  }
+
+  pub fn parse(ty: &str) -> Self {
+    match ty.trim().to_ascii_lowercase().as_str() {
+      "zip" => Self::Zip,
+      "tar.gz" | "tgz" | "targz" => Self::TarGz,
+      "7z" | "sevenz" => Self::SevenZ,
+      "file" => Self::File,
+      "dir" => Self::Dir,
+      _ => Self::Unknown(ty.to_string()),
+    }
+  }
+
+  // 只认识的扩展名才算数，其他情况交给sniff
+  pub fn from_file_name(file_name: &str) -> Option<Self> {
+    let file_name = file_name.to_ascii_lowercase();
+    if file_name.ends_with(".zip") {
+      Some(Self::Zip)
+    } else if file_name.ends_with(".tar.gz")
+      || file_name.ends_with(".tgz")
+    {
+      Some(Self::TarGz)
+    } else if file_name.ends_with(".7z") {
+      Some(Self::SevenZ)
+    } else {
+      None
+    }
+  }
+
+  // 根据文件头的magic bytes判断压缩格式
+  pub fn sniff(bytes: &[u8]) -> Option<Self> {
+    if bytes.starts_with(b"PK\x03\x04")
+      || bytes.starts_with(b"PK\x05\x06")
+    {
+      Some(Self::Zip)
+    } else if bytes.starts_with(b"7z\xBC\xAF\x27\x1C") {
+      Some(Self::SevenZ)
+    } else if bytes.starts_with(b"\x1F\x8B") {
+      Some(Self::TarGz)
+    } else {
+      None
+    }
+  }
+
+  pub async fn sniff_file(
+    path: &Path,
+  ) -> std::io::Result<Option<Self>> {
+    let mut header = Vec::with_capacity(8);
+    fs::File::open(path)
+      .await?
+      .take(8)
+      .read_to_end(&mut header)
+      .await?;
+    Ok(Self::sniff(&header))
+  }
+
+  // 文件头必须与格式一致，zip还要能找到中央目录结尾记录；
+  // 用来在解压前识别错误页面和下载不完整的文件
+  pub async fn validate_file(
+    &self,
+    path: &Path,
+  ) -> std::io::Result<bool> {
+    match self {
+      Self::Zip | Self::TarGz | Self::SevenZ => {}
+      _ => return Ok(true),
+    }
+    if Self::sniff_file(path).await?.as_ref() != Some(self) {
+      return Ok(false);
+    }
+    if *self != Self::Zip {
+      return Ok(true);
+    }
+    let mut file = fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    file
+      .seek(std::io::SeekFrom::Start(
+        len.saturating_sub(EOCD_SEARCH_LEN),
+      ))
+      .await?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).await?;
+    Ok(tail.windows(4).any(|window| window == b"PK\x05\x06"))
+  }
+
+  // 声明的类型缺失或无法识别时以文件内容为准
+  pub async fn resolve(
+    path: &Path,
+    declared: Option<Self>,
+  ) -> std::io::Result<Option<Self>> {
+    match declared {
+      Some(Self::Unknown(_)) | None => Self::sniff_file(path).await,
+      declared => Ok(declared),
+    }
+  }
+}
+
+impl fmt::Display for ArchiveType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl Serialize for ArchiveType {
+  fn serialize<S: Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.as_str())
+  }
+}
+
+impl<'de> Deserialize<'de> for ArchiveType {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    Ok(Self::parse(&String::deserialize(deserializer)?))
+  }
+}
+
+// 校验下载的文件，结果为小写的十六进制
+pub async fn sha256_file(path: &Path) -> std::io::Result<String> {
+  Ok(hex::encode(Sha256::digest(fs::read(path).await?)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sniffs_magic_bytes() {
+    let cases: [(&[u8], Option<ArchiveType>); 8] = [
+      (b"PK\x03\x04\x14\x00\x00\x00", Some(ArchiveType::Zip)),
+      // 空的zip只有中央目录结尾记录
+      (b"PK\x05\x06\x00\x00\x00\x00", Some(ArchiveType::Zip)),
+      (b"7z\xBC\xAF\x27\x1C\x00\x04", Some(ArchiveType::SevenZ)),
+      (
+        b"\x1F\x8B\x08\x00\x00\x00\x00\x00",
+        Some(ArchiveType::TarGz),
+      ),
+      (b"<!DOCTYPE html>", None),
+      (b"PK\x01\x02", None),
+      (b"PK", None),
+      (b"", None),
+    ];
+    for (bytes, ty) in cases {
+      assert_eq!(ArchiveType::sniff(bytes), ty, "{:?}", bytes);
+    }
+  }
+
+  #[tokio::test]
+  async fn sniffs_file_header() {
+    let path = std::env::temp_dir()
+      .join(format!("kmm-archive-{}-sniff", std::process::id()));
+    std::fs::write(&path, b"7z\xBC\xAF\x27\x1C rest of file")
+      .unwrap();
+    let ty = ArchiveType::sniff_file(&path).await.unwrap();
+    let resolved = ArchiveType::resolve(
+      &path,
+      Some(ArchiveType::Unknown("rar".to_string())),
+    )
+    .await
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(ty, Some(ArchiveType::SevenZ));
+    // 无法识别的声明类型以文件内容为准
+    assert_eq!(resolved, Some(ArchiveType::SevenZ));
+  }
+
+  #[test]
+  fn deserializes_case_insensitively() {
+    let cases = [
+      ("\"zip\"", ArchiveType::Zip),
+      ("\"ZIP\"", ArchiveType::Zip),
+      ("\" Zip \"", ArchiveType::Zip),
+      ("\"TGZ\"", ArchiveType::TarGz),
+      ("\"Tar.Gz\"", ArchiveType::TarGz),
+      ("\"7Z\"", ArchiveType::SevenZ),
+      ("\"SevenZ\"", ArchiveType::SevenZ),
+      ("\"Dir\"", ArchiveType::Dir),
+      ("\"RAR\"", ArchiveType::Unknown("RAR".to_string())),
+    ];
+    for (json, ty) in cases {
+      let parsed: ArchiveType = serde_json::from_str(json).unwrap();
+      assert_eq!(parsed, ty, "{}", json);
+    }
+  }
+
+  // 写出的是规范的小写名称，未知类型原样保留
+  #[test]
+  fn serializes_canonical_names() {
+    for (ty, json) in [
+      (ArchiveType::parse("TGZ"), "\"tar.gz\""),
+      (ArchiveType::parse("SevenZ"), "\"7z\""),
+      (ArchiveType::Unknown("RAR".to_string()), "\"RAR\""),
+    ] {
+      assert_eq!(serde_json::to_string(&ty).unwrap(), json);
+    }
+  }
+}
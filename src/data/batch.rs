@@ -0,0 +1,382 @@
+use std::{
+  collections::{HashMap, HashSet},
+  time::{Duration, Instant},
+};
+
+use url::Url;
+
+use super::{archive::ArchiveType, version::ModVersion};
+
+// 确认时从registry取下的下载信息。批次进行中registry重新加载，
+// 下载和安装也只用这一份，记录的版本和下载的文件保持一致
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseSnapshot {
+  pub id: String,
+  pub url: Url,
+  pub version: ModVersion,
+  pub size: Option<u64>,
+  pub sha256: Option<String>,
+  pub ty: Option<ArchiveType>,
+}
+
+// 一次更新中单个模组所处的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchPhase {
+  Uninstalling,
+  Downloading,
+  Installing,
+  Done,
+  Failed,
+}
+
+impl BatchPhase {
+  pub fn is_finished(&self) -> bool {
+    matches!(self, Self::Done | Self::Failed)
+  }
+}
+
+// 批次进行中积累的结果，结束时生成汇总
+#[derive(Debug, Clone)]
+pub struct BatchTally {
+  started: Instant,
+  downloaded: u64,
+  // 失败的模组和原因，重试或忽略时移除
+  failures: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchSummary {
+  pub succeeded: usize,
+  pub failures: Vec<(String, String)>,
+  pub downloaded: u64,
+  pub elapsed: Duration,
+}
+
+impl BatchTally {
+  pub fn new(started: Instant) -> Self {
+    Self {
+      started,
+      downloaded: 0,
+      failures: Vec::new(),
+    }
+  }
+
+  pub fn add_download(&mut self, bytes: u64) {
+    self.downloaded += bytes;
+  }
+
+  pub fn add_failure(&mut self, id: &str, error: &str) {
+    self.clear_failure(id);
+    self.failures.push((id.to_owned(), error.to_owned()));
+  }
+
+  pub fn clear_failure(&mut self, id: &str) {
+    self.failures.retain(|(failed, _)| failed != id);
+  }
+
+  // `total`是批次中模组的数量，没有失败的都算成功
+  pub fn summary(&self, total: usize, now: Instant) -> BatchSummary {
+    let mut failures = self.failures.to_owned();
+    failures.sort();
+    BatchSummary {
+      succeeded: total.saturating_sub(failures.len()),
+      failures,
+      downloaded: self.downloaded,
+      elapsed: now.saturating_duration_since(self.started),
+    }
+  }
+}
+
+impl BatchSummary {
+  // 复制到剪贴板的失败列表，每行一个模组
+  pub fn failure_list(&self) -> String {
+    self
+      .failures
+      .iter()
+      .map(|(id, error)| format!("{}: {}", id, error))
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+}
+
+// 每个模组在下一次更新中要执行的操作，保持不变的不记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingAction {
+  Keep,
+  Install,
+  Uninstall,
+}
+
+impl PendingAction {
+  pub const ALL: [Self; 3] =
+    [Self::Keep, Self::Install, Self::Uninstall];
+}
+
+// 由选择的操作得出要下载安装和要卸载的模组；
+// 重新安装已安装的模组时直接装在旧版本上，不用先卸载
+pub fn plan_batch(
+  actions: &HashMap<String, PendingAction>,
+  installed: &HashSet<String>,
+) -> (Vec<String>, Vec<String>) {
+  let mut install = Vec::new();
+  let mut uninstall = Vec::new();
+  for (id, action) in actions {
+    match action {
+      PendingAction::Keep => {}
+      PendingAction::Install => install.push(id.to_owned()),
+      PendingAction::Uninstall if installed.contains(id) => {
+        uninstall.push(id.to_owned())
+      }
+      PendingAction::Uninstall => {}
+    }
+  }
+  install.sort();
+  uninstall.sort();
+  (install, uninstall)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchItem {
+  pub phase: BatchPhase,
+  // registry声明的大小，用作权重
+  pub size: Option<u64>,
+  // 当前阶段的进度
+  pub progress: Option<f32>,
+}
+
+// 下载占每个模组进度的比例，剩下的算作安装
+const DOWNLOAD_SHARE: f32 = 0.8;
+
+// 按声明的大小加权，没有声明大小的按已知大小的平均值计算。
+// 安装阶段没有按压缩包中的条目数另外加权：条目数要到解压时
+// 打开压缩包才知道，解压进度本身也是按写入的字节数算的，
+// 和压缩包的大小大致成正比
+pub fn batch_progress(items: &[BatchItem]) -> f32 {
+  let known = items.iter().filter_map(|item| item.size);
+  let count = known.clone().count();
+  let fallback = match count {
+    0 => 1.,
+    count => known.sum::<u64>() as f32 / count as f32,
+  };
+  let (done, total) =
+    items.iter().fold((0., 0.), |(done, total), item| {
+      let weight = item.size.map_or(fallback, |size| size as f32);
+      let progress = item.progress.unwrap_or(0.).clamp(0., 1.);
+      let fraction = match item.phase {
+        // 纯卸载没有下载阶段
+        BatchPhase::Uninstalling => progress,
+        BatchPhase::Downloading => DOWNLOAD_SHARE * progress,
+        BatchPhase::Installing => {
+          DOWNLOAD_SHARE + (1. - DOWNLOAD_SHARE) * progress
+        }
+        BatchPhase::Done | BatchPhase::Failed => 1.,
+      };
+      (done + weight * fraction, total + weight)
+    });
+  if total > 0. { done / total } else { 1. }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::data::plan::{Installed, Target, build_plan};
+
+  fn actions(
+    actions: &[(&str, PendingAction)],
+  ) -> HashMap<String, PendingAction> {
+    actions
+      .iter()
+      .map(|(id, action)| (id.to_string(), *action))
+      .collect()
+  }
+
+  fn ids(ids: &[&str]) -> HashSet<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+  }
+
+  #[test]
+  fn reinstall_is_done_in_place() {
+    let (install, uninstall) = plan_batch(
+      &actions(&[
+        ("fresh", PendingAction::Install),
+        ("old", PendingAction::Install),
+        ("gone", PendingAction::Uninstall),
+        ("kept", PendingAction::Keep),
+        ("absent", PendingAction::Uninstall),
+      ]),
+      &ids(&["old", "gone", "kept"]),
+    );
+    assert_eq!(install, ["fresh", "old"]);
+    // 重新安装的和没有安装的模组都不用卸载，保持不变的不动
+    assert_eq!(uninstall, ["gone"]);
+  }
+
+  // registry中已经没有的模组照常出现在plan_batch的结果中，
+  // 和App中一样交给build_plan后单独列出
+  #[test]
+  fn missing_mods_are_listed() {
+    let (install, uninstall) = plan_batch(
+      &actions(&[
+        ("old", PendingAction::Install),
+        ("gone", PendingAction::Install),
+      ]),
+      &ids(&["old", "gone"]),
+    );
+    assert_eq!(install, ["gone", "old"]);
+    let plan = build_plan(
+      &install,
+      &uninstall,
+      |_| {
+        Some(Installed {
+          version: "0.9".into(),
+          sha256: None,
+          intact: true,
+        })
+      },
+      |id| {
+        (id != "gone").then(|| Target {
+          version: "1.0".into(),
+          size: Some(100),
+          sha256: None,
+        })
+      },
+      &HashSet::new(),
+      None,
+    );
+    assert_eq!(plan.missing, ["gone"]);
+    let upgrades: Vec<_> =
+      plan.upgrades.into_iter().map(|item| item.id).collect();
+    assert_eq!(upgrades, ["old"]);
+  }
+
+  fn item(
+    phase: BatchPhase,
+    size: Option<u64>,
+    progress: Option<f32>,
+  ) -> BatchItem {
+    BatchItem {
+      phase,
+      size,
+      progress,
+    }
+  }
+
+  fn assert_progress(items: &[BatchItem], expected: f32) {
+    let progress = batch_progress(items);
+    assert!(
+      (progress - expected).abs() < 1e-6,
+      "{} != {}",
+      progress,
+      expected
+    );
+  }
+
+  #[test]
+  fn empty_batch_is_finished() {
+    assert_progress(&[], 1.);
+  }
+
+  #[test]
+  fn weighted_by_declared_size() {
+    assert_progress(
+      &[
+        item(BatchPhase::Done, Some(300), None),
+        item(BatchPhase::Downloading, Some(100), Some(0.)),
+      ],
+      0.75,
+    );
+    // 下载完一半算0.4，安装完一半算0.9
+    assert_progress(
+      &[item(BatchPhase::Downloading, Some(100), Some(0.5))],
+      0.4,
+    );
+    assert_progress(
+      &[item(BatchPhase::Installing, Some(100), Some(0.5))],
+      0.9,
+    );
+  }
+
+  // 没有声明大小的按已知大小的平均值算
+  #[test]
+  fn unknown_sizes_use_average() {
+    assert_progress(
+      &[
+        item(BatchPhase::Downloading, Some(100), Some(0.5)),
+        item(BatchPhase::Done, Some(300), None),
+        item(BatchPhase::Downloading, None, None),
+      ],
+      (40. + 300.) / 600.,
+    );
+    // 全都不知道大小时每个模组一样重
+    assert_progress(
+      &[
+        item(BatchPhase::Done, None, None),
+        item(BatchPhase::Downloading, None, Some(2.)),
+      ],
+      (1. + DOWNLOAD_SHARE) / 2.,
+    );
+  }
+
+  #[test]
+  fn uninstall_has_no_download_share() {
+    assert_progress(
+      &[item(BatchPhase::Uninstalling, None, Some(0.5))],
+      0.5,
+    );
+  }
+
+  #[test]
+  fn failed_counts_as_finished() {
+    assert_progress(
+      &[
+        item(BatchPhase::Failed, Some(100), Some(0.2)),
+        item(BatchPhase::Downloading, Some(100), None),
+      ],
+      0.5,
+    );
+  }
+
+  #[test]
+  fn tally_tracks_latest_failures() {
+    let started = Instant::now();
+    let mut tally = BatchTally::new(started);
+    tally.add_download(100);
+    tally.add_download(50);
+    tally.add_failure("zeta", "timeout");
+    tally.add_failure("alpha", "not found");
+    // 再次失败时只保留最新的原因
+    tally.add_failure("zeta", "checksum");
+    tally.add_failure("retried", "timeout");
+    // 重试成功的不算失败
+    tally.clear_failure("retried");
+    let summary = tally.summary(5, started + Duration::from_secs(3));
+    assert_eq!(
+      summary,
+      BatchSummary {
+        succeeded: 3,
+        failures: vec![
+          ("alpha".to_string(), "not found".to_string()),
+          ("zeta".to_string(), "checksum".to_string()),
+        ],
+        downloaded: 150,
+        elapsed: Duration::from_secs(3),
+      }
+    );
+    assert_eq!(
+      summary.failure_list(),
+      "alpha: not found\nzeta: checksum"
+    );
+  }
+
+  #[test]
+  fn tally_without_failures() {
+    let started = Instant::now() + Duration::from_secs(1);
+    let tally = BatchTally::new(started);
+    // 结束时间早于开始时间时不会溢出
+    let summary = tally.summary(2, Instant::now());
+    assert_eq!(summary.succeeded, 2);
+    assert!(summary.failures.is_empty());
+    assert_eq!(summary.failure_list(), "");
+    assert_eq!(summary.elapsed, Duration::ZERO);
+  }
+}
@@ -0,0 +1,99 @@
+// 模组列表的快速筛选
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModFilter {
+  #[default]
+  All,
+  Installed,
+  Updatable,
+  NotInstalled,
+}
+
+impl ModFilter {
+  pub const ALL: [Self; 4] = [
+    Self::All,
+    Self::Installed,
+    Self::Updatable,
+    Self::NotInstalled,
+  ];
+
+  // installed取自安装记录，available表示registry中有这个模组
+  pub fn matches(
+    self,
+    installed: bool,
+    updatable: bool,
+    available: bool,
+  ) -> bool {
+    match self {
+      Self::All => true,
+      Self::Installed => installed,
+      Self::Updatable => installed && updatable,
+      Self::NotInstalled => !installed && available,
+    }
+  }
+}
+
+// 搜索框的内容按空白分成几个词，每个词都要出现在某一项中，
+// 不区分大小写；没有输入时全部匹配
+pub fn matches_query(query: &str, fields: &[&str]) -> bool {
+  let fields = fields
+    .iter()
+    .map(|field| field.to_lowercase())
+    .collect::<Vec<_>>();
+  query.split_whitespace().all(|word| {
+    let word = word.to_lowercase();
+    fields.iter().any(|field| field.contains(&word))
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // (已安装, 有更新, registry中有)
+  const CASES: [(bool, bool, bool); 5] = [
+    // 只在本地的模组
+    (true, false, false),
+    // 已是最新
+    (true, false, true),
+    (true, true, true),
+    (false, false, true),
+    // 已经卸载，registry中也没有了
+    (false, false, false),
+  ];
+
+  fn matched(filter: ModFilter) -> Vec<bool> {
+    CASES
+      .iter()
+      .map(|&(installed, updatable, available)| {
+        filter.matches(installed, updatable, available)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn filters_by_state() {
+    assert_eq!(matched(ModFilter::All), [true; 5]);
+    assert_eq!(
+      matched(ModFilter::Installed),
+      [true, true, true, false, false]
+    );
+    assert_eq!(
+      matched(ModFilter::Updatable),
+      [false, false, true, false, false]
+    );
+    assert_eq!(
+      matched(ModFilter::NotInstalled),
+      [false, false, false, true, false]
+    );
+  }
+
+  #[test]
+  fn query_words_match_any_field() {
+    let fields = ["example", "Example Mod", "keeps ships pretty"];
+    assert!(matches_query("", &fields));
+    assert!(matches_query("  ", &fields));
+    assert!(matches_query("MOD", &fields));
+    assert!(matches_query("pretty example", &fields));
+    assert!(!matches_query("pretty other", &fields));
+  }
+}
@@ -0,0 +1,125 @@
+use std::{
+  collections::HashSet,
+  path::{Path, PathBuf},
+};
+
+use crate::mod_manager::Records;
+
+// 收编的无主文件记在这个id名下，之后可以像模组一样卸载
+pub const ADOPTED_ID: &str = "adopted-files";
+
+// 安装记录本身，不算无主文件
+const RECORDS_FILE: &str = ".kmmgr.json";
+
+// 编辑器和安装程序常留下的备份
+const BACKUP_SUFFIXES: [&str; 4] = [".bak", ".orig", ".old", "~"];
+const TEMP_SUFFIXES: [&str; 3] = [".tmp", ".temp", ".part"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModHealth {
+  Intact,
+  // 缺少的文件，按记录中的顺序
+  Missing(Vec<PathBuf>),
+  AllMissing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModReport {
+  pub id: String,
+  pub version: String,
+  pub health: ModHealth,
+}
+
+// 安装记录和res_mods实际内容的差异，路径都相对res_mods
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HealthReport {
+  // 按id排序
+  pub mods: Vec<ModReport>,
+  // 不属于任何模组的文件
+  pub orphans: Vec<PathBuf>,
+  pub backups: Vec<PathBuf>,
+  pub temp_files: Vec<PathBuf>,
+}
+
+fn has_suffix(path: &Path, suffixes: &[&str]) -> bool {
+  path.file_name().is_some_and(|name| {
+    let name = name.to_string_lossy().to_lowercase();
+    suffixes.iter().any(|suffix| name.ends_with(suffix))
+  })
+}
+
+impl HealthReport {
+  // `files`和`dirs`是遍历res_mods得到的文件和目录
+  pub fn build(
+    records: &Records,
+    files: &[PathBuf],
+    dirs: &[PathBuf],
+  ) -> Self {
+    let existing = files.iter().chain(dirs).collect::<HashSet<_>>();
+    let mut mods = records
+      .records
+      .iter()
+      .map(|(id, record)| {
+        let missing = record
+          .files
+          .iter()
+          .filter(|file| !existing.contains(file))
+          .cloned()
+          .collect::<Vec<_>>();
+        let health = if missing.is_empty() {
+          ModHealth::Intact
+        } else if missing.len() == record.files.len() {
+          ModHealth::AllMissing
+        } else {
+          ModHealth::Missing(missing)
+        };
+        ModReport {
+          id: id.to_owned(),
+          version: record.version.to_string(),
+          health,
+        }
+      })
+      .collect::<Vec<_>>();
+    mods.sort_by(|a, b| a.id.cmp(&b.id));
+    let recorded = records
+      .records
+      .values()
+      .flat_map(|record| record.files.iter())
+      .collect::<HashSet<_>>();
+    let mut report = Self {
+      mods,
+      ..Default::default()
+    };
+    let mut unrecorded = files
+      .iter()
+      .filter(|file| {
+        !recorded.contains(file) && *file != Path::new(RECORDS_FILE)
+      })
+      .collect::<Vec<_>>();
+    unrecorded.sort();
+    for file in unrecorded {
+      if has_suffix(file, &BACKUP_SUFFIXES) {
+        report.backups.push(file.to_owned());
+      } else if has_suffix(file, &TEMP_SUFFIXES) {
+        report.temp_files.push(file.to_owned());
+      } else {
+        report.orphans.push(file.to_owned());
+      }
+    }
+    report
+  }
+
+  pub fn broken_mods(&self) -> impl Iterator<Item = &ModReport> {
+    self
+      .mods
+      .iter()
+      .filter(|report| report.health != ModHealth::Intact)
+  }
+
+  pub fn is_healthy(&self) -> bool {
+    self.broken_mods().next().is_none()
+      && self.orphans.is_empty()
+      && self.backups.is_empty()
+      && self.temp_files.is_empty()
+  }
+}
@@ -0,0 +1,135 @@
+// 从其他安装器迁移时导入它们的选择，解析和匹配分开进行
+
+// 编辑距离换算出的相似度低于这个值时不算匹配
+const MIN_SIMILARITY: f64 = 0.8;
+// 包含关系至少要有这么长，免得很短的名称到处匹配
+const MIN_CONTAINED_LEN: usize = 5;
+
+// 取出选择文件中的模组名称。Aslain的选项文件是Inno Setup的格式，
+// 选中的组件写在Components=一行，逗号分隔，子组件用\连接，
+// !开头的是取消选择的；没有这一行时按每行一个名称处理
+pub fn parse_selection(content: &str) -> Vec<String> {
+  let content = content.trim_start_matches('\u{feff}');
+  let components = content.lines().find_map(|line| {
+    let (key, value) = line.split_once('=')?;
+    key
+      .trim()
+      .eq_ignore_ascii_case("components")
+      .then_some(value)
+  });
+  let names: Vec<&str> = match components {
+    Some(value) => value
+      .split(',')
+      .map(str::trim)
+      .filter(|component| !component.starts_with('!'))
+      .filter_map(|component| {
+        component.trim_start_matches('*').rsplit('\\').next()
+      })
+      .collect(),
+    None => content
+      .lines()
+      .map(|line| line.split(['#', ';']).next().unwrap_or_default())
+      .filter(|line| !line.trim_start().starts_with('['))
+      .map(|line| line.trim().trim_start_matches(['-', '*']))
+      .collect(),
+  };
+  let mut entries = Vec::<String>::new();
+  for name in names.into_iter().map(str::trim) {
+    if !name.is_empty() && !entries.iter().any(|x| x == name) {
+      entries.push(name.to_string());
+    }
+  }
+  entries
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelectionMatch {
+  // 匹配到的模组id，按选择文件中的顺序，不重复
+  pub ids: Vec<String>,
+  pub unmatched: Vec<String>,
+}
+
+// 按id和名称匹配，忽略大小写和标点。完全一致的优先，其次是
+// 一方包含另一方或足够相似的，分数相同时取靠前的模组
+pub fn match_selection<'a>(
+  entries: &[String],
+  mods: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> SelectionMatch {
+  let mods = mods
+    .into_iter()
+    .map(|(id, name)| (id, normalize(id), normalize(name)))
+    .collect::<Vec<_>>();
+  let mut result = SelectionMatch::default();
+  for entry in entries {
+    let key = normalize(entry);
+    let best = mods
+      .iter()
+      .map(|(id, normalized_id, name)| {
+        (id, score(&key, normalized_id).max(score(&key, name)))
+      })
+      .filter(|(_, score)| *score >= MIN_SIMILARITY)
+      .fold(
+        None,
+        |best: Option<(&&str, f64)>, (id, score)| match best {
+          Some((_, best_score)) if best_score >= score => best,
+          _ => Some((id, score)),
+        },
+      );
+    match best {
+      Some((id, _)) => {
+        if !result.ids.iter().any(|x| x == id) {
+          result.ids.push(id.to_string());
+        }
+      }
+      None => result.unmatched.push(entry.to_owned()),
+    }
+  }
+  result
+}
+
+fn normalize(name: &str) -> String {
+  name
+    .chars()
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+// 完全一致为1，包含关系在0.8到1之间，其余按编辑距离
+fn score(a: &str, b: &str) -> f64 {
+  if a.is_empty() || b.is_empty() {
+    return 0.;
+  }
+  if a == b {
+    return 1.;
+  }
+  let (a_len, b_len) = (a.chars().count(), b.chars().count());
+  let (shorter, longer) =
+    if a_len <= b_len { (a, b) } else { (b, a) };
+  let ratio = a_len.min(b_len) as f64 / a_len.max(b_len) as f64;
+  if a_len.min(b_len) >= MIN_CONTAINED_LEN && longer.contains(shorter)
+  {
+    // 略低于完全一致，不会超过1
+    return 0.8 + 0.19 * ratio;
+  }
+  1. - distance(a, b) as f64 / a_len.max(b_len) as f64
+}
+
+fn distance(a: &str, b: &str) -> usize {
+  let b = b.chars().collect::<Vec<_>>();
+  let mut row = (0..=b.len()).collect::<Vec<_>>();
+  for (i, a) in a.chars().enumerate() {
+    let mut previous = row[0];
+    row[0] = i + 1;
+    for (j, b) in b.iter().enumerate() {
+      let current = row[j + 1];
+      row[j + 1] = if a == *b {
+        previous
+      } else {
+        1 + previous.min(row[j]).min(current)
+      };
+      previous = current;
+    }
+  }
+  row[b.len()]
+}
@@ -0,0 +1,26 @@
+use std::time::{Duration, Instant};
+
+// 提示信息显示这么久之后自动消失，警告要手动关闭
+const INFO_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+  Info,
+  Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+  pub id: usize,
+  pub severity: Severity,
+  pub title: String,
+  pub text: String,
+  pub created: Instant,
+}
+
+impl Notification {
+  pub fn is_expired(&self, now: Instant) -> bool {
+    self.severity == Severity::Info
+      && now.duration_since(self.created) >= INFO_TIMEOUT
+  }
+}
@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error("Io: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("SerdeJson: {0}")]
+  SerdeJson(#[from] serde_json::Error),
+}
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PendingKind {
+  Install,
+  Uninstall,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PendingOperation {
+  pub kind: PendingKind,
+  pub id: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub version: Option<String>,
+  // 已下载好的压缩包，不存在时需要重新下载
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub path: Option<PathBuf>,
+}
+
+// 恢复时的做法
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resume {
+  Uninstall,
+  // 压缩包还在，直接安装
+  Install(PathBuf),
+  // 压缩包已经不在了，重新从registry下载
+  Redownload,
+}
+
+impl PendingOperation {
+  pub fn resume(&self) -> Resume {
+    match (self.kind, &self.path) {
+      (PendingKind::Uninstall, _) => Resume::Uninstall,
+      (PendingKind::Install, Some(path)) if path.is_file() => {
+        Resume::Install(path.to_owned())
+      }
+      (PendingKind::Install, _) => Resume::Redownload,
+    }
+  }
+}
+
+// 尚未完成的安装/卸载，程序意外退出后用于恢复
+#[derive(
+  Debug, Clone, Default, PartialEq, Deserialize, Serialize,
+)]
+pub struct PendingQueue {
+  pub operations: Vec<PendingOperation>,
+}
+
+impl PendingQueue {
+  pub async fn load(path: &Path) -> Result<Self, Error> {
+    match fs::read(path).await {
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        Ok(Self::default())
+      }
+      data => Ok(serde_json::from_slice(data?.as_slice())?),
+    }
+  }
+
+  pub async fn save(&self, path: &Path) -> Result<(), Error> {
+    if self.operations.is_empty() {
+      return match fs::remove_file(path).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+          Err(err.into())
+        }
+        _ => Ok(()),
+      };
+    }
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "kmm-pending-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+  }
+
+  fn install(id: &str, path: Option<PathBuf>) -> PendingOperation {
+    PendingOperation {
+      kind: PendingKind::Install,
+      id: id.to_string(),
+      version: Some("1.0".to_string()),
+      path,
+    }
+  }
+
+  #[tokio::test]
+  async fn save_and_load() {
+    let dir = temp_dir("round-trip");
+    let path = dir.join("pending.json");
+    let queue = PendingQueue {
+      operations: vec![
+        install("example", Some(dir.join("example.zip"))),
+        PendingOperation {
+          kind: PendingKind::Uninstall,
+          id: "old".to_string(),
+          version: None,
+          path: None,
+        },
+      ],
+    };
+    queue.save(&path).await.unwrap();
+    assert_eq!(PendingQueue::load(&path).await.unwrap(), queue);
+
+    // 队列清空后删除文件
+    PendingQueue::default().save(&path).await.unwrap();
+    assert!(!path.exists());
+    PendingQueue::default().save(&path).await.unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+
+  #[tokio::test]
+  async fn missing_or_corrupt_file() {
+    let dir = temp_dir("corrupt");
+    let path = dir.join("pending.json");
+    assert_eq!(
+      PendingQueue::load(&path).await.unwrap(),
+      PendingQueue::default()
+    );
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&path, "{\"operations\": [").unwrap();
+    assert!(matches!(
+      PendingQueue::load(&path).await,
+      Err(Error::SerdeJson(_))
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+
+  #[test]
+  fn resume_redownloads_missing_archive() {
+    let dir = temp_dir("resume");
+    std::fs::create_dir_all(&dir).unwrap();
+    let archive = dir.join("example.zip");
+    std::fs::write(&archive, "zip").unwrap();
+    assert_eq!(
+      install("example", Some(archive.to_owned())).resume(),
+      Resume::Install(archive.to_owned())
+    );
+    std::fs::remove_file(&archive).unwrap();
+    assert_eq!(
+      install("example", Some(archive)).resume(),
+      Resume::Redownload
+    );
+    assert_eq!(install("example", None).resume(), Resume::Redownload);
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+
+use super::version::ModVersion;
+
+// registry只提供压缩包大小，解压后按两倍估算
+const UNPACKED_RATIO: u64 = 2;
+// 装完后至少还要留下这么多空间
+const SPACE_MARGIN: u64 = 512 * 1024 * 1024;
+
+// 一次更新将要执行的操作，确认前展示给用户
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchPlan {
+  // 没有安装过的模组，需要下载
+  pub installs: Vec<PlanItem>,
+  // 已安装的模组，直接在旧版本上装上选中的版本
+  pub upgrades: Vec<PlanItem>,
+  pub uninstalls: Vec<PlanItem>,
+  // 和已安装的完全相同，不会重新下载安装
+  pub up_to_date: Vec<PlanItem>,
+  // registry中已经没有的模组，不会被安装
+  pub missing: Vec<String>,
+  // 游戏目录所在分区的剩余空间，未知时不做检查
+  pub available_space: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanItem {
+  pub id: String,
+  pub from: Option<ModVersion>,
+  pub to: Option<ModVersion>,
+  pub size: Option<u64>,
+}
+
+// 安装记录中的版本，`intact`表示记录的文件都还在
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installed {
+  pub version: ModVersion,
+  pub sha256: Option<String>,
+  pub intact: bool,
+}
+
+// 将要安装的版本，大小和sha256都以registry声明的为准
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+  pub version: ModVersion,
+  pub size: Option<u64>,
+  pub sha256: Option<String>,
+}
+
+// 版本相同、文件完整，并且两边都有sha256时也相同，才算已是最新
+pub fn is_up_to_date(installed: &Installed, target: &Target) -> bool {
+  installed.version == target.version
+    && installed.intact
+    && match (&installed.sha256, &target.sha256) {
+      (Some(installed), Some(target)) => {
+        installed.eq_ignore_ascii_case(target)
+      }
+      _ => true,
+    }
+}
+
+// 目标版本由`target`给出，已安装的版本由`installed`给出；
+// `forced`中的模组即使已是最新也重新安装
+pub fn build_plan(
+  install: &[String],
+  uninstall: &[String],
+  installed: impl Fn(&str) -> Option<Installed>,
+  target: impl Fn(&str) -> Option<Target>,
+  forced: &HashSet<String>,
+  available_space: Option<u64>,
+) -> BatchPlan {
+  let mut plan = BatchPlan {
+    available_space,
+    ..Default::default()
+  };
+  for id in install {
+    let Some(to) = target(id) else {
+      plan.missing.push(id.to_owned());
+      continue;
+    };
+    let from = installed(id);
+    let up_to_date = !forced.contains(id)
+      && from.as_ref().is_some_and(|from| is_up_to_date(from, &to));
+    let item = PlanItem {
+      id: id.to_owned(),
+      from: from.map(|from| from.version),
+      to: Some(to.version),
+      size: to.size,
+    };
+    if up_to_date {
+      plan.up_to_date.push(item);
+    } else if item.from.is_some() {
+      plan.upgrades.push(item);
+    } else {
+      plan.installs.push(item);
+    }
+  }
+  // 重新安装的模组已经算在升级里
+  plan.uninstalls = uninstall
+    .iter()
+    .filter(|id| !install.contains(id))
+    .map(|id| PlanItem {
+      id: id.to_owned(),
+      from: installed(id).map(|from| from.version),
+      to: None,
+      size: None,
+    })
+    .collect();
+  plan
+}
+
+impl BatchPlan {
+  pub fn is_empty(&self) -> bool {
+    self.installs.is_empty()
+      && self.upgrades.is_empty()
+      && self.uninstalls.is_empty()
+  }
+
+  // 解压后预计占用的空间，不扣除卸载释放的部分
+  pub fn required_space(&self) -> u64 {
+    self.download_size().0.saturating_mul(UNPACKED_RATIO)
+  }
+
+  pub fn up_to_date_ids(&self) -> Vec<String> {
+    self
+      .up_to_date
+      .iter()
+      .map(|item| item.id.to_owned())
+      .collect()
+  }
+
+  // 空间不够时返回需要和可用的大小，只卸载时不检查
+  pub fn space_shortage(&self) -> Option<(u64, u64)> {
+    let available = self.available_space?;
+    let required = self.required_space();
+    (required > 0
+      && required.saturating_add(SPACE_MARGIN) > available)
+      .then_some((required, available))
+  }
+
+  // 需要下载的总大小，以及其中大小未知的数量
+  pub fn download_size(&self) -> (u64, usize) {
+    self.installs.iter().chain(self.upgrades.iter()).fold(
+      (0, 0),
+      |(known, unknown), item| match item.size {
+        Some(size) => (known + size, unknown),
+        None => (known, unknown + 1),
+      },
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn installed(version: &str, sha256: Option<&str>) -> Installed {
+    Installed {
+      version: version.into(),
+      sha256: sha256.map(str::to_string),
+      intact: true,
+    }
+  }
+
+  fn target(version: &str, sha256: Option<&str>) -> Target {
+    Target {
+      version: version.into(),
+      size: Some(100),
+      sha256: sha256.map(str::to_string),
+    }
+  }
+
+  #[test]
+  fn same_version_is_up_to_date() {
+    assert!(is_up_to_date(
+      &installed("1.0", None),
+      &target("1.0", Some("ab"))
+    ));
+    assert!(is_up_to_date(
+      &installed("1.0", Some("AB")),
+      &target("1.0", Some("ab"))
+    ));
+    assert!(!is_up_to_date(
+      &installed("1.0", None),
+      &target("2.0", None)
+    ));
+  }
+
+  // 版本号相同但压缩包换过，或者文件缺了，都要重新安装
+  #[test]
+  fn changed_or_damaged_is_not_up_to_date() {
+    assert!(!is_up_to_date(
+      &installed("1.0", Some("ab")),
+      &target("1.0", Some("cd"))
+    ));
+    let damaged = Installed {
+      intact: false,
+      ..installed("1.0", None)
+    };
+    assert!(!is_up_to_date(&damaged, &target("1.0", None)));
+  }
+
+  fn plan(forced: &[&str]) -> BatchPlan {
+    let ids = ["fresh", "same", "old"].map(str::to_string);
+    build_plan(
+      &ids,
+      &["same".to_string(), "old".to_string()],
+      |id| match id {
+        "same" => Some(installed("1.0", Some("ab"))),
+        "old" => Some(installed("0.9", None)),
+        _ => None,
+      },
+      |_| Some(target("1.0", Some("ab"))),
+      &forced.iter().map(|id| id.to_string()).collect(),
+      None,
+    )
+  }
+
+  #[test]
+  fn up_to_date_mods_are_skipped() {
+    let plan = plan(&[]);
+    assert_eq!(plan.up_to_date_ids(), ["same"]);
+    assert_eq!(plan.installs[0].id, "fresh");
+    assert_eq!(plan.upgrades[0].id, "old");
+    assert!(plan.uninstalls.is_empty());
+    assert_eq!(plan.download_size(), (200, 0));
+  }
+
+  #[test]
+  fn forced_mods_are_reinstalled() {
+    let plan = plan(&["same"]);
+    assert!(plan.up_to_date.is_empty());
+    assert_eq!(plan.upgrades.len(), 2);
+    assert_eq!(plan.download_size(), (300, 0));
+  }
+
+  // registry中已经没有的模组单独列出，不算进下载大小
+  #[test]
+  fn missing_mods_are_listed() {
+    let plan = build_plan(
+      &["gone".to_string(), "fresh".to_string()],
+      &[],
+      |_| None,
+      |id| (id == "fresh").then(|| target("1.0", None)),
+      &HashSet::new(),
+      None,
+    );
+    assert_eq!(plan.missing, ["gone"]);
+    assert_eq!(plan.installs.len(), 1);
+    assert_eq!(plan.download_size(), (100, 0));
+  }
+
+  // 只卸载的模组不需要目标版本，也不占下载和空间
+  #[test]
+  fn uninstall_only() {
+    let plan = build_plan(
+      &[],
+      &["old".to_string()],
+      |_| Some(installed("0.9", None)),
+      |_| None,
+      &HashSet::new(),
+      Some(0),
+    );
+    assert!(!plan.is_empty());
+    assert_eq!(
+      plan.uninstalls,
+      [PlanItem {
+        id: "old".to_string(),
+        from: Some("0.9".into()),
+        to: None,
+        size: None,
+      }]
+    );
+    assert_eq!(plan.download_size(), (0, 0));
+    assert_eq!(plan.space_shortage(), None);
+    assert!(build_plan(
+      &[],
+      &[],
+      |_| None,
+      |_| None,
+      &HashSet::new(),
+      None,
+    )
+    .is_empty());
+  }
+
+  #[test]
+  fn unknown_sizes_are_counted() {
+    let plan = build_plan(
+      &["a".to_string(), "b".to_string(), "c".to_string()],
+      &[],
+      |_| None,
+      |id| {
+        Some(Target {
+          size: (id != "b").then_some(100),
+          ..target("1.0", None)
+        })
+      },
+      &HashSet::new(),
+      None,
+    );
+    assert_eq!(plan.download_size(), (200, 1));
+  }
+
+  // 解压后按两倍估算，另外至少要留下512 MB
+  #[test]
+  fn space_shortage_keeps_margin() {
+    let with_space = |available| {
+      build_plan(
+        &["a".to_string()],
+        &[],
+        |_| None,
+        |_| {
+          Some(Target {
+            size: Some(1024 * 1024 * 1024),
+            ..target("1.0", None)
+          })
+        },
+        &HashSet::new(),
+        available,
+      )
+    };
+    let required = 2 * 1024 * 1024 * 1024;
+    assert_eq!(with_space(None).space_shortage(), None);
+    assert_eq!(with_space(None).required_space(), required);
+    assert_eq!(
+      with_space(Some(required + SPACE_MARGIN)).space_shortage(),
+      None
+    );
+    assert_eq!(
+      with_space(Some(required + SPACE_MARGIN - 1)).space_shortage(),
+      Some((required, required + SPACE_MARGIN - 1))
+    );
+  }
+}
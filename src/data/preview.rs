@@ -0,0 +1,183 @@
+use std::{
+  collections::{BTreeMap, HashSet},
+  path::PathBuf,
+};
+
+use crate::mod_manager::{ArchiveEntry, Conflict};
+
+// 安装前需要留意的条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flag {
+  // 会碰到已有的文件，带上记录了它的模组
+  Conflict(Option<String>),
+  // 原始路径想写到res_mods以外
+  Escapes(String),
+  Executable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeNode {
+  pub name: String,
+  pub path: PathBuf,
+  pub dir: bool,
+  // 目录为其中所有文件的大小之和
+  pub size: u64,
+  pub flags: Vec<Flag>,
+  // 自身或下级有标记，折叠时也要提示
+  pub flagged: bool,
+  pub children: Vec<TreeNode>,
+}
+
+// 压缩包的内容，按清理后实际写入的路径组织
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveTree {
+  pub nodes: Vec<TreeNode>,
+  pub files: usize,
+  pub size: u64,
+  pub conflicts: usize,
+  pub suspicious: usize,
+}
+
+#[derive(Default)]
+struct Builder {
+  dir: bool,
+  size: u64,
+  flags: Vec<Flag>,
+  children: BTreeMap<String, Builder>,
+}
+
+impl Builder {
+  fn insert(
+    &mut self,
+    components: &[String],
+    entry: &ArchiveEntry,
+    flags: Vec<Flag>,
+  ) {
+    let [name, rest @ ..] = components else {
+      return;
+    };
+    let child = self.children.entry(name.to_owned()).or_default();
+    if rest.is_empty() {
+      child.dir |= entry.dir;
+      if !entry.dir {
+        child.size += entry.size;
+      }
+      child.flags.extend(flags);
+    } else {
+      child.dir = true;
+      child.insert(rest, entry, flags);
+    }
+  }
+
+  fn build(self, name: String, path: PathBuf) -> TreeNode {
+    let mut children = self
+      .children
+      .into_iter()
+      .map(|(name, child)| {
+        let path = path.join(&name);
+        child.build(name, path)
+      })
+      .collect::<Vec<_>>();
+    // 目录在前，同类按名称
+    children
+      .sort_by(|a, b| b.dir.cmp(&a.dir).then(a.name.cmp(&b.name)));
+    let size = if self.dir {
+      children.iter().map(|child| child.size).sum()
+    } else {
+      self.size
+    };
+    TreeNode {
+      flagged: !self.flags.is_empty()
+        || children.iter().any(|child| child.flagged),
+      name,
+      path,
+      dir: self.dir,
+      size,
+      flags: self.flags,
+      children,
+    }
+  }
+}
+
+impl ArchiveTree {
+  pub fn build(
+    entries: &[ArchiveEntry],
+    conflicts: &[Conflict],
+  ) -> Self {
+    let mut root = Builder {
+      dir: true,
+      ..Default::default()
+    };
+    let mut suspicious = 0;
+    for entry in entries {
+      let mut flags = conflicts
+        .iter()
+        .filter(|conflict| conflict.file == entry.path)
+        .map(|conflict| Flag::Conflict(conflict.owner.to_owned()))
+        .take(1)
+        .collect::<Vec<_>>();
+      if entry.escapes() {
+        flags.push(Flag::Escapes(entry.name.to_owned()));
+      }
+      if entry.is_executable() {
+        flags.push(Flag::Executable);
+      }
+      if flags.iter().any(|flag| !matches!(flag, Flag::Conflict(_))) {
+        suspicious += 1;
+      }
+      let components = entry
+        .path
+        .components()
+        .map(|component| {
+          component.as_os_str().to_string_lossy().to_string()
+        })
+        .collect::<Vec<_>>();
+      root.insert(&components, entry, flags);
+    }
+    let root = root.build(String::new(), PathBuf::new());
+    Self {
+      nodes: root.children,
+      files: entries.iter().filter(|entry| !entry.dir).count(),
+      size: root.size,
+      conflicts: conflicts.len(),
+      suspicious,
+    }
+  }
+
+  // 默认展开有标记的目录，问题一眼就能看到
+  pub fn flagged_dirs(&self) -> HashSet<PathBuf> {
+    fn collect(nodes: &[TreeNode], dirs: &mut HashSet<PathBuf>) {
+      for node in nodes.iter().filter(|node| node.dir && node.flagged)
+      {
+        dirs.insert(node.path.to_owned());
+        collect(&node.children, dirs);
+      }
+    }
+    let mut dirs = HashSet::new();
+    collect(&self.nodes, &mut dirs);
+    dirs
+  }
+
+  // 按显示顺序列出展开后可见的节点和层级
+  pub fn visible(
+    &self,
+    expanded: &HashSet<PathBuf>,
+  ) -> Vec<(usize, &TreeNode)> {
+    fn walk<'a>(
+      nodes: &'a [TreeNode],
+      depth: usize,
+      expanded: &HashSet<PathBuf>,
+      out: &mut Vec<(usize, &'a TreeNode)>,
+    ) {
+      for node in nodes {
+        out.push((depth, node));
+        if node.dir && expanded.contains(&node.path) {
+          walk(&node.children, depth + 1, expanded, out);
+        }
+      }
+    }
+    let mut out = Vec::new();
+    walk(&self.nodes, 0, expanded, &mut out);
+    out
+  }
+}
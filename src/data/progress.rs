@@ -1,5 +1,138 @@
+use std::{
+  collections::VecDeque,
+  time::{Duration, Instant},
+};
+
 #[derive(Debug, Clone, Copy)]
 pub struct Progress {
   pub current: u64,
-  pub max: u64,
+  // 总量未知时为None，例如服务器没有返回Content-Length
+  pub max: Option<u64>,
+}
+
+impl Progress {
+  pub fn fraction(&self) -> Option<f32> {
+    self
+      .max
+      .filter(|max| *max > 0)
+      .map(|max| self.current as f32 / max as f32)
+  }
+}
+
+const SPEED_SAMPLES: usize = 20;
+
+// 取最近若干个采样点的平均速度，避免显示的数值来回跳动
+#[derive(Debug, Clone, Default)]
+pub struct SpeedMeter {
+  samples: VecDeque<(Instant, u64)>,
+}
+
+impl SpeedMeter {
+  pub fn record(&mut self, at: Instant, current: u64) {
+    // 进度回退说明下载重新开始了
+    if self.samples.back().is_some_and(|&(_, last)| current < last) {
+      self.samples.clear();
+    }
+    if self.samples.len() == SPEED_SAMPLES {
+      self.samples.pop_front();
+    }
+    self.samples.push_back((at, current));
+  }
+
+  // 字节/秒
+  pub fn speed(&self) -> Option<f64> {
+    let (first_at, first) = self.samples.front()?;
+    let (last_at, last) = self.samples.back()?;
+    let elapsed = last_at.duration_since(*first_at).as_secs_f64();
+    (elapsed > 0.).then(|| (last - first) as f64 / elapsed)
+  }
+
+  pub fn eta(&self, progress: Progress) -> Option<Duration> {
+    let speed = self.speed().filter(|speed| *speed > 0.)?;
+    let max = progress.max.filter(|max| *max > 0)?;
+    Some(Duration::from_secs_f64(
+      max.saturating_sub(progress.current) as f64 / speed,
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn meter(samples: &[(u64, u64)]) -> (Instant, SpeedMeter) {
+    let start = Instant::now();
+    let mut meter = SpeedMeter::default();
+    for (secs, current) in samples {
+      meter.record(start + Duration::from_secs(*secs), *current);
+    }
+    (start, meter)
+  }
+
+  // 只有一个采样点或者时间没有前进时还算不出速度
+  #[test]
+  fn needs_elapsed_time() {
+    assert_eq!(SpeedMeter::default().speed(), None);
+    let (start, mut meter) = meter(&[(0, 100)]);
+    assert_eq!(meter.speed(), None);
+    meter.record(start, 500);
+    assert_eq!(meter.speed(), None);
+    let progress = Progress {
+      current: 500,
+      max: Some(1000),
+    };
+    assert_eq!(meter.eta(progress), None);
+  }
+
+  #[test]
+  fn averages_over_window() {
+    let (_, meter) = meter(&[(0, 0), (1, 100), (2, 600), (4, 800)]);
+    assert_eq!(meter.speed(), Some(200.));
+  }
+
+  // 超过20个采样点后只看最近的，之前的慢速不再拖低平均值
+  #[test]
+  fn old_samples_leave_window() {
+    let samples = (0..30)
+      .map(|secs| (secs, if secs < 10 { secs } else { secs * 1000 }))
+      .collect::<Vec<_>>();
+    let (_, meter) = meter(&samples);
+    assert_eq!(meter.samples.len(), SPEED_SAMPLES);
+    assert_eq!(meter.speed(), Some(1000.));
+  }
+
+  #[test]
+  fn restart_clears_samples() {
+    let (start, mut meter) = meter(&[(0, 0), (1, 1000)]);
+    meter.record(start + Duration::from_secs(2), 10);
+    assert_eq!(meter.speed(), None);
+  }
+
+  // 不知道总量时只有速度，没有剩余时间
+  #[test]
+  fn eta_needs_known_total() {
+    let (_, meter) = meter(&[(0, 0), (2, 200)]);
+    assert_eq!(meter.speed(), Some(100.));
+    assert_eq!(
+      meter.eta(Progress {
+        current: 200,
+        max: Some(1200),
+      }),
+      Some(Duration::from_secs(10))
+    );
+    assert_eq!(
+      meter.eta(Progress {
+        current: 200,
+        max: None,
+      }),
+      None
+    );
+    assert_eq!(
+      meter.eta(Progress {
+        current: 200,
+        max: Some(0),
+      }),
+      None
+    );
+  }
 }
@@ -1,7 +1,16 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+  collections::{HashMap, HashSet},
+  path::Path,
+};
 
+use base64::prelude::{BASE64_STANDARD, Engine};
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::fs;
+use url::Url;
+
+use super::{archive::ArchiveType, version::ModVersion};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -9,26 +18,1286 @@ pub enum Error {
   Io(#[from] std::io::Error),
   #[error("SerdeJson: {0}")]
   SerdeJson(#[from] serde_json::Error),
+  #[error("AsyncZip: {0}")]
+  AsyncZip(#[from] async_zip::error::ZipError),
+  #[error("DeltaGap: registry at {current:?}, delta since {since}")]
+  DeltaGap { current: Option<u64>, since: u64 },
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct Registry {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sequence: Option<u64>,
   #[serde(flatten)]
   pub mods: HashMap<String, Mod>,
+  #[serde(skip)]
+  pub base_url: Option<Url>,
+  // 加载时使用的地址，用于显示模组的来源
+  #[serde(skip)]
+  pub source: Option<Url>,
+}
+
+// 从地址加载registry失败的原因
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+  #[error("Network: {0}")]
+  Network(#[from] reqwest::Error),
+  #[error("BadFormat: {0}")]
+  BadFormat(serde_json::Error),
+  #[error("BadDelta")]
+  BadDelta,
+  #[error("BadFile: {0}")]
+  BadFile(Error),
+  #[error("BadHex: {0}")]
+  BadHex(#[from] hex::FromHexError),
+  #[error("BadHexContent: {0}")]
+  BadHexContent(serde_json::Error),
+  #[error("BadBase64: {0}")]
+  BadBase64(#[from] base64::DecodeError),
+  #[error("UnsupportedData: {0}")]
+  UnsupportedData(String),
+  #[error("UnsupportedScheme: {0}")]
+  UnsupportedScheme(String),
+}
+
+// 增量更新：只包含`since`之后变化的条目，删除以墓碑条目表示
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RegistryDelta {
+  pub since: u64,
+  pub sequence: u64,
+  #[serde(flatten)]
+  pub changes: HashMap<String, DeltaEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum DeltaEntry {
+  Changed(Box<Mod>),
+  Removed { removed: bool },
+}
+
+// 单个registry地址的加载状态，失败时带上显示给用户的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+  Pending,
+  Loaded,
+  Failed(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum RegistryResponse {
+  Delta(RegistryDelta),
+  Full(Registry),
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Mod {
   pub id: String,
-  pub ty: String,
-  pub version: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub ty: Option<ArchiveType>,
+  pub version: ModVersion,
   pub url: String,
   pub image_url: String,
   pub name: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sha256: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub size: Option<u64>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub game_versions: Vec<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub versions: Vec<ModRelease>,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub yanked: bool,
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub deprecation_note: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub replaced_by: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub author: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub homepage: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModRelease {
+  pub version: ModVersion,
+  pub url: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sha256: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub changelog: Option<String>,
 }
 
 impl Registry {
   pub async fn load(path: &Path) -> Result<Self, Error> {
     Ok(serde_json::from_slice(fs::read(path).await?.as_slice())?)
   }
+
+  pub async fn save(&self, path: &Path) -> Result<(), Error> {
+    fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+    Ok(())
+  }
+
+  // 扫描目录下的zip模组，id和版本优先取压缩包内的mod.json，
+  // 否则按`id-version.zip`的文件名约定推断
+  pub async fn generate(dir: &Path) -> Result<Self, Error> {
+    let mut registry = Self::default();
+    let mut read_dir = fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+      let path = entry.path();
+      if !entry.file_type().await?.is_file()
+        || !path
+          .extension()
+          .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+      {
+        continue;
+      }
+      let file_name = entry.file_name().to_string_lossy().to_string();
+      let (stem_id, stem_version) = identity_from_file_name(&path);
+      let manifest = read_manifest(&path).await?.unwrap_or_default();
+      let id = manifest.id.unwrap_or(stem_id);
+      let data = fs::read(&path).await?;
+
+      registry.mods.insert(
+        id.to_owned(),
+        Mod {
+          name: manifest.name.unwrap_or_else(|| id.to_owned()),
+          id,
+          ty: Some(ArchiveType::Zip),
+          version: manifest.version.unwrap_or(stem_version).into(),
+          url: file_name,
+          image_url: String::new(),
+          sha256: Some(hex::encode(Sha256::digest(&data))),
+          size: Some(data.len() as u64),
+          game_versions: manifest.game_versions,
+          versions: Vec::new(),
+          yanked: false,
+          deprecation_note: String::new(),
+          replaced_by: None,
+          author: None,
+          homepage: None,
+        },
+      );
+    }
+    Ok(registry)
+  }
+
+  // 去掉无法解析的主页地址，返回受影响的模组id
+  pub fn sanitize_homepages(&mut self) -> Vec<String> {
+    let mut invalid = self
+      .mods
+      .values_mut()
+      .filter(|modr| {
+        modr.homepage.is_some() && modr.homepage_url().is_none()
+      })
+      .map(|modr| {
+        modr.homepage = None;
+        modr.id.to_owned()
+      })
+      .collect::<Vec<_>>();
+    invalid.sort();
+    invalid
+  }
+
+  // 服务端不支持增量时会忽略since参数直接返回完整registry
+  pub fn delta_url(&self, url: &Url) -> Option<Url> {
+    let sequence = self.sequence?;
+    let mut url = url.to_owned();
+    url
+      .query_pairs_mut()
+      .append_pair("since", sequence.to_string().as_str());
+    Some(url)
+  }
+
+  pub fn apply_delta(
+    &mut self,
+    delta: RegistryDelta,
+  ) -> Result<(), Error> {
+    // 已经应用过的旧增量直接忽略
+    if self
+      .sequence
+      .is_some_and(|sequence| delta.sequence <= sequence)
+    {
+      return Ok(());
+    }
+    if self.sequence.is_none_or(|sequence| delta.since > sequence) {
+      return Err(Error::DeltaGap {
+        current: self.sequence,
+        since: delta.since,
+      });
+    }
+    for (id, entry) in delta.changes {
+      match entry {
+        DeltaEntry::Changed(modr) => {
+          self.mods.insert(id, *modr);
+        }
+        DeltaEntry::Removed { removed: true } => {
+          self.mods.remove(&id);
+        }
+        DeltaEntry::Removed { removed: false } => {}
+      }
+    }
+    self.sequence = Some(delta.sequence);
+    Ok(())
+  }
+
+  pub fn with_base_url(mut self, base_url: Url) -> Self {
+    self.base_url = Some(base_url);
+    self
+  }
+
+  pub fn with_source(mut self, source: Url) -> Self {
+    self.source = Some(source);
+    self
+  }
+
+  // 相对地址相对于registry自身所在的位置解析
+  pub fn resolve_url(
+    &self,
+    url: &str,
+  ) -> Result<Url, url::ParseError> {
+    match (Url::parse(url), &self.base_url) {
+      (
+        Err(url::ParseError::RelativeUrlWithoutBase),
+        Some(base_url),
+      ) => base_url.join(url),
+      (res, _) => res,
+    }
+  }
+}
+
+// 与generate相同的规则：优先取压缩包内的mod.json，否则按文件名推断
+pub async fn identify_archive(path: &Path) -> (String, String) {
+  let (stem_id, stem_version) = identity_from_file_name(path);
+  let manifest =
+    read_manifest(path).await.ok().flatten().unwrap_or_default();
+  (
+    manifest.id.unwrap_or(stem_id),
+    manifest.version.unwrap_or(stem_version),
+  )
+}
+
+// `id-version.zip`，没有版本号时视为0
+fn identity_from_file_name(path: &Path) -> (String, String) {
+  let file_name =
+    path.file_name().unwrap_or_default().to_string_lossy();
+  let lower = file_name.to_ascii_lowercase();
+  let stem = [".tar.gz", ".tgz", ".zip", ".7z"]
+    .iter()
+    .find(|ext| lower.ends_with(*ext))
+    .map_or_else(
+      || path.file_stem().unwrap_or_default().to_string_lossy(),
+      |ext| file_name[..file_name.len() - ext.len()].into(),
+    );
+  let (id, version) =
+    stem.rsplit_once('-').unwrap_or((stem.as_ref(), "0"));
+  (id.to_string(), version.to_string())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ModManifest {
+  id: Option<String>,
+  version: Option<String>,
+  name: Option<String>,
+  #[serde(default)]
+  game_versions: Vec<String>,
+}
+
+async fn read_manifest(
+  path: &Path,
+) -> Result<Option<ModManifest>, Error> {
+  let zip =
+    async_zip::tokio::read::fs::ZipFileReader::new(path).await?;
+  let Some(index) = zip.file().entries().iter().position(|entry| {
+    entry
+      .filename()
+      .as_str()
+      .is_ok_and(|name| name == "mod.json")
+  }) else {
+    return Ok(None);
+  };
+  let mut content = String::new();
+  zip
+    .reader_with_entry(index)
+    .await?
+    .read_to_string_checked(&mut content)
+    .await?;
+  Ok(Some(serde_json::from_str(&content)?))
+}
+
+// 模组条目来自哪个registry，以及被它覆盖掉的低优先级registry
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModSource {
+  // 只存在于安装记录中的模组没有来源
+  pub source: Option<Url>,
+  pub shadowed: Vec<Url>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergedMod<'a> {
+  pub modr: &'a Mod,
+  pub source: ModSource,
+}
+
+// 靠前的registry优先，`local`中只补上所有registry都没有的模组；
+// 已撤回的模组只对已安装它的用户可见
+pub fn merge_mods<'a>(
+  registries: impl IntoIterator<Item = &'a Registry>,
+  installed: &HashSet<String>,
+  local: impl IntoIterator<Item = &'a Mod>,
+) -> Vec<MergedMod<'a>> {
+  let mut merged: Vec<MergedMod> = Vec::new();
+  let mut index: HashMap<&str, usize> = HashMap::new();
+  let entries = registries
+    .into_iter()
+    .flat_map(|registry| {
+      registry
+        .mods
+        .values()
+        .map(|modr| (registry.source.as_ref(), modr))
+    })
+    .chain(local.into_iter().map(|modr| (None, modr)));
+  for (source, modr) in entries {
+    match index.get(modr.id.as_str()) {
+      Some(&pos) => {
+        merged[pos].source.shadowed.extend(source.cloned());
+      }
+      None => {
+        index.insert(modr.id.as_str(), merged.len());
+        merged.push(MergedMod {
+          modr,
+          source: ModSource {
+            source: source.cloned(),
+            shadowed: Vec::new(),
+          },
+        });
+      }
+    }
+  }
+  merged.retain(|entry| {
+    !entry.modr.yanked || installed.contains(&entry.modr.id)
+  });
+  merged
+}
+
+// 合并后的模组条目所在的位置，`Local`是`local`中的下标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModLocation {
+  Registry(usize),
+  Local(usize),
+}
+
+// 和merge_mods相同的优先级，每个id只记第一次出现的位置。
+// 已撤回的模组也记下，按id查找时不用逐个registry地找
+pub fn locate_mods<'a>(
+  registries: impl IntoIterator<Item = &'a Registry>,
+  local: impl IntoIterator<Item = &'a Mod>,
+) -> HashMap<String, ModLocation> {
+  let mut locations = HashMap::new();
+  for (pos, registry) in registries.into_iter().enumerate() {
+    for id in registry.mods.keys() {
+      locations
+        .entry(id.to_owned())
+        .or_insert(ModLocation::Registry(pos));
+    }
+  }
+  for (pos, modr) in local.into_iter().enumerate() {
+    locations
+      .entry(modr.id.to_owned())
+      .or_insert(ModLocation::Local(pos));
+  }
+  locations
+}
+
+impl Mod {
+  // 只存在于安装记录中的模组，没有下载地址
+  pub fn local(id: &str, version: &str) -> Self {
+    Self {
+      id: id.to_string(),
+      ty: None,
+      version: version.into(),
+      url: String::new(),
+      image_url: String::new(),
+      name: id.to_string(),
+      sha256: None,
+      size: None,
+      game_versions: Vec::new(),
+      versions: Vec::new(),
+      yanked: false,
+      deprecation_note: String::new(),
+      replaced_by: None,
+      author: None,
+      homepage: None,
+    }
+  }
+
+  pub fn current_release(&self) -> ModRelease {
+    self.release(&self.version).unwrap_or_else(|| ModRelease {
+      version: self.version.to_owned(),
+      url: self.url.to_owned(),
+      sha256: self.sha256.to_owned(),
+      changelog: None,
+    })
+  }
+
+  // 顶层的version/url总是作为一个可安装的版本
+  pub fn releases(&self) -> Vec<ModRelease> {
+    let mut releases = self.versions.to_owned();
+    if !releases
+      .iter()
+      .any(|release| release.version == self.version)
+    {
+      releases.insert(0, self.current_release());
+    }
+    releases
+  }
+
+  pub fn release(&self, version: &str) -> Option<ModRelease> {
+    self
+      .versions
+      .iter()
+      .find(|release| release.version == version)
+      .cloned()
+  }
+
+  // 版本列表中没有时，顶层的当前版本也算
+  pub fn any_release(&self, version: &str) -> Option<ModRelease> {
+    self.release(version).or_else(|| {
+      (self.version == version).then(|| self.current_release())
+    })
+  }
+
+  pub fn homepage_url(&self) -> Option<Url> {
+    self
+      .homepage
+      .as_deref()
+      .and_then(|homepage| Url::parse(homepage).ok())
+      .filter(|url| matches!(url.scheme(), "http" | "https"))
+  }
+
+  // 未声明兼容版本的模组视为兼容
+  pub fn supports_game_version(&self, game_version: &str) -> bool {
+    self.game_versions.is_empty()
+      || self.game_versions.iter().any(|version| {
+        game_version == version
+          || game_version
+            .strip_prefix(version.as_str())
+            .is_some_and(|rest| rest.starts_with('.'))
+      })
+  }
+}
+
+async fn fetch_registry(
+  url: Url,
+  client: &Client,
+) -> Result<RegistryResponse, LoadError> {
+  // 404等错误页即使恰好是JSON也不能当成registry
+  let bytes = client
+    .get(url)
+    .send()
+    .await?
+    .error_for_status()?
+    .bytes()
+    .await?;
+  serde_json::from_slice(&bytes).map_err(LoadError::BadFormat)
+}
+
+// 按地址的scheme加载完整的registry
+pub async fn load_from_url(
+  url: &Url,
+  client: &Client,
+) -> Result<Registry, LoadError> {
+  match url.scheme() {
+    "http" | "https" => {
+      match fetch_registry(url.to_owned(), client).await? {
+        RegistryResponse::Full(registry) => Ok(
+          registry
+            .with_base_url(url.to_owned())
+            .with_source(url.to_owned()),
+        ),
+        RegistryResponse::Delta(_) => Err(LoadError::BadDelta),
+      }
+    }
+    // 地址中的路径是百分号编码的，Windows上还带着盘符前的`/`
+    "file" => {
+      let path = url.to_file_path().map_err(|()| {
+        LoadError::BadFile(Error::Io(std::io::Error::new(
+          std::io::ErrorKind::InvalidInput,
+          format!("not a local path: {}", url),
+        )))
+      })?;
+      Ok(
+        Registry::load(&path)
+          .await
+          .map_err(LoadError::BadFile)?
+          .with_base_url(url.to_owned())
+          .with_source(url.to_owned()),
+      )
+    }
+    // 标准的`data:类型;base64,内容`，以及早先的`data:hex;内容`
+    "data" => {
+      let registry: Registry = match url.path().split_once(',') {
+        Some((meta, data)) if meta.ends_with(";base64") => {
+          let data = BASE64_STANDARD.decode(data)?;
+          serde_json::from_slice(data.as_slice())
+            .map_err(LoadError::BadFormat)?
+        }
+        Some((meta, _)) => {
+          return Err(LoadError::UnsupportedData(meta.to_string()));
+        }
+        None => {
+          let (ty, data) =
+            url.path().split_once(";").unwrap_or(("hex", url.path()));
+          if ty != "hex" {
+            return Err(LoadError::UnsupportedData(ty.to_string()));
+          }
+          let data = hex::decode(data)?;
+          serde_json::from_slice(data.as_slice())
+            .map_err(LoadError::BadHexContent)?
+        }
+      };
+      Ok(registry.with_source(url.to_owned()))
+    }
+    scheme => Err(LoadError::UnsupportedScheme(scheme.to_string())),
+  }
+}
+
+// 有缓存的http地址优先请求增量更新，其余情况完整加载
+pub async fn refresh_from_url(
+  url: &Url,
+  cached: Option<Registry>,
+  client: &Client,
+) -> Result<Registry, LoadError> {
+  let Some((cached, delta_url)) = cached
+    .filter(|_| matches!(url.scheme(), "http" | "https"))
+    .and_then(|cached| {
+      let delta_url = cached.delta_url(url)?;
+      Some((cached, delta_url))
+    })
+  else {
+    return load_from_url(url, client).await;
+  };
+  let registry = match fetch_registry(delta_url, client).await? {
+    RegistryResponse::Full(registry) => registry,
+    RegistryResponse::Delta(delta) => {
+      let mut cached = cached;
+      match cached.apply_delta(delta) {
+        Ok(()) => cached,
+        // 增量无法应用时退回完整拉取
+        Err(_) => return load_from_url(url, client).await,
+      }
+    }
+  };
+  Ok(
+    registry
+      .with_base_url(url.to_owned())
+      .with_source(url.to_owned()),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::PathBuf;
+  use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+  };
+
+  const REGISTRY: &str = r#"{
+    "sequence": 1,
+    "example": {
+      "id": "example",
+      "version": "1.0",
+      "url": "example.zip",
+      "image_url": "",
+      "name": "Example"
+    }
+  }"#;
+
+  // 对所有请求返回同样内容的HTTP服务器
+  async fn serve(body: &'static str) -> Url {
+    serve_with(move |_| body).await
+  }
+
+  // 按请求行决定返回的内容
+  async fn serve_with(
+    route: impl Fn(&str) -> &'static str + Send + 'static,
+  ) -> Url {
+    serve_status("200 OK", route).await
+  }
+
+  async fn serve_status(
+    status: &'static str,
+    route: impl Fn(&str) -> &'static str + Send + 'static,
+  ) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      while let Ok((mut stream, _)) = listener.accept().await {
+        let mut buf = [0; 1024];
+        let len = stream.read(&mut buf).await.unwrap_or_default();
+        let request = String::from_utf8_lossy(&buf[..len]);
+        let body = route(request.lines().next().unwrap_or_default());
+        let head = format!(
+          "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+          status,
+          body.len()
+        );
+        let _ = stream.write_all(head.as_bytes()).await;
+        let _ = stream.write_all(body.as_bytes()).await;
+      }
+    });
+    Url::parse(&format!("http://{}/registry.json", addr)).unwrap()
+  }
+
+  fn temp_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+      "kmm-registry-{}-{}",
+      std::process::id(),
+      name
+    ));
+    std::fs::write(&path, content).unwrap();
+    path
+  }
+
+  async fn load(url: &str) -> Result<Registry, LoadError> {
+    load_from_url(&Url::parse(url).unwrap(), &Client::new()).await
+  }
+
+  fn registry_with(ids: &[&str]) -> Registry {
+    Registry {
+      mods: ids
+        .iter()
+        .map(|id| (id.to_string(), Mod::local(id, "1.0")))
+        .collect(),
+      ..Default::default()
+    }
+  }
+
+  fn delta(
+    since: u64,
+    sequence: u64,
+    changes: Vec<(&str, DeltaEntry)>,
+  ) -> RegistryDelta {
+    RegistryDelta {
+      since,
+      sequence,
+      changes: changes
+        .into_iter()
+        .map(|(id, entry)| (id.to_string(), entry))
+        .collect(),
+    }
+  }
+
+  fn changed(id: &str, version: &str) -> DeltaEntry {
+    DeltaEntry::Changed(Box::new(Mod::local(id, version)))
+  }
+
+  fn removed(removed: bool) -> DeltaEntry {
+    DeltaEntry::Removed { removed }
+  }
+
+  fn versions(registry: &Registry) -> Vec<(String, String)> {
+    let mut versions = registry
+      .mods
+      .values()
+      .map(|modr| (modr.id.to_owned(), modr.version.to_string()))
+      .collect::<Vec<_>>();
+    versions.sort();
+    versions
+  }
+
+  #[test]
+  fn delta_adds_replaces_and_removes() {
+    let mut registry = Registry {
+      sequence: Some(1),
+      ..registry_with(&["a", "b", "c"])
+    };
+    registry
+      .apply_delta(delta(
+        1,
+        2,
+        vec![
+          ("a", changed("a", "2.0")),
+          ("b", removed(true)),
+          // 不是真正的删除，保持原样
+          ("c", removed(false)),
+          ("d", changed("d", "0.1")),
+        ],
+      ))
+      .unwrap();
+    assert_eq!(registry.sequence, Some(2));
+    assert_eq!(
+      versions(&registry),
+      [
+        ("a".to_string(), "2.0".to_string()),
+        ("c".to_string(), "1.0".to_string()),
+        ("d".to_string(), "0.1".to_string()),
+      ]
+    );
+    // 删除不存在的模组不算错误
+    registry
+      .apply_delta(delta(2, 3, vec![("b", removed(true))]))
+      .unwrap();
+    assert_eq!(registry.sequence, Some(3));
+    assert_eq!(registry.mods.len(), 3);
+  }
+
+  #[test]
+  fn delta_tombstones_parse_from_json() {
+    let delta: RegistryDelta = serde_json::from_str(
+      r#"{
+        "since": 1,
+        "sequence": 2,
+        "gone": { "removed": true },
+        "example": {
+          "id": "example",
+          "version": "2.0",
+          "url": "example.zip",
+          "image_url": "",
+          "name": "Example"
+        }
+      }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+      delta.changes["gone"],
+      DeltaEntry::Removed { removed: true }
+    ));
+    assert!(matches!(
+      &delta.changes["example"],
+      DeltaEntry::Changed(modr) if modr.version == "2.0"
+    ));
+  }
+
+  // 已经应用过的增量，包括序号相同的，不改变任何内容
+  #[test]
+  fn stale_delta_is_ignored() {
+    let mut registry = Registry {
+      sequence: Some(3),
+      ..registry_with(&["a"])
+    };
+    for stale in [
+      delta(1, 2, vec![("a", removed(true))]),
+      delta(2, 3, vec![("a", changed("a", "9.0"))]),
+    ] {
+      registry.apply_delta(stale).unwrap();
+    }
+    assert_eq!(registry.sequence, Some(3));
+    assert_eq!(versions(&registry), [("a".into(), "1.0".into())]);
+  }
+
+  // 中间缺了一段时不应用，等补上之前的增量后再来
+  #[test]
+  fn out_of_order_deltas() {
+    let mut registry = Registry {
+      sequence: Some(1),
+      ..registry_with(&["a"])
+    };
+    let later = delta(2, 3, vec![("a", changed("a", "3.0"))]);
+    let err = registry.apply_delta(later.to_owned()).unwrap_err();
+    assert!(matches!(
+      err,
+      Error::DeltaGap {
+        current: Some(1),
+        since: 2
+      }
+    ));
+    assert_eq!(registry.sequence, Some(1));
+    assert_eq!(versions(&registry), [("a".into(), "1.0".into())]);
+
+    registry
+      .apply_delta(delta(1, 2, vec![("a", changed("a", "2.0"))]))
+      .unwrap();
+    registry.apply_delta(later).unwrap();
+    assert_eq!(registry.sequence, Some(3));
+    assert_eq!(versions(&registry), [("a".into(), "3.0".into())]);
+
+    // 起点更早、覆盖了当前序号的增量照样可以应用
+    registry
+      .apply_delta(delta(2, 4, vec![("b", changed("b", "1.0"))]))
+      .unwrap();
+    assert_eq!(registry.sequence, Some(4));
+    assert_eq!(registry.mods.len(), 2);
+  }
+
+  // 没有序号的registry无从比较，只能完整拉取
+  #[test]
+  fn delta_needs_sequence() {
+    let mut registry = registry_with(&["a"]);
+    let err = registry
+      .apply_delta(delta(0, 1, vec![("a", removed(true))]))
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      Error::DeltaGap {
+        current: None,
+        since: 0
+      }
+    ));
+    assert!(
+      registry.delta_url(&"http://a/r".parse().unwrap()).is_none()
+    );
+    assert_eq!(registry.mods.len(), 1);
+  }
+
+  #[tokio::test]
+  async fn http_delta_gap_falls_back_to_full() {
+    let url = serve_with(|request| {
+      if request.contains("since=") {
+        r#"{ "since": 5, "sequence": 6, "example": { "removed": true } }"#
+      } else {
+        r#"{
+          "sequence": 7,
+          "fresh": {
+            "id": "fresh",
+            "version": "1.0",
+            "url": "fresh.zip",
+            "image_url": "",
+            "name": "Fresh"
+          }
+        }"#
+      }
+    })
+    .await;
+    let cached = serde_json::from_str(REGISTRY).unwrap();
+    let registry =
+      refresh_from_url(&url, Some(cached), &Client::new())
+        .await
+        .unwrap();
+    assert_eq!(registry.sequence, Some(7));
+    assert_eq!(versions(&registry), [("fresh".into(), "1.0".into())]);
+    assert_eq!(registry.source, Some(url));
+  }
+
+  // 服务端不支持增量时直接返回完整的registry
+  #[tokio::test]
+  async fn http_delta_ignored_by_server() {
+    let url = serve_with(|_| {
+      r#"{
+        "sequence": 9,
+        "other": {
+          "id": "other",
+          "version": "2.0",
+          "url": "other.zip",
+          "image_url": "",
+          "name": "Other"
+        }
+      }"#
+    })
+    .await;
+    let cached = serde_json::from_str(REGISTRY).unwrap();
+    let registry =
+      refresh_from_url(&url, Some(cached), &Client::new())
+        .await
+        .unwrap();
+    assert_eq!(registry.sequence, Some(9));
+    assert_eq!(versions(&registry), [("other".into(), "2.0".into())]);
+  }
+
+  #[test]
+  fn locate_prefers_earlier_registry() {
+    let registries =
+      [registry_with(&["a", "b"]), registry_with(&["b", "c"])];
+    let local = [Mod::local("c", "0.1"), Mod::local("d", "0.1")];
+    let locations = locate_mods(&registries, &local);
+    assert_eq!(locations.len(), 4);
+    assert_eq!(locations["a"], ModLocation::Registry(0));
+    assert_eq!(locations["b"], ModLocation::Registry(0));
+    assert_eq!(locations["c"], ModLocation::Registry(1));
+    assert_eq!(locations["d"], ModLocation::Local(1));
+  }
+
+  // 已撤回的模组只对已安装的用户可见，其余的不受影响
+  #[test]
+  fn yanked_mods_visible_only_when_installed() {
+    let mut registry =
+      registry_with(&["kept", "yanked", "installed"]);
+    for id in ["yanked", "installed"] {
+      registry.mods.get_mut(id).unwrap().yanked = true;
+    }
+    let installed = HashSet::from(["installed".to_string()]);
+    let cases = [
+      ("kept", false, true),
+      ("kept", true, true),
+      ("yanked", false, false),
+      ("installed", true, true),
+    ];
+    for (id, is_installed, visible) in cases {
+      let installed = if is_installed {
+        HashSet::from([id.to_string()])
+      } else {
+        HashSet::new()
+      };
+      let merged = merge_mods([&registry], &installed, []);
+      assert_eq!(
+        merged.iter().any(|entry| entry.modr.id == id),
+        visible,
+        "{} installed={}",
+        id,
+        is_installed
+      );
+    }
+    let mut ids = merge_mods([&registry], &installed, [])
+      .into_iter()
+      .map(|entry| entry.modr.id.as_str())
+      .collect::<Vec<_>>();
+    ids.sort();
+    assert_eq!(ids, ["installed", "kept"]);
+    // 按id查找时撤回的也能找到
+    assert!(locate_mods([&registry], []).contains_key("yanked"));
+  }
+
+  // 被靠前的registry覆盖的条目记下来源，安装记录不算来源
+  #[test]
+  fn merge_reports_shadowed_registries() {
+    let sources =
+      ["https://a.example.com/", "https://b.example.com/"]
+        .map(|url| Url::parse(url).unwrap());
+    let mut first = registry_with(&["shared", "first"]);
+    first.source = Some(sources[0].to_owned());
+    let mut second = registry_with(&["shared", "second"]);
+    second.source = Some(sources[1].to_owned());
+    let local = [Mod::local("shared", "0.1")];
+    let merged = merge_mods(
+      [&first, &second],
+      &HashSet::from(["shared".to_string()]),
+      &local,
+    );
+    let source = |id: &str| {
+      merged
+        .iter()
+        .find(|entry| entry.modr.id == id)
+        .map(|entry| entry.source.to_owned())
+        .unwrap()
+    };
+    assert_eq!(
+      source("shared"),
+      ModSource {
+        source: Some(sources[0].to_owned()),
+        shadowed: vec![sources[1].to_owned()],
+      }
+    );
+    assert!(source("first").shadowed.is_empty());
+    assert_eq!(source("second").source.as_ref(), Some(&sources[1]));
+    // 按id查找时也是靠前的registry
+    assert_eq!(
+      locate_mods([&first, &second], &local)["shared"],
+      ModLocation::Registry(0)
+    );
+  }
+
+  // 只在registry中、只有安装记录、两边都有的三种情况；
+  // 两边都有时用registry中的条目
+  #[test]
+  fn merges_registry_and_records() {
+    let source = Url::parse("https://example.com/registry").unwrap();
+    let mut registry = registry_with(&["both", "remote"]);
+    registry.source = Some(source.to_owned());
+    let both = registry.mods.get_mut("both").unwrap();
+    both.version = "2.0".into();
+    both.name = "Both".to_string();
+    let local =
+      [Mod::local("both", "1.0"), Mod::local("record", "0.1")];
+    let installed =
+      HashSet::from(["both".to_string(), "record".to_string()]);
+    let merged = merge_mods([&registry], &installed, &local);
+    assert_eq!(merged.len(), 3);
+    let entry = |id: &str| {
+      merged.iter().find(|entry| entry.modr.id == id).unwrap()
+    };
+
+    let remote = entry("remote");
+    assert_eq!(remote.source.source.as_ref(), Some(&source));
+    let both = entry("both");
+    assert_eq!(both.modr.version, "2.0");
+    assert_eq!(both.modr.name, "Both");
+    assert_eq!(both.source.source.as_ref(), Some(&source));
+    assert!(both.source.shadowed.is_empty());
+    let record = entry("record");
+    assert_eq!(record.modr.version, "0.1");
+    assert_eq!(record.source.source, None);
+  }
+
+  #[test]
+  fn supports_game_version() {
+    let cases: [(&[&str], &str, bool); 9] = [
+      (&[], "13.5.0", true),
+      (&["13.5"], "13.5", true),
+      (&["13.5"], "13.5.0", true),
+      (&["13.5"], "13.5.0.1", true),
+      (&["13.5"], "13.50", false),
+      (&["13.5"], "13.6.0", false),
+      (&["13.5.1"], "13.5", false),
+      (&["13.4", "13.5"], "13.5.2", true),
+      (&["13.4", "13.5"], "14.0", false),
+    ];
+    for (game_versions, game_version, supported) in cases {
+      let modr = Mod {
+        game_versions: game_versions
+          .iter()
+          .map(|version| version.to_string())
+          .collect(),
+        ..Mod::local("example", "1.0")
+      };
+      assert_eq!(
+        modr.supports_game_version(game_version),
+        supported,
+        "{:?} {}",
+        game_versions,
+        game_version
+      );
+    }
+  }
+
+  async fn zip(entries: &[(&str, &str)]) -> Vec<u8> {
+    use async_zip::{
+      Compression, ZipEntryBuilder, base::write::ZipFileWriter,
+    };
+    let mut writer = ZipFileWriter::new(Vec::new());
+    for (name, content) in entries {
+      let entry = ZipEntryBuilder::new(
+        name.to_string().into(),
+        Compression::Stored,
+      );
+      writer
+        .write_entry_whole(entry, content.as_bytes())
+        .await
+        .unwrap();
+    }
+    writer.close().await.unwrap()
+  }
+
+  // 生成、保存再读回，内容不变
+  #[tokio::test]
+  async fn generate_round_trip() {
+    let dir = std::env::temp_dir()
+      .join(format!("kmm-registry-{}-generate", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub.zip")).unwrap();
+    let manifest = r#"{
+      "id": "renamed",
+      "version": "2.0",
+      "name": "Renamed",
+      "game_versions": ["13.5"]
+    }"#;
+    let with_manifest =
+      zip(&[("mod.json", manifest), ("a.txt", "a")]).await;
+    std::fs::write(dir.join("example-1.0.zip"), &with_manifest)
+      .unwrap();
+    std::fs::write(
+      dir.join("plain-0.3.zip"),
+      zip(&[("b", "")]).await,
+    )
+    .unwrap();
+    std::fs::write(dir.join("single.ZIP"), zip(&[("c", "")]).await)
+      .unwrap();
+    std::fs::write(dir.join("readme.txt"), "not a mod").unwrap();
+
+    let registry = Registry::generate(&dir).await.unwrap();
+    assert_eq!(
+      versions(&registry),
+      [
+        ("plain".into(), "0.3".into()),
+        ("renamed".into(), "2.0".into()),
+        ("single".into(), "0".into()),
+      ]
+    );
+    let renamed = &registry.mods["renamed"];
+    assert_eq!(renamed.name, "Renamed");
+    assert_eq!(renamed.url, "example-1.0.zip");
+    assert_eq!(renamed.game_versions, ["13.5"]);
+    assert_eq!(renamed.size, Some(with_manifest.len() as u64));
+    assert_eq!(
+      renamed.sha256.as_deref(),
+      Some(hex::encode(Sha256::digest(&with_manifest)).as_str())
+    );
+    assert_eq!(renamed.ty, Some(ArchiveType::Zip));
+    assert_eq!(registry.mods["plain"].name, "plain");
+
+    let path = dir.join("registry.json");
+    registry.save(&path).await.unwrap();
+    let loaded = Registry::load(&path).await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+      serde_json::to_value(&loaded).unwrap(),
+      serde_json::to_value(&registry).unwrap()
+    );
+    assert_eq!(loaded.sequence, None);
+  }
+
+  #[tokio::test]
+  async fn http() {
+    let url = serve(REGISTRY).await;
+    let registry = load_from_url(&url, &Client::new()).await.unwrap();
+    assert!(registry.mods.contains_key("example"));
+    assert_eq!(registry.source, Some(url.to_owned()));
+    assert_eq!(
+      registry.resolve_url("example.zip").unwrap(),
+      url.join("example.zip").unwrap()
+    );
+  }
+
+  #[tokio::test]
+  async fn http_malformed() {
+    let url = serve("not json").await;
+    let err = load_from_url(&url, &Client::new()).await.unwrap_err();
+    assert!(matches!(err, LoadError::BadFormat(_)));
+  }
+
+  #[tokio::test]
+  async fn http_not_found() {
+    let url = serve_status("404 Not Found", |_| REGISTRY).await;
+    let err = load_from_url(&url, &Client::new()).await.unwrap_err();
+    match err {
+      LoadError::Network(err) => {
+        assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND))
+      }
+      err => panic!("unexpected error: {}", err),
+    }
+  }
+
+  #[tokio::test]
+  async fn http_unreachable() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    let err = load(&format!("http://{}/registry.json", addr))
+      .await
+      .unwrap_err();
+    assert!(matches!(err, LoadError::Network(_)));
+  }
+
+  #[tokio::test]
+  async fn http_delta() {
+    let url = serve(
+      r#"{ "since": 1, "sequence": 2, "example": { "removed": true } }"#,
+    )
+    .await;
+    let cached = serde_json::from_str(REGISTRY).unwrap();
+    let registry =
+      refresh_from_url(&url, Some(cached), &Client::new())
+        .await
+        .unwrap();
+    assert_eq!(registry.sequence, Some(2));
+    assert!(registry.mods.is_empty());
+  }
+
+  #[tokio::test]
+  async fn file() {
+    let path = temp_file("file.json", REGISTRY);
+    let url = Url::from_file_path(&path).unwrap();
+    let registry = load_from_url(&url, &Client::new()).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(registry.mods.contains_key("example"));
+    assert_eq!(registry.base_url, Some(url));
+  }
+
+  // 路径中的空格和中文在地址里是百分号编码的
+  #[tokio::test]
+  async fn file_with_encoded_path() {
+    let path = temp_file("带 空格.json", REGISTRY);
+    let url = Url::from_file_path(&path).unwrap();
+    assert!(url.path().contains("%20"));
+    let registry = load_from_url(&url, &Client::new()).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(registry.mods.contains_key("example"));
+  }
+
+  // 其他主机上的文件不是本地路径，Windows上会当作网络共享
+  #[cfg(not(windows))]
+  #[tokio::test]
+  async fn file_on_remote_host() {
+    let err =
+      load("file://example.com/registry.json").await.unwrap_err();
+    assert!(
+      matches!(&err, LoadError::BadFile(Error::Io(err)) if err.kind() == std::io::ErrorKind::InvalidInput),
+      "{}",
+      err
+    );
+  }
+
+  #[tokio::test]
+  async fn file_unreadable() {
+    let path = std::env::temp_dir().join("kmm-registry-missing.json");
+    let url = Url::from_file_path(path).unwrap();
+    let err = load_from_url(&url, &Client::new()).await.unwrap_err();
+    assert!(matches!(err, LoadError::BadFile(Error::Io(_))));
+  }
+
+  #[tokio::test]
+  async fn file_malformed() {
+    let path = temp_file("malformed.json", "not json");
+    let url = Url::from_file_path(&path).unwrap();
+    let err = load_from_url(&url, &Client::new()).await.unwrap_err();
+    std::fs::remove_file(path).unwrap();
+    assert!(matches!(err, LoadError::BadFile(Error::SerdeJson(_))));
+  }
+
+  #[tokio::test]
+  async fn data_hex() {
+    let url = format!("data:hex;{}", hex::encode(REGISTRY));
+    let registry = load(&url).await.unwrap();
+    assert!(registry.mods.contains_key("example"));
+    // 没有类型时默认为hex
+    let url = format!("data:{}", hex::encode(REGISTRY));
+    assert!(load(&url).await.is_ok());
+  }
+
+  #[tokio::test]
+  async fn data_bad_hex() {
+    let err = load("data:hex;xyz").await.unwrap_err();
+    assert!(matches!(err, LoadError::BadHex(_)));
+  }
+
+  #[tokio::test]
+  async fn data_malformed() {
+    let url = format!("data:hex;{}", hex::encode("not json"));
+    let err = load(&url).await.unwrap_err();
+    assert!(matches!(err, LoadError::BadHexContent(_)));
+  }
+
+  #[tokio::test]
+  async fn data_base64() {
+    let url = format!(
+      "data:application/json;base64,{}",
+      BASE64_STANDARD.encode(REGISTRY)
+    );
+    let registry = load(&url).await.unwrap();
+    assert!(registry.mods.contains_key("example"));
+    assert_eq!(registry.source, Some(Url::parse(&url).unwrap()));
+  }
+
+  #[tokio::test]
+  async fn data_bad_base64() {
+    let err =
+      load("data:application/json;base64,e30").await.unwrap_err();
+    assert!(matches!(err, LoadError::BadBase64(_)));
+    let url = format!(
+      "data:application/json;base64,{}",
+      BASE64_STANDARD.encode("not json")
+    );
+    let err = load(&url).await.unwrap_err();
+    assert!(matches!(err, LoadError::BadFormat(_)));
+    let err = load("data:text/plain,{}").await.unwrap_err();
+    assert!(
+      matches!(err, LoadError::UnsupportedData(ty) if ty == "text/plain")
+    );
+  }
+
+  #[tokio::test]
+  async fn unsupported() {
+    let err = load("data:base64;e30=").await.unwrap_err();
+    assert!(
+      matches!(err, LoadError::UnsupportedData(ty) if ty == "base64")
+    );
+    let err =
+      load("ftp://example.com/registry.json").await.unwrap_err();
+    assert!(
+      matches!(err, LoadError::UnsupportedScheme(scheme) if scheme == "ftp")
+    );
+  }
 }
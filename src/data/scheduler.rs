@@ -0,0 +1,568 @@
+use std::{collections::VecDeque, path::PathBuf};
+
+// 需要独占ModManager的操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+  Uninstall(String),
+  Install(String),
+  SaveNote { id: String, note: String },
+  // 把没有记录的文件记到id名下
+  AdoptFiles { id: String, files: Vec<PathBuf> },
+  // 把回收站中的一项放回res_mods，读取之前不知道是哪个模组
+  RestoreTrashed(PathBuf),
+  CheckHealth,
+  // 把当前构建号写入安装记录
+  StampBuild,
+  RefreshCurrentMods,
+  RefreshRecords,
+}
+
+impl Operation {
+  // 刷新不针对某个模组
+  pub fn id(&self) -> Option<&str> {
+    match self {
+      Self::Uninstall(id)
+      | Self::Install(id)
+      | Self::SaveNote { id, .. }
+      | Self::AdoptFiles { id, .. } => Some(id),
+      Self::RestoreTrashed(_)
+      | Self::CheckHealth
+      | Self::StampBuild
+      | Self::RefreshCurrentMods
+      | Self::RefreshRecords => None,
+    }
+  }
+
+  // 会改动res_mods中的文件，备注也写在其中的安装记录里
+  pub fn writes_files(&self) -> bool {
+    !matches!(
+      self,
+      Self::CheckHealth
+        | Self::RefreshCurrentMods
+        | Self::RefreshRecords
+    )
+  }
+}
+
+// 决定ModManager空闲时下一步做什么。ModManager同一时间只在
+// 一个操作手里，操作结束后交还给调度器，由它取出下一个。
+// 同一模组的操作按加入的顺序进行；卸载可以越过其他模组的
+// 操作先进行，先腾出空间；刷新合并成一次，排在所有操作之后。
+// 安装记录的刷新会一并更新当前模组，两者都在等待时只刷新记录
+#[derive(Debug)]
+pub struct Scheduler<M> {
+  manager: Option<M>,
+  queue: VecDeque<Operation>,
+  running: Option<Operation>,
+  refresh_current_mods: bool,
+  refresh_records: bool,
+}
+
+impl<M> Default for Scheduler<M> {
+  fn default() -> Self {
+    Self {
+      manager: None,
+      queue: VecDeque::new(),
+      running: None,
+      refresh_current_mods: false,
+      refresh_records: false,
+    }
+  }
+}
+
+impl<M> Scheduler<M> {
+  // ModManager空闲时立即返回要执行的操作
+  pub fn enqueue(
+    &mut self,
+    operation: Operation,
+  ) -> Option<(Operation, M)> {
+    match operation {
+      Operation::RefreshCurrentMods => {
+        self.refresh_current_mods = true
+      }
+      Operation::RefreshRecords => self.refresh_records = true,
+      operation => self.queue.push_back(operation),
+    }
+    self.dispatch()
+  }
+
+  // ModManager交还回来，进行中的操作视为已经结束
+  pub fn on_manager_available(
+    &mut self,
+    manager: M,
+  ) -> Option<(Operation, M)> {
+    self.running = None;
+    self.manager = Some(manager);
+    self.dispatch()
+  }
+
+  // 需要重试的操作放回队首，不会被同一模组后来的操作越过
+  pub fn on_operation_finished(
+    &mut self,
+    manager: M,
+    retry: bool,
+  ) -> Option<(Operation, M)> {
+    match self.running.take().filter(|_| retry) {
+      Some(Operation::RefreshCurrentMods) => {
+        self.refresh_current_mods = true
+      }
+      Some(Operation::RefreshRecords) => self.refresh_records = true,
+      Some(operation) => self.queue.push_front(operation),
+      None => {}
+    }
+    self.on_manager_available(manager)
+  }
+
+  // 只能取消还在排队的操作，进行中的刷新结果照常使用
+  pub fn cancel(&mut self, operation: &Operation) -> bool {
+    match operation {
+      Operation::RefreshCurrentMods => {
+        std::mem::take(&mut self.refresh_current_mods)
+      }
+      Operation::RefreshRecords => {
+        std::mem::take(&mut self.refresh_records)
+      }
+      operation => {
+        match self.queue.iter().position(|x| x == operation) {
+          Some(pos) => {
+            self.queue.remove(pos);
+            true
+          }
+          None => false,
+        }
+      }
+    }
+  }
+
+  // 切换游戏目录时全部作废，手里的ModManager也丢弃
+  pub fn clear(&mut self) {
+    *self = Self::default();
+  }
+
+  pub fn running(&self) -> Option<&Operation> {
+    self.running.as_ref()
+  }
+
+  pub fn is_idle(&self) -> bool {
+    self.manager.is_some()
+  }
+
+  pub fn is_queued(&self, operation: &Operation) -> bool {
+    match operation {
+      Operation::RefreshCurrentMods => {
+        self.refresh_current_mods || self.refresh_records
+      }
+      Operation::RefreshRecords => self.refresh_records,
+      operation => self.queue.contains(operation),
+    }
+  }
+
+  fn dispatch(&mut self) -> Option<(Operation, M)> {
+    let manager = self.manager.take()?;
+    match self.next() {
+      Some(operation) => {
+        self.running = Some(operation.to_owned());
+        Some((operation, manager))
+      }
+      None => {
+        self.manager = Some(manager);
+        None
+      }
+    }
+  }
+
+  fn next(&mut self) -> Option<Operation> {
+    let uninstall =
+      self.queue.iter().enumerate().position(|(pos, operation)| {
+        matches!(operation, Operation::Uninstall(_))
+          && !self
+            .queue
+            .iter()
+            .take(pos)
+            .any(|earlier| earlier.id() == operation.id())
+      });
+    if let Some(operation) =
+      self.queue.remove(uninstall.unwrap_or_default())
+    {
+      return Some(operation);
+    }
+    if self.refresh_records {
+      self.refresh_records = false;
+      self.refresh_current_mods = false;
+      return Some(Operation::RefreshRecords);
+    }
+    if self.refresh_current_mods {
+      self.refresh_current_mods = false;
+      return Some(Operation::RefreshCurrentMods);
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  fn install(id: &str) -> Operation {
+    Operation::Install(id.to_string())
+  }
+
+  fn uninstall(id: &str) -> Operation {
+    Operation::Uninstall(id.to_string())
+  }
+
+  fn note(id: &str, note: &str) -> Operation {
+    Operation::SaveNote {
+      id: id.to_string(),
+      note: note.to_string(),
+    }
+  }
+
+  fn op(next: Option<(Operation, u32)>) -> Option<Operation> {
+    next.map(|(operation, _)| operation)
+  }
+
+  // 依次完成所有操作，返回执行的顺序
+  fn drain(scheduler: &mut Scheduler<u32>) -> Vec<Operation> {
+    let mut order = Vec::new();
+    let mut next = scheduler.on_manager_available(0);
+    while let Some((operation, manager)) = next {
+      order.push(operation);
+      next = scheduler.on_operation_finished(manager, false);
+    }
+    order
+  }
+
+  #[test]
+  fn waits_for_manager() {
+    let mut scheduler = Scheduler::default();
+    assert!(scheduler.enqueue(install("a")).is_none());
+    assert!(!scheduler.is_idle());
+    assert_eq!(
+      op(scheduler.on_manager_available(1)),
+      Some(install("a"))
+    );
+    assert_eq!(scheduler.running(), Some(&install("a")));
+  }
+
+  #[test]
+  fn idle_manager_starts_immediately() {
+    let mut scheduler = Scheduler::default();
+    assert!(scheduler.on_manager_available(1).is_none());
+    assert!(scheduler.is_idle());
+    let (operation, manager) =
+      scheduler.enqueue(install("a")).unwrap();
+    assert_eq!((operation, manager), (install("a"), 1));
+    // 进行中时只排队
+    assert!(scheduler.enqueue(install("b")).is_none());
+    assert!(scheduler.is_queued(&install("b")));
+    assert_eq!(
+      op(scheduler.on_operation_finished(manager, false)),
+      Some(install("b"))
+    );
+    assert!(
+      scheduler.on_operation_finished(manager, false).is_none()
+    );
+    assert!(scheduler.is_idle());
+    assert!(scheduler.running().is_none());
+  }
+
+  #[test]
+  fn uninstall_runs_before_other_installs() {
+    let mut scheduler = Scheduler::default();
+    scheduler.enqueue(install("a"));
+    scheduler.enqueue(install("b"));
+    scheduler.enqueue(uninstall("c"));
+    assert_eq!(
+      drain(&mut scheduler),
+      vec![uninstall("c"), install("a"), install("b")]
+    );
+  }
+
+  #[test]
+  fn same_id_keeps_order() {
+    let mut scheduler = Scheduler::default();
+    scheduler.enqueue(install("a"));
+    scheduler.enqueue(uninstall("a"));
+    scheduler.enqueue(uninstall("b"));
+    scheduler.enqueue(install("b"));
+    assert_eq!(
+      drain(&mut scheduler),
+      vec![
+        uninstall("b"),
+        install("a"),
+        uninstall("a"),
+        install("b")
+      ]
+    );
+  }
+
+  #[test]
+  fn notes_keep_order() {
+    let mut scheduler = Scheduler::default();
+    scheduler.enqueue(note("a", "1"));
+    scheduler.enqueue(note("a", "2"));
+    scheduler.enqueue(uninstall("a"));
+    assert_eq!(
+      drain(&mut scheduler),
+      vec![note("a", "1"), note("a", "2"), uninstall("a")]
+    );
+  }
+
+  #[test]
+  fn retry_runs_before_later_operations() {
+    let mut scheduler = Scheduler::default();
+    scheduler.on_manager_available(1);
+    let (_, manager) = scheduler.enqueue(install("a")).unwrap();
+    scheduler.enqueue(uninstall("a"));
+    scheduler.enqueue(uninstall("b"));
+    // 其他模组的卸载照样可以先进行
+    assert_eq!(
+      op(scheduler.on_operation_finished(manager, true)),
+      Some(uninstall("b"))
+    );
+    assert_eq!(
+      drain(&mut scheduler),
+      vec![install("a"), uninstall("a")]
+    );
+  }
+
+  #[test]
+  fn refreshes_are_merged_and_run_last() {
+    let mut scheduler = Scheduler::default();
+    scheduler.enqueue(Operation::RefreshRecords);
+    scheduler.enqueue(Operation::RefreshRecords);
+    scheduler.enqueue(Operation::RefreshCurrentMods);
+    scheduler.enqueue(install("a"));
+    assert_eq!(
+      drain(&mut scheduler),
+      vec![install("a"), Operation::RefreshRecords]
+    );
+  }
+
+  #[test]
+  fn current_mods_refresh_alone() {
+    let mut scheduler = Scheduler::default();
+    scheduler.enqueue(Operation::RefreshCurrentMods);
+    scheduler.enqueue(Operation::RefreshCurrentMods);
+    assert_eq!(
+      drain(&mut scheduler),
+      vec![Operation::RefreshCurrentMods]
+    );
+  }
+
+  // 每个操作结束后都要求刷新，等待中的刷新始终只有一次
+  #[test]
+  fn rapid_refreshes_are_coalesced() {
+    let mut scheduler = Scheduler::default();
+    scheduler.on_manager_available(1);
+    let (_, manager) = scheduler.enqueue(install("a")).unwrap();
+    for id in ["b", "c", "d"] {
+      scheduler.enqueue(install(id));
+    }
+    for _ in 0..100 {
+      scheduler.enqueue(Operation::RefreshCurrentMods);
+      scheduler.enqueue(Operation::RefreshRecords);
+    }
+    let mut order = Vec::new();
+    let mut next = scheduler.on_operation_finished(manager, false);
+    while let Some((operation, manager)) = next {
+      if operation.id().is_some() {
+        scheduler.enqueue(Operation::RefreshRecords);
+        scheduler.enqueue(Operation::RefreshCurrentMods);
+      }
+      order.push(operation);
+      next = scheduler.on_operation_finished(manager, false);
+    }
+    assert_eq!(
+      order,
+      vec![
+        install("b"),
+        install("c"),
+        install("d"),
+        Operation::RefreshRecords
+      ]
+    );
+  }
+
+  #[test]
+  fn queued_refresh_can_be_cancelled() {
+    let mut scheduler = Scheduler::default();
+    scheduler.on_manager_available(1);
+    let (_, manager) =
+      scheduler.enqueue(Operation::RefreshRecords).unwrap();
+    // 进行中的取消不了，之后要求的可以
+    assert!(!scheduler.cancel(&Operation::RefreshRecords));
+    scheduler.enqueue(Operation::RefreshRecords);
+    assert!(scheduler.is_queued(&Operation::RefreshCurrentMods));
+    assert!(scheduler.cancel(&Operation::RefreshRecords));
+    assert!(!scheduler.is_queued(&Operation::RefreshRecords));
+    assert!(
+      scheduler.on_operation_finished(manager, false).is_none()
+    );
+  }
+
+  #[test]
+  fn refresh_requested_while_running_is_kept() {
+    let mut scheduler = Scheduler::default();
+    scheduler.on_manager_available(1);
+    let (_, manager) =
+      scheduler.enqueue(Operation::RefreshRecords).unwrap();
+    assert!(!scheduler.is_queued(&Operation::RefreshRecords));
+    scheduler.enqueue(Operation::RefreshRecords);
+    assert_eq!(
+      op(scheduler.on_operation_finished(manager, false)),
+      Some(Operation::RefreshRecords)
+    );
+  }
+
+  #[test]
+  fn cancel_only_removes_queued() {
+    let mut scheduler = Scheduler::default();
+    scheduler.on_manager_available(1);
+    scheduler.enqueue(install("a"));
+    scheduler.enqueue(install("b"));
+    assert!(!scheduler.cancel(&install("a")));
+    assert!(scheduler.cancel(&install("b")));
+    assert!(!scheduler.cancel(&install("b")));
+    // 取消进行中的操作后ModManager照常交还
+    assert!(scheduler.on_manager_available(1).is_none());
+    assert!(scheduler.is_idle());
+  }
+
+  #[test]
+  fn clear_drops_everything() {
+    let mut scheduler = Scheduler::default();
+    scheduler.on_manager_available(1);
+    scheduler.enqueue(install("a"));
+    scheduler.enqueue(install("b"));
+    scheduler.enqueue(Operation::RefreshRecords);
+    scheduler.clear();
+    assert!(!scheduler.is_idle());
+    assert!(scheduler.running().is_none());
+    assert!(!scheduler.is_queued(&install("b")));
+    assert!(scheduler.on_manager_available(2).is_none());
+  }
+
+  // 固定种子的伪随机数，保证失败时可以复现
+  struct Rng(u64);
+
+  impl Rng {
+    fn below(&mut self, n: u64) -> u64 {
+      self.0 = self
+        .0
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+      (self.0 >> 33) % n
+    }
+  }
+
+  // 随机交错加入、完成、重试和取消，检查每个操作都恰好完成
+  // 一次，同一模组按加入的顺序完成，刷新只在队列空时进行，
+  // 有操作等待时ModManager不会闲着
+  #[test]
+  fn random_interleavings() {
+    for seed in 0..200 {
+      let mut rng = Rng(seed);
+      let mut scheduler = Scheduler::default();
+      assert!(scheduler.on_manager_available(0).is_none());
+      // 每个模组还没完成的操作，进行中的总在最前面
+      let mut pending: HashMap<&str, Vec<Operation>> = HashMap::new();
+      let mut refresh_pending = false;
+      let mut running: Option<(Operation, u32)> = None;
+      let mut notes = 0;
+      let mut step = 0;
+      loop {
+        // 200步之后只完成，不再加入新操作
+        let action = if step < 200 { rng.below(6) } else { 3 };
+        step += 1;
+        let mut next = None;
+        match action {
+          0..=2 => {
+            let id = ["a", "b", "c"][rng.below(3) as usize];
+            let operation = match rng.below(4) {
+              0 => install(id),
+              1 => uninstall(id),
+              2 => {
+                notes += 1;
+                note(id, &notes.to_string())
+              }
+              _ => Operation::RefreshRecords,
+            };
+            match operation.id() {
+              Some(_) => pending
+                .entry(id)
+                .or_default()
+                .push(operation.to_owned()),
+              None => refresh_pending = true,
+            }
+            next = scheduler.enqueue(operation);
+          }
+          3 | 4 => {
+            let Some((operation, manager)) = running.take() else {
+              if step > 200 {
+                break;
+              }
+              continue;
+            };
+            let retry = action == 4;
+            match (retry, operation.id()) {
+              (false, Some(id)) => {
+                let list = pending.get_mut(id).unwrap();
+                assert_eq!(
+                  list.remove(0),
+                  operation,
+                  "seed {}",
+                  seed
+                );
+              }
+              (true, None) => refresh_pending = true,
+              _ => {}
+            }
+            next = scheduler.on_operation_finished(manager, retry);
+          }
+          _ => {
+            let id = ["a", "b", "c"][rng.below(3) as usize];
+            let list = pending.entry(id).or_default();
+            let skip =
+              running.as_ref().is_some_and(|(operation, _)| {
+                operation.id() == Some(id)
+              }) as usize;
+            if let Some(operation) = list.get(skip).cloned() {
+              assert!(scheduler.cancel(&operation), "seed {}", seed);
+              list.remove(skip);
+            }
+          }
+        }
+        if let Some((operation, manager)) = next {
+          assert!(running.is_none(), "seed {}", seed);
+          match operation.id() {
+            Some(id) => {
+              assert_eq!(
+                pending[id].first(),
+                Some(&operation),
+                "seed {}",
+                seed
+              );
+            }
+            None => {
+              assert!(pending.values().all(Vec::is_empty));
+              refresh_pending = false;
+            }
+          }
+          running = Some((operation, manager));
+        }
+        if running.is_none() {
+          assert!(scheduler.is_idle(), "seed {}", seed);
+          assert!(
+            pending.values().all(Vec::is_empty),
+            "seed {}",
+            seed
+          );
+          assert!(!refresh_pending, "seed {}", seed);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,278 @@
+use std::{
+  collections::HashMap,
+  env::current_dir,
+  fmt,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use url::Url;
+
+use crate::{
+  http::HttpOptions,
+  i18n::Locale,
+  tasks::download::{DownloadTimeouts, RetryPolicy},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error("Io: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("TomlDe: {0}")]
+  TomlDe(#[from] toml::de::Error),
+  #[error("TomlSer: {0}")]
+  TomlSer(#[from] toml::ser::Error),
+}
+
+// 编译时可以用同名的环境变量换成镜像等其他地址，多个地址以逗号分隔
+pub const DEFAULT_REGISTRY_URLS: &str =
+  match option_env!("KMM_DEFAULT_REGISTRY_URLS") {
+    Some(urls) => urls,
+    None => "https://kmm.worker.zerodegress.ink/registry",
+  };
+
+// 设置中没有地址时读取的环境变量，格式同上
+pub const REGISTRY_URLS_ENV: &str = "KMM_REGISTRY_URLS";
+
+// 旧版本的配置文件缺少的字段取默认值
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+  // 没有时使用启动目录
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub game_dir: Option<PathBuf>,
+  // 保存的多个游戏目录，如正式服和测试服，可以在顶栏切换
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub games: Vec<GameInstall>,
+  // 按字符串保存，单个地址写错不影响其余配置；
+  // 为空时使用环境变量或编译时的默认地址
+  pub registry_urls: Vec<String>,
+  // registry地址到显示名称的映射
+  #[serde(skip_serializing_if = "HashMap::is_empty")]
+  pub registry_aliases: HashMap<String, String>,
+  pub theme: String,
+  pub cache_archives: bool,
+  // 启动时更新有新版本的已安装模组，auto_update_ask时先询问
+  pub auto_update: bool,
+  pub auto_update_ask: bool,
+  // res_mods可以是指向游戏目录以外的链接或目录联接，
+  // 比如挪到了另一个盘上
+  pub allow_external_res_mods: bool,
+  // 卸载时把文件移到回收站而不是直接删除，
+  // 超过保留天数的在启动时清理
+  pub safe_delete: bool,
+  pub trash_retention_days: u32,
+  // 网络设置在启动时用来创建共用的客户端，修改后重启生效。
+  // 代理如`http://127.0.0.1:7890`，没有时使用系统代理
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub proxy: Option<String>,
+  // 个别下载站点不认默认的User-Agent时可以替换
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub user_agent: Option<String>,
+  pub connect_timeout_secs: u64,
+  // 允许重定向从HTTPS降级到HTTP，默认拒绝
+  pub allow_insecure_redirects: bool,
+  // 下载中连续这么久没有收到数据就断开重试
+  pub idle_timeout_secs: u64,
+  // 暂时性的错误自动重试的次数
+  pub download_retries: u32,
+  pub sort_key: SortKey,
+  pub sort_descending: bool,
+  // 没有时跟随系统语言
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub language: Option<Locale>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub window: Option<WindowGeometry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GameInstall {
+  pub name: String,
+  pub path: PathBuf,
+}
+
+impl GameInstall {
+  // 没有起名字时用目录名
+  pub fn new(name: &str, path: PathBuf) -> Self {
+    let name = match name.trim() {
+      "" => path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string()),
+      name => name.to_string(),
+    };
+    Self { name, path }
+  }
+}
+
+impl fmt::Display for GameInstall {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.name)
+  }
+}
+
+// 最大化时保留的是还原后的大小和位置
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct WindowGeometry {
+  pub width: f32,
+  pub height: f32,
+  // 没有时居中显示
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub position: Option<(f32, f32)>,
+  #[serde(default)]
+  pub maximized: bool,
+}
+
+// 模组列表的排序方式，相同时再按id排序
+#[derive(
+  Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+  #[default]
+  Name,
+  Installed,
+  UpdateAvailable,
+  UpdateTime,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      game_dir: None,
+      games: Vec::new(),
+      registry_urls: Vec::new(),
+      registry_aliases: HashMap::new(),
+      theme: "Nord".to_string(),
+      cache_archives: false,
+      auto_update: false,
+      auto_update_ask: true,
+      allow_external_res_mods: false,
+      safe_delete: false,
+      trash_retention_days: 7,
+      proxy: None,
+      user_agent: None,
+      connect_timeout_secs: 15,
+      allow_insecure_redirects: false,
+      idle_timeout_secs: 60,
+      download_retries: 3,
+      sort_key: SortKey::default(),
+      sort_descending: false,
+      language: None,
+      window: None,
+    }
+  }
+}
+
+impl Settings {
+  // 启动时还没有异步运行时，只能同步读取
+  pub fn load(path: &Path) -> Result<Self, Error> {
+    match std::fs::read_to_string(path) {
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        Ok(Self::default())
+      }
+      content => Ok(toml::from_str(&content?)?),
+    }
+  }
+
+  // 路径无效时由调用方在创建ModManager时报错
+  pub fn game_dir_path(&self) -> PathBuf {
+    self
+      .game_dir
+      .to_owned()
+      .or_else(|| current_dir().ok())
+      .unwrap_or_default()
+  }
+
+  pub fn game_install(&self, path: &Path) -> Option<&GameInstall> {
+    self.games.iter().find(|game| game.path == path)
+  }
+
+  pub fn parsed_registry_urls(&self) -> (Vec<Url>, Vec<String>) {
+    resolve_registry_urls(
+      &self.registry_urls,
+      std::env::var(REGISTRY_URLS_ENV).ok().as_deref(),
+      DEFAULT_REGISTRY_URLS,
+    )
+  }
+
+  // 有别名时用别名，否则显示主机名，data等没有主机的显示协议名
+  pub fn registry_label(&self, url: &Url) -> String {
+    self
+      .registry_aliases
+      .iter()
+      .find(|(key, _)| Url::parse(key).is_ok_and(|key| key == *url))
+      .map(|(_, alias)| alias.to_owned())
+      .or_else(|| url.host_str().map(str::to_string))
+      .unwrap_or_else(|| url.scheme().to_string())
+  }
+
+  pub fn http_options(&self) -> HttpOptions {
+    HttpOptions {
+      connect_timeout: Duration::from_secs(self.connect_timeout_secs),
+      allow_insecure_redirects: self.allow_insecure_redirects,
+      proxy: self.proxy.to_owned().filter(|proxy| !proxy.is_empty()),
+      user_agent: self
+        .user_agent
+        .to_owned()
+        .filter(|user_agent| !user_agent.is_empty()),
+    }
+  }
+
+  pub fn download_timeouts(&self) -> DownloadTimeouts {
+    DownloadTimeouts {
+      idle: Duration::from_secs(self.idle_timeout_secs.max(1)),
+    }
+  }
+
+  pub fn retry_policy(&self) -> RetryPolicy {
+    RetryPolicy {
+      max_retries: self.download_retries,
+      ..RetryPolicy::default()
+    }
+  }
+
+  pub async fn save(&self, path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, toml::to_string_pretty(self)?).await?;
+    Ok(())
+  }
+}
+
+// 依次取设置、环境变量和编译时默认值中第一个配置了地址的来源。
+// 能解析的地址按原顺序返回，其余的原样返回用于提示
+pub fn resolve_registry_urls(
+  settings: &[String],
+  env: Option<&str>,
+  default: &str,
+) -> (Vec<Url>, Vec<String>) {
+  let split = |urls: &str| {
+    urls
+      .split(',')
+      .map(str::trim)
+      .filter(|url| !url.is_empty())
+      .map(str::to_string)
+      .collect::<Vec<_>>()
+  };
+  let configured = if settings.is_empty() {
+    env
+      .map(split)
+      .filter(|urls| !urls.is_empty())
+      .unwrap_or_else(|| split(default))
+  } else {
+    settings.to_vec()
+  };
+  let mut urls = Vec::new();
+  let mut invalid = Vec::new();
+  for url in configured {
+    match Url::parse(&url) {
+      Ok(parsed) => urls.push(parsed),
+      Err(_) => invalid.push(url),
+    }
+  }
+  (urls, invalid)
+}
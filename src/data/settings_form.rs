@@ -0,0 +1,221 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use url::Url;
+
+use super::settings::{GameInstall, Settings};
+use crate::i18n::Locale;
+
+// 设置界面中正在编辑的内容，点击应用后才写回Settings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SettingsForm {
+  pub theme: String,
+  pub language: Option<Locale>,
+  pub registry_urls: Vec<String>,
+  // 名称和路径，路径为空的在应用时去掉
+  pub games: Vec<(String, String)>,
+  pub cache_archives: bool,
+  pub auto_update: bool,
+  pub auto_update_ask: bool,
+  pub allow_external_res_mods: bool,
+  pub safe_delete: bool,
+  pub trash_retention_days: u32,
+  pub connect_timeout_secs: u64,
+  pub allow_insecure_redirects: bool,
+  pub idle_timeout_secs: u64,
+  pub download_retries: u32,
+  // 校验失败的registry地址，按行号记录原因
+  pub errors: BTreeMap<usize, UrlError>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UrlError {
+  Invalid(String),
+  UnsupportedScheme(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum SettingsEdit {
+  Theme(String),
+  Language(Option<Locale>),
+  RegistryUrl(usize, String),
+  AddRegistryUrl,
+  RemoveRegistryUrl(usize),
+  GameName(usize, String),
+  GamePath(usize, String),
+  AddGame(PathBuf),
+  RemoveGame(usize),
+  CacheArchives(bool),
+  AutoUpdate(bool),
+  AutoUpdateAsk(bool),
+  AllowExternalResMods(bool),
+  SafeDelete(bool),
+  TrashRetentionDays(u32),
+  ConnectTimeout(u64),
+  AllowInsecureRedirects(bool),
+  IdleTimeout(u64),
+  DownloadRetries(u32),
+}
+
+const REGISTRY_SCHEMES: [&str; 4] = ["http", "https", "file", "data"];
+
+impl SettingsForm {
+  pub fn from_settings(settings: &Settings) -> Self {
+    Self {
+      theme: settings.theme.to_owned(),
+      language: settings.language,
+      registry_urls: settings.registry_urls.to_owned(),
+      games: settings
+        .games
+        .iter()
+        .map(|game| {
+          (
+            game.name.to_owned(),
+            game.path.to_string_lossy().to_string(),
+          )
+        })
+        .collect(),
+      cache_archives: settings.cache_archives,
+      auto_update: settings.auto_update,
+      auto_update_ask: settings.auto_update_ask,
+      allow_external_res_mods: settings.allow_external_res_mods,
+      safe_delete: settings.safe_delete,
+      trash_retention_days: settings.trash_retention_days,
+      connect_timeout_secs: settings.connect_timeout_secs,
+      allow_insecure_redirects: settings.allow_insecure_redirects,
+      idle_timeout_secs: settings.idle_timeout_secs,
+      download_retries: settings.download_retries,
+      errors: BTreeMap::new(),
+    }
+  }
+
+  pub fn update(&mut self, edit: SettingsEdit) {
+    match edit {
+      SettingsEdit::Theme(theme) => self.theme = theme,
+      SettingsEdit::Language(language) => self.language = language,
+      SettingsEdit::RegistryUrl(index, url) => {
+        if let Some(current) = self.registry_urls.get_mut(index) {
+          *current = url;
+          self.errors.remove(&index);
+        }
+      }
+      SettingsEdit::AddRegistryUrl => {
+        self.registry_urls.push(String::new());
+      }
+      SettingsEdit::RemoveRegistryUrl(index) => {
+        if index < self.registry_urls.len() {
+          self.registry_urls.remove(index);
+          // 之后的行号都前移了，重新校验
+          self.errors = self.validate();
+        }
+      }
+      SettingsEdit::GameName(index, name) => {
+        if let Some(game) = self.games.get_mut(index) {
+          game.0 = name;
+        }
+      }
+      SettingsEdit::GamePath(index, path) => {
+        if let Some(game) = self.games.get_mut(index) {
+          game.1 = path;
+        }
+      }
+      SettingsEdit::AddGame(path) => {
+        let game = GameInstall::new("", path);
+        self
+          .games
+          .push((game.name, game.path.to_string_lossy().to_string()));
+      }
+      SettingsEdit::RemoveGame(index) => {
+        if index < self.games.len() {
+          self.games.remove(index);
+        }
+      }
+      SettingsEdit::CacheArchives(cache_archives) => {
+        self.cache_archives = cache_archives;
+      }
+      SettingsEdit::AutoUpdate(auto_update) => {
+        self.auto_update = auto_update;
+      }
+      SettingsEdit::AutoUpdateAsk(ask) => self.auto_update_ask = ask,
+      SettingsEdit::AllowExternalResMods(allow) => {
+        self.allow_external_res_mods = allow;
+      }
+      SettingsEdit::SafeDelete(safe_delete) => {
+        self.safe_delete = safe_delete;
+      }
+      SettingsEdit::TrashRetentionDays(days) => {
+        self.trash_retention_days = days.max(1);
+      }
+      SettingsEdit::ConnectTimeout(secs) => {
+        self.connect_timeout_secs = secs.max(1);
+      }
+      SettingsEdit::AllowInsecureRedirects(allow) => {
+        self.allow_insecure_redirects = allow;
+      }
+      SettingsEdit::IdleTimeout(secs) => {
+        self.idle_timeout_secs = secs.max(1);
+      }
+      SettingsEdit::DownloadRetries(retries) => {
+        self.download_retries = retries;
+      }
+    }
+  }
+
+  // 空行直接忽略，其余的必须是支持的地址
+  pub fn validate(&self) -> BTreeMap<usize, UrlError> {
+    self
+      .registry_urls
+      .iter()
+      .enumerate()
+      .filter(|(_, url)| !url.trim().is_empty())
+      .filter_map(|(index, url)| match Url::parse(url.trim()) {
+        Ok(url) if REGISTRY_SCHEMES.contains(&url.scheme()) => None,
+        Ok(url) => Some((
+          index,
+          UrlError::UnsupportedScheme(url.scheme().to_string()),
+        )),
+        Err(err) => Some((index, UrlError::Invalid(err.to_string()))),
+      })
+      .collect()
+  }
+
+  // 校验通过时返回新的设置，界面以外的字段保持不变；
+  // 失败时返回各行的错误，编辑中的内容不受影响
+  pub fn apply(
+    &self,
+    base: &Settings,
+  ) -> Result<Settings, BTreeMap<usize, UrlError>> {
+    let errors = self.validate();
+    if !errors.is_empty() {
+      return Err(errors);
+    }
+    Ok(Settings {
+      theme: self.theme.to_owned(),
+      language: self.language,
+      registry_urls: self
+        .registry_urls
+        .iter()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect(),
+      games: self
+        .games
+        .iter()
+        .filter(|(_, path)| !path.trim().is_empty())
+        .map(|(name, path)| {
+          GameInstall::new(name, PathBuf::from(path.trim()))
+        })
+        .collect(),
+      cache_archives: self.cache_archives,
+      auto_update: self.auto_update,
+      auto_update_ask: self.auto_update_ask,
+      allow_external_res_mods: self.allow_external_res_mods,
+      safe_delete: self.safe_delete,
+      trash_retention_days: self.trash_retention_days,
+      connect_timeout_secs: self.connect_timeout_secs,
+      allow_insecure_redirects: self.allow_insecure_redirects,
+      idle_timeout_secs: self.idle_timeout_secs,
+      download_retries: self.download_retries,
+      ..base.to_owned()
+    })
+  }
+}
@@ -0,0 +1,71 @@
+use base64::prelude::{BASE64_STANDARD, Engine};
+
+use super::registry::{Mod, Registry};
+
+// 超过这个长度的链接在聊天软件里多半会被截断，复制时提醒
+pub const LINK_WARN_LEN: usize = 8 * 1024;
+
+// 已安装模组的id、版本和安装时压缩包的sha256
+pub type Installed<'a> = (&'a str, &'a str, Option<&'a str>);
+
+// 把已安装的模组整理成只包含它们的registry，版本固定为已安装的
+// 版本，地址按来源registry解析成绝对地址。和列表一样靠前的
+// registry优先；找不到或已经下载不到这个版本的返回在第二项中
+pub fn share_registry<'a>(
+  registries: &[Registry],
+  installed: impl IntoIterator<Item = Installed<'a>>,
+) -> (Registry, Vec<String>) {
+  let mut shared = Registry::default();
+  let mut skipped = Vec::new();
+  for (id, version, archive_sha256) in installed {
+    match shared_mod(registries, id, version, archive_sha256) {
+      Some(modr) => {
+        shared.mods.insert(id.to_string(), modr);
+      }
+      None => skipped.push(id.to_string()),
+    }
+  }
+  skipped.sort();
+  (shared, skipped)
+}
+
+fn shared_mod(
+  registries: &[Registry],
+  id: &str,
+  version: &str,
+  archive_sha256: Option<&str>,
+) -> Option<Mod> {
+  let (registry, modr) = registries.iter().find_map(|registry| {
+    registry.mods.get(id).map(|x| (registry, x))
+  })?;
+  let release = modr.any_release(version)?;
+  let url = registry.resolve_url(&release.url).ok()?;
+  let image_url = match modr.image_url.as_str() {
+    "" => String::new(),
+    image_url => registry
+      .resolve_url(image_url)
+      .map_or_else(|_| image_url.to_string(), String::from),
+  };
+  Some(Mod {
+    // 只有当前版本的大小是已知的
+    size: (modr.version == version).then_some(modr.size).flatten(),
+    version: release.version,
+    url: url.to_string(),
+    // 用实际安装的压缩包的，对方下载到不同的文件时校验会失败
+    sha256: archive_sha256.map(str::to_string).or(release.sha256),
+    image_url,
+    versions: Vec::new(),
+    ..modr.to_owned()
+  })
+}
+
+// 编码成data:链接，可以直接作为registry地址添加
+pub fn share_link(
+  registry: &Registry,
+) -> Result<String, serde_json::Error> {
+  let json = serde_json::to_vec(registry)?;
+  Ok(format!(
+    "data:application/json;base64,{}",
+    BASE64_STANDARD.encode(json)
+  ))
+}
@@ -0,0 +1,26 @@
+// 状态栏显示的最近一次事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusLine {
+  Progress(String),
+  Success(String),
+  Error(String),
+}
+
+impl StatusLine {
+  pub fn text(&self) -> &str {
+    match self {
+      Self::Progress(text)
+      | Self::Success(text)
+      | Self::Error(text) => text,
+    }
+  }
+
+  pub fn is_error(&self) -> bool {
+    matches!(self, Self::Error(_))
+  }
+
+  // 错误一直保留到被新的结果替换或被关闭，进度不会覆盖它
+  pub fn replaced_by(&self, next: &StatusLine) -> bool {
+    !(self.is_error() && matches!(next, Self::Progress(_)))
+  }
+}
@@ -0,0 +1,255 @@
+use std::{
+  cmp::Ordering,
+  fmt,
+  hash::{Hash, Hasher},
+  ops::Deref,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// registry和安装记录中的版本号。原样保存写入时的字符串，
+// 序列化后和读入的完全相同；比较时使用解析出的各段
+#[derive(Debug, Clone, Default)]
+pub struct ModVersion {
+  raw: String,
+  core: Vec<Segment>,
+  // 预发布部分，`1.0-beta`中的`beta`
+  pre: Option<Vec<Segment>>,
+}
+
+// 数字段排在非数字段之前
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Segment {
+  Number(u64),
+  Text(String),
+}
+
+impl ModVersion {
+  pub fn new(raw: impl Into<String>) -> Self {
+    let raw = raw.into();
+    let (core, pre) = split_version(&raw);
+    Self {
+      core: parse_segments(core),
+      pre: pre.map(parse_segments),
+      raw,
+    }
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.raw
+  }
+
+  // 宽松的semver比较：数字段按数值比较，其余按字符串比较，
+  // 带预发布后缀的版本排在同号正式版之前。1.0和1.0.0相等
+  pub fn cmp_numeric(&self, other: &Self) -> Ordering {
+    compare_segments(&self.core, &other.core).then_with(|| {
+      match (&self.pre, &other.pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_segments(a, b),
+      }
+    })
+  }
+
+  pub fn is_newer_than(&self, other: &Self) -> bool {
+    self.cmp_numeric(other) == Ordering::Greater
+  }
+}
+
+// 数值上相等时再按原始字符串排，和相等判断保持一致
+impl Ord for ModVersion {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self
+      .cmp_numeric(other)
+      .then_with(|| self.raw.cmp(&other.raw))
+  }
+}
+
+impl PartialOrd for ModVersion {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// 相等按原始字符串判断，registry中的版本号是按原样匹配的
+impl PartialEq for ModVersion {
+  fn eq(&self, other: &Self) -> bool {
+    self.raw == other.raw
+  }
+}
+
+impl Eq for ModVersion {}
+
+impl Hash for ModVersion {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.raw.hash(state);
+  }
+}
+
+impl PartialEq<str> for ModVersion {
+  fn eq(&self, other: &str) -> bool {
+    self.raw == other
+  }
+}
+
+impl PartialEq<&str> for ModVersion {
+  fn eq(&self, other: &&str) -> bool {
+    self.raw == *other
+  }
+}
+
+impl PartialEq<String> for ModVersion {
+  fn eq(&self, other: &String) -> bool {
+    &self.raw == other
+  }
+}
+
+impl PartialEq<ModVersion> for str {
+  fn eq(&self, other: &ModVersion) -> bool {
+    self == other.raw
+  }
+}
+
+impl PartialEq<ModVersion> for &str {
+  fn eq(&self, other: &ModVersion) -> bool {
+    *self == other.raw
+  }
+}
+
+impl PartialEq<ModVersion> for String {
+  fn eq(&self, other: &ModVersion) -> bool {
+    *self == other.raw
+  }
+}
+
+impl Deref for ModVersion {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.raw
+  }
+}
+
+impl fmt::Display for ModVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.raw)
+  }
+}
+
+impl From<&str> for ModVersion {
+  fn from(raw: &str) -> Self {
+    Self::new(raw)
+  }
+}
+
+impl From<String> for ModVersion {
+  fn from(raw: String) -> Self {
+    Self::new(raw)
+  }
+}
+
+impl From<ModVersion> for String {
+  fn from(version: ModVersion) -> Self {
+    version.raw
+  }
+}
+
+impl Serialize for ModVersion {
+  fn serialize<S: Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.raw)
+  }
+}
+
+// 任何字符串都接受，解析不出数字的段按字符串比较
+impl<'de> Deserialize<'de> for ModVersion {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    String::deserialize(deserializer).map(Self::new)
+  }
+}
+
+// 去掉前缀v和构建元数据，拆出预发布部分
+fn split_version(version: &str) -> (&str, Option<&str>) {
+  let version = version.trim();
+  let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+  let version = version
+    .split_once('+')
+    .map_or(version, |(version, _)| version);
+  match version.split_once('-') {
+    Some((core, pre)) => (core, Some(pre)),
+    None => (version, None),
+  }
+}
+
+fn parse_segments(part: &str) -> Vec<Segment> {
+  part
+    .split('.')
+    .map(|segment| match segment.parse::<u64>() {
+      Ok(number) => Segment::Number(number),
+      Err(_) => Segment::Text(segment.to_string()),
+    })
+    .collect()
+}
+
+// 缺少的段视为0，所以1.0和1.0.0相等
+fn compare_segments(a: &[Segment], b: &[Segment]) -> Ordering {
+  let zero = Segment::Number(0);
+  (0..a.len().max(b.len()))
+    .map(|index| {
+      a.get(index)
+        .unwrap_or(&zero)
+        .cmp(b.get(index).unwrap_or(&zero))
+    })
+    .find(|ordering| *ordering != Ordering::Equal)
+    .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn v(raw: &str) -> ModVersion {
+    ModVersion::new(raw)
+  }
+
+  #[test]
+  fn compares_numerically() {
+    assert!(v("1.10").is_newer_than(&v("1.9")));
+    assert!(v("v2.0").is_newer_than(&v("1.99.9")));
+    assert!(v("1.0").is_newer_than(&v("1.0-beta")));
+    assert!(v("1.0-beta.2").is_newer_than(&v("1.0-beta.1")));
+    assert!(v("1.0.a").is_newer_than(&v("1.0.9")));
+    assert_eq!(
+      v("1.0").cmp_numeric(&v("1.0.0+build")),
+      Ordering::Equal
+    );
+  }
+
+  // 数值上相等的版本仍然可以区分，排序结果是确定的
+  #[test]
+  fn equality_keeps_raw_string() {
+    assert_ne!(v("1.0"), v("1.0.0"));
+    assert_eq!(v("1.0"), "1.0");
+    assert!(v("1.0") < v("1.0.0"));
+    let mut versions =
+      vec![v("1.10"), v("1.2"), v("1.0.0"), v("1.0")];
+    versions.sort();
+    assert_eq!(versions, ["1.0", "1.0.0", "1.2", "1.10"]);
+  }
+
+  #[test]
+  fn serde_round_trips_exact_string() {
+    for raw in ["1.0", " v1.2.3-beta+7 ", "2024.05.01", "最新版", ""]
+    {
+      let json = serde_json::to_string(raw).unwrap();
+      let version: ModVersion = serde_json::from_str(&json).unwrap();
+      assert_eq!(version.as_str(), raw);
+      assert_eq!(serde_json::to_string(&version).unwrap(), json);
+    }
+  }
+}
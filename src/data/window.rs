@@ -0,0 +1,115 @@
+use std::ops::Range;
+
+// 列表中实际需要构建的行，前后用等高的占位代替
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListWindow {
+  pub range: Range<usize>,
+  pub before: f32,
+  pub after: f32,
+}
+
+// 偏移超出内容时按滚到底处理，筛选或排序后列表变短也不会留下空白；
+// 还不知道视口高度时构建所有行
+pub fn visible_window(
+  heights: &[f32],
+  offset: f32,
+  viewport: f32,
+  overscan: usize,
+) -> ListWindow {
+  if viewport <= 0. {
+    return ListWindow {
+      range: 0..heights.len(),
+      before: 0.,
+      after: 0.,
+    };
+  }
+  let total = heights.iter().sum::<f32>();
+  let offset = offset.clamp(0., (total - viewport).max(0.));
+  let mut start = heights.len();
+  let mut end = heights.len();
+  let mut top = 0.;
+  for (index, height) in heights.iter().enumerate() {
+    if start == heights.len() && top + height > offset {
+      start = index;
+    }
+    if top >= offset + viewport {
+      end = index;
+      break;
+    }
+    top += height;
+  }
+  let start = start.saturating_sub(overscan);
+  let end = (end + overscan).min(heights.len()).max(start);
+  ListWindow {
+    before: heights[..start].iter().sum(),
+    after: heights[end..].iter().sum(),
+    range: start..end,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn window(
+    heights: &[f32],
+    offset: f32,
+    viewport: f32,
+  ) -> (Range<usize>, f32, f32) {
+    let window = visible_window(heights, offset, viewport, 2);
+    (window.range, window.before, window.after)
+  }
+
+  #[test]
+  fn uniform_rows() {
+    let heights = [10.; 100];
+    // 顶部：0..5可见，后面多构建两行
+    assert_eq!(window(&heights, 0., 50.), (0..7, 0., 930.));
+    // 中间：30..35可见，前后各多两行
+    assert_eq!(window(&heights, 300., 50.), (28..37, 280., 630.));
+    // 半行也算可见
+    assert_eq!(window(&heights, 305., 50.), (28..38, 280., 620.));
+    // 底部
+    assert_eq!(window(&heights, 950., 50.), (93..100, 930., 0.));
+  }
+
+  #[test]
+  fn placeholders_keep_total_height() {
+    let heights =
+      (0..50).map(|i| (i % 3 + 1) as f32 * 8.).collect::<Vec<_>>();
+    let total = heights.iter().sum::<f32>();
+    for offset in [0., 17., 100., 333., total] {
+      let window = visible_window(&heights, offset, 60., 1);
+      let built = heights[window.range].iter().sum::<f32>();
+      assert_eq!(window.before + built + window.after, total);
+    }
+  }
+
+  // 筛选后列表变短，旧的偏移超出了内容
+  #[test]
+  fn offset_clamped_after_shrinking() {
+    let heights = [10.; 8];
+    assert_eq!(window(&heights, 900., 50.), (1..8, 10., 0.));
+    // 内容不满一屏时从头显示全部
+    let heights = [10.; 3];
+    assert_eq!(window(&heights, 900., 50.), (0..3, 0., 0.));
+    assert_eq!(window(&heights, -20., 50.), (0..3, 0., 0.));
+  }
+
+  #[test]
+  fn empty_and_unknown_viewport() {
+    assert_eq!(window(&[], 100., 50.), (0..0, 0., 0.));
+    // 还没收到滚动事件时构建所有行
+    assert_eq!(window(&[10.; 20], 0., 0.), (0..20, 0., 0.));
+  }
+
+  // 高度为0的行（如已经不在列表中的模组）不影响定位
+  #[test]
+  fn zero_height_rows() {
+    let heights = [10., 0., 0., 10., 10., 10.];
+    let window = visible_window(&heights, 10., 10., 0);
+    assert_eq!(window.range, 3..4);
+    assert_eq!(window.before, 10.);
+    assert_eq!(window.after, 20.);
+  }
+}
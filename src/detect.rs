@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+
+use crate::mod_manager::ModManager;
+
+// 候选目录的来源，排在前面的更可信
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+  Registry,
+  Executable,
+  Common,
+}
+
+// 找出本机上可能的游戏安装目录，只保留能被ModManager识别的
+pub async fn detect_game_dirs() -> Vec<PathBuf> {
+  let found = registry_dirs()
+    .into_iter()
+    .map(|path| (Source::Registry, path))
+    .chain(
+      executable_dirs()
+        .into_iter()
+        .map(|path| (Source::Executable, path)),
+    )
+    .chain(
+      common_dirs().into_iter().map(|path| (Source::Common, path)),
+    );
+  game_dirs(found).await
+}
+
+// 排序去重后去掉不是游戏目录的候选
+pub async fn game_dirs(
+  found: impl IntoIterator<Item = (Source, PathBuf)>,
+) -> Vec<PathBuf> {
+  let mut dirs = Vec::new();
+  for path in rank(found) {
+    if is_game_dir(&path).await {
+      dirs.push(path);
+    }
+  }
+  dirs
+}
+
+pub async fn is_game_dir(path: &Path) -> bool {
+  ModManager::try_from_game_dir(path).await.is_ok()
+}
+
+// 按来源排序并去重，同一目录只保留最可信的那次
+pub fn rank(
+  found: impl IntoIterator<Item = (Source, PathBuf)>,
+) -> Vec<PathBuf> {
+  let mut found = found.into_iter().collect::<Vec<_>>();
+  found.sort_by_key(|(source, _)| *source);
+  let mut ranked: Vec<PathBuf> = Vec::new();
+  for (_, path) in found {
+    if !ranked.contains(&path) {
+      ranked.push(path);
+    }
+  }
+  ranked
+}
+
+// 程序可能被放在游戏目录或其子目录中
+fn executable_dirs() -> Vec<PathBuf> {
+  std::env::current_exe()
+    .ok()
+    .map(|exe| {
+      exe
+        .ancestors()
+        .skip(1)
+        .take(3)
+        .map(Path::to_path_buf)
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(windows)]
+fn common_dirs() -> Vec<PathBuf> {
+  ["C", "D", "E"]
+    .into_iter()
+    .flat_map(|drive| {
+      ["Games\\Korabli", "Games\\Mir Korabley", "Lesta\\Korabli"]
+        .into_iter()
+        .map(move |dir| PathBuf::from(format!("{}:\\{}", drive, dir)))
+    })
+    .collect()
+}
+
+#[cfg(not(windows))]
+fn common_dirs() -> Vec<PathBuf> {
+  Vec::new()
+}
+
+// 莱服游戏中心安装的游戏会在卸载列表中登记安装位置
+#[cfg(windows)]
+fn registry_dirs() -> Vec<PathBuf> {
+  use winreg::{
+    RegKey,
+    enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE},
+  };
+
+  const UNINSTALL_KEYS: [&str; 2] = [
+    "Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+    "Software\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+  ];
+  const NAMES: [&str; 3] =
+    ["korabli", "mir korabley", "мир кораблей"];
+
+  [HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE]
+    .into_iter()
+    .map(RegKey::predef)
+    .flat_map(|root| {
+      UNINSTALL_KEYS
+        .iter()
+        .filter_map(|key| root.open_subkey(key).ok())
+        .collect::<Vec<_>>()
+    })
+    .flat_map(|uninstall| {
+      uninstall
+        .enum_keys()
+        .filter_map(Result::ok)
+        .filter_map(|name| uninstall.open_subkey(name).ok())
+        .collect::<Vec<_>>()
+    })
+    .filter(|app| {
+      app.get_value::<String, _>("DisplayName").is_ok_and(|name| {
+        let name = name.to_lowercase();
+        NAMES.iter().any(|candidate| name.contains(candidate))
+      })
+    })
+    .filter_map(|app| {
+      app.get_value::<String, _>("InstallLocation").ok()
+    })
+    .filter(|location| !location.is_empty())
+    .map(PathBuf::from)
+    .collect()
+}
+
+#[cfg(not(windows))]
+fn registry_dirs() -> Vec<PathBuf> {
+  Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "kmm-detect-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn ranked_by_source_then_order() {
+    let ranked = rank([
+      (Source::Common, PathBuf::from("common")),
+      (Source::Executable, PathBuf::from("exe-a")),
+      (Source::Registry, PathBuf::from("registry")),
+      (Source::Executable, PathBuf::from("exe-b")),
+    ]);
+    assert_eq!(
+      ranked,
+      ["registry", "exe-a", "exe-b", "common"].map(PathBuf::from)
+    );
+  }
+
+  // 同一目录出现多次时排在最可信的来源的位置
+  #[test]
+  fn duplicates_keep_best_source() {
+    let ranked = rank([
+      (Source::Common, PathBuf::from("game")),
+      (Source::Executable, PathBuf::from("other")),
+      (Source::Registry, PathBuf::from("game")),
+    ]);
+    assert_eq!(ranked, ["game", "other"].map(PathBuf::from));
+    assert!(rank([]).is_empty());
+  }
+
+  #[tokio::test]
+  async fn invalid_candidates_are_dropped() {
+    let root = temp_dir("validate");
+    let game = root.join("game");
+    std::fs::create_dir_all(game.join("bin/1000/res_mods")).unwrap();
+    // 没有构建目录和根本不存在的目录
+    let empty = root.join("empty");
+    std::fs::create_dir_all(empty.join("bin/backup")).unwrap();
+    let missing = root.join("missing");
+    let dirs = game_dirs([
+      (Source::Registry, missing),
+      (Source::Executable, empty),
+      (Source::Common, game.to_owned()),
+      (Source::Executable, game.to_owned()),
+    ])
+    .await;
+    assert_eq!(dirs, [game]);
+    let _ = std::fs::remove_dir_all(&root);
+  }
+}
@@ -1,5 +1,213 @@
-#[derive(Debug, thiserror::Error)]
+use std::{fmt, sync::Arc};
+
+use crate::{
+  data::{registry, settings},
+  format::format_bytes,
+  i18n::t,
+  mod_manager,
+  tasks::{download, install},
+};
+
+// 各模块的错误在这里统一，显示给用户的文字只在这里生成；
+// 不能Clone的错误用Arc包起来，方便在界面消息中传递
+#[derive(Debug, Clone)]
 pub enum Error {
-  #[error("Reqwest: {0}")]
-  Reqwest(#[from] reqwest::Error),
+  Network(Arc<reqwest::Error>),
+  Io(Arc<std::io::Error>),
+  Url(url::ParseError),
+  Settings(Arc<settings::Error>),
+  Registry(Arc<registry::Error>),
+  RegistryLoad(Arc<registry::LoadError>),
+  ModManager(Arc<mod_manager::Error>),
+  Download(download::Error),
+  Install(install::Error),
+  NotFound(String),
+  NotInstalled(String),
+  // 出错的操作和对象，显示时放在最前面
+  Context {
+    operation: Operation,
+    id: String,
+    inner: Box<Error>,
+  },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+  Install,
+  Uninstall,
+  Update,
+}
+
+impl fmt::Display for Operation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::Install => t!("operation.install"),
+      Self::Uninstall => t!("operation.uninstall"),
+      Self::Update => t!("operation.update"),
+    })
+  }
+}
+
+impl Error {
+  pub fn context(self, operation: Operation, id: &str) -> Self {
+    Self::Context {
+      operation,
+      id: id.to_string(),
+      inner: Box::new(self),
+    }
+  }
+
+  // 去掉外层的上下文，得到实际出错的位置
+  pub fn root(&self) -> &Self {
+    match self {
+      Self::Context { inner, .. } => inner.root(),
+      err => err,
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let text = match self {
+      Self::Network(err) => t!("error.network", error = err),
+      Self::Io(err) => t!("error.io", error = err),
+      Self::Url(err) => t!("error.url", error = err),
+      Self::Settings(err) => t!("error.settings", error = err),
+      Self::Registry(err) => t!("error.registry", error = err),
+      Self::RegistryLoad(err) => load_error_text(err),
+      Self::ModManager(err) => t!("error.mod_manager", error = err),
+      Self::Download(err) => t!("error.download", error = err),
+      Self::Install(install::Error::InvalidArchive { size }) => {
+        t!("error.invalid_archive", size = format_bytes(*size))
+      }
+      Self::Install(err) => t!("error.install", error = err),
+      Self::NotFound(id) => t!("error.not_found", id = id),
+      Self::NotInstalled(id) => t!("error.not_installed", id = id),
+      Self::Context {
+        operation,
+        id,
+        inner,
+      } => t!(
+        "error.context",
+        operation = operation,
+        id = id,
+        error = inner
+      ),
+    };
+    f.write_str(&text)
+  }
+}
+
+// registry的加载错误直接显示原因，和加载状态中的一致
+fn load_error_text(err: &registry::LoadError) -> String {
+  use registry::LoadError;
+  match err {
+    LoadError::Network(_) => t!("registry.network_error").to_string(),
+    LoadError::BadFormat(_) => t!("registry.bad_format").to_string(),
+    LoadError::BadDelta => t!("registry.bad_delta").to_string(),
+    LoadError::BadFile(err) => t!("registry.bad_file", error = err),
+    LoadError::BadHex(_) => t!("registry.bad_hex").to_string(),
+    LoadError::BadHexContent(err) => {
+      t!("registry.bad_hex_content", error = err)
+    }
+    LoadError::BadBase64(_) => t!("registry.bad_base64").to_string(),
+    LoadError::UnsupportedData(ty) => {
+      t!("registry.unsupported_data", ty = ty)
+    }
+    LoadError::UnsupportedScheme(scheme) => {
+      t!("registry.unsupported_scheme", scheme = scheme)
+    }
+  }
+}
+
+// 上下文中的错误已经写进了显示的文字，不再作为source以免日志中重复
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Self::Network(err) => Some(err.as_ref()),
+      Self::Io(err) => Some(err.as_ref()),
+      Self::Url(err) => Some(err),
+      Self::Settings(err) => Some(err.as_ref()),
+      Self::Registry(err) => Some(err.as_ref()),
+      Self::RegistryLoad(err) => Some(err.as_ref()),
+      Self::ModManager(err) => Some(err.as_ref()),
+      Self::Download(err) => Some(err),
+      Self::Install(err) => Some(err),
+      Self::NotFound(_)
+      | Self::NotInstalled(_)
+      | Self::Context { .. } => None,
+    }
+  }
+}
+
+impl From<url::ParseError> for Error {
+  fn from(err: url::ParseError) -> Self {
+    Self::Url(err)
+  }
+}
+
+impl From<download::Error> for Error {
+  fn from(err: download::Error) -> Self {
+    Self::Download(err)
+  }
+}
+
+impl From<install::Error> for Error {
+  fn from(err: install::Error) -> Self {
+    Self::Install(err)
+  }
+}
+
+impl From<reqwest::Error> for Error {
+  fn from(err: reqwest::Error) -> Self {
+    Self::Network(Arc::new(err))
+  }
+}
+
+impl From<std::io::Error> for Error {
+  fn from(err: std::io::Error) -> Self {
+    Self::Io(Arc::new(err))
+  }
+}
+
+impl From<settings::Error> for Error {
+  fn from(err: settings::Error) -> Self {
+    Self::Settings(Arc::new(err))
+  }
+}
+
+impl From<registry::Error> for Error {
+  fn from(err: registry::Error) -> Self {
+    Self::Registry(Arc::new(err))
+  }
+}
+
+impl From<registry::LoadError> for Error {
+  fn from(err: registry::LoadError) -> Self {
+    Self::RegistryLoad(Arc::new(err))
+  }
+}
+
+impl From<mod_manager::Error> for Error {
+  fn from(err: mod_manager::Error) -> Self {
+    Self::ModManager(Arc::new(err))
+  }
+}
+
+pub trait ResultExt<T> {
+  fn context(
+    self,
+    operation: Operation,
+    id: &str,
+  ) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for Result<T, E> {
+  fn context(
+    self,
+    operation: Operation,
+    id: &str,
+  ) -> Result<T, Error> {
+    self.map_err(|err| err.into().context(operation, id))
+  }
 }
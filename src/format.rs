@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use crate::i18n::Locale;
+
+pub fn format_bytes(bytes: u64) -> String {
+  const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+  let mut value = bytes as f64;
+  let mut unit = 0;
+  while value >= 1024. && unit < UNITS.len() - 1 {
+    value /= 1024.;
+    unit += 1;
+  }
+  if unit == 0 {
+    format!("{} {}", bytes, UNITS[unit])
+  } else {
+    format!("{:.1} {}", value, UNITS[unit])
+  }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+  let secs = duration.as_secs();
+  if secs >= 3600 {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+  } else {
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+  }
+}
+
+// 超过`max_chars`个字符时截断并加上省略号
+pub fn ellipsize(text: &str, max_chars: usize) -> String {
+  match text.char_indices().nth(max_chars) {
+    Some((end, _)) => format!("{}…", &text[..end]),
+    None => text.to_string(),
+  }
+}
+
+// 安装记录中的unix时间戳，按本地时区显示；0和超出范围的值视为未知
+pub fn format_timestamp(secs: u64, locale: Locale) -> Option<String> {
+  if secs == 0 {
+    return None;
+  }
+  let time = DateTime::from_timestamp(i64::try_from(secs).ok()?, 0)?
+    .with_timezone(&Local);
+  let pattern = match locale {
+    Locale::Ru => "%d.%m.%Y %H:%M",
+    Locale::ZhCn | Locale::En => "%Y-%m-%d %H:%M",
+  };
+  Some(time.format(pattern).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bytes_unit_boundaries() {
+    let cases = [
+      (0, "0 B"),
+      (1, "1 B"),
+      (1023, "1023 B"),
+      (1024, "1.0 KB"),
+      (1536, "1.5 KB"),
+      (1024 * 1024 - 1, "1024.0 KB"),
+      (1024 * 1024, "1.0 MB"),
+      (5 * 1024 * 1024 * 1024, "5.0 GB"),
+      (1024u64.pow(4), "1.0 TB"),
+      // 最大的单位是TB
+      (1024u64.pow(5), "1024.0 TB"),
+    ];
+    for (bytes, text) in cases {
+      assert_eq!(format_bytes(bytes), text, "{}", bytes);
+    }
+  }
+
+  #[test]
+  fn duration_switches_to_hours() {
+    let cases = [
+      (0, "00:00"),
+      (59, "00:59"),
+      (61, "01:01"),
+      (3599, "59:59"),
+      (3600, "1:00:00"),
+      (3661, "1:01:01"),
+      (100 * 3600 + 5, "100:00:05"),
+    ];
+    for (secs, text) in cases {
+      assert_eq!(
+        format_duration(Duration::from_secs(secs)),
+        text,
+        "{}",
+        secs
+      );
+    }
+    // 不满一秒的部分舍去
+    assert_eq!(format_duration(Duration::from_millis(1999)), "00:01");
+  }
+
+  #[test]
+  fn ellipsize_counts_chars() {
+    assert_eq!(ellipsize("abc", 3), "abc");
+    assert_eq!(ellipsize("abcd", 3), "abc…");
+    assert_eq!(ellipsize("中文模组名称", 2), "中文…");
+    assert_eq!(ellipsize("中文", 2), "中文");
+    assert_eq!(ellipsize("", 0), "");
+  }
+
+  #[test]
+  fn timestamp_by_locale() {
+    assert_eq!(format_timestamp(0, Locale::En), None);
+    assert_eq!(format_timestamp(u64::MAX, Locale::En), None);
+    // 具体的时间取决于本地时区，只检查格式
+    let secs = 1_700_000_000;
+    let iso = format_timestamp(secs, Locale::ZhCn).unwrap();
+    assert_eq!(format_timestamp(secs, Locale::En).unwrap(), iso);
+    assert_eq!(iso.len(), "2023-11-14 22:13".len());
+    assert!(iso.starts_with("2023-11-1"), "{}", iso);
+    let ru = format_timestamp(secs, Locale::Ru).unwrap();
+    let (date, time) = ru.split_once(' ').unwrap();
+    assert_eq!(date.split('.').collect::<Vec<_>>().len(), 3);
+    assert!(date.ends_with(".11.2023"), "{}", ru);
+    assert!(iso.ends_with(time), "{} {}", iso, ru);
+  }
+}
@@ -0,0 +1,241 @@
+use std::{sync::OnceLock, time::Duration};
+
+use reqwest::{Client, ClientBuilder, Proxy, redirect};
+
+const USER_AGENT: &str =
+  concat!("korabli-mod-manager/", env!("CARGO_PKG_VERSION"));
+
+// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpOptions {
+  pub connect_timeout: Duration,
+  // 重定向时是否允许从HTTPS降级到HTTP
+  pub allow_insecure_redirects: bool,
+  // 没有时使用系统代理
+  pub proxy: Option<String>,
+  // 没有时使用`korabli-mod-manager/<版本>`
+  pub user_agent: Option<String>,
+}
+
+impl Default for HttpOptions {
+  fn default() -> Self {
+    Self {
+      connect_timeout: Duration::from_secs(15),
+      allow_insecure_redirects: false,
+      proxy: None,
+      user_agent: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum RedirectError {
+  #[error("TooManyRedirects: {url}")]
+  TooManyRedirects { url: url::Url },
+  #[error("InsecureRedirect: {url}")]
+  InsecureRedirect { url: url::Url },
+}
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+// 所有网络请求共用一个客户端，复用连接池和TLS上下文。
+// 没有调用过init_client时按默认选项创建
+pub fn client() -> &'static Client {
+  init_client(&HttpOptions::default())
+}
+
+// 启动时按设置创建共用的客户端。已经创建过时直接返回，
+// 所以网络设置改动后要重启才生效
+pub fn init_client(options: &HttpOptions) -> &'static Client {
+  CLIENT.get_or_init(|| {
+    client_builder(options).build().unwrap_or_else(|err| {
+      tracing::warn!(%err, "failed to build http client");
+      Client::new()
+    })
+  })
+}
+
+// 共用客户端的全部配置，测试中可以在此基础上再做调整
+pub fn client_builder(options: &HttpOptions) -> ClientBuilder {
+  let allow_insecure_redirects = options.allow_insecure_redirects;
+  let builder = Client::builder()
+    .user_agent(options.user_agent.as_deref().unwrap_or(USER_AGENT))
+    .connect_timeout(options.connect_timeout)
+    .redirect(redirect::Policy::custom(move |attempt| {
+      if attempt.previous().len() > MAX_REDIRECTS {
+        let url = attempt.url().to_owned();
+        return attempt
+          .error(RedirectError::TooManyRedirects { url });
+      }
+      let downgrade = attempt
+        .previous()
+        .last()
+        .is_some_and(|previous| previous.scheme() == "https")
+        && attempt.url().scheme() == "http";
+      if downgrade && !allow_insecure_redirects {
+        let url = attempt.url().to_owned();
+        return attempt
+          .error(RedirectError::InsecureRedirect { url });
+      }
+      attempt.follow()
+    }));
+  match options.proxy.as_deref().map(Proxy::all) {
+    Some(Ok(proxy)) => builder.proxy(proxy),
+    Some(Err(err)) => {
+      tracing::warn!(%err, "invalid proxy");
+      builder
+    }
+    None => builder,
+  }
+}
+
+// 优先使用RFC 6266的`filename*=`，其次是普通的`filename=`
+pub fn content_disposition_file_name(value: &str) -> Option<String> {
+  let params = value
+    .split(';')
+    .filter_map(|param| param.split_once('='))
+    .map(|(key, value)| {
+      (key.trim().to_ascii_lowercase(), value.trim())
+    })
+    .collect::<Vec<_>>();
+  let extended = params
+    .iter()
+    .find(|(key, _)| key == "filename*")
+    .and_then(|(_, value)| {
+      let (charset, rest) = value.split_once('\'')?;
+      let (_, encoded) = rest.split_once('\'')?;
+      charset
+        .eq_ignore_ascii_case("utf-8")
+        .then(|| percent_decode(encoded.as_bytes()))
+        .flatten()
+    });
+  extended
+    .or_else(|| {
+      params.iter().find(|(key, _)| key == "filename").map(
+        |(_, value)| {
+          value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value)
+            .replace("\\\"", "\"")
+        },
+      )
+    })
+    .filter(|file_name| !file_name.is_empty())
+}
+
+pub fn percent_decode(bytes: &[u8]) -> Option<String> {
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut iter = bytes.iter();
+  while let Some(&byte) = iter.next() {
+    if byte == b'%' {
+      let hex = [*iter.next()?, *iter.next()?];
+      decoded.push(
+        u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16)
+          .ok()?,
+      );
+    } else {
+      decoded.push(byte);
+    }
+  }
+  String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 下载和registry都通过client()取得同一个实例
+  #[test]
+  fn client_is_shared() {
+    let first = client();
+    assert!(std::ptr::eq(first, client()));
+    // 已经创建后不再按新的选项重建
+    let options = HttpOptions {
+      connect_timeout: Duration::from_secs(1),
+      ..HttpOptions::default()
+    };
+    assert!(std::ptr::eq(first, init_client(&options)));
+  }
+
+  #[test]
+  fn proxy_from_options() {
+    let options = HttpOptions {
+      proxy: Some("http://127.0.0.1:7890".to_string()),
+      user_agent: Some("custom".to_string()),
+      ..HttpOptions::default()
+    };
+    assert!(client_builder(&options).build().is_ok());
+    // 写错的代理地址被忽略
+    let options = HttpOptions {
+      proxy: Some("not a url".to_string()),
+      ..HttpOptions::default()
+    };
+    assert!(client_builder(&options).build().is_ok());
+  }
+
+  #[test]
+  fn file_name_forms() {
+    let cases = [
+      ("attachment; filename=\"mod.zip\"", Some("mod.zip")),
+      ("attachment; filename=mod-1.0.zip", Some("mod-1.0.zip")),
+      ("attachment;filename = \"a b.zip\" ", Some("a b.zip")),
+      ("attachment; FILENAME=\"upper.zip\"", Some("upper.zip")),
+      (
+        "attachment; filename=\"say \\\"hi\\\".zip\"",
+        Some("say \"hi\".zip"),
+      ),
+      (
+        "attachment; filename*=UTF-8''%E4%B8%AD%E6%96%87.zip",
+        Some("中文.zip"),
+      ),
+      ("attachment; filename*=utf-8'en'a%20b.zip", Some("a b.zip")),
+      // 两种都有时取filename*，顺序无关
+      (
+        "attachment; filename*=UTF-8''%E6%A8%A1.zip; filename=\"fallback.zip\"",
+        Some("模.zip"),
+      ),
+      (
+        "attachment; filename=\"fallback.zip\"; filename*=UTF-8''new.zip",
+        Some("new.zip"),
+      ),
+      // 不支持的字符集和坏掉的编码退回filename
+      (
+        "attachment; filename*=ISO-8859-1''caf%E9.zip; filename=cafe.zip",
+        Some("cafe.zip"),
+      ),
+      (
+        "attachment; filename*=UTF-8''bad%ZZ.zip; filename=ok.zip",
+        Some("ok.zip"),
+      ),
+      ("attachment; filename*=UTF-8''%FF.zip", None),
+      ("attachment; filename=\"\"", None),
+      ("attachment", None),
+      ("inline; name=\"mod.zip\"", None),
+    ];
+    for (value, file_name) in cases {
+      assert_eq!(
+        content_disposition_file_name(value).as_deref(),
+        file_name,
+        "{}",
+        value
+      );
+    }
+  }
+
+  #[test]
+  fn percent_decoding() {
+    assert_eq!(percent_decode(b"plain").as_deref(), Some("plain"));
+    assert_eq!(
+      percent_decode(b"a%20b%2Fc").as_deref(),
+      Some("a b/c")
+    );
+    assert_eq!(percent_decode(b"%e4%b8%ad").as_deref(), Some("中"));
+    // 不完整、非十六进制和解码后不是UTF-8的都视为无效
+    for invalid in [&b"%2"[..], b"%", b"%G0", b"%FF%FE"] {
+      assert_eq!(percent_decode(invalid), None, "{:?}", invalid);
+    }
+  }
+}
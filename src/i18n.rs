@@ -0,0 +1,229 @@
+use std::{
+  fmt,
+  sync::atomic::{AtomicU8, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+mod en;
+mod ru;
+mod zh_cn;
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Locale {
+  #[default]
+  ZhCn,
+  En,
+  Ru,
+}
+
+impl Locale {
+  pub const ALL: [Locale; 3] = [Self::ZhCn, Self::En, Self::Ru];
+
+  // 按系统语言选择，不认识的语言使用中文
+  pub fn detect() -> Self {
+    let locale = sys_locale::get_locale()
+      .unwrap_or_default()
+      .to_ascii_lowercase();
+    if locale.starts_with("en") {
+      Self::En
+    } else if locale.starts_with("ru") {
+      Self::Ru
+    } else {
+      Self::ZhCn
+    }
+  }
+
+  pub fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+    match self {
+      Self::ZhCn => zh_cn::CATALOG,
+      Self::En => en::CATALOG,
+      Self::Ru => ru::CATALOG,
+    }
+  }
+}
+
+impl fmt::Display for Locale {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::ZhCn => "简体中文",
+      Self::En => "English",
+      Self::Ru => "Русский",
+    })
+  }
+}
+
+static LOCALE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_locale(locale: Locale) {
+  LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+  Locale::ALL
+    .get(LOCALE.load(Ordering::Relaxed) as usize)
+    .copied()
+    .unwrap_or_default()
+}
+
+fn lookup(
+  catalog: &'static [(&'static str, &'static str)],
+  key: &str,
+) -> Option<&'static str> {
+  catalog
+    .iter()
+    .find(|(entry, _)| *entry == key)
+    .map(|(_, text)| *text)
+}
+
+// 当前语言缺少的条目退回中文，中文也没有时直接显示key
+pub fn tr(key: &'static str) -> &'static str {
+  lookup(locale().catalog(), key)
+    .or_else(|| lookup(zh_cn::CATALOG, key))
+    .unwrap_or(key)
+}
+
+// 把模板中的`{name}`替换为对应的参数
+pub fn fill(template: &str, args: &[(&str, String)]) -> String {
+  args
+    .iter()
+    .fold(template.to_string(), |text, (name, value)| {
+      text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+#[macro_export]
+macro_rules! t {
+  ($key:literal) => {
+    $crate::i18n::tr($key)
+  };
+  ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+    $crate::i18n::fill(
+      $crate::i18n::tr($key),
+      &[$((stringify!($name), $value.to_string())),+],
+    )
+  };
+}
+
+pub use crate::t;
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::BTreeSet, path::Path};
+
+  use super::*;
+
+  // 模板中的`{name}`
+  fn placeholders(text: &str) -> BTreeSet<&str> {
+    text
+      .split('{')
+      .skip(1)
+      .filter_map(|rest| rest.split_once('}'))
+      .map(|(name, _)| name)
+      .collect()
+  }
+
+  // 源码中`t!`的第一个参数，跳过`format!`等以t结尾的宏
+  fn used_keys(source: &str, keys: &mut BTreeSet<String>) {
+    let mut rest = source;
+    while let Some(index) = rest.find("t!(") {
+      let boundary = !rest[..index]
+        .ends_with(|c: char| c.is_alphanumeric() || c == '_');
+      rest = &rest[index + 3..];
+      let key = rest
+        .trim_start()
+        .strip_prefix('"')
+        .and_then(|key| key.split_once('"'))
+        .map(|(key, _)| key)
+        .filter(|key| {
+          key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._".contains(c))
+        });
+      if let (true, Some(key)) = (boundary, key) {
+        keys.insert(key.to_string());
+      }
+    }
+  }
+
+  fn collect_keys(dir: &Path, keys: &mut BTreeSet<String>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+      let path = entry.unwrap().path();
+      if path.is_dir() {
+        collect_keys(&path, keys);
+      } else if path.extension().is_some_and(|ext| ext == "rs") {
+        used_keys(&std::fs::read_to_string(&path).unwrap(), keys);
+      }
+    }
+  }
+
+  // 各语言的条目一一对应，参数也相同
+  #[test]
+  fn catalogs_match() {
+    for locale in Locale::ALL {
+      let catalog = locale.catalog();
+      let keys = catalog.iter().map(|(key, _)| *key);
+      assert_eq!(
+        keys.to_owned().collect::<BTreeSet<_>>().len(),
+        catalog.len(),
+        "duplicate keys in {}",
+        locale
+      );
+      for (key, text) in zh_cn::CATALOG {
+        let translated = lookup(catalog, key);
+        assert!(
+          translated.is_some(),
+          "{} is missing {}",
+          locale,
+          key
+        );
+        assert_eq!(
+          placeholders(translated.unwrap()),
+          placeholders(text),
+          "{} {}",
+          locale,
+          key
+        );
+      }
+      for key in keys {
+        assert!(
+          lookup(zh_cn::CATALOG, key).is_some(),
+          "{} has unknown {}",
+          locale,
+          key
+        );
+      }
+    }
+  }
+
+  // 界面、命令行和任务日志中用到的key都要有翻译
+  #[test]
+  fn used_keys_are_translated() {
+    let mut keys = BTreeSet::new();
+    collect_keys(
+      &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+      &mut keys,
+    );
+    assert!(keys.contains("task_log.retrying"));
+    let missing = keys
+      .iter()
+      .filter(|key| lookup(zh_cn::CATALOG, key).is_none())
+      .collect::<Vec<_>>();
+    assert!(missing.is_empty(), "{:?}", missing);
+  }
+
+  #[test]
+  fn fills_placeholders() {
+    assert_eq!(
+      fill(
+        "{a} and {b}, {a}",
+        &[("a", "1".into()), ("b", "2".into())]
+      ),
+      "1 and 2, 1"
+    );
+    assert_eq!(placeholders("x {a} {b}"), BTreeSet::from(["a", "b"]));
+  }
+}
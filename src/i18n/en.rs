@@ -0,0 +1,470 @@
+pub const CATALOG: &[(&str, &str)] = &[
+  ("app.title", "Mir Korabley Mod Manager"),
+  ("language", "Language"),
+  ("error.reason", "Reason: {error}"),
+  ("settings.load_failed", "Failed to read settings"),
+  (
+    "settings.load_failed_text",
+    "Default settings are used\nReason: {error}",
+  ),
+  (
+    "settings.invalid_registry_urls",
+    "Invalid registry URLs in settings",
+  ),
+  ("game_dir.label", "Game directory"),
+  ("game_dir.saved", "Switch game"),
+  ("game_dir.browse", "Browse…"),
+  ("game_dir.apply", "Apply"),
+  ("game_dir.detected", "Detected game directories"),
+  ("game_dir.pick", "Select the game directory"),
+  (
+    "game_dir.unmanageable",
+    "Cannot manage this directory: {error}",
+  ),
+  (
+    "game_dir.busy",
+    "Tasks are running, switch directories after they finish",
+  ),
+  ("game_dir.not_found", "Directory does not exist"),
+  ("filter.label", "Filter:"),
+  ("filter.all", "All"),
+  ("filter.installed", "Installed"),
+  ("filter.updatable", "Updatable"),
+  ("filter.not_installed", "Not installed"),
+  ("filter.search", "Search id, name or note"),
+  ("plan.title", "Update plan"),
+  ("plan.installs", "Install"),
+  ("plan.upgrades", "Upgrade"),
+  ("plan.uninstalls", "Uninstall"),
+  ("plan.up_to_date", "Already up to date"),
+  ("plan.download_size", "To download: {size}"),
+  ("plan.cancel", "Cancel"),
+  ("plan.confirm", "Confirm"),
+  ("plan.no_space_title", "Not enough disk space"),
+  (
+    "plan.no_space",
+    "Requires {required} plus a safety margin, {available} available",
+  ),
+  ("settings.title", "Settings"),
+  ("settings.appearance", "Appearance"),
+  ("settings.theme", "Theme"),
+  ("settings.system_language", "System default"),
+  ("settings.registries", "Registries"),
+  ("settings.add_registry", "Add"),
+  ("settings.remove", "Remove"),
+  ("settings.games", "Game installations"),
+  ("settings.game_name", "Name"),
+  ("settings.add_game", "Add current directory"),
+  ("settings.downloads", "Downloads"),
+  ("settings.idle_timeout", "Stalled download timeout, seconds"),
+  ("settings.download_retries", "Retries after network errors"),
+  ("settings.connect_timeout", "Connection timeout, seconds"),
+  (
+    "settings.allow_insecure_redirects",
+    "Allow redirects from HTTPS to HTTP",
+  ),
+  (
+    "settings.network_restart_hint",
+    "Connection settings take effect after a restart",
+  ),
+  ("settings.startup", "Startup"),
+  ("settings.auto_update", "Update installed mods on startup"),
+  ("settings.auto_update_ask", "Ask before updating"),
+  (
+    "settings.allow_external_res_mods",
+    "Allow res_mods to link outside the game directory",
+  ),
+  ("settings.uninstall", "Uninstall"),
+  (
+    "settings.safe_delete",
+    "Safe delete: move uninstalled files to the recycle bin",
+  ),
+  (
+    "settings.trash_retention_days",
+    "Days to keep in recycle bin",
+  ),
+  ("settings.apply", "Apply"),
+  ("settings.invalid_url", "Invalid URL: {reason}"),
+  ("note.label", "Note: {note}"),
+  ("note.add", "Add note"),
+  ("note.edit", "Edit note"),
+  ("note.save", "Save"),
+  (
+    "note.placeholder",
+    "Why it's installed, what settings changed…",
+  ),
+  ("note.save_failed", "Failed to save note"),
+  ("summary.title", "Update finished"),
+  ("summary.details", "Downloaded {size} in {elapsed}"),
+  ("summary.copy_failures", "Copy failure list"),
+  ("summary.failures_copied", "{count} failed mods"),
+  ("summary.close", "Close"),
+  ("crash.title", "The app quit unexpectedly last time"),
+  ("crash.text", "A crash report was saved to {path}"),
+  ("crash.open", "Open report"),
+  ("crash.ignore", "Ignore"),
+  ("cli.fatal", "Error: {error}"),
+  (
+    "cli.registry_failed",
+    "Failed to load registry {url}: {error}",
+  ),
+  ("cli.downloading", "Downloading {id} {version}"),
+  ("cli.installed", "Installed {id} {version}"),
+  ("cli.uninstalled", "Uninstalled {id}"),
+  ("cli.up_to_date", "All mods are up to date"),
+  (
+    "cli.settings_invalid",
+    "Failed to read settings, using defaults: {error}",
+  ),
+  (
+    "cli.invalid_registry",
+    "Skipping invalid registry URL in settings: {url}",
+  ),
+  ("error.network", "Network error: {error}"),
+  ("error.io", "File access failed: {error}"),
+  ("error.url", "Invalid URL: {error}"),
+  (
+    "error.settings",
+    "Failed to read or write settings: {error}",
+  ),
+  ("error.registry", "Registry error: {error}"),
+  ("error.mod_manager", "Failed to change game files: {error}"),
+  ("error.download", "Download failed: {error}"),
+  ("error.install", "Install failed: {error}"),
+  (
+    "error.invalid_archive",
+    "Install failed: the downloaded file is not a valid archive (the download may be incomplete or the link broken), size: {size}",
+  ),
+  ("error.not_found", "Mod {id} was not found"),
+  ("error.not_installed", "Mod {id} is not installed"),
+  ("error.context", "{operation} {id}: {error}"),
+  ("operation.install", "Install"),
+  ("operation.uninstall", "Uninstall"),
+  ("operation.update", "Update"),
+  ("sort.label", "Sort"),
+  ("sort.name", "Name"),
+  ("sort.installed", "Installed"),
+  ("sort.update_available", "Updatable"),
+  ("sort.update_time", "Updated"),
+  ("select.updatable", "Select updatable"),
+  ("select.not_installed", "Select not installed"),
+  ("select.clear", "Clear selection"),
+  ("select.import", "Import selection"),
+  ("import.pick", "Select another installer's selection file"),
+  ("import.filter", "Selection files"),
+  ("import.read_failed", "Cannot read the selection file"),
+  ("import.empty", "No mods found in the file"),
+  (
+    "import.done",
+    "Selected {count} mods, review them before updating",
+  ),
+  ("import.unmatched", "Not found: {names}"),
+  ("import.blocked", "Cannot be installed: {ids}"),
+  ("table.status", "Status"),
+  ("table.icon", "Icon"),
+  ("table.id", "ID"),
+  ("table.name", "Name"),
+  ("table.version", "Version"),
+  ("table.progress", "Progress"),
+  ("table.actions", "Actions"),
+  ("mod.local_only", "Local only"),
+  ("mod.update_available", "Update available"),
+  ("mod.homepage", "Homepage"),
+  ("mod.downloaded", "Downloaded {size}"),
+  ("mod.paused", "Paused · downloaded {size}"),
+  ("mod.pause", "Pause"),
+  ("mod.resume", "Resume"),
+  ("mod.cancel_install", "Cancel install"),
+  ("mod.incompatible", "Incompatible"),
+  ("mod.force", "Force"),
+  ("mod.force_reinstall", "Force reinstall"),
+  ("mod.yanked", "Yanked"),
+  ("mod.replaced_by", " (use {id} instead)"),
+  (
+    "mod.installed_info",
+    "Installed {version}, {files} files, {date}",
+  ),
+  ("mod.date_unknown", "unknown date"),
+  ("mod.copy_id", "Copy ID"),
+  ("mod.copy_url", "Copy URL"),
+  ("mod.install_log", "Install log"),
+  (
+    "mod.no_install_log",
+    "No install log has been written for this mod yet",
+  ),
+  ("mod.archive_sha256", "SHA256: {hash}"),
+  ("mod.checksum_mismatch", "Doesn't match the registry"),
+  ("mod.copy_hash", "Copy SHA256"),
+  ("mod.preview", "Preview contents"),
+  ("mod.source", "Source: {source}"),
+  (
+    "mod.source_shadowing",
+    "Source: {source} (shadows {shadowed})",
+  ),
+  ("mod.keep", "Keep"),
+  ("mod.install", "Install/update"),
+  ("mod.uninstall", "Uninstall"),
+  ("error.subject_reason", "{subject}\nReason: {error}"),
+  ("row.queued", "Queued"),
+  ("row.downloading", "Downloading {percent}%"),
+  ("row.installing", "Installing"),
+  ("row.verifying", "Verifying"),
+  ("row.uninstalling", "Uninstalling"),
+  ("row.failed", "Failed: {error}"),
+  ("row.done", "Done"),
+  ("row.up_to_date", "Up to date"),
+  ("row.retry", "Retry"),
+  ("row.ignore", "Ignore"),
+  ("size.unknown", "unknown"),
+  ("size.partially_unknown", "{known} + unknown"),
+  ("download.speed_eta", "{speed} · {eta} left"),
+  ("download.failed", "Mod download failed"),
+  ("download.failed_text", "{id}\nDownload failed: {error}"),
+  ("download.invalid_url", "Invalid mod download URL"),
+  ("install.failed", "Mod installation failed!"),
+  ("uninstall.failed", "Mod uninstallation failed!"),
+  ("archive.unknown_format", "Unrecognized archive format"),
+  ("batch.finished", "{finished}/{total} done"),
+  ("toolbar.tasks", "Tasks ({count})"),
+  ("toolbar.install_local", "Install local file"),
+  ("toolbar.reload_registries", "Reload registries"),
+  ("toolbar.share", "Share installed mods"),
+  ("toolbar.health_check", "Check mod files"),
+  ("toolbar.trash", "Recycle bin"),
+  ("share.nothing", "No mods to share"),
+  ("share.failed", "Cannot create the share link"),
+  ("share.copied", "Copied a registry link with {count} mods"),
+  (
+    "share.skipped",
+    "Installed version not found in any registry, left out: {ids}",
+  ),
+  (
+    "share.too_long",
+    "The link is {size} long and may not send in full",
+  ),
+  ("toolbar.cache_archives", "Keep archives"),
+  ("toolbar.generate_registry", "Generate registry"),
+  ("toolbar.estimated_download", "Estimated download {size}"),
+  ("toolbar.update_all", "Update all ({count})"),
+  (
+    "toolbar.selected",
+    "Selected {install} to install, {uninstall} to uninstall",
+  ),
+  ("toolbar.update_mods", "Apply changes"),
+  ("toolbar.launch_game", "Launch game"),
+  ("launch.failed", "Failed to launch the game"),
+  ("task.panel", "Tasks"),
+  ("task.clear_finished", "Clear finished"),
+  ("task.details", "Details"),
+  ("task.download", "Download"),
+  ("task.install", "Install"),
+  ("task.verify", "Verify"),
+  ("task.uninstall", "Uninstall"),
+  ("task.cancelled", "Cancelled"),
+  ("task.log_failed", "Cannot open the task log"),
+  ("task_log.error", "Error: {error}"),
+  ("task_log.cause", "Caused by: {error}"),
+  ("task_log.start", "Downloading {url}"),
+  ("task_log.resume", "Resuming from byte {offset}: {url}"),
+  ("task_log.paused", "Paused after {bytes} bytes"),
+  (
+    "task_log.cache_verified",
+    "The cached file passed the checksum",
+  ),
+  (
+    "task_log.retrying",
+    "Download failed, retry {retry} in {delay}s: {error}",
+  ),
+  ("task_log.downloaded", "Downloaded: {path}"),
+  ("task_log.verifying", "Checking archive {path}"),
+  ("task_log.archive_type", "Archive format: {ty}"),
+  ("task_log.retry", "Retrying"),
+  (
+    "task_log.install_attempt",
+    "Install attempt {attempt}: {path}",
+  ),
+  ("task_log.files_written", "Wrote {count} files"),
+  ("task_log.installed", "Installed"),
+  ("task_log.uninstall_attempt", "Uninstall attempt {attempt}"),
+  ("task_log.not_installed", "No install record, skipped"),
+  ("task_log.uninstalled", "Uninstalled"),
+  ("task_status.queued", "Queued"),
+  ("task_status.running", "Running"),
+  ("task_status.paused", "Paused"),
+  ("task_status.failed", "Failed"),
+  ("task_status.finished", "Done"),
+  ("notification.history", "History"),
+  ("status.copied", "Copied: {text}"),
+  ("status.free_space", "Free space: {size}"),
+  ("status.launched", "Launched: {launcher}"),
+  ("status.dismiss", "Dismiss"),
+  ("status.queued", "Queued: {count}"),
+  ("status.downloading", "Downloading {id}… {percent}%"),
+  ("status.downloading_bytes", "Downloading {id}… {size}"),
+  ("status.installing", "Installing {id}…"),
+  ("status.verifying", "Verifying {id}…"),
+  ("status.installed", "Installed: {id} {version}"),
+  (
+    "status.up_to_date_skipped",
+    "Already up to date, skipped: {ids}",
+  ),
+  ("status.uninstalling", "Uninstalling {id}…"),
+  ("status.uninstalled", "Uninstalled: {id}"),
+  ("status.registry_loaded", "Registry loaded: {source}"),
+  ("status.auto_update", "Updating {count} mods automatically"),
+  ("status.health_checking", "Checking mod files…"),
+  ("status.health_checked", "Mod files checked"),
+  ("status.trash_emptied", "Recycle bin emptied, freed {size}"),
+  ("status.trash_restored", "Restored {id}"),
+  (
+    "status.batch_done",
+    "Done: {succeeded} succeeded, {failed} failed",
+  ),
+  ("open_url.failed", "Cannot open the link"),
+  (
+    "generate_registry.pick_dir",
+    "Select the directory with mod archives",
+  ),
+  ("generate_registry.done", "Registry generated"),
+  ("generate_registry.failed", "Failed to generate registry"),
+  ("pending.load_failed", "Cannot read unfinished tasks"),
+  ("pending.resume_title", "Resume unfinished tasks"),
+  (
+    "pending.resume_text",
+    "{count} install/uninstall tasks were unfinished at the last exit. Continue?",
+  ),
+  ("auto_update.title", "Mod updates"),
+  (
+    "auto_update.text",
+    "{count} mods can be updated. Update now?",
+  ),
+  ("registry.loading", "Loading registries… ({done}/{total})"),
+  ("registry.source_failed", "Failed to load {source}: {error}"),
+  ("registry.bad_file", "Cannot read registry file: {error}"),
+  (
+    "registry.invalid_homepages",
+    "Registry contains invalid homepages",
+  ),
+  (
+    "registry.bad_delta",
+    "The server returned a delta that cannot be applied",
+  ),
+  ("registry.bad_hex", "Malformed hex data"),
+  ("registry.bad_base64", "Malformed base64 data"),
+  (
+    "registry.bad_hex_content",
+    "Malformed hex data content: {error}",
+  ),
+  ("registry.unsupported_data", "Unsupported data type: {ty}"),
+  (
+    "registry.unsupported_scheme",
+    "Unsupported URL scheme: {scheme}",
+  ),
+  (
+    "registry.network_error",
+    "Network error while loading the registry",
+  ),
+  (
+    "registry.bad_format",
+    "The downloaded registry is malformed",
+  ),
+  ("mods.not_found", "Mods not found"),
+  (
+    "mods.not_found_text",
+    "These mods are not in any registry and were skipped: {ids}",
+  ),
+  ("local.pick", "Select a mod archive"),
+  ("local.filter", "Mod archives"),
+  (
+    "local.confirm_text",
+    "The file will be installed as id: {id} version: {version}. Continue?",
+  ),
+  ("local.unsupported", "Cannot install this file"),
+  (
+    "local.unsupported_text",
+    "{path} is not a supported mod archive",
+  ),
+  ("preview.title", "Archive contents of {id}"),
+  ("preview.loading", "Reading archive…"),
+  ("preview.unsupported", "Only zip archives can be previewed"),
+  ("preview.summary", "{files} files, {size} unpacked"),
+  (
+    "preview.conflicts",
+    "{count} files conflict with existing files",
+  ),
+  ("preview.suspicious", "{count} suspicious entries"),
+  ("preview.conflict", "Already exists"),
+  ("preview.conflict_mod", "Belongs to {id}"),
+  (
+    "preview.escapes",
+    "Original path is outside res_mods: {name}",
+  ),
+  ("preview.executable", "Executable"),
+  ("preview.flagged", "Contains flagged files"),
+  ("preview.install", "Install"),
+  ("preview.close", "Close"),
+  ("health.title", "Mod file check"),
+  (
+    "health.summary",
+    "{mods} install records, {broken} with problems",
+  ),
+  (
+    "health.healthy",
+    "All mod files are in place and there are no stray files",
+  ),
+  ("health.missing", "{count} files missing"),
+  ("health.all_missing", "All files are missing"),
+  ("health.repair", "Reinstall"),
+  ("health.forget", "Forget record"),
+  ("health.more_files", "{count} more files"),
+  ("health.orphans", "{count} files belong to no mod"),
+  (
+    "health.orphans_hint",
+    "Possibly mods added by hand. Adopt them to list them as one mod that can be uninstalled",
+  ),
+  ("health.backups", "{count} backup files"),
+  ("health.temp_files", "{count} temporary files"),
+  (
+    "health.leftovers_hint",
+    "Left behind by other tools. Adopt them and uninstall to clean up",
+  ),
+  ("health.adopt", "Adopt"),
+  ("health.failed", "Mod file check failed"),
+  ("health.adopt_failed", "Failed to adopt files"),
+  ("trash.title", "Recycle bin"),
+  (
+    "trash.hint",
+    "Files of mods uninstalled with safe delete. They are removed automatically after the retention period",
+  ),
+  ("trash.empty_hint", "The recycle bin is empty"),
+  ("trash.restore", "Restore"),
+  ("trash.empty", "Empty recycle bin ({size})"),
+  ("trash.empty_failed", "Failed to empty the recycle bin"),
+  ("trash.restore_failed", "Failed to restore mod files"),
+  (
+    "records.build_mismatch",
+    "Install records are from another game build",
+  ),
+  (
+    "records.build_mismatch_text",
+    "Install records were written for build {recorded}, the game now uses build {build}. Check the mod files, then keep the records for this build",
+  ),
+  ("records.stamp_build", "Keep records"),
+  ("records.stamp_failed", "Failed to update install records"),
+  ("local.read_failed", "Cannot read the mod archive"),
+  ("install_log.version", "Version: {version}"),
+  ("install_log.archive", "Archive: {path}"),
+  ("install_log.conflict", "Conflict: {path} ({owner})"),
+  ("install_log.untracked", "untracked file"),
+  ("install_log.directory", "Directory: {path}"),
+  ("install_log.write", "Written: {path}"),
+  ("install_log.remove_old", "Removed old file: {path}"),
+  ("install_log.trash_entry", "Trash: {path}"),
+  ("install_log.missing", "Already gone: {path}"),
+  ("install_log.trashed", "Moved to trash: {path}"),
+  ("install_log.removed", "Deleted: {path}"),
+  ("install_log.installed", "Succeeded, wrote {count} files"),
+  ("install_log.succeeded", "Succeeded"),
+  ("install_log.failed", "Failed: {error}"),
+  ("install_log.result", "Result: {result}"),
+];
@@ -0,0 +1,506 @@
+pub const CATALOG: &[(&str, &str)] = &[
+  ("app.title", "Менеджер модов Мира кораблей"),
+  ("language", "Язык"),
+  ("error.reason", "Причина: {error}"),
+  ("settings.load_failed", "Не удалось прочитать настройки"),
+  (
+    "settings.load_failed_text",
+    "Используются настройки по умолчанию\nПричина: {error}",
+  ),
+  (
+    "settings.invalid_registry_urls",
+    "Неверные адреса реестров в настройках",
+  ),
+  ("game_dir.label", "Папка игры"),
+  ("game_dir.saved", "Сменить игру"),
+  ("game_dir.browse", "Обзор…"),
+  ("game_dir.apply", "Применить"),
+  ("game_dir.detected", "Найденные папки игры"),
+  ("game_dir.pick", "Выберите папку игры"),
+  (
+    "game_dir.unmanageable",
+    "Невозможно управлять этой папкой: {error}",
+  ),
+  (
+    "game_dir.busy",
+    "Выполняются задачи, смените папку после их завершения",
+  ),
+  ("game_dir.not_found", "Папка не существует"),
+  ("filter.label", "Фильтр:"),
+  ("filter.all", "Все"),
+  ("filter.installed", "Установленные"),
+  ("filter.updatable", "Есть обновления"),
+  ("filter.not_installed", "Не установленные"),
+  ("filter.search", "Поиск по id, названию или заметке"),
+  ("plan.title", "План обновления"),
+  ("plan.installs", "Установка"),
+  ("plan.upgrades", "Обновление"),
+  ("plan.uninstalls", "Удаление"),
+  ("plan.up_to_date", "Уже актуальны"),
+  ("plan.download_size", "Загрузить: {size}"),
+  ("plan.cancel", "Отмена"),
+  ("plan.confirm", "Подтвердить"),
+  ("plan.no_space_title", "Недостаточно места на диске"),
+  (
+    "plan.no_space",
+    "Требуется {required} с запасом, доступно {available}",
+  ),
+  ("settings.title", "Настройки"),
+  ("settings.appearance", "Внешний вид"),
+  ("settings.theme", "Тема"),
+  ("settings.system_language", "Как в системе"),
+  ("settings.registries", "Реестры"),
+  ("settings.add_registry", "Добавить"),
+  ("settings.remove", "Удалить"),
+  ("settings.games", "Установки игры"),
+  ("settings.game_name", "Название"),
+  ("settings.add_game", "Добавить текущую папку"),
+  ("settings.downloads", "Загрузки"),
+  (
+    "settings.idle_timeout",
+    "Тайм-аут зависшей загрузки, секунд",
+  ),
+  ("settings.download_retries", "Повторов после ошибок сети"),
+  ("settings.connect_timeout", "Тайм-аут подключения, секунд"),
+  (
+    "settings.allow_insecure_redirects",
+    "Разрешить перенаправления с HTTPS на HTTP",
+  ),
+  (
+    "settings.network_restart_hint",
+    "Настройки подключения применяются после перезапуска",
+  ),
+  ("settings.startup", "Запуск"),
+  (
+    "settings.auto_update",
+    "Обновлять установленные моды при запуске",
+  ),
+  ("settings.auto_update_ask", "Спрашивать перед обновлением"),
+  (
+    "settings.allow_external_res_mods",
+    "Разрешить res_mods ссылаться за пределы папки игры",
+  ),
+  ("settings.uninstall", "Удаление"),
+  (
+    "settings.safe_delete",
+    "Безопасное удаление: перемещать файлы в корзину",
+  ),
+  ("settings.trash_retention_days", "Хранить в корзине, дней"),
+  ("settings.apply", "Применить"),
+  ("settings.invalid_url", "Неверный адрес: {reason}"),
+  ("note.label", "Заметка: {note}"),
+  ("note.add", "Добавить заметку"),
+  ("note.edit", "Изменить заметку"),
+  ("note.save", "Сохранить"),
+  (
+    "note.placeholder",
+    "Зачем установлен, какие настройки изменены…",
+  ),
+  ("note.save_failed", "Не удалось сохранить заметку"),
+  ("summary.title", "Обновление завершено"),
+  ("summary.details", "Загружено {size} за {elapsed}"),
+  ("summary.copy_failures", "Копировать список ошибок"),
+  ("summary.failures_copied", "модов с ошибками: {count}"),
+  ("summary.close", "Закрыть"),
+  (
+    "crash.title",
+    "В прошлый раз приложение аварийно завершилось",
+  ),
+  ("crash.text", "Отчёт о сбое сохранён в {path}"),
+  ("crash.open", "Открыть отчёт"),
+  ("crash.ignore", "Игнорировать"),
+  ("cli.fatal", "Ошибка: {error}"),
+  (
+    "cli.registry_failed",
+    "Не удалось загрузить реестр {url}: {error}",
+  ),
+  ("cli.downloading", "Загрузка {id} {version}"),
+  ("cli.installed", "Установлен {id} {version}"),
+  ("cli.uninstalled", "Удалён {id}"),
+  ("cli.up_to_date", "Все моды актуальны"),
+  (
+    "cli.settings_invalid",
+    "Не удалось прочитать настройки, используются настройки по умолчанию: {error}",
+  ),
+  (
+    "cli.invalid_registry",
+    "Пропущен недопустимый адрес реестра из настроек: {url}",
+  ),
+  ("error.network", "Ошибка сети: {error}"),
+  ("error.io", "Ошибка доступа к файлу: {error}"),
+  ("error.url", "Недопустимый адрес: {error}"),
+  (
+    "error.settings",
+    "Не удалось прочитать или сохранить настройки: {error}",
+  ),
+  ("error.registry", "Ошибка реестра: {error}"),
+  (
+    "error.mod_manager",
+    "Не удалось изменить файлы игры: {error}",
+  ),
+  ("error.download", "Ошибка загрузки: {error}"),
+  ("error.install", "Ошибка установки: {error}"),
+  (
+    "error.invalid_archive",
+    "Ошибка установки: загруженный файл не является архивом (загрузка могла оборваться или ссылка устарела), размер: {size}",
+  ),
+  ("error.not_found", "Мод {id} не найден"),
+  ("error.not_installed", "Мод {id} не установлен"),
+  ("error.context", "{operation} {id}: {error}"),
+  ("operation.install", "Установка"),
+  ("operation.uninstall", "Удаление"),
+  ("operation.update", "Обновление"),
+  ("sort.label", "Сортировка"),
+  ("sort.name", "Название"),
+  ("sort.installed", "Установлен"),
+  ("sort.update_available", "Обновление"),
+  ("sort.update_time", "Обновлён"),
+  ("select.updatable", "Выбрать обновляемые"),
+  ("select.not_installed", "Выбрать неустановленные"),
+  ("select.clear", "Снять выбор"),
+  ("select.import", "Импорт выбора"),
+  ("import.pick", "Выберите файл выбора другого установщика"),
+  ("import.filter", "Файлы выбора"),
+  ("import.read_failed", "Не удалось прочитать файл выбора"),
+  ("import.empty", "В файле не найдено модов"),
+  (
+    "import.done",
+    "Выбрано модов: {count}, проверьте их перед обновлением",
+  ),
+  ("import.unmatched", "Не найдено: {names}"),
+  ("import.blocked", "Невозможно установить: {ids}"),
+  ("table.status", "Статус"),
+  ("table.icon", "Иконка"),
+  ("table.id", "ID"),
+  ("table.name", "Название"),
+  ("table.version", "Версия"),
+  ("table.progress", "Прогресс"),
+  ("table.actions", "Действия"),
+  ("mod.local_only", "Только локально"),
+  ("mod.update_available", "Есть обновление"),
+  ("mod.homepage", "Сайт"),
+  ("mod.downloaded", "Загружено {size}"),
+  ("mod.paused", "Пауза · загружено {size}"),
+  ("mod.pause", "Пауза"),
+  ("mod.resume", "Продолжить"),
+  ("mod.cancel_install", "Отменить установку"),
+  ("mod.incompatible", "Несовместим"),
+  ("mod.force", "Принудительно"),
+  ("mod.force_reinstall", "Переустановить"),
+  ("mod.yanked", "Отозван"),
+  ("mod.replaced_by", " (используйте {id})"),
+  (
+    "mod.installed_info",
+    "Установлено {version}, файлов: {files}, {date}",
+  ),
+  ("mod.date_unknown", "дата неизвестна"),
+  ("mod.copy_id", "Копировать ID"),
+  ("mod.copy_url", "Копировать ссылку"),
+  ("mod.install_log", "Журнал установки"),
+  (
+    "mod.no_install_log",
+    "Для этого мода ещё нет журнала установки",
+  ),
+  ("mod.archive_sha256", "SHA256: {hash}"),
+  ("mod.checksum_mismatch", "Не совпадает с реестром"),
+  ("mod.copy_hash", "Копировать SHA256"),
+  ("mod.preview", "Содержимое"),
+  ("mod.source", "Источник: {source}"),
+  (
+    "mod.source_shadowing",
+    "Источник: {source} (перекрывает {shadowed})",
+  ),
+  ("mod.keep", "Оставить"),
+  ("mod.install", "Установить/обновить"),
+  ("mod.uninstall", "Удалить"),
+  ("error.subject_reason", "{subject}\nПричина: {error}"),
+  ("row.queued", "В очереди"),
+  ("row.downloading", "Загрузка {percent}%"),
+  ("row.installing", "Установка"),
+  ("row.verifying", "Проверка"),
+  ("row.uninstalling", "Удаление"),
+  ("row.failed", "Ошибка: {error}"),
+  ("row.done", "Готово"),
+  ("row.up_to_date", "Актуально"),
+  ("row.retry", "Повторить"),
+  ("row.ignore", "Игнорировать"),
+  ("size.unknown", "неизвестно"),
+  ("size.partially_unknown", "{known} + неизвестно"),
+  ("download.speed_eta", "{speed} · осталось {eta}"),
+  ("download.failed", "Не удалось загрузить мод"),
+  ("download.failed_text", "{id}\nОшибка загрузки: {error}"),
+  ("download.invalid_url", "Неверный адрес загрузки мода"),
+  ("install.failed", "Не удалось установить мод!"),
+  ("uninstall.failed", "Не удалось удалить мод!"),
+  ("archive.unknown_format", "Неизвестный формат архива"),
+  ("batch.finished", "Готово {finished}/{total}"),
+  ("toolbar.tasks", "Задачи ({count})"),
+  ("toolbar.install_local", "Установить из файла"),
+  ("toolbar.reload_registries", "Обновить реестры"),
+  ("toolbar.share", "Поделиться модами"),
+  ("toolbar.health_check", "Проверить файлы модов"),
+  ("toolbar.trash", "Корзина"),
+  ("share.nothing", "Нет модов, которыми можно поделиться"),
+  ("share.failed", "Не удалось создать ссылку"),
+  (
+    "share.copied",
+    "Скопирована ссылка на реестр с модами: {count}",
+  ),
+  (
+    "share.skipped",
+    "Установленная версия не найдена в реестрах, пропущены: {ids}",
+  ),
+  (
+    "share.too_long",
+    "Длина ссылки {size}, она может не отправиться целиком",
+  ),
+  ("toolbar.cache_archives", "Хранить архивы"),
+  ("toolbar.generate_registry", "Создать реестр"),
+  ("toolbar.estimated_download", "Будет загружено {size}"),
+  ("toolbar.update_all", "Обновить все ({count})"),
+  (
+    "toolbar.selected",
+    "Выбрано: {install} установить, {uninstall} удалить",
+  ),
+  ("toolbar.update_mods", "Применить"),
+  ("toolbar.launch_game", "Запустить игру"),
+  ("launch.failed", "Не удалось запустить игру"),
+  ("task.panel", "Задачи"),
+  ("task.clear_finished", "Очистить завершённые"),
+  ("task.details", "Подробнее"),
+  ("task.download", "Загрузка"),
+  ("task.install", "Установка"),
+  ("task.verify", "Проверка"),
+  ("task.uninstall", "Удаление"),
+  ("task.cancelled", "Отменено"),
+  ("task.log_failed", "Не удалось открыть журнал задачи"),
+  ("task_log.error", "Ошибка: {error}"),
+  ("task_log.cause", "Причина: {error}"),
+  ("task_log.start", "Загрузка {url}"),
+  ("task_log.resume", "Продолжение с байта {offset}: {url}"),
+  ("task_log.paused", "Пауза, загружено байт: {bytes}"),
+  (
+    "task_log.cache_verified",
+    "Файл из кэша прошёл проверку контрольной суммы",
+  ),
+  (
+    "task_log.retrying",
+    "Ошибка загрузки, попытка {retry} через {delay} с: {error}",
+  ),
+  ("task_log.downloaded", "Загружено: {path}"),
+  ("task_log.verifying", "Проверка архива {path}"),
+  ("task_log.archive_type", "Формат архива: {ty}"),
+  ("task_log.retry", "Повтор"),
+  (
+    "task_log.install_attempt",
+    "Попытка установки {attempt}: {path}",
+  ),
+  ("task_log.files_written", "Записано файлов: {count}"),
+  ("task_log.installed", "Установка завершена"),
+  ("task_log.uninstall_attempt", "Попытка удаления {attempt}"),
+  (
+    "task_log.not_installed",
+    "Нет записи об установке, пропущено",
+  ),
+  ("task_log.uninstalled", "Удаление завершено"),
+  ("task_status.queued", "В очереди"),
+  ("task_status.running", "Выполняется"),
+  ("task_status.paused", "Пауза"),
+  ("task_status.failed", "Ошибка"),
+  ("task_status.finished", "Готово"),
+  ("notification.history", "История"),
+  ("status.copied", "Скопировано: {text}"),
+  ("status.free_space", "Свободно: {size}"),
+  ("status.launched", "Запущено: {launcher}"),
+  ("status.dismiss", "Закрыть"),
+  ("status.queued", "В очереди: {count}"),
+  ("status.downloading", "Загрузка {id}… {percent}%"),
+  ("status.downloading_bytes", "Загрузка {id}… {size}"),
+  ("status.installing", "Установка {id}…"),
+  ("status.verifying", "Проверка {id}…"),
+  ("status.installed", "Установлен: {id} {version}"),
+  (
+    "status.up_to_date_skipped",
+    "Уже актуальны, пропущены: {ids}",
+  ),
+  ("status.uninstalling", "Удаление {id}…"),
+  ("status.uninstalled", "Удалён: {id}"),
+  ("status.registry_loaded", "Реестр загружен: {source}"),
+  (
+    "status.auto_update",
+    "Автоматическое обновление модов: {count}",
+  ),
+  ("status.health_checking", "Проверка файлов модов…"),
+  ("status.health_checked", "Проверка файлов модов завершена"),
+  (
+    "status.trash_emptied",
+    "Корзина очищена, освобождено {size}",
+  ),
+  ("status.trash_restored", "{id} восстановлен"),
+  (
+    "status.batch_done",
+    "Готово: {succeeded} успешно, {failed} с ошибкой",
+  ),
+  ("open_url.failed", "Не удалось открыть ссылку"),
+  (
+    "generate_registry.pick_dir",
+    "Выберите папку с архивами модов",
+  ),
+  ("generate_registry.done", "Реестр создан"),
+  ("generate_registry.failed", "Не удалось создать реестр"),
+  (
+    "pending.load_failed",
+    "Не удалось прочитать незавершённые задачи",
+  ),
+  ("pending.resume_title", "Возобновить незавершённые задачи"),
+  (
+    "pending.resume_text",
+    "При прошлом выходе осталось незавершённых задач установки/удаления: {count}. Продолжить?",
+  ),
+  ("auto_update.title", "Обновления модов"),
+  (
+    "auto_update.text",
+    "Доступны обновления для модов: {count}. Обновить сейчас?",
+  ),
+  ("registry.loading", "Загрузка реестров… ({done}/{total})"),
+  (
+    "registry.source_failed",
+    "Не удалось загрузить {source}: {error}",
+  ),
+  (
+    "registry.bad_file",
+    "Не удалось прочитать файл реестра: {error}",
+  ),
+  (
+    "registry.invalid_homepages",
+    "Реестр содержит неверные адреса сайтов",
+  ),
+  (
+    "registry.bad_delta",
+    "Сервер вернул обновление, которое нельзя применить",
+  ),
+  ("registry.bad_hex", "Неверный формат hex data"),
+  ("registry.bad_base64", "Неверный формат base64 data"),
+  (
+    "registry.bad_hex_content",
+    "Неверное содержимое hex data: {error}",
+  ),
+  (
+    "registry.unsupported_data",
+    "Неподдерживаемый тип data: {ty}",
+  ),
+  (
+    "registry.unsupported_scheme",
+    "Неподдерживаемый протокол: {scheme}",
+  ),
+  (
+    "registry.network_error",
+    "Сетевая ошибка при загрузке реестра",
+  ),
+  (
+    "registry.bad_format",
+    "Загруженный реестр имеет неверный формат",
+  ),
+  ("mods.not_found", "Моды не найдены"),
+  (
+    "mods.not_found_text",
+    "Этих модов нет ни в одном реестре, они пропущены: {ids}",
+  ),
+  ("local.pick", "Выберите архив мода"),
+  ("local.filter", "Архивы модов"),
+  (
+    "local.confirm_text",
+    "Файл будет установлен как id: {id} версия: {version}. Продолжить?",
+  ),
+  ("local.unsupported", "Невозможно установить этот файл"),
+  ("local.unsupported_text", "{path} не является архивом мода"),
+  ("preview.title", "Содержимое архива {id}"),
+  ("preview.loading", "Чтение архива…"),
+  (
+    "preview.unsupported",
+    "Просмотр доступен только для zip-архивов",
+  ),
+  (
+    "preview.summary",
+    "Файлов: {files}, после распаковки {size}",
+  ),
+  (
+    "preview.conflicts",
+    "Конфликтов с существующими файлами: {count}",
+  ),
+  ("preview.suspicious", "Подозрительных записей: {count}"),
+  ("preview.conflict", "Уже существует"),
+  ("preview.conflict_mod", "Принадлежит {id}"),
+  ("preview.escapes", "Исходный путь вне res_mods: {name}"),
+  ("preview.executable", "Исполняемый файл"),
+  ("preview.flagged", "Содержит отмеченные файлы"),
+  ("preview.install", "Установить"),
+  ("preview.close", "Закрыть"),
+  ("health.title", "Проверка файлов модов"),
+  (
+    "health.summary",
+    "Записей об установке: {mods}, с проблемами: {broken}",
+  ),
+  (
+    "health.healthy",
+    "Все файлы модов на месте, лишних файлов нет",
+  ),
+  ("health.missing", "Не хватает файлов: {count}"),
+  ("health.all_missing", "Все файлы отсутствуют"),
+  ("health.repair", "Переустановить"),
+  ("health.forget", "Удалить запись"),
+  ("health.more_files", "Ещё файлов: {count}"),
+  ("health.orphans", "Файлов, не принадлежащих модам: {count}"),
+  (
+    "health.orphans_hint",
+    "Возможно, моды, добавленные вручную. Присвойте их, чтобы они отображались как один мод, который можно удалить",
+  ),
+  ("health.backups", "Файлов резервных копий: {count}"),
+  ("health.temp_files", "Временных файлов: {count}"),
+  (
+    "health.leftovers_hint",
+    "Оставлены другими программами. Присвойте их и удалите для очистки",
+  ),
+  ("health.adopt", "Присвоить"),
+  ("health.failed", "Не удалось проверить файлы модов"),
+  ("health.adopt_failed", "Не удалось присвоить файлы"),
+  ("trash.title", "Корзина"),
+  (
+    "trash.hint",
+    "Файлы модов, удалённых в режиме безопасного удаления. По истечении срока хранения они удаляются автоматически",
+  ),
+  ("trash.empty_hint", "Корзина пуста"),
+  ("trash.restore", "Восстановить"),
+  ("trash.empty", "Очистить корзину ({size})"),
+  ("trash.empty_failed", "Не удалось очистить корзину"),
+  ("trash.restore_failed", "Не удалось восстановить файлы мода"),
+  (
+    "records.build_mismatch",
+    "Записи об установке от другой сборки игры",
+  ),
+  (
+    "records.build_mismatch_text",
+    "Записи об установке созданы для сборки {recorded}, игра сейчас использует сборку {build}. Проверьте файлы модов и сохраните записи для этой сборки",
+  ),
+  ("records.stamp_build", "Сохранить записи"),
+  (
+    "records.stamp_failed",
+    "Не удалось обновить записи об установке",
+  ),
+  ("local.read_failed", "Не удалось прочитать архив мода"),
+  ("install_log.version", "Версия: {version}"),
+  ("install_log.archive", "Архив: {path}"),
+  ("install_log.conflict", "Конфликт: {path} ({owner})"),
+  ("install_log.untracked", "неучтённый файл"),
+  ("install_log.directory", "Папка: {path}"),
+  ("install_log.write", "Записан: {path}"),
+  ("install_log.remove_old", "Удалён старый файл: {path}"),
+  ("install_log.trash_entry", "Корзина: {path}"),
+  ("install_log.missing", "Уже отсутствует: {path}"),
+  ("install_log.trashed", "Перемещён в корзину: {path}"),
+  ("install_log.removed", "Удалён: {path}"),
+  ("install_log.installed", "Успешно, записано файлов: {count}"),
+  ("install_log.succeeded", "Успешно"),
+  ("install_log.failed", "Ошибка: {error}"),
+  ("install_log.result", "Результат: {result}"),
+];
@@ -0,0 +1,378 @@
+// 所有条目的来源，其他语言缺少的条目使用这里的文本
+pub const CATALOG: &[(&str, &str)] = &[
+  ("app.title", "战舰世界莱服模组管理器"),
+  ("language", "语言"),
+  ("error.reason", "理由：{error}"),
+  ("settings.load_failed", "配置文件读取失败"),
+  ("settings.load_failed_text", "已使用默认配置\n理由：{error}"),
+  ("settings.invalid_registry_urls", "配置中的Registry地址无效"),
+  ("game_dir.label", "游戏根目录"),
+  ("game_dir.saved", "切换游戏"),
+  ("game_dir.browse", "浏览…"),
+  ("game_dir.apply", "应用"),
+  ("game_dir.detected", "检测到的游戏目录"),
+  ("game_dir.pick", "选择游戏根目录"),
+  ("game_dir.unmanageable", "无法管理这个目录: {error}"),
+  ("game_dir.busy", "有任务正在进行，完成后才能切换目录"),
+  ("game_dir.not_found", "目录不存在"),
+  ("filter.label", "筛选:"),
+  ("filter.all", "全部"),
+  ("filter.installed", "已安装"),
+  ("filter.updatable", "可更新"),
+  ("filter.not_installed", "未安装"),
+  ("filter.search", "搜索id、名称或备注"),
+  ("plan.title", "更新计划"),
+  ("plan.installs", "新安装"),
+  ("plan.upgrades", "升级"),
+  ("plan.uninstalls", "卸载"),
+  ("plan.up_to_date", "已是最新"),
+  ("plan.download_size", "需要下载: {size}"),
+  ("plan.cancel", "取消"),
+  ("plan.confirm", "确认"),
+  ("plan.no_space_title", "空间不足"),
+  (
+    "plan.no_space",
+    "需要 {required}（另需保留余量），可用 {available}",
+  ),
+  ("settings.title", "设置"),
+  ("settings.appearance", "外观"),
+  ("settings.theme", "主题"),
+  ("settings.system_language", "跟随系统"),
+  ("settings.registries", "Registry 地址"),
+  ("settings.add_registry", "添加"),
+  ("settings.remove", "删除"),
+  ("settings.games", "游戏目录"),
+  ("settings.game_name", "名称"),
+  ("settings.add_game", "添加当前目录"),
+  ("settings.downloads", "下载"),
+  ("settings.idle_timeout", "下载无响应超时（秒）"),
+  ("settings.download_retries", "网络错误重试次数"),
+  ("settings.connect_timeout", "连接超时（秒）"),
+  (
+    "settings.allow_insecure_redirects",
+    "允许重定向从HTTPS降级到HTTP",
+  ),
+  ("settings.network_restart_hint", "连接设置在重启后生效"),
+  ("settings.startup", "启动"),
+  ("settings.auto_update", "启动时自动更新已安装模组"),
+  ("settings.auto_update_ask", "更新前先询问"),
+  (
+    "settings.allow_external_res_mods",
+    "允许res_mods链接到游戏目录以外",
+  ),
+  ("settings.uninstall", "卸载"),
+  ("settings.safe_delete", "安全删除：卸载的文件先移到回收站"),
+  ("settings.trash_retention_days", "回收站保留天数"),
+  ("settings.apply", "应用"),
+  ("settings.invalid_url", "地址无效: {reason}"),
+  ("note.label", "备注: {note}"),
+  ("note.add", "添加备注"),
+  ("note.edit", "编辑备注"),
+  ("note.save", "保存"),
+  ("note.placeholder", "为什么安装、改了哪些设置……"),
+  ("note.save_failed", "备注保存失败"),
+  ("summary.title", "更新完成"),
+  ("summary.details", "共下载 {size}，用时 {elapsed}"),
+  ("summary.copy_failures", "复制失败列表"),
+  ("summary.failures_copied", "{count} 个失败的模组"),
+  ("summary.close", "关闭"),
+  ("crash.title", "程序上次意外退出"),
+  ("crash.text", "崩溃报告已保存到 {path}"),
+  ("crash.open", "打开报告"),
+  ("crash.ignore", "忽略"),
+  ("cli.fatal", "错误: {error}"),
+  ("cli.registry_failed", "registry {url} 加载失败: {error}"),
+  ("cli.downloading", "正在下载 {id} {version}"),
+  ("cli.installed", "已安装 {id} {version}"),
+  ("cli.uninstalled", "已卸载 {id}"),
+  ("cli.up_to_date", "所有模组都是最新版本"),
+  (
+    "cli.settings_invalid",
+    "配置文件读取失败，已使用默认配置: {error}",
+  ),
+  (
+    "cli.invalid_registry",
+    "设置中的registry地址无效，已跳过: {url}",
+  ),
+  ("error.network", "网络错误: {error}"),
+  ("error.io", "文件读写失败: {error}"),
+  ("error.url", "地址无效: {error}"),
+  ("error.settings", "设置读写失败: {error}"),
+  ("error.registry", "registry错误: {error}"),
+  ("error.mod_manager", "游戏文件操作失败: {error}"),
+  ("error.download", "下载失败: {error}"),
+  ("error.install", "安装失败: {error}"),
+  (
+    "error.invalid_archive",
+    "安装失败: 下载的文件不是有效的压缩包（可能下载不完整或链接失效），大小: {size}",
+  ),
+  ("error.not_found", "找不到模组 {id}"),
+  ("error.not_installed", "模组 {id} 没有安装"),
+  ("error.context", "{operation} {id}: {error}"),
+  ("operation.install", "安装"),
+  ("operation.uninstall", "卸载"),
+  ("operation.update", "更新"),
+  ("sort.label", "排序"),
+  ("sort.name", "名称"),
+  ("sort.installed", "已安装"),
+  ("sort.update_available", "可更新"),
+  ("sort.update_time", "更新时间"),
+  ("select.updatable", "全选可更新"),
+  ("select.not_installed", "全选未安装"),
+  ("select.clear", "清除选择"),
+  ("select.import", "导入选择"),
+  ("import.pick", "选择其他安装器的选择文件"),
+  ("import.filter", "选择文件"),
+  ("import.read_failed", "无法读取选择文件"),
+  ("import.empty", "文件中没有找到模组"),
+  ("import.done", "已勾选{count}个模组，请检查后再更新"),
+  ("import.unmatched", "没有找到：{names}"),
+  ("import.blocked", "无法安装：{ids}"),
+  ("table.status", "安装状态"),
+  ("table.icon", "图标"),
+  ("table.id", "ID"),
+  ("table.name", "名称"),
+  ("table.version", "版本"),
+  ("table.progress", "进度"),
+  ("table.actions", "操作"),
+  ("mod.local_only", "仅本地"),
+  ("mod.update_available", "可更新"),
+  ("mod.homepage", "主页"),
+  ("mod.downloaded", "已下载 {size}"),
+  ("mod.paused", "已暂停 · 已下载 {size}"),
+  ("mod.pause", "暂停"),
+  ("mod.resume", "继续"),
+  ("mod.cancel_install", "取消安装"),
+  ("mod.incompatible", "不兼容"),
+  ("mod.force", "强制"),
+  ("mod.force_reinstall", "强制重装"),
+  ("mod.yanked", "已撤回"),
+  ("mod.replaced_by", " (建议替换为 {id})"),
+  (
+    "mod.installed_info",
+    "已安装 {version}，{files} 个文件，{date}",
+  ),
+  ("mod.date_unknown", "日期未知"),
+  ("mod.copy_id", "复制ID"),
+  ("mod.copy_url", "复制链接"),
+  ("mod.install_log", "安装日志"),
+  ("mod.no_install_log", "这个模组还没有安装日志"),
+  ("mod.archive_sha256", "SHA256: {hash}"),
+  ("mod.checksum_mismatch", "与registry声明的不一致"),
+  ("mod.copy_hash", "复制SHA256"),
+  ("mod.preview", "预览内容"),
+  ("mod.source", "来源: {source}"),
+  (
+    "mod.source_shadowing",
+    "来源: {source}（覆盖了 {shadowed} 中的同名条目）",
+  ),
+  ("mod.keep", "保持"),
+  ("mod.install", "安装/更新"),
+  ("mod.uninstall", "卸载"),
+  ("error.subject_reason", "{subject}\n理由：{error}"),
+  ("row.queued", "排队中"),
+  ("row.downloading", "下载中 {percent}%"),
+  ("row.installing", "安装中"),
+  ("row.verifying", "检查中"),
+  ("row.uninstalling", "卸载中"),
+  ("row.failed", "失败: {error}"),
+  ("row.done", "已完成"),
+  ("row.up_to_date", "已是最新"),
+  ("row.retry", "重试"),
+  ("row.ignore", "忽略"),
+  ("size.unknown", "未知"),
+  ("size.partially_unknown", "{known} + 未知"),
+  ("download.speed_eta", "{speed} · 剩余 {eta}"),
+  ("download.failed", "模组下载失败"),
+  ("download.failed_text", "{id}\n下载失败: {error}"),
+  ("download.invalid_url", "模组下载地址无效"),
+  ("install.failed", "模组安装失败！"),
+  ("uninstall.failed", "模组卸载失败！"),
+  ("archive.unknown_format", "无法识别的压缩格式"),
+  ("batch.finished", "{finished}/{total} 完成"),
+  ("toolbar.tasks", "任务 ({count})"),
+  ("toolbar.install_local", "安装本地文件"),
+  ("toolbar.reload_registries", "重新加载Registry"),
+  ("toolbar.share", "分享已安装的模组"),
+  ("toolbar.health_check", "检查模组文件"),
+  ("toolbar.trash", "回收站"),
+  ("share.nothing", "没有可以分享的模组"),
+  ("share.failed", "无法生成分享链接"),
+  ("share.copied", "已复制包含{count}个模组的registry链接"),
+  (
+    "share.skipped",
+    "registry中找不到已安装的版本，未包含：{ids}",
+  ),
+  ("share.too_long", "链接长达{size}，可能无法完整发送"),
+  ("toolbar.cache_archives", "保留压缩包"),
+  ("toolbar.generate_registry", "生成Registry"),
+  ("toolbar.estimated_download", "预计下载 {size}"),
+  ("toolbar.update_all", "全部更新 ({count})"),
+  (
+    "toolbar.selected",
+    "已选择 {install} 个安装, {uninstall} 个卸载",
+  ),
+  ("toolbar.update_mods", "更新模组"),
+  ("toolbar.launch_game", "启动游戏"),
+  ("launch.failed", "启动游戏失败"),
+  ("task.panel", "任务"),
+  ("task.clear_finished", "清除已结束"),
+  ("task.details", "查看详情"),
+  ("task.download", "下载"),
+  ("task.install", "安装"),
+  ("task.verify", "检查"),
+  ("task.uninstall", "卸载"),
+  ("task.cancelled", "已取消"),
+  ("task.log_failed", "无法打开任务日志"),
+  ("task_log.error", "错误: {error}"),
+  ("task_log.cause", "原因: {error}"),
+  ("task_log.start", "开始下载 {url}"),
+  ("task_log.resume", "从第{offset}字节继续下载 {url}"),
+  ("task_log.paused", "已暂停，已下载{bytes}字节"),
+  ("task_log.cache_verified", "缓存中的文件校验通过"),
+  (
+    "task_log.retrying",
+    "下载失败，{delay}秒后第{retry}次重试: {error}",
+  ),
+  ("task_log.downloaded", "下载完成: {path}"),
+  ("task_log.verifying", "检查压缩包 {path}"),
+  ("task_log.archive_type", "压缩包格式: {ty}"),
+  ("task_log.retry", "重试"),
+  ("task_log.install_attempt", "第{attempt}次尝试安装 {path}"),
+  ("task_log.files_written", "写入了{count}个文件"),
+  ("task_log.installed", "安装完成"),
+  ("task_log.uninstall_attempt", "第{attempt}次尝试卸载"),
+  ("task_log.not_installed", "没有安装记录，跳过"),
+  ("task_log.uninstalled", "卸载完成"),
+  ("task_status.queued", "排队中"),
+  ("task_status.running", "运行中"),
+  ("task_status.paused", "已暂停"),
+  ("task_status.failed", "失败"),
+  ("task_status.finished", "完成"),
+  ("notification.history", "历史"),
+  ("status.copied", "已复制: {text}"),
+  ("status.free_space", "可用空间: {size}"),
+  ("status.launched", "已启动: {launcher}"),
+  ("status.dismiss", "关闭"),
+  ("status.queued", "排队中: {count}"),
+  ("status.downloading", "正在下载 {id}… {percent}%"),
+  ("status.downloading_bytes", "正在下载 {id}… {size}"),
+  ("status.installing", "正在安装 {id}…"),
+  ("status.verifying", "正在检查 {id}…"),
+  ("status.installed", "安装完成: {id} {version}"),
+  ("status.up_to_date_skipped", "已是最新，跳过: {ids}"),
+  ("status.uninstalling", "正在卸载 {id}…"),
+  ("status.uninstalled", "卸载完成: {id}"),
+  ("status.registry_loaded", "Registry加载完成: {source}"),
+  ("status.auto_update", "正在自动更新 {count} 个模组"),
+  ("status.health_checking", "正在检查模组文件…"),
+  ("status.health_checked", "模组文件检查完成"),
+  ("status.trash_emptied", "已清空回收站，腾出{size}"),
+  ("status.trash_restored", "已恢复{id}"),
+  ("status.batch_done", "完成: {succeeded} 成功, {failed} 失败"),
+  ("open_url.failed", "无法打开链接"),
+  ("generate_registry.pick_dir", "选择模组压缩包所在目录"),
+  ("generate_registry.done", "Registry已生成"),
+  ("generate_registry.failed", "Registry生成失败"),
+  ("pending.load_failed", "无法读取未完成的任务"),
+  ("pending.resume_title", "恢复未完成的任务"),
+  (
+    "pending.resume_text",
+    "上次退出时还有{count}个安装/卸载任务没有完成，是否继续？",
+  ),
+  ("auto_update.title", "模组更新"),
+  ("auto_update.text", "有 {count} 个模组可更新，现在更新？"),
+  ("registry.loading", "正在加载Registry… ({done}/{total})"),
+  ("registry.source_failed", "{source} 加载失败: {error}"),
+  ("registry.bad_file", "无法读取Registry文件: {error}"),
+  ("registry.invalid_homepages", "Registry包含无效的主页地址"),
+  ("registry.bad_delta", "服务器返回了无法应用的增量更新"),
+  ("registry.bad_hex", "hex data格式错误"),
+  ("registry.bad_base64", "base64 data格式错误"),
+  ("registry.bad_hex_content", "hex data内容格式错误: {error}"),
+  ("registry.unsupported_data", "不支持的data类型: {ty}"),
+  ("registry.unsupported_scheme", "不支持的地址协议: {scheme}"),
+  ("registry.network_error", "从网络加载Registry时遭遇错误"),
+  ("registry.bad_format", "从网络获取的Registry格式错误"),
+  ("mods.not_found", "找不到模组"),
+  (
+    "mods.not_found_text",
+    "以下模组不在任何registry中，已跳过: {ids}",
+  ),
+  ("local.pick", "选择模组压缩包"),
+  ("local.filter", "模组压缩包"),
+  (
+    "local.confirm_text",
+    "将以 id: {id} 版本: {version} 安装该文件，是否继续？",
+  ),
+  ("local.unsupported", "无法安装该文件"),
+  ("local.unsupported_text", "{path} 不是支持的模组压缩包"),
+  ("preview.title", "{id} 的压缩包内容"),
+  ("preview.loading", "正在读取压缩包…"),
+  ("preview.unsupported", "只能预览zip压缩包"),
+  ("preview.summary", "共 {files} 个文件，解压后 {size}"),
+  ("preview.conflicts", "{count} 个文件与已有文件冲突"),
+  ("preview.suspicious", "{count} 个可疑的条目"),
+  ("preview.conflict", "已存在"),
+  ("preview.conflict_mod", "属于 {id}"),
+  ("preview.escapes", "原路径在res_mods以外: {name}"),
+  ("preview.executable", "可执行文件"),
+  ("preview.flagged", "含需要留意的文件"),
+  ("preview.install", "安装"),
+  ("preview.close", "关闭"),
+  ("health.title", "模组文件检查"),
+  ("health.summary", "共{mods}个安装记录，{broken}个有问题"),
+  ("health.healthy", "所有模组的文件都在，也没有多余的文件"),
+  ("health.missing", "缺少{count}个文件"),
+  ("health.all_missing", "文件全部不在了"),
+  ("health.repair", "重新安装"),
+  ("health.forget", "删除记录"),
+  ("health.more_files", "还有{count}个文件"),
+  ("health.orphans", "{count}个文件不属于任何模组"),
+  (
+    "health.orphans_hint",
+    "可能是手动放入的模组。收编后作为一个模组显示，可以一起卸载",
+  ),
+  ("health.backups", "{count}个备份文件"),
+  ("health.temp_files", "{count}个临时文件"),
+  (
+    "health.leftovers_hint",
+    "其他工具留下的文件。收编后卸载即可清理",
+  ),
+  ("health.adopt", "收编"),
+  ("health.failed", "模组文件检查失败"),
+  ("health.adopt_failed", "收编文件失败"),
+  ("trash.title", "回收站"),
+  (
+    "trash.hint",
+    "开启安全删除后卸载的模组文件，超过保留天数会自动清理",
+  ),
+  ("trash.empty_hint", "回收站是空的"),
+  ("trash.restore", "恢复"),
+  ("trash.empty", "清空回收站（{size}）"),
+  ("trash.empty_failed", "清空回收站失败"),
+  ("trash.restore_failed", "无法恢复模组文件"),
+  ("records.build_mismatch", "安装记录不属于当前游戏版本"),
+  (
+    "records.build_mismatch_text",
+    "安装记录是为构建{recorded}写的，游戏现在使用构建{build}。检查模组文件后可以确认沿用这些记录",
+  ),
+  ("records.stamp_build", "沿用记录"),
+  ("records.stamp_failed", "无法更新安装记录"),
+  ("local.read_failed", "无法读取模组压缩包"),
+  ("install_log.version", "版本: {version}"),
+  ("install_log.archive", "压缩包: {path}"),
+  ("install_log.conflict", "冲突: {path} ({owner})"),
+  ("install_log.untracked", "未记录的文件"),
+  ("install_log.directory", "目录: {path}"),
+  ("install_log.write", "写入: {path}"),
+  ("install_log.remove_old", "删除旧文件: {path}"),
+  ("install_log.trash_entry", "回收站: {path}"),
+  ("install_log.missing", "已不存在: {path}"),
+  ("install_log.trashed", "移到回收站: {path}"),
+  ("install_log.removed", "删除: {path}"),
+  ("install_log.installed", "成功，写入了{count}个文件"),
+  ("install_log.succeeded", "成功"),
+  ("install_log.failed", "失败: {error}"),
+  ("install_log.result", "结果: {result}"),
+];
@@ -0,0 +1,156 @@
+// 每次安装和卸载在数据目录中留下的日志，记下具体动了哪些文件，
+// 用户反馈模组弄坏了游戏时可以拿来排查
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use tokio::fs;
+
+use crate::i18n::t;
+
+// 超过这个数量时删掉最旧的
+pub const MAX_LOGS: usize = 100;
+const LOG_SUFFIX: &str = ".log";
+// 精确到毫秒，按字典序排列就是先后顺序
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%3f";
+const TIMESTAMP_LEN: usize = 18;
+const KINDS: [&str; 2] = ["install", "uninstall"];
+
+// 先在内存中记下，操作结束时连同结果一起写入
+#[derive(Debug, Clone)]
+pub struct OperationLog {
+  kind: &'static str,
+  id: String,
+  time: DateTime<Local>,
+  lines: Vec<String>,
+}
+
+impl OperationLog {
+  pub fn install(id: &str) -> Self {
+    Self::new(KINDS[0], id)
+  }
+
+  pub fn uninstall(id: &str) -> Self {
+    Self::new(KINDS[1], id)
+  }
+
+  fn new(kind: &'static str, id: &str) -> Self {
+    Self {
+      kind,
+      id: id.to_string(),
+      time: Local::now(),
+      lines: Vec::new(),
+    }
+  }
+
+  pub fn line(&mut self, line: impl Into<String>) {
+    self.lines.push(line.into());
+  }
+
+  // 写入`dir`并清理多余的旧日志，返回日志文件的路径
+  pub async fn write(
+    self,
+    dir: &Path,
+    result: &str,
+  ) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir).await?;
+    let path = dir.join(log_name(self.kind, &self.id, self.time));
+    let content = format!(
+      "{} {}\n{}\n\n{}\n\n{}\n",
+      self.kind,
+      self.id,
+      self.time.to_rfc3339(),
+      self.lines.join("\n"),
+      t!("install_log.result", result = result)
+    );
+    fs::write(&path, content).await?;
+    rotate(dir, MAX_LOGS).await?;
+    Ok(path)
+  }
+}
+
+// `install-<id>-<时间戳>.log`，id中不能用在文件名里的字符会被替换
+pub fn log_name(
+  kind: &str,
+  id: &str,
+  time: DateTime<Local>,
+) -> String {
+  format!(
+    "{}-{}-{}{}",
+    kind,
+    sanitize_filename::sanitize(id),
+    time.format(TIMESTAMP_FORMAT),
+    LOG_SUFFIX
+  )
+}
+
+struct LogFile {
+  // 日志名中时间戳之前的`kind-id`
+  subject: String,
+  timestamp: String,
+  name: String,
+}
+
+// 不是操作日志的文件返回None，导出的任务日志也在同一个目录
+fn parse_name(name: &str) -> Option<LogFile> {
+  let stem = name.strip_suffix(LOG_SUFFIX)?;
+  let split = stem.len().checked_sub(TIMESTAMP_LEN + 1)?;
+  let subject = stem.get(..split)?;
+  let timestamp = stem.get(split..)?.strip_prefix('-')?;
+  let valid = timestamp.char_indices().all(|(index, c)| {
+    if index == 8 {
+      c == '-'
+    } else {
+      c.is_ascii_digit()
+    }
+  });
+  let known = KINDS.iter().any(|kind| {
+    subject
+      .strip_prefix(kind)
+      .is_some_and(|rest| rest.starts_with('-'))
+  });
+  (valid && known).then(|| LogFile {
+    subject: subject.to_string(),
+    timestamp: timestamp.to_string(),
+    name: name.to_string(),
+  })
+}
+
+// 目录不存在时视为没有日志
+async fn log_files(dir: &Path) -> Vec<LogFile> {
+  let mut logs = Vec::new();
+  let Ok(mut read_dir) = fs::read_dir(dir).await else {
+    return logs;
+  };
+  while let Ok(Some(entry)) = read_dir.next_entry().await {
+    logs.extend(parse_name(&entry.file_name().to_string_lossy()));
+  }
+  logs
+}
+
+// `id`最近的一份日志，安装和卸载的都算
+pub async fn latest_log(dir: &Path, id: &str) -> Option<PathBuf> {
+  let id = sanitize_filename::sanitize(id);
+  log_files(dir)
+    .await
+    .into_iter()
+    .filter(|log| {
+      KINDS
+        .iter()
+        .any(|kind| log.subject == format!("{}-{}", kind, id))
+    })
+    .max_by(|a, b| a.timestamp.cmp(&b.timestamp))
+    .map(|log| dir.join(log.name))
+}
+
+// 只保留最新的`max`份
+pub async fn rotate(dir: &Path, max: usize) -> std::io::Result<()> {
+  let mut logs = log_files(dir).await;
+  if logs.len() <= max {
+    return Ok(());
+  }
+  logs.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+  for log in logs.into_iter().skip(max) {
+    fs::remove_file(dir.join(log.name)).await?;
+  }
+  Ok(())
+}
@@ -0,0 +1,133 @@
+use std::{
+  fmt,
+  path::{Path, PathBuf},
+  process::Command,
+};
+
+// 找不到客户端时交给游戏中心启动
+const GAME_CENTER_LINK: &str = "lgc://";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Launcher {
+  Executable(PathBuf),
+  GameCenter,
+}
+
+impl fmt::Display for Launcher {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Executable(path) => write!(f, "{}", path.display()),
+      Self::GameCenter => write!(f, "{}", GAME_CENTER_LINK),
+    }
+  }
+}
+
+// 根目录的启动程序会自己选择最新的构建，
+// 没有时取bin下构建号最大的64位客户端
+pub fn find_executable(game_dir: &Path) -> Option<PathBuf> {
+  let root = game_dir.join("Korabli.exe");
+  if root.is_file() {
+    return Some(root);
+  }
+  std::fs::read_dir(game_dir.join("bin"))
+    .ok()?
+    .filter_map(Result::ok)
+    .filter_map(|entry| {
+      let build =
+        entry.file_name().to_string_lossy().parse::<u64>().ok()?;
+      let path = entry.path().join("bin64").join("Korabli64.exe");
+      path.is_file().then_some((build, path))
+    })
+    .max_by_key(|(build, _)| *build)
+    .map(|(_, path)| path)
+}
+
+pub fn find_launcher(game_dir: &Path) -> Launcher {
+  find_executable(game_dir)
+    .map_or(Launcher::GameCenter, Launcher::Executable)
+}
+
+// 不等待客户端退出，失败时带上尝试启动的路径
+pub async fn launch(
+  game_dir: PathBuf,
+) -> Result<Launcher, (Launcher, String)> {
+  let launcher = find_launcher(&game_dir);
+  let res = match &launcher {
+    Launcher::Executable(path) => Command::new(path)
+      .current_dir(&game_dir)
+      .spawn()
+      .map(|_| ()),
+    Launcher::GameCenter => open::that_detached(GAME_CENTER_LINK),
+  };
+  match res {
+    Ok(()) => Ok(launcher),
+    Err(err) => Err((launcher, err.to_string())),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn game_dir(name: &str, files: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "kmm-launch-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for file in files {
+      let path = dir.join(file);
+      std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+      std::fs::write(path, "").unwrap();
+    }
+    dir
+  }
+
+  #[test]
+  fn launcher_only() {
+    let dir = game_dir("launcher", &["Korabli.exe"]);
+    assert_eq!(find_executable(&dir), Some(dir.join("Korabli.exe")));
+  }
+
+  // 取构建号最大的，不是按名字排序
+  #[test]
+  fn newest_client() {
+    let dir = game_dir(
+      "client",
+      &[
+        "bin/9000/bin64/Korabli64.exe",
+        "bin/10000/bin64/Korabli64.exe",
+        "bin/20000/bin32/Korabli.exe",
+        "bin/backup/bin64/Korabli64.exe",
+      ],
+    );
+    assert_eq!(
+      find_executable(&dir),
+      Some(dir.join("bin/10000/bin64/Korabli64.exe"))
+    );
+    assert_eq!(
+      find_launcher(&dir),
+      Launcher::Executable(dir.join("bin/10000/bin64/Korabli64.exe"))
+    );
+  }
+
+  #[test]
+  fn launcher_preferred_over_client() {
+    let dir = game_dir(
+      "both",
+      &["Korabli.exe", "bin/10000/bin64/Korabli64.exe"],
+    );
+    assert_eq!(find_executable(&dir), Some(dir.join("Korabli.exe")));
+  }
+
+  #[test]
+  fn neither_falls_back_to_game_center() {
+    let dir = game_dir("neither", &["bin/10000/res/readme.txt"]);
+    assert_eq!(find_executable(&dir), None);
+    assert_eq!(find_launcher(&dir), Launcher::GameCenter);
+    assert_eq!(Launcher::GameCenter.to_string(), "lgc://");
+    assert_eq!(find_executable(&dir.join("missing")), None);
+  }
+}
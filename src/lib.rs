@@ -0,0 +1,14 @@
+// 模组管理的核心功能，不依赖界面，也可以在脚本中使用
+#[cfg(feature = "control")]
+pub mod control;
+pub mod data;
+pub mod detect;
+pub mod error;
+pub mod format;
+pub mod http;
+pub mod i18n;
+pub mod install_log;
+pub mod mod_manager;
+pub mod paths;
+pub mod tasks;
+pub mod trash;
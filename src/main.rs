@@ -1,12 +1,20 @@
 use app::iced_main;
+#[cfg(feature = "control")]
+use korabli_mod_manager::control;
+use korabli_mod_manager::{
+  data, detect, error, format, http, i18n, install_log, mod_manager,
+  paths, tasks, trash,
+};
 
 mod app;
-mod data;
-mod error;
+mod crash;
+mod launch;
 mod messages;
-mod mod_manager;
-mod tasks;
 
-fn main() {
-  iced_main().expect("wtf iced")
+fn main() -> iced::Result {
+  tracing_subscriber::fmt()
+    .with_writer(|| crash::LogWriter)
+    .init();
+  crash::install_panic_hook(paths::data_dir());
+  iced_main()
 }
@@ -1,18 +1,44 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{collections::HashSet, path::PathBuf, time::Instant};
 
+use iced::{Point, Size, window};
 use url::Url;
 
 use crate::{
-  data::registry::Registry,
-  mod_manager::{ModManager, Records},
-  tasks::{
-    download::DownloadUpdate, install::InstallUpdate,
-    uninstall::UninstallUpdate,
+  app::tasks::{
+    operation::OperationUpdate, uninstall::UninstallUpdate,
   },
+  data::{
+    archive::ArchiveType,
+    batch::{PendingAction, ReleaseSnapshot},
+    filter::ModFilter,
+    health::HealthReport,
+    pending::PendingQueue,
+    preview::ArchiveTree,
+    registry::Registry,
+    settings::{Settings, SortKey},
+    settings_form::SettingsEdit,
+    version::ModVersion,
+  },
+  i18n::Locale,
+  mod_manager::{ModManager, Record, Records},
+  trash::TrashEntry,
 };
 
 #[derive(Debug, Clone)]
 pub enum Message {
+  Game(GameMsg),
+  Registry(RegistryMsg),
+  Task(TaskMsg),
+  Settings(SettingsMsg),
+  Ui(UiMsg),
+  // 控制接口收到的请求
+  #[cfg(feature = "control")]
+  Control(crate::control::Call),
+}
+
+// 游戏目录、安装记录和备注
+#[derive(Debug, Clone)]
+pub enum GameMsg {
   GameDirInput(String),
   RecordsUpdated {
     mod_manager: ModManager,
@@ -22,30 +48,155 @@ pub enum Message {
     mod_manager: ModManager,
   },
   QueueUpdateRecords,
-  Warning {
-    title: String,
-    text: String,
+  LaunchGame,
+  GameLaunched(String),
+  ModManagerReady {
+    mod_manager: ModManager,
+  },
+  // 其他程序改动了res_mods，changed_at是最后一次变化的时间
+  ResModsChangedExternally {
+    path: PathBuf,
+    changed_at: Instant,
+  },
+  AddCurrentMod {
+    id: String,
+  },
+  // 安装/卸载结束后只更新这一个模组，record为None时已经卸载
+  RecordChanged {
+    id: String,
+    record: Option<Record>,
+  },
+  EditNote {
+    id: String,
+  },
+  NoteInput(String),
+  SaveNote,
+  CancelNote,
+  NoteSaved {
+    mod_manager: ModManager,
+    result: Result<(), String>,
+  },
+  PrepareModManager {
+    game_dir_path: PathBuf,
+  },
+  ModManagerPrepared {
+    mod_manager: ModManager,
+    game_version: String,
+  },
+  ModManagerFailed {
+    game_dir_path: PathBuf,
+    error: String,
+  },
+  BrowseGameDir,
+  ApplyGameDir,
+  GameDirsDetected(Vec<PathBuf>),
+  PickGameDir(PathBuf),
+  QueueUpdateCurrentMods,
+  UpdateCurrentMods {
+    mod_manager: ModManager,
+  },
+  CurrentModsUpdated {
+    mod_manager: ModManager,
+    current_mods: HashSet<String>,
+  },
+  CheckHealth,
+  HealthChecked {
+    mod_manager: ModManager,
+    result: Result<HealthReport, String>,
+  },
+  CloseHealthReport,
+  // 重新安装记录中的版本，补上缺少的文件
+  RepairMod {
+    id: String,
+  },
+  // 文件已经全部不在了，只删除安装记录
+  ForgetRecord {
+    id: String,
+  },
+  AdoptFiles(Vec<PathBuf>),
+  FilesAdopted {
+    mod_manager: ModManager,
+    result: Result<(), String>,
+  },
+  // 复制过来的记录确认适用于当前构建
+  StampBuild,
+  BuildStamped {
+    mod_manager: ModManager,
+    result: Result<(), String>,
+  },
+  ShowTrash,
+  TrashListed(Vec<TrashEntry>),
+  CloseTrash,
+  EmptyTrash,
+  // 腾出的字节数
+  TrashEmptied(Result<u64, String>),
+  RestoreTrashed(PathBuf),
+  // 恢复的模组id
+  TrashRestored {
+    mod_manager: ModManager,
+    result: Result<String, String>,
+  },
+}
+
+// registry的加载和生成
+#[derive(Debug, Clone)]
+pub enum RegistryMsg {
+  GenerateRegistry,
+  LoadRegistries {
+    urls: Vec<Url>,
+  },
+  RegistryLoaded {
+    url: Url,
+    // registry比其他消息大得多，装箱后再传递
+    result: Result<Box<Registry>, String>,
   },
+  RetryRegistry(Url),
+  ReloadRegistries,
+}
+
+// 下载、安装、卸载任务和批量更新
+#[derive(Debug, Clone)]
+pub enum TaskMsg {
+  RefreshFreeSpace,
+  FreeSpaceUpdated(Option<u64>),
+  PreviewBatch,
+  ConfirmBatch,
+  CancelBatch,
   UpdateMods {
-    install: Vec<String>,
+    install: Vec<ReleaseSnapshot>,
+    // registry中已经没有的模组
+    missing: Vec<String>,
     uninstall: Vec<String>,
+    // 已是最新、不再安装的模组，取消勾选
+    skipped: Vec<String>,
   },
-  GetMod {
-    url: Url,
+  UpdateAll,
+  GetMod(ReleaseSnapshot),
+  PauseDownload {
     id: String,
   },
-  GetModUpdated {
+  ResumeDownload {
     id: String,
-    update: DownloadUpdate,
   },
   InstallMod {
     path: PathBuf,
     id: String,
-    ty: String,
+    version: ModVersion,
+    ty: Option<ArchiveType>,
+  },
+  // 下载、检查和解压共用
+  OperationUpdated {
+    id: String,
+    update: OperationUpdate,
+  },
+  CancelInstall {
+    id: String,
+  },
+  RetryTask {
+    id: String,
   },
-  InstallModUpdated {
+  DismissFailedTask {
     id: String,
-    update: InstallUpdate,
   },
   UninstallMod {
     id: String,
@@ -54,40 +205,118 @@ pub enum Message {
     id: String,
     update: UninstallUpdate,
   },
-  ModManagerReady {
-    mod_manager: ModManager,
+  CopyBatchFailures,
+  DismissBatchSummary,
+  PendingQueueLoaded(PendingQueue),
+  InstallLocalArchive,
+  AddLocalArchive(PathBuf),
+  LocalArchiveReady {
+    path: PathBuf,
+    id: String,
+    version: String,
+  },
+  ClearTaskHistory,
+  // version不为空时是等待确认安装的本地压缩包
+  PreviewArchive {
+    id: String,
+    path: PathBuf,
+    version: Option<String>,
+  },
+  ArchivePreviewed {
+    path: PathBuf,
+    result: Result<Box<ArchiveTree>, String>,
+  },
+  ClosePreview {
+    install: bool,
+  },
+}
+
+// 设置的修改和保存
+#[derive(Debug, Clone)]
+pub enum SettingsMsg {
+  SelectLanguage(Locale),
+  OpenSettings,
+  EditSettings(SettingsEdit),
+  ApplySettings,
+  CancelSettings,
+  ToggleArchiveCache(bool),
+  SettingsChanged(Settings),
+  SortMods { key: SortKey, descending: bool },
+}
+
+// 只影响界面显示的操作，以及窗口和通知
+#[derive(Debug, Clone)]
+pub enum UiMsg {
+  Warning {
+    title: String,
+    text: String,
   },
-  AddInstallMod {
+  Info {
+    title: String,
+    text: String,
+  },
+  OpenUrl(Url),
+  SelectModFilter(ModFilter),
+  ModQueryChanged(String),
+  SelectUpdatable,
+  SelectNotInstalled,
+  ClearSelection,
+  ImportSelection,
+  SelectionLoaded(String),
+  ShareInstalledMods,
+  CopyModId {
     id: String,
   },
-  RemoveInstallMod {
+  CopyModUrl {
     id: String,
   },
-  AddUninstallMod {
+  // 打开这个模组最近一次安装或卸载的日志
+  OpenInstallLog {
     id: String,
   },
-  RemoveUninstallMod {
+  CopyArchiveHash {
     id: String,
   },
-  AddCurrentMod {
+  TogglePreviewDir(PathBuf),
+  SetPendingAction {
     id: String,
+    action: PendingAction,
   },
-  RemoveCurrentMod {
+  SetForceReinstall {
     id: String,
+    force: bool,
   },
-  LoadRegistries {
-    urls: Vec<Url>,
+  DismissStatus,
+  DismissNotification(usize),
+  ExpireNotifications,
+  ToggleNotificationHistory,
+  OpenCrashReport,
+  DismissCrashReport,
+  ModListScrolled {
+    offset: f32,
+    height: f32,
   },
-  RegistryLoaded(Registry),
-  PrepareModManager {
-    game_dir_path: PathBuf,
+  WindowOpened(window::Id),
+  WindowChanged {
+    id: window::Id,
+    size: Option<Size>,
+    position: Option<Point>,
   },
-  QueueUpdateCurrentMods,
-  UpdateCurrentMods {
-    mod_manager: ModManager,
+  WindowSettled {
+    id: window::Id,
+    generation: usize,
   },
-  CurrentModsUpdated {
-    mod_manager: ModManager,
-    current_mods: HashSet<String>,
+  WindowMaximized(bool),
+  SelectModVersion {
+    id: String,
+    version: String,
+  },
+  AddCompatOverride {
+    id: String,
+  },
+  RemoveCompatOverride {
+    id: String,
   },
+  ToggleTaskPanel,
+  ShowTaskLog(usize),
 }
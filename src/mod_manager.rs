@@ -1,34 +1,155 @@
 use std::{
-  collections::HashMap,
-  path::{Path, PathBuf},
+  collections::{HashMap, HashSet},
+  path::{Component, Path, PathBuf},
   time::{SystemTime, SystemTimeError, UNIX_EPOCH},
 };
 
-use futures::FutureExt;
+use futures::AsyncReadExt;
 use serde::{Deserialize, Serialize};
-use tokio::{fs, io::AsyncWriteExt};
-use tokio_util::compat::TokioAsyncReadCompatExt;
+use tokio::{fs, io::AsyncWriteExt, sync::mpsc::UnboundedSender};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct Metadata {}
+use crate::{
+  data::{
+    archive::sha256_file, health::HealthReport, progress::Progress,
+    version::ModVersion,
+  },
+  i18n::t,
+  install_log::OperationLog,
+  paths::simplify_path,
+  trash,
+};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Metadata {
+  // 用户填写的备注
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub note: String,
+  // 解压的压缩包的sha256，用来核对大家装的是不是同一个文件
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub archive_sha256: Option<String>,
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Record {
   pub metadata: Option<Metadata>,
   pub update_time: u64,
-  pub version: String,
+  pub version: ModVersion,
   pub files: Vec<PathBuf>,
 }
 
+impl Record {
+  pub fn note(&self) -> &str {
+    self
+      .metadata
+      .as_ref()
+      .map_or("", |metadata| metadata.note.as_str())
+  }
+
+  pub fn archive_sha256(&self) -> Option<&str> {
+    self
+      .metadata
+      .as_ref()
+      .and_then(|metadata| metadata.archive_sha256.as_deref())
+  }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Records {
+  // 写入时的构建号，旧版本写的记录没有。名字不会和模组id冲突
+  #[serde(
+    rename = "$build",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub build: Option<u64>,
   #[serde(flatten)]
   pub records: HashMap<String, Record>,
 }
 
+impl Records {
+  // 记录是为其他构建写的，一般是更新后把res_mods复制了过来。
+  // 返回记录中的构建号，没有记下构建号的不算
+  pub fn build_mismatch(&self, build: u64) -> Option<u64> {
+    self.build.filter(|recorded| *recorded != build)
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct ModManager {
+  game_dir_path: PathBuf,
   res_mods_path: PathBuf,
+  build: u64,
+  events: Option<UnboundedSender<ModEvent>>,
+  // 解压时每次读写的字节数，整个条目不会一次读进内存
+  extract_buffer_size: usize,
+  // 设置后每次安装和卸载都在这里留下一份日志
+  log_dir: Option<PathBuf>,
+  // 设置后卸载的文件移到这里的回收站，而不是直接删除
+  trash_dir: Option<PathBuf>,
+}
+
+pub const DEFAULT_EXTRACT_BUFFER_SIZE: usize = 256 * 1024;
+// 超过这个大小的条目在解压过程中也汇报进度
+const ENTRY_PROGRESS_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+// 安装和卸载过程中的事件，其他工具可以借此得知模组的变化
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModEvent {
+  InstallStarted { id: String, version: String },
+  FileWritten { id: String, path: PathBuf },
+  InstallFinished { id: String, files: usize },
+  UninstallFinished { id: String },
+  RecordsWritten { path: PathBuf },
+}
+
+// 压缩包中的一项，安装和预览使用同一份列表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+  // 压缩包中原始的路径
+  pub name: String,
+  // 清理后相对res_mods的路径，实际写入的位置
+  pub path: PathBuf,
+  pub dir: bool,
+  pub size: u64,
+}
+
+// 不该出现在res_mods中的可执行文件
+const EXECUTABLE_EXTENSIONS: [&str; 9] = [
+  "exe", "dll", "bat", "cmd", "com", "scr", "ps1", "vbs", "msi",
+];
+
+impl ArchiveEntry {
+  // 原始路径想写到res_mods以外，清理后虽然不会，但压缩包可疑
+  pub fn escapes(&self) -> bool {
+    let name = self.name.replace('\\', "/");
+    name.starts_with('/')
+      || name.split('/').any(|component| component == "..")
+      || name
+        .split('/')
+        .next()
+        .is_some_and(|first| first.contains(':'))
+  }
+
+  pub fn is_executable(&self) -> bool {
+    !self.dir
+      && self
+        .path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+          EXECUTABLE_EXTENSIONS
+            .iter()
+            .any(|executable| ext.eq_ignore_ascii_case(executable))
+        })
+  }
+}
+
+// 安装时会碰到的已有文件。owner是记录了这个文件的其他模组，
+// 没有时是游戏自带或手动放进去的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+  pub file: PathBuf,
+  pub owner: Option<String>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -45,40 +166,115 @@ pub enum Error {
   FileConflict { file: PathBuf },
   #[error("ResModsDirNotFound: {game_dir_path}")]
   ResModsDirNotFound { game_dir_path: PathBuf },
+  #[error("ResModsOutsideGameDir: {res_mods_path}")]
+  ResModsOutsideGameDir { res_mods_path: PathBuf },
+  #[error("RecordExists: {id}")]
+  RecordExists { id: String },
 }
 
 impl ModManager {
-  pub fn try_from_game_dir(
+  // 取bin下编号最大的构建目录，bin为空或没有数字命名的目录时
+  // 都视为找不到res_mods
+  pub async fn try_from_game_dir(
     game_dir_path: &Path,
   ) -> Result<Self, Error> {
-    #[allow(clippy::manual_try_fold)]
-    let dir = std::fs::read_dir(game_dir_path.join("bin"))?.fold(
-      Err(Error::ResModsDirNotFound {
+    Self::try_from_game_dir_with(game_dir_path, false).await
+  }
+
+  // 保存的是res_mods的真实位置，之后的拼接和越界检查都基于它。
+  // 真实位置必须在游戏目录下；`allow_external`时也可以是
+  // res_mods本身这一层链接直接指向的别处，如另一个盘上的目录
+  pub async fn try_from_game_dir_with(
+    game_dir_path: &Path,
+    allow_external: bool,
+  ) -> Result<Self, Error> {
+    let mut builds = Vec::new();
+    let mut read_dir =
+      fs::read_dir(game_dir_path.join("bin")).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+      let Ok(build) =
+        entry.file_name().to_string_lossy().parse::<u64>()
+      else {
+        continue;
+      };
+      if entry.file_type().await?.is_dir() {
+        builds.push((build, entry.path()));
+      }
+    }
+    let Some((build, dir)) =
+      builds.into_iter().max_by_key(|(build, _)| *build)
+    else {
+      return Err(Error::ResModsDirNotFound {
         game_dir_path: game_dir_path.to_path_buf(),
-      }),
-      |x, y| {
-        let y = y?;
-        let Ok(y_num) =
-          y.file_name().to_string_lossy().to_string().parse::<u64>()
-        else {
-          return x;
-        };
-        let Ok(x) = x else {
-          return Ok(y);
-        };
-        let Ok(x_num) =
-          x.file_name().to_string_lossy().to_string().parse::<u64>()
-        else {
-          return Ok(y);
-        };
-        Ok(if x_num > y_num { x } else { y })
-      },
-    )?;
+      });
+    };
+    let res_mods_path =
+      resolve_res_mods(game_dir_path, &dir, allow_external).await?;
     Ok(Self {
-      res_mods_path: dir.path().join("res_mods"),
+      game_dir_path: game_dir_path.to_path_buf(),
+      res_mods_path,
+      build,
+      events: None,
+      extract_buffer_size: DEFAULT_EXTRACT_BUFFER_SIZE,
+      log_dir: None,
+      trash_dir: None,
     })
   }
 
+  pub fn set_event_sink(&mut self, sink: UnboundedSender<ModEvent>) {
+    self.events = Some(sink);
+  }
+
+  pub fn set_extract_buffer_size(&mut self, size: usize) {
+    self.extract_buffer_size = size.max(1);
+  }
+
+  pub fn set_log_dir(&mut self, dir: PathBuf) {
+    self.log_dir = Some(dir);
+  }
+
+  pub fn set_trash_dir(&mut self, dir: Option<PathBuf>) {
+    self.trash_dir = dir;
+  }
+
+  // 接收端已经关闭时直接丢弃
+  fn emit(&self, event: ModEvent) {
+    if let Some(events) = self.events.as_ref() {
+      let _ = events.send(event);
+    }
+  }
+
+  // 日志写不下来不影响安装和卸载本身
+  async fn write_log(&self, log: OperationLog, result: &str) {
+    let Some(dir) = self.log_dir.as_ref() else {
+      return;
+    };
+    if let Err(err) = log.write(dir, result).await {
+      tracing::warn!(%err, "failed to write operation log");
+    }
+  }
+
+  pub fn game_dir_path(&self) -> &Path {
+    &self.game_dir_path
+  }
+
+  pub fn res_mods_path(&self) -> &Path {
+    &self.res_mods_path
+  }
+
+  pub fn build(&self) -> u64 {
+    self.build
+  }
+
+  // 优先读取game_info.xml中的客户端版本，读不到时退回bin下的构建号
+  pub async fn game_version(&self) -> String {
+    fs::read_to_string(self.game_dir_path.join("game_info.xml"))
+      .await
+      .ok()
+      .and_then(|content| parse_game_info_version(&content))
+      .unwrap_or_else(|| self.build.to_string())
+  }
+
   pub async fn ensure_records(&mut self) -> Result<(), Error> {
     let mut file = match fs::File::options()
       .create_new(true)
@@ -94,8 +290,12 @@ impl ModManager {
       }
       Ok(file) => file,
     };
+    let records = Records {
+      build: Some(self.build),
+      ..Default::default()
+    };
     file
-      .write_all(serde_json::to_vec(&Records::default())?.as_slice())
+      .write_all(serde_json::to_vec(&records)?.as_slice())
       .await?;
     Ok(())
   }
@@ -108,110 +308,358 @@ impl ModManager {
     )?)
   }
 
+  // 只取一个模组的记录，安装后用来更新界面
+  pub async fn record(
+    &self,
+    id: &str,
+  ) -> Result<Option<Record>, Error> {
+    Ok(self.records().await?.records.remove(id))
+  }
+
+  // 同时记下当前的构建号
   async fn write_records(
     &mut self,
-    records: &Records,
+    records: &mut Records,
   ) -> Result<(), Error> {
-    fs::write(
-      self.res_mods_path.join(".kmmgr.json"),
-      serde_json::to_vec(&records)?,
-    )
-    .await?;
+    records.build = Some(self.build);
+    let path = self.res_mods_path.join(".kmmgr.json");
+    fs::write(&path, serde_json::to_vec(&records)?).await?;
+    self.emit(ModEvent::RecordsWritten { path });
     Ok(())
   }
 
+  // 确认记录适用于当前构建，只重新写入构建号
+  pub async fn stamp_build(&mut self) -> Result<(), Error> {
+    let mut records = self.records().await?;
+    self.write_records(&mut records).await
+  }
+
+  // 没有安装记录的模组不保存备注
+  pub async fn set_note(
+    &mut self,
+    id: &str,
+    note: &str,
+  ) -> Result<(), Error> {
+    let mut records = self.records().await?;
+    let Some(record) = records.records.get_mut(id) else {
+      return Ok(());
+    };
+    record.metadata.get_or_insert_default().note = note.to_string();
+    self.write_records(&mut records).await
+  }
+
+  /// 把zip压缩包解压到res_mods并写入安装记录，返回写入的文件数。
+  ///
+  /// ```
+  /// # use async_zip::{Compression, ZipEntryBuilder};
+  /// # use async_zip::base::write::ZipFileWriter;
+  /// # use korabli_mod_manager::mod_manager::ModManager;
+  /// # #[tokio::main]
+  /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  /// let game_dir = std::env::temp_dir().join("kmm-doc-install");
+  /// # let _ = std::fs::remove_dir_all(&game_dir);
+  /// let res_mods = game_dir.join("bin").join("1000").join("res_mods");
+  /// std::fs::create_dir_all(&res_mods)?;
+  ///
+  /// let mut writer = ZipFileWriter::new(Vec::new());
+  /// let entry = ZipEntryBuilder::new(
+  ///   "example.txt".to_string().into(),
+  ///   Compression::Stored,
+  /// );
+  /// writer.write_entry_whole(entry, b"hello").await?;
+  /// let archive = game_dir.join("example.zip");
+  /// std::fs::write(&archive, writer.close().await?)?;
+  ///
+  /// let mut mod_manager = ModManager::try_from_game_dir(&game_dir).await?;
+  /// mod_manager.ensure_records().await?;
+  /// mod_manager.install_zip_mod(&archive, "example", "1.0").await?;
+  ///
+  /// assert!(res_mods.join("example.txt").is_file());
+  /// let records = mod_manager.records().await?;
+  /// assert_eq!(records.records["example"].version, "1.0");
+  /// # Ok(())
+  /// # }
+  /// ```
   pub async fn install_zip_mod(
     &mut self,
     mod_path: &Path,
     id: &str,
     version: &str,
-  ) -> Result<(), Error> {
+  ) -> Result<usize, Error> {
+    self
+      .install_zip_mod_with_progress(mod_path, id, version, |_| {})
+      .await
+  }
+
+  // 进度按已经写入的字节数计算，总量是所有文件解压后的大小
+  pub async fn install_zip_mod_with_progress(
+    &mut self,
+    mod_path: &Path,
+    id: &str,
+    version: &str,
+    on_progress: impl FnMut(Progress),
+  ) -> Result<usize, Error> {
+    let mut log = OperationLog::install(id);
+    log.line(t!("install_log.version", version = version));
+    log.line(t!("install_log.archive", path = mod_path.display()));
+    let result = self
+      .extract_zip_mod(mod_path, id, version, &mut log, on_progress)
+      .await;
+    let summary = match &result {
+      Ok(count) => t!("install_log.installed", count = count),
+      Err(err) => t!("install_log.failed", error = err),
+    };
+    self.write_log(log, &summary).await;
+    result
+  }
+
+  async fn extract_zip_mod(
+    &mut self,
+    mod_path: &Path,
+    id: &str,
+    version: &str,
+    log: &mut OperationLog,
+    mut on_progress: impl FnMut(Progress),
+  ) -> Result<usize, Error> {
+    self.emit(ModEvent::InstallStarted {
+      id: id.to_owned(),
+      version: version.to_owned(),
+    });
     let mut record = Record {
       metadata: None,
-      version: version.to_string(),
+      version: version.into(),
       update_time: SystemTime::now()
         .duration_since(UNIX_EPOCH)?
         .as_secs(),
       files: Vec::new(),
     };
+    let mut records = self.records().await?;
+    // 升级/降级时保留用户的备注
+    record.metadata = records
+      .records
+      .get(id)
+      .and_then(|record| record.metadata.to_owned());
+    record.metadata.get_or_insert_default().archive_sha256 =
+      Some(sha256_file(mod_path).await?);
+    // 自身旧版本的文件，新版本不再包含的最后删掉
+    let own_files = records
+      .records
+      .get(id)
+      .map(|record| {
+        record.files.iter().cloned().collect::<HashSet<_>>()
+      })
+      .unwrap_or_default();
     let zip_mod =
       async_zip::tokio::read::fs::ZipFileReader::new(mod_path)
         .await?;
-    let mut tasks = Vec::new();
-
-    for (index, entry) in zip_mod.file().entries().iter().enumerate()
-    {
-      let sanitized_file_path =
-        sanitize_file_path(entry.filename().as_str().unwrap());
+    let entries = zip_entries(zip_mod.file())?;
+    let conflicts =
+      find_conflicts(&self.res_mods_path, &records, id, &entries);
+    for conflict in conflicts.iter() {
+      log.line(t!(
+        "install_log.conflict",
+        path = conflict.file.display(),
+        owner = conflict
+          .owner
+          .as_deref()
+          .unwrap_or(t!("install_log.untracked"))
+      ));
+    }
+    if let Some(conflict) = conflicts.into_iter().next() {
+      return Err(Error::FileConflict {
+        file: conflict.file,
+      });
+    }
+    let mut progress = Progress {
+      current: 0,
+      max: Some(
+        entries
+          .iter()
+          .filter(|entry| !entry.dir)
+          .map(|entry| entry.size)
+          .sum(),
+      ),
+    };
+    let mut buffer = vec![0; self.extract_buffer_size];
 
-      record.files.push(sanitized_file_path.to_owned());
+    for (index, entry) in entries.into_iter().enumerate() {
+      record.files.push(entry.path.to_owned());
 
-      let path =
-        self.res_mods_path.join(sanitized_file_path.as_path());
+      let path = self.res_mods_path.join(entry.path.as_path());
 
-      if entry.dir()? {
-        if path.exists() {
-          continue;
+      if entry.dir {
+        if !path.exists() {
+          fs::create_dir_all(path).await?;
+        }
+        log.line(t!(
+          "install_log.directory",
+          path = entry.path.display()
+        ));
+        continue;
+      }
+      let mut reader = zip_mod.reader_without_entry(index).await?;
+      let mut writer = fs::File::options()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(&path)
+        .await?;
+      // 很大的条目要写很久，中途也让进度条动起来
+      let large = entry.size >= ENTRY_PROGRESS_THRESHOLD;
+      loop {
+        let len = reader.read(&mut buffer).await?;
+        if len == 0 {
+          break;
         }
-        tasks.push(
-          async move {
-            fs::create_dir_all(path).await?;
-            Ok(())
-          }
-          .boxed(),
-        );
-      } else {
-        if path.exists() {
-          return Err(Error::FileConflict {
-            file: sanitized_file_path.to_owned(),
-          });
+        writer.write_all(&buffer[..len]).await?;
+        progress.current += len as u64;
+        if large {
+          on_progress(progress);
         }
-
-        let mut reader = zip_mod.reader_without_entry(index).await?;
-
-        tasks.push(
-          async move {
-            let mut writer = fs::File::options()
-              .create(true)
-              .truncate(true)
-              .write(true)
-              .open(path)
-              .await?
-              .compat();
-            futures::io::copy(&mut reader, &mut writer).await?;
-            Ok::<(), Error>(())
-          }
-          .boxed(),
-        );
       }
+      writer.flush().await?;
+      if !large {
+        on_progress(progress);
+      }
+      log.line(t!("install_log.write", path = entry.path.display()));
+      self.emit(ModEvent::FileWritten {
+        id: id.to_owned(),
+        path,
+      });
     }
 
-    for task in tasks {
-      task.await?;
+    // 清理旧版本中新版本不再包含的文件
+    for file in
+      own_files.difference(&record.files.iter().cloned().collect())
+    {
+      let file_path = self.res_mods_path.join(file.as_path());
+      if file_path.is_file() {
+        fs::remove_file(file_path.as_path()).await?;
+        log.line(t!("install_log.remove_old", path = file.display()));
+      }
     }
-    let mut records = self.records().await?;
 
+    // 返回写入的文件数，供任务日志使用
+    let count = record.files.len();
     records.records.insert(id.to_owned(), record);
 
-    self.write_records(&records).await?;
+    self.write_records(&mut records).await?;
+    self.emit(ModEvent::InstallFinished {
+      id: id.to_owned(),
+      files: count,
+    });
 
+    Ok(count)
+  }
+
+  // 取消安装时删除本次解压出来的文件；冲突检查保证了不属于
+  // 自身旧版本的文件都是这次写入的，被覆盖的旧版本文件无法恢复
+  pub async fn rollback_zip_mod(
+    &mut self,
+    mod_path: &Path,
+    id: &str,
+  ) -> Result<(), Error> {
+    let own_files = self
+      .records()
+      .await?
+      .records
+      .get(id)
+      .map(|record| {
+        record.files.iter().cloned().collect::<HashSet<_>>()
+      })
+      .unwrap_or_default();
+    let zip_mod =
+      async_zip::tokio::read::fs::ZipFileReader::new(mod_path)
+        .await?;
+    for entry in zip_entries(zip_mod.file())? {
+      if entry.dir || own_files.contains(&entry.path) {
+        continue;
+      }
+      let path = self.res_mods_path.join(entry.path);
+      if path.is_file() {
+        fs::remove_file(path).await?;
+      }
+    }
     Ok(())
   }
 
+  /// 删除安装记录中的文件并移除记录，没有记录时返回`false`。
+  ///
+  /// ```
+  /// # use async_zip::{Compression, ZipEntryBuilder};
+  /// # use async_zip::base::write::ZipFileWriter;
+  /// # use korabli_mod_manager::mod_manager::ModManager;
+  /// # #[tokio::main]
+  /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  /// let game_dir = std::env::temp_dir().join("kmm-doc-uninstall");
+  /// # let _ = std::fs::remove_dir_all(&game_dir);
+  /// let res_mods = game_dir.join("bin").join("1000").join("res_mods");
+  /// std::fs::create_dir_all(&res_mods)?;
+  ///
+  /// let mut writer = ZipFileWriter::new(Vec::new());
+  /// let entry = ZipEntryBuilder::new(
+  ///   "example.txt".to_string().into(),
+  ///   Compression::Stored,
+  /// );
+  /// writer.write_entry_whole(entry, b"hello").await?;
+  /// let archive = game_dir.join("example.zip");
+  /// std::fs::write(&archive, writer.close().await?)?;
+  ///
+  /// let mut mod_manager = ModManager::try_from_game_dir(&game_dir).await?;
+  /// mod_manager.ensure_records().await?;
+  /// mod_manager.install_zip_mod(&archive, "example", "1.0").await?;
+  ///
+  /// assert!(mod_manager.uninstall_mod("example").await?);
+  ///
+  /// assert!(!res_mods.join("example.txt").exists());
+  /// let records = mod_manager.records().await?;
+  /// assert!(!records.records.contains_key("example"));
+  /// assert!(!mod_manager.uninstall_mod("example").await?);
+  /// # Ok(())
+  /// # }
+  /// ```
   pub async fn uninstall_mod(
     &mut self,
     id: &str,
+  ) -> Result<bool, Error> {
+    let mut log = OperationLog::uninstall(id);
+    let result = self.remove_mod_files(id, &mut log).await;
+    // 没有记录时什么都没做，不留日志
+    let summary = match &result {
+      Ok(false) => return Ok(false),
+      Ok(true) => t!("install_log.succeeded").to_string(),
+      Err(err) => t!("install_log.failed", error = err),
+    };
+    self.write_log(log, &summary).await;
+    result
+  }
+
+  async fn remove_mod_files(
+    &mut self,
+    id: &str,
+    log: &mut OperationLog,
   ) -> Result<bool, Error> {
     let mut records = self.records().await?;
-    let Some(record) = records.records.get(id) else {
-      records.records.remove(id);
-      self.write_records(&records).await?;
+    let Some(record) = records.records.remove(id) else {
       return Ok(false);
     };
+    log.line(t!("install_log.version", version = record.version));
+    let trash_entry = match self.trash_dir.as_ref() {
+      Some(dir) => {
+        let entry = trash::create_entry(dir, id, &record).await?;
+        log.line(t!(
+          "install_log.trash_entry",
+          path = entry.display()
+        ));
+        Some(entry)
+      }
+      None => None,
+    };
 
-    for file_path in record.files.iter() {
-      let file_path = self.res_mods_path.join(file_path.as_path());
+    for file in record.files.iter() {
+      let file_path = self.res_mods_path.join(file.as_path());
       if !file_path.exists() {
+        log.line(t!("install_log.missing", path = file.display()));
         continue;
       }
 
@@ -219,11 +667,295 @@ impl ModManager {
         // TODO: 最好还是清理一下文件夹
         continue;
       }
-      fs::remove_file(file_path.as_path()).await?;
+      match trash_entry.as_ref() {
+        Some(entry) => {
+          trash::move_file(&file_path, &entry.join(file)).await?;
+          log.line(t!("install_log.trashed", path = file.display()));
+        }
+        None => {
+          fs::remove_file(file_path.as_path()).await?;
+          log.line(t!("install_log.removed", path = file.display()));
+        }
+      }
     }
 
+    self.write_records(&mut records).await?;
+    self.emit(ModEvent::UninstallFinished { id: id.to_owned() });
     Ok(true)
   }
+
+  // 把回收站中的一项放回res_mods并恢复安装记录，返回模组id。
+  // 已有同名的记录或任何文件已经存在时不做改动；
+  // 放回后从回收站中删除这一项
+  pub async fn restore_trashed(
+    &mut self,
+    entry: &Path,
+  ) -> Result<String, Error> {
+    let meta = trash::read_meta(entry).await?;
+    let mut records = self.records().await?;
+    if records.records.contains_key(&meta.id) {
+      return Err(Error::RecordExists { id: meta.id });
+    }
+    // 卸载时已经不存在的文件不在回收站里
+    let files = meta
+      .record
+      .files
+      .iter()
+      .filter(|file| {
+        file
+          .components()
+          .all(|component| matches!(component, Component::Normal(_)))
+      })
+      .filter(|file| entry.join(file).is_file())
+      .collect::<Vec<_>>();
+    if let Some(file) = files
+      .iter()
+      .find(|file| self.res_mods_path.join(file).exists())
+    {
+      return Err(Error::FileConflict {
+        file: file.to_path_buf(),
+      });
+    }
+    for file in files {
+      let target = self.res_mods_path.join(file);
+      if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+      }
+      fs::copy(entry.join(file), target).await?;
+    }
+    records.records.insert(meta.id.to_owned(), meta.record);
+    self.write_records(&mut records).await?;
+    fs::remove_dir_all(entry).await?;
+    Ok(meta.id)
+  }
+
+  // 遍历res_mods，返回相对路径的文件和目录。符号链接不跟随，
+  // 当作文件
+  pub async fn scan_res_mods(
+    &self,
+  ) -> Result<(Vec<PathBuf>, Vec<PathBuf>), Error> {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut stack = vec![PathBuf::new()];
+    while let Some(dir) = stack.pop() {
+      let mut read_dir =
+        fs::read_dir(self.res_mods_path.join(&dir)).await?;
+      while let Some(entry) = read_dir.next_entry().await? {
+        let path = dir.join(entry.file_name());
+        if entry.file_type().await?.is_dir() {
+          dirs.push(path.to_owned());
+          stack.push(path);
+        } else {
+          files.push(path);
+        }
+      }
+    }
+    Ok((files, dirs))
+  }
+
+  pub async fn health_check(&self) -> Result<HealthReport, Error> {
+    let records = self.records().await?;
+    let (files, dirs) = self.scan_res_mods().await?;
+    Ok(HealthReport::build(&records, &files, &dirs))
+  }
+
+  // 把没有记录的文件记到`id`名下，之后可以像模组一样卸载
+  pub async fn adopt_files(
+    &mut self,
+    id: &str,
+    files: &[PathBuf],
+  ) -> Result<(), Error> {
+    let mut records = self.records().await?;
+    let update_time =
+      SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let record =
+      records.records.entry(id.to_owned()).or_insert(Record {
+        metadata: None,
+        update_time,
+        version: "0".into(),
+        files: Vec::new(),
+      });
+    for file in files {
+      if !record.files.contains(file) {
+        record.files.push(file.to_owned());
+      }
+    }
+    self.write_records(&mut records).await
+  }
+}
+
+// 取`name="client"`所在标签的installed属性，属性顺序不限
+fn parse_game_info_version(content: &str) -> Option<String> {
+  let offset = content.find(r#"name="client""#)?;
+  let start = content[..offset].rfind('<')?;
+  let end = offset + content[offset..].find('>')?;
+  let tag = &content[start..end];
+  let (_, rest) = tag.split_once(r#" installed=""#)?;
+  let (version, _) = rest.split_once('"')?;
+  (!version.is_empty()).then(|| version.to_string())
+}
+
+// 读取zip的目录，文件名无法解码时出错
+pub async fn list_zip_entries(
+  path: &Path,
+) -> Result<Vec<ArchiveEntry>, Error> {
+  let zip =
+    async_zip::tokio::read::fs::ZipFileReader::new(path).await?;
+  zip_entries(zip.file())
+}
+
+fn zip_entries(
+  file: &async_zip::ZipFile,
+) -> Result<Vec<ArchiveEntry>, Error> {
+  file
+    .entries()
+    .iter()
+    .map(|entry| {
+      let name = entry.filename().as_str()?.to_string();
+      Ok(ArchiveEntry {
+        path: sanitize_file_path(&name),
+        dir: entry.dir()?,
+        size: entry.uncompressed_size(),
+        name,
+      })
+    })
+    .collect()
+}
+
+// 规范化res_mods的路径并检查它的位置。res_mods还不存在时
+// 按构建目录规范化，之后在那里创建
+async fn resolve_res_mods(
+  game_dir_path: &Path,
+  build_dir: &Path,
+  allow_external: bool,
+) -> Result<PathBuf, Error> {
+  let game_dir =
+    fs::canonicalize(game_dir_path).await.map(simplify_path)?;
+  let path = build_dir.join("res_mods");
+  let (res_mods, link_target) =
+    match fs::symlink_metadata(&path).await {
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        let build_dir =
+          fs::canonicalize(build_dir).await.map(simplify_path)?;
+        (build_dir.join("res_mods"), None)
+      }
+      Err(err) => return Err(err.into()),
+      Ok(metadata) => {
+        // Windows上目录联接也算作链接，可以读出目标，
+        // 读出的是带`\\?\`前缀的绝对路径
+        let link_target = if is_link(&metadata) {
+          let target = fs::read_link(&path).await?;
+          Some(simplify_path(build_dir.join(target)))
+        } else {
+          None
+        };
+        (
+          fs::canonicalize(&path).await.map(simplify_path)?,
+          link_target,
+        )
+      }
+    };
+  if res_mods_contained(
+    &game_dir,
+    &res_mods,
+    link_target.as_deref(),
+    allow_external,
+  ) {
+    Ok(res_mods)
+  } else {
+    Err(Error::ResModsOutsideGameDir {
+      res_mods_path: res_mods,
+    })
+  }
+}
+
+#[cfg(windows)]
+fn is_link(metadata: &std::fs::Metadata) -> bool {
+  use std::os::windows::fs::FileTypeExt;
+  // 目录联接和目录符号链接都是is_symlink_dir
+  let file_type = metadata.file_type();
+  file_type.is_symlink() || file_type.is_symlink_dir()
+}
+
+#[cfg(not(windows))]
+fn is_link(metadata: &std::fs::Metadata) -> bool {
+  metadata.file_type().is_symlink()
+}
+
+// `link_target`是res_mods本身作为链接时写在链接里的目标，
+// 和真实位置相同说明只跳了一次。路径都已经规范化
+pub fn res_mods_contained(
+  game_dir: &Path,
+  res_mods: &Path,
+  link_target: Option<&Path>,
+  allow_external: bool,
+) -> bool {
+  path_starts_with(res_mods, game_dir)
+    || (allow_external
+      && link_target
+        .is_some_and(|target| same_path(target, res_mods)))
+}
+
+// Windows的路径不区分大小写，目录联接里写的目标可能和
+// 规范化后的真实位置大小写不同
+#[cfg(windows)]
+fn same_path(a: &Path, b: &Path) -> bool {
+  a.as_os_str().eq_ignore_ascii_case(b.as_os_str())
+}
+
+#[cfg(not(windows))]
+fn same_path(a: &Path, b: &Path) -> bool {
+  a == b
+}
+
+#[cfg(windows)]
+fn path_starts_with(path: &Path, base: &Path) -> bool {
+  let mut components = path.components();
+  base.components().all(|base| {
+    components.next().is_some_and(|component| {
+      component.as_os_str().eq_ignore_ascii_case(base.as_os_str())
+    })
+  })
+}
+
+#[cfg(not(windows))]
+fn path_starts_with(path: &Path, base: &Path) -> bool {
+  path.starts_with(base)
+}
+
+// 安装的预演：已经存在、又不属于该模组旧版本的文件。
+// 升级/降级时覆盖自身旧版本的文件不算冲突
+pub fn find_conflicts(
+  res_mods_path: &Path,
+  records: &Records,
+  id: &str,
+  entries: &[ArchiveEntry],
+) -> Vec<Conflict> {
+  let own_files = records
+    .records
+    .get(id)
+    .map(|record| record.files.iter().collect::<HashSet<_>>())
+    .unwrap_or_default();
+  entries
+    .iter()
+    .filter(|entry| {
+      !entry.dir
+        && !own_files.contains(&entry.path)
+        && res_mods_path.join(&entry.path).exists()
+    })
+    .map(|entry| Conflict {
+      file: entry.path.to_owned(),
+      owner: records
+        .records
+        .iter()
+        .filter(|(other, record)| {
+          *other != id && record.files.contains(&entry.path)
+        })
+        .map(|(other, _)| other)
+        .min()
+        .cloned(),
+    })
+    .collect()
 }
 
 fn sanitize_file_path(path: &str) -> PathBuf {
@@ -235,3 +967,129 @@ fn sanitize_file_path(path: &str) -> PathBuf {
     .map(sanitize_filename::sanitize)
     .collect()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn res_mods_inside_game_dir() {
+    let game_dir = Path::new("/games/korabli");
+    let res_mods = Path::new("/games/korabli/bin/1000/res_mods");
+    assert!(res_mods_contained(game_dir, res_mods, None, false));
+    // 链接到游戏目录中的其他位置也可以
+    let moved = Path::new("/games/korabli/mods");
+    assert!(res_mods_contained(game_dir, moved, Some(moved), false));
+    // 只是名字以游戏目录开头的兄弟目录不算
+    let sibling = Path::new("/games/korabli2/res_mods");
+    assert!(!res_mods_contained(game_dir, sibling, None, true));
+  }
+
+  #[test]
+  fn external_res_mods_needs_setting() {
+    let game_dir = Path::new("/games/korabli");
+    let external = Path::new("/mnt/d/res_mods");
+    assert!(!res_mods_contained(
+      game_dir,
+      external,
+      Some(external),
+      false
+    ));
+    assert!(res_mods_contained(
+      game_dir,
+      external,
+      Some(external),
+      true
+    ));
+  }
+
+  // 链接目标还要再跳一次，或者是上层目录被链接出去的都不允许
+  #[test]
+  fn external_res_mods_allows_one_hop() {
+    let game_dir = Path::new("/games/korabli");
+    let external = Path::new("/mnt/d/res_mods");
+    assert!(!res_mods_contained(
+      game_dir,
+      external,
+      Some(Path::new("/mnt/c/link")),
+      true
+    ));
+    assert!(!res_mods_contained(game_dir, external, None, true));
+  }
+
+  #[test]
+  fn game_info_version() {
+    let cases = [
+      (
+        r#"<protocol name="game_info">
+          <game>
+            <part_versions>
+              <version name="locale" installed="RU_2024"/>
+              <version name="client" installed="13.5.0.1234567"/>
+            </part_versions>
+          </game>
+        </protocol>"#,
+        Some("13.5.0.1234567"),
+      ),
+      // 属性的顺序和其他属性不影响
+      (
+        r#"<version installed="13.6.0" name="client" available="x">"#,
+        Some("13.6.0"),
+      ),
+      (
+        r#"<version name="client" not_installed="1" installed="2">"#,
+        Some("2"),
+      ),
+      (
+        r#"<version name="client" available="13.7" installed="13.6.0">"#,
+        Some("13.6.0"),
+      ),
+      // 没有客户端版本
+      (r#"<version name="locale" installed="RU"/>"#, None),
+      ("", None),
+      // 客户端这一项没有installed，不能取后面其他项的
+      (
+        r#"<version name="client"/><version name="sdk" installed="1.0"/>"#,
+        None,
+      ),
+      (r#"<version name="client" installed=""/>"#, None),
+      // 不完整的内容
+      (r#"<version name="client" installed="13.5"#, None),
+      (r#"<version name="client" installed="13.5""#, None),
+      ("not xml at all", None),
+    ];
+    for (content, version) in cases {
+      assert_eq!(
+        parse_game_info_version(content).as_deref(),
+        version,
+        "{}",
+        content
+      );
+    }
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn windows_paths_ignore_case() {
+    let game_dir = Path::new(r"C:\Games\Korabli");
+    let res_mods = Path::new(r"c:\games\korabli\bin\1000\res_mods");
+    assert!(res_mods_contained(game_dir, res_mods, None, false));
+    let sibling = Path::new(r"C:\Games\Korabli_PT\res_mods");
+    assert!(!res_mods_contained(game_dir, sibling, None, false));
+    // 联接里的目标和真实位置只有大小写不同
+    let external = Path::new(r"D:\Mods\res_mods");
+    let target = Path::new(r"d:\mods\RES_MODS");
+    assert!(res_mods_contained(
+      game_dir,
+      external,
+      Some(target),
+      true
+    ));
+    assert!(!res_mods_contained(
+      game_dir,
+      external,
+      Some(target),
+      false
+    ));
+  }
+}
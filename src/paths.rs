@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+
+// 取不到系统缓存目录时退回临时目录
+pub fn cache_dir() -> PathBuf {
+  dirs::cache_dir()
+    .unwrap_or_else(std::env::temp_dir)
+    .join("korabli-mod-manager")
+}
+
+pub fn downloads_dir() -> PathBuf {
+  cache_dir().join("downloads")
+}
+
+// 开启缓存后压缩包下载到这里，安装后不会被删除
+pub fn archive_cache_dir() -> PathBuf {
+  cache_dir().join("archives")
+}
+
+pub fn data_dir() -> PathBuf {
+  dirs::data_dir()
+    .unwrap_or_else(std::env::temp_dir)
+    .join("korabli-mod-manager")
+}
+
+pub fn config_dir() -> PathBuf {
+  dirs::config_dir()
+    .unwrap_or_else(std::env::temp_dir)
+    .join("korabli-mod-manager")
+}
+
+pub fn settings_path() -> PathBuf {
+  config_dir().join("settings.toml")
+}
+
+pub fn logs_dir() -> PathBuf {
+  data_dir().join("logs")
+}
+
+// 安全删除模式下卸载的文件
+pub fn trash_dir() -> PathBuf {
+  data_dir().join("trash")
+}
+
+pub fn pending_queue_path() -> PathBuf {
+  data_dir().join("pending.json")
+}
+
+// 控制接口的令牌，只有能读到这个文件的进程才能操作
+pub fn control_token_path() -> PathBuf {
+  data_dir().join("control_token")
+}
+
+// 规范化路径，链接都解析到真实位置。Windows上去掉`\\?\`前缀，
+// 和用户输入的路径写法保持一致
+pub fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+  std::fs::canonicalize(path).map(simplify_path)
+}
+
+#[cfg(windows)]
+pub fn simplify_path(path: PathBuf) -> PathBuf {
+  use std::path::{Component, Prefix};
+  // 只处理`\\?\C:\`这样的本地盘符，UNC路径去掉前缀后含义会变
+  let verbatim_disk = matches!(
+    path.components().next(),
+    Some(Component::Prefix(prefix))
+      if matches!(prefix.kind(), Prefix::VerbatimDisk(_))
+  );
+  if !verbatim_disk {
+    return path;
+  }
+  let simplified = path
+    .to_str()
+    .and_then(|path| path.strip_prefix(r"\\?\"))
+    .map(PathBuf::from);
+  simplified.unwrap_or(path)
+}
+
+#[cfg(not(windows))]
+pub fn simplify_path(path: PathBuf) -> PathBuf {
+  path
+}
+
+// `path`所在分区的剩余空间，查询失败时为None
+pub async fn available_space(path: PathBuf) -> Option<u64> {
+  tokio::task::spawn_blocking(move || {
+    fs2::available_space(Path::new(&path))
+  })
+  .await
+  .ok()?
+  .inspect_err(
+    |err| tracing::warn!(%err, "failed to query free space"),
+  )
+  .ok()
+}
@@ -1,3 +1,94 @@
 pub mod download;
 pub mod install;
-pub mod uninstall;
+
+use std::{fmt, time::Instant};
+
+use crate::{data::progress::Progress, i18n::t};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskStatus {
+  Queued,
+  Running { progress: Option<f32> },
+  Paused,
+  Failed,
+  Finished,
+}
+
+impl TaskStatus {
+  pub fn label(&self) -> &'static str {
+    match self {
+      Self::Queued => t!("task_status.queued"),
+      Self::Running { .. } => t!("task_status.running"),
+      Self::Paused => t!("task_status.paused"),
+      Self::Failed => t!("task_status.failed"),
+      Self::Finished => t!("task_status.finished"),
+    }
+  }
+
+  pub fn progress(&self) -> Option<f32> {
+    match self {
+      Self::Running { progress } => *progress,
+      Self::Finished => Some(1.),
+      _ => None,
+    }
+  }
+}
+
+// 任务通过sipper汇报的事件，日志和进度共用一个通道
+#[derive(Debug, Clone)]
+pub enum TaskEvent {
+  Progress(Progress),
+  Log(String),
+}
+
+// 任务的关键事件，失败后可以导出附在问题报告里
+#[derive(Debug, Clone)]
+pub struct TaskLog {
+  started: Instant,
+  lines: Vec<String>,
+}
+
+impl Default for TaskLog {
+  fn default() -> Self {
+    Self {
+      started: Instant::now(),
+      lines: Vec::new(),
+    }
+  }
+}
+
+impl TaskLog {
+  pub fn push(&mut self, line: impl Into<String>) {
+    self.lines.push(format!(
+      "[{:>9.3}s] {}",
+      self.started.elapsed().as_secs_f32(),
+      line.into()
+    ));
+  }
+
+  // 接上另一个阶段的日志，各行保留原来的时间
+  pub fn append(&mut self, other: &TaskLog) {
+    self.lines.extend(other.lines.iter().cloned());
+  }
+
+  // 连同底层原因一起记录
+  pub fn error(&mut self, err: &dyn std::error::Error) {
+    let mut line = t!("task_log.error", error = err);
+    let mut source = err.source();
+    while let Some(err) = source {
+      line.push_str("\n  ");
+      line.push_str(&t!("task_log.cause", error = err));
+      source = err.source();
+    }
+    self.push(line);
+  }
+}
+
+impl fmt::Display for TaskLog {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for line in &self.lines {
+      writeln!(f, "{}", line)?;
+    }
+    Ok(())
+  }
+}
@@ -1,21 +1,30 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+  collections::HashSet,
+  io::SeekFrom,
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::Duration,
+};
 
 use futures::StreamExt;
-use iced::{
-  Task,
-  task::{self, Straw, sipper},
+use reqwest::Client;
+use tokio::{
+  fs,
+  io::{AsyncSeekExt, AsyncWriteExt},
 };
-use tokio::{fs, io::AsyncWriteExt};
+use tokio_util::io::ReaderStream;
 use url::Url;
 
-use crate::data::progress::Progress;
-
-#[derive(Debug, Clone)]
-pub struct Download {
-  url: Url,
-  id: String,
-  state: DownloadState,
-}
+use crate::{
+  data::{archive::sha256_file, progress::Progress},
+  http::{
+    RedirectError, client, content_disposition_file_name,
+    percent_decode,
+  },
+  i18n::t,
+  paths::downloads_dir,
+  tasks::TaskEvent,
+};
 
 #[derive(Debug, thiserror::Error, Clone)]
 pub enum Error {
@@ -23,115 +32,502 @@ pub enum Error {
   Reqwest(#[from] Arc<reqwest::Error>),
   #[error("Io: {0}")]
   Io(#[from] Arc<std::io::Error>),
+  #[error("HTTP {}: {url}", status.as_u16())]
+  Http {
+    status: reqwest::StatusCode,
+    url: Url,
+  },
+  #[error("Timeout: no data for {}s", after.as_secs())]
+  Timeout { after: Duration },
+  #[error("ChecksumMismatch: expected {expected}, got {actual}")]
+  ChecksumMismatch { expected: String, actual: String },
+  #[error("Redirect: {0}")]
+  Redirect(RedirectError),
+  #[error("InvalidFileUrl: {0}")]
+  InvalidFileUrl(Url),
 }
 
-#[derive(Debug, Clone)]
-pub enum DownloadState {
-  Running {
-    progress: f32,
-    _task_handle: task::Handle,
-  },
-  Finished,
-  Failed,
-  Ready,
+impl Error {
+  // 连接错误、超时和5xx通常是暂时性的，值得重试
+  pub fn is_transient(&self) -> bool {
+    match self {
+      // 传输中途断开时reqwest报告的是解码错误
+      Self::Reqwest(err) => {
+        err.is_connect()
+          || err.is_timeout()
+          || err.is_request()
+          || err.is_body()
+          || err.is_decode()
+      }
+      Self::Http { status, .. } => status.is_server_error(),
+      Self::Timeout { .. } => true,
+      Self::Io(_)
+      | Self::ChecksumMismatch { .. }
+      | Self::Redirect(_)
+      | Self::InvalidFileUrl(_) => false,
+    }
+  }
 }
 
-#[derive(Debug, Clone)]
-pub enum DownloadUpdate {
-  Downloading(Progress),
-  Finished(Result<PathBuf, Error>),
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+  pub max_retries: u32,
+  pub base_delay: Duration,
+  pub max_delay: Duration,
 }
 
-impl Download {
-  pub fn new(id: String, url: Url) -> Self {
+impl Default for RetryPolicy {
+  fn default() -> Self {
     Self {
-      url,
-      id,
-      state: DownloadState::Ready,
+      max_retries: 3,
+      base_delay: Duration::from_secs(1),
+      max_delay: Duration::from_secs(30),
     }
   }
+}
 
-  pub fn state(&self) -> &DownloadState {
-    &self.state
+impl RetryPolicy {
+  // 第`retry`次重试前的等待时间，指数增长并封顶
+  pub fn delay(&self, retry: u32) -> Duration {
+    self
+      .base_delay
+      .saturating_mul(2u32.saturating_pow(retry))
+      .min(self.max_delay)
   }
 
-  pub fn id(&self) -> &str {
-    &self.id
+  pub fn should_retry(&self, retry: u32, err: &Error) -> bool {
+    retry < self.max_retries && err.is_transient()
   }
+}
 
-  pub fn start(&mut self) -> Task<DownloadUpdate> {
-    match self.state {
-      DownloadState::Failed
-      | DownloadState::Ready
-      | DownloadState::Finished => {
-        let (task, handle) = Task::sip(
-          download_to(
-            self.url.to_owned(),
-            temp_file::empty().path().to_path_buf(),
-          ),
-          DownloadUpdate::Downloading,
-          DownloadUpdate::Finished,
-        )
-        .abortable();
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadTimeouts {
+  // 连续这么久没有收到数据就认为连接已经卡死
+  pub idle: Duration,
+}
 
-        self.state = DownloadState::Running {
-          progress: 0.,
-          _task_handle: handle.abort_on_drop(),
-        };
+impl Default for DownloadTimeouts {
+  fn default() -> Self {
+    Self {
+      idle: Duration::from_secs(60),
+    }
+  }
+}
 
-        task
-      }
-      DownloadState::Running { .. } => Task::none(),
+#[derive(Debug, Clone)]
+pub struct DownloadedFile {
+  pub path: PathBuf,
+  // 服务器建议的文件名，没有时取URL的最后一段
+  pub file_name: Option<String>,
+}
+
+// 同一模组的同一版本总是下载到同一个位置，便于复用和排查
+pub fn cached_download_path(
+  id: &str,
+  version: &str,
+  url: &Url,
+) -> PathBuf {
+  let ext = Path::new(url.path())
+    .extension()
+    .map(|ext| ext.to_string_lossy().to_string())
+    .unwrap_or_else(|| "download".to_string());
+  downloads_dir().join(sanitize_filename::sanitize(format!(
+    "{}-{}.{}",
+    id, version, ext
+  )))
+}
+
+// 清理下载目录中长时间没有用到的文件，`keep`中的除外
+pub async fn sweep_stale_downloads(
+  max_age: Duration,
+  keep: &HashSet<PathBuf>,
+) -> std::io::Result<usize> {
+  sweep_dir(&downloads_dir(), max_age, keep).await
+}
+
+async fn sweep_dir(
+  dir: &Path,
+  max_age: Duration,
+  keep: &HashSet<PathBuf>,
+) -> std::io::Result<usize> {
+  let mut removed = 0;
+  let mut read_dir = match fs::read_dir(dir).await {
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      return Ok(0);
+    }
+    read_dir => read_dir?,
+  };
+  while let Some(entry) = read_dir.next_entry().await? {
+    let metadata = entry.metadata().await?;
+    let stale = metadata
+      .modified()
+      .ok()
+      .and_then(|modified| modified.elapsed().ok())
+      .is_some_and(|age| age > max_age);
+    if metadata.is_file() && stale && !keep.contains(&entry.path()) {
+      fs::remove_file(entry.path()).await?;
+      removed += 1;
     }
   }
+  Ok(removed)
+}
 
-  pub fn update(&mut self, update: DownloadUpdate) {
-    if let DownloadState::Running { progress, .. } = &mut self.state {
-      match update {
-        DownloadUpdate::Downloading(new_progress) => {
-          *progress = if new_progress.max == 0 {
-            -1.
-          } else {
-            new_progress.current as f32 / new_progress.max as f32
-          };
+// 一次下载需要的全部参数
+#[derive(Debug, Clone)]
+pub struct DownloadRequest {
+  pub url: Url,
+  pub path: PathBuf,
+  // registry声明的大小，只用来检查
+  pub expected_size: Option<u64>,
+  pub sha256: Option<String>,
+  pub retry_policy: RetryPolicy,
+  pub timeouts: DownloadTimeouts,
+  // 从这个位置继续下载，服务器不支持时从头开始
+  pub resume_from: u64,
+}
+
+// 进度和日志通过`on_event`汇报，暂时性的错误按重试策略自动重试
+pub async fn download(
+  request: DownloadRequest,
+  on_event: impl FnMut(TaskEvent),
+) -> Result<DownloadedFile, crate::error::Error> {
+  download_with_client(client(), request, on_event).await
+}
+
+// 与download相同，使用指定的客户端发出请求
+pub async fn download_with_client(
+  client: &Client,
+  request: DownloadRequest,
+  on_event: impl FnMut(TaskEvent),
+) -> Result<DownloadedFile, crate::error::Error> {
+  Ok(fetch(client, request, on_event).await?)
+}
+
+async fn fetch(
+  client: &Client,
+  request: DownloadRequest,
+  mut on_event: impl FnMut(TaskEvent),
+) -> Result<DownloadedFile, Error> {
+  let DownloadRequest {
+    url,
+    path,
+    expected_size,
+    sha256,
+    retry_policy,
+    timeouts,
+    resume_from,
+  } = request;
+  let url_file_name = url
+    .path_segments()
+    .and_then(|mut segments| segments.next_back())
+    .filter(|segment| !segment.is_empty())
+    .and_then(|segment| percent_decode(segment.as_bytes()));
+  // 缓存中已有校验通过的文件时不再联网
+  let cached = match &sha256 {
+    Some(expected) => sha256_file(&path)
+      .await
+      .is_ok_and(|actual| actual.eq_ignore_ascii_case(expected)),
+    None => false,
+  };
+  if cached {
+    on_event(TaskEvent::Log(
+      t!("task_log.cache_verified").to_string(),
+    ));
+    return Ok(DownloadedFile {
+      path,
+      file_name: url_file_name,
+    });
+  }
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).await.map_err(Arc::new)?;
+  }
+  let idle_timeout = Error::Timeout {
+    after: timeouts.idle,
+  };
+  let mut retry = 0;
+  let mut resume_from = resume_from;
+  loop {
+    let attempt: Result<DownloadedFile, Error> = async {
+      let (file_name, start, max, mut reader_stream) = if url.scheme()
+        == "file"
+      {
+        // 本地或网络共享上的文件直接复制，进度按文件大小计算
+        let source = url
+          .to_file_path()
+          .map_err(|()| Error::InvalidFileUrl(url.to_owned()))?;
+        let mut file =
+          fs::File::open(&source).await.map_err(Arc::new)?;
+        let max = file.metadata().await.ok().map(|m| m.len());
+        let start = if max.is_some_and(|max| resume_from <= max) {
+          file
+            .seek(SeekFrom::Start(resume_from))
+            .await
+            .map_err(Arc::new)?
+        } else {
+          0
+        };
+        (
+          url_file_name.to_owned(),
+          start,
+          max,
+          ReaderStream::new(file)
+            .map(|bytes| {
+              bytes.map_err(|err| Error::Io(Arc::new(err)))
+            })
+            .boxed(),
+        )
+      } else {
+        let mut request = client.get(url.to_owned());
+        if resume_from > 0 {
+          request = request.header(
+            reqwest::header::RANGE,
+            format!("bytes={}-", resume_from),
+          );
+        }
+        let res = tokio::time::timeout(timeouts.idle, request.send())
+          .await
+          .map_err(|_| idle_timeout.to_owned())?
+          .map_err(|err| {
+            // 被拒绝的重定向单独报告，方便用户知道最终指向了哪里
+            match std::error::Error::source(&err).and_then(|source| {
+              source.downcast_ref::<RedirectError>()
+            }) {
+              Some(redirect) => Error::Redirect(redirect.to_owned()),
+              None => Error::Reqwest(Arc::new(err)),
+            }
+          })?;
+        // 错误页面不能当作模组文件写入
+        if !res.status().is_success() {
+          return Err(Error::Http {
+            status: res.status(),
+            url: url.to_owned(),
+          });
         }
-        DownloadUpdate::Finished(res) => {
-          self.state = if res.is_ok() {
-            DownloadState::Finished
+        let file_name = res
+          .headers()
+          .get(reqwest::header::CONTENT_DISPOSITION)
+          .and_then(|value| value.to_str().ok())
+          .and_then(content_disposition_file_name)
+          .or_else(|| url_file_name.to_owned());
+        // 服务器不支持Range时会返回完整内容，只能从头开始
+        let start =
+          if res.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+            resume_from
           } else {
-            DownloadState::Failed
-          }
+            0
+          };
+        (
+          file_name,
+          start,
+          res.content_length().map(|len| len + start),
+          res
+            .bytes_stream()
+            .map(|bytes| {
+              bytes.map_err(|err| Error::Reqwest(Arc::new(err)))
+            })
+            .boxed(),
+        )
+      };
+      let mut current = start;
+      // 与registry声明的大小相差过大时记录下来，方便维护者修正
+      if let Some((expected, actual)) =
+        expected_size.zip(max).filter(|(expected, actual)| {
+          expected.abs_diff(*actual) > expected / 10
+        })
+      {
+        tracing::warn!(
+          %url,
+          expected,
+          actual,
+          "download size differs from the registry"
+        );
+      }
+      on_event(TaskEvent::Progress(Progress { current, max }));
+
+      let mut writer = fs::File::options()
+        .create(true)
+        .truncate(start == 0)
+        .append(start > 0)
+        .write(true)
+        .open(&path)
+        .await
+        .map_err(Arc::new)?;
+
+      while let Some(bytes) =
+        tokio::time::timeout(timeouts.idle, reader_stream.next())
+          .await
+          .map_err(|_| idle_timeout.to_owned())?
+      {
+        let bytes = bytes?;
+        current += bytes.len() as u64;
+        writer.write_all(&bytes).await.map_err(Arc::new)?;
+        on_event(TaskEvent::Progress(Progress { current, max }));
+      }
+      writer.flush().await.map_err(Arc::new)?;
+      if let Some(expected) = &sha256 {
+        let actual = sha256_file(&path).await.map_err(Arc::new)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+          return Err(Error::ChecksumMismatch {
+            expected: expected.to_owned(),
+            actual,
+          });
         }
       }
+      Ok(DownloadedFile {
+        path: path.to_owned(),
+        file_name,
+      })
+    }
+    .await;
+
+    match attempt {
+      Err(err) if retry_policy.should_retry(retry, &err) => {
+        let delay = retry_policy.delay(retry);
+        tracing::warn!(
+          %url,
+          %err,
+          retry,
+          ?delay,
+          "download failed, retrying"
+        );
+        retry += 1;
+        resume_from = 0;
+        on_event(TaskEvent::Log(t!(
+          "task_log.retrying",
+          delay = delay.as_secs_f32(),
+          retry = retry,
+          error = err
+        )));
+        // 重新开始下载，进度归零
+        on_event(TaskEvent::Progress(Progress {
+          current: 0,
+          max: None,
+        }));
+        tokio::time::sleep(delay).await;
+      }
+      attempt => return attempt,
     }
   }
 }
 
-fn download_to(
-  url: Url,
-  path: PathBuf,
-) -> impl Straw<PathBuf, Progress, Error> {
-  sipper(move |mut progress| async move {
-    let res = reqwest::get(url).await.map_err(Arc::new)?;
-    let mut current = 0;
-    let max = res.content_length().unwrap_or(0);
-    progress.send(Progress { current, max }).await;
-    let mut reader_stream = res.bytes_stream();
-
-    let mut writer = fs::File::options()
-      .create(true)
-      .truncate(true)
-      .write(true)
-      .open(&path)
-      .await
-      .map_err(Arc::new)?;
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-    while let Some(bytes) = reader_stream.next().await {
-      let bytes = bytes.map_err(Arc::new)?;
-      current += bytes.len() as u64;
-      writer.write_all(&bytes).await.map_err(Arc::new)?;
-      progress.send(Progress { current, max }).await;
+  fn http(status: u16) -> Error {
+    Error::Http {
+      status: reqwest::StatusCode::from_u16(status).unwrap(),
+      url: Url::parse("https://example.com/mod.zip").unwrap(),
     }
-    Ok(path)
-  })
+  }
+
+  #[test]
+  fn transient_errors() {
+    let cases = [
+      (http(500), true),
+      (http(503), true),
+      (http(404), false),
+      (http(403), false),
+      (
+        Error::Timeout {
+          after: Duration::from_secs(60),
+        },
+        true,
+      ),
+      (
+        Error::ChecksumMismatch {
+          expected: "a".to_string(),
+          actual: "b".to_string(),
+        },
+        false,
+      ),
+      (
+        Error::Io(Arc::new(std::io::Error::other("disk full"))),
+        false,
+      ),
+      (
+        Error::Redirect(RedirectError::InsecureRedirect {
+          url: Url::parse("http://example.com/").unwrap(),
+        }),
+        false,
+      ),
+      (
+        Error::InvalidFileUrl(
+          Url::parse("file://host/mod.zip").unwrap(),
+        ),
+        false,
+      ),
+    ];
+    for (err, transient) in cases {
+      assert_eq!(err.is_transient(), transient, "{}", err);
+    }
+  }
+
+  #[test]
+  fn retry_delay_grows_and_caps() {
+    let policy = RetryPolicy {
+      max_retries: 3,
+      base_delay: Duration::from_secs(1),
+      max_delay: Duration::from_secs(5),
+    };
+    let delays = (0..5)
+      .map(|retry| policy.delay(retry).as_secs())
+      .collect::<Vec<_>>();
+    assert_eq!(delays, [1, 2, 4, 5, 5]);
+    // 非常大的重试次数也不会溢出
+    assert_eq!(policy.delay(u32::MAX), Duration::from_secs(5));
+  }
+
+  #[test]
+  fn retries_only_transient_errors_within_limit() {
+    let policy = RetryPolicy {
+      max_retries: 2,
+      ..RetryPolicy::default()
+    };
+    assert!(policy.should_retry(0, &http(502)));
+    assert!(policy.should_retry(1, &http(502)));
+    assert!(!policy.should_retry(2, &http(502)));
+    assert!(!policy.should_retry(0, &http(404)));
+  }
+
+  // 超过时间的删除，`keep`中的和刚用过的留着，子目录不动
+  #[tokio::test]
+  async fn sweep_keeps_recent_and_listed() {
+    let dir = std::env::temp_dir()
+      .join(format!("kmm-sweep-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    let old =
+      std::time::SystemTime::now() - Duration::from_secs(7200);
+    let [stale, kept, recent] =
+      ["stale.zip", "kept.zip", "recent.zip"]
+        .map(|name| dir.join(name));
+    for path in [&stale, &kept, &recent] {
+      std::fs::write(path, "zip").unwrap();
+    }
+    for path in [&stale, &kept] {
+      std::fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+    }
+    let keep = HashSet::from([kept.to_owned()]);
+    let removed = sweep_dir(&dir, Duration::from_secs(3600), &keep)
+      .await
+      .unwrap();
+    assert_eq!(removed, 1);
+    assert!(!stale.exists());
+    assert!(kept.is_file());
+    assert!(recent.is_file());
+    assert!(dir.join("nested").is_dir());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    // 下载目录还不存在时什么都不做
+    assert_eq!(
+      sweep_dir(&dir, Duration::ZERO, &keep).await.unwrap(),
+      0
+    );
+  }
 }
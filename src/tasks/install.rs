@@ -3,144 +3,144 @@ use std::{
   sync::Arc,
 };
 
-use iced::{
-  Task,
-  task::{self, Straw, sipper},
-};
+use tokio::fs;
 
 use crate::{
-  data::progress::Progress,
+  data::archive::ArchiveType,
+  i18n::t,
   mod_manager::{self, ModManager},
+  paths::archive_cache_dir,
+  tasks::TaskEvent,
 };
 
-#[derive(Debug, Clone)]
-pub struct Install {
-  id: String,
-  path: PathBuf,
-  version: String,
-  state: InstallState,
-  ty: String,
-}
-
-#[derive(Debug, Clone)]
-pub enum InstallState {
-  Running {
-    progress: f32,
-    _task_handle: task::Handle,
-  },
-  Failed,
-  Finished,
-  Ready,
-}
-
 #[derive(Debug, thiserror::Error, Clone)]
 pub enum Error {
   #[error("ModManager: {0}")]
   ModManager(#[from] Arc<mod_manager::Error>),
+  #[error("Io: {0}")]
+  Io(#[from] Arc<std::io::Error>),
+  #[error("UnsupportedArchive: {0}")]
+  UnsupportedArchive(ArchiveType),
+  #[error("UnknownArchive: {0}")]
+  UnknownArchive(PathBuf),
+  // 显示给用户的文字在crate::error中生成
+  #[error("InvalidArchive: {size} bytes")]
+  InvalidArchive { size: u64 },
 }
 
-#[derive(Debug, Clone)]
-pub enum InstallUpdate {
-  Running(Progress),
-  Finished((Result<(), Error>, ModManager)),
+// 检查压缩包后解压到游戏目录，返回写入的文件数；
+// 压缩包由调用方清理，失败时可以留着重试
+pub async fn install(
+  mod_manager: &mut ModManager,
+  id: &str,
+  version: &str,
+  path: &Path,
+  ty: Option<ArchiveType>,
+  on_event: impl FnMut(TaskEvent),
+) -> Result<usize, crate::error::Error> {
+  Ok(
+    install_archive(mod_manager, id, version, path, ty, on_event)
+      .await?,
+  )
 }
 
-impl Install {
-  pub fn new(id: &str, path: &Path, version: &str, ty: &str) -> Self {
-    Self {
-      id: id.to_string(),
-      path: path.to_path_buf(),
-      version: version.to_string(),
-      state: InstallState::Ready,
-      ty: ty.to_string(),
-    }
-  }
+async fn install_archive(
+  mod_manager: &mut ModManager,
+  id: &str,
+  version: &str,
+  path: &Path,
+  ty: Option<ArchiveType>,
+  mut on_event: impl FnMut(TaskEvent),
+) -> Result<usize, Error> {
+  let ty = verify_archive(path, ty).await?;
+  on_event(TaskEvent::Log(t!("task_log.archive_type", ty = ty)));
+  let count = match ty {
+    ArchiveType::Zip => mod_manager
+      .install_zip_mod_with_progress(path, id, version, |progress| {
+        on_event(TaskEvent::Progress(progress))
+      })
+      .await
+      .map_err(Arc::new)?,
+    ty => return Err(Error::UnsupportedArchive(ty)),
+  };
+  on_event(TaskEvent::Log(t!(
+    "task_log.files_written",
+    count = count
+  )));
+  Ok(count)
+}
 
-  pub fn state(&self) -> &InstallState {
-    &self.state
+// 确定压缩包的格式并检查文件是否完整，不需要ModManager。
+// registry没有声明类型时根据文件内容判断
+pub async fn verify_archive(
+  path: &Path,
+  ty: Option<ArchiveType>,
+) -> Result<ArchiveType, Error> {
+  let ty = ArchiveType::resolve(path, ty)
+    .await
+    .map_err(Arc::new)?
+    .ok_or_else(|| Error::UnknownArchive(path.to_path_buf()))?;
+  if !ty.validate_file(path).await.map_err(Arc::new)? {
+    let size = fs::metadata(path)
+      .await
+      .map_or(0, |metadata| metadata.len());
+    return Err(Error::InvalidArchive { size });
   }
+  Ok(ty)
+}
 
-  pub fn id(&self) -> &str {
-    &self.id
-  }
+// 缓存目录中的压缩包留着下次复用，其他的用完即删
+pub async fn discard_archive(path: PathBuf) {
+  discard_unless_cached(path, &archive_cache_dir()).await
+}
 
-  pub fn start(
-    &mut self,
-    mod_manager: ModManager,
-  ) -> Task<InstallUpdate> {
-    match self.state {
-      InstallState::Failed
-      | InstallState::Finished
-      | InstallState::Ready => {
-        let (task, handle) = Task::sip(
-          install_mod(
-            self.id.to_owned(),
-            self.path.to_owned(),
-            self.version.to_owned(),
-            self.ty.to_owned(),
-            mod_manager,
-          ),
-          InstallUpdate::Running,
-          |res| {
-            InstallUpdate::Finished(match res {
-              Ok(mod_manager) => (Ok(()), mod_manager),
-              Err((err, mod_manager)) => (Err(err), mod_manager),
-            })
-          },
-        )
-        .abortable();
-        self.state = InstallState::Running {
-          progress: 0.,
-          _task_handle: handle,
-        };
-        task
-      }
-      InstallState::Running { .. } => Task::none(),
-    }
+async fn discard_unless_cached(path: PathBuf, cache_dir: &Path) {
+  if path.starts_with(cache_dir) {
+    return;
   }
-
-  pub fn update(&mut self, update: InstallUpdate) {
-    if let InstallState::Running { progress, .. } = &mut self.state {
-      match update {
-        InstallUpdate::Running(new_progress) => {
-          *progress = if new_progress.max == 0 {
-            -1.
-          } else {
-            new_progress.current as f32 / new_progress.max as f32
-          };
-        }
-        InstallUpdate::Finished((res, ..)) => {
-          self.state = if res.is_ok() {
-            InstallState::Finished
-          } else {
-            InstallState::Failed
-          }
-        }
-      }
+  match fs::remove_file(&path).await {
+    Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+      tracing::warn!(
+        path = %path.display(),
+        %err,
+        "failed to remove archive"
+      );
     }
+    _ => {}
   }
 }
 
-fn install_mod(
-  id: String,
-  path: PathBuf,
-  version: String,
-  ty: String,
-  mut mod_manager: ModManager,
-) -> impl Straw<ModManager, Progress, (Error, ModManager)> {
-  sipper(async move |progress| {
-    match ty.as_str() {
-      "zip" => mod_manager.install_zip_mod(
-        path.as_ref(),
-        id.as_ref(),
-        version.as_ref(),
-      ),
-      _ => todo!(),
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "kmm-install-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("archives")).unwrap();
+    std::fs::create_dir_all(dir.join("downloads")).unwrap();
+    dir
+  }
+
+  #[tokio::test]
+  async fn cached_archives_are_kept() {
+    let dir = temp_dir("discard");
+    let cache = dir.join("archives");
+    let cached = cache.join("example-1.0.zip");
+    let downloaded = dir.join("downloads/example-1.0.zip");
+    for path in [&cached, &downloaded] {
+      std::fs::write(path, "zip").unwrap();
     }
-    .await
-    .map_err(|err| {
-      (Error::ModManager(Arc::new(err)), mod_manager.to_owned())
-    })?;
-    Ok(mod_manager)
-  })
+    discard_unless_cached(cached.to_owned(), &cache).await;
+    discard_unless_cached(downloaded.to_owned(), &cache).await;
+    assert!(cached.is_file());
+    assert!(!downloaded.exists());
+    // 已经不在的文件不算错误
+    discard_unless_cached(downloaded, &cache).await;
+    let _ = std::fs::remove_dir_all(&dir);
+  }
 }
@@ -0,0 +1,157 @@
+// 安全删除模式下卸载的文件先移到数据目录的回收站中，
+// 每次卸载一项，保留在res_mods中的相对路径。删错了可以恢复，
+// 超过保留天数后自动清理
+use std::{
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{data::version::ModVersion, mod_manager::Record};
+
+// 每一项中记下模组id和卸载前的安装记录，恢复时写回
+pub const META_FILE: &str = ".kmmgr-trash.json";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%3f";
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TrashMeta {
+  pub id: String,
+  // unix时间，秒
+  pub trashed_at: u64,
+  pub record: Record,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrashEntry {
+  pub path: PathBuf,
+  pub id: String,
+  pub version: ModVersion,
+  pub trashed_at: u64,
+  // 其中所有文件的大小
+  pub size: u64,
+}
+
+// `<id>-<时间戳>`，id中不能用在文件名里的字符会被替换
+pub fn entry_name(id: &str, time: DateTime<Local>) -> String {
+  format!(
+    "{}-{}",
+    sanitize_filename::sanitize(id),
+    time.format(TIMESTAMP_FORMAT)
+  )
+}
+
+// 先写下记录再移动文件，中途失败的项也能认出来
+pub async fn create_entry(
+  dir: &Path,
+  id: &str,
+  record: &Record,
+) -> std::io::Result<PathBuf> {
+  let time = Local::now();
+  let path = dir.join(entry_name(id, time));
+  fs::create_dir_all(&path).await?;
+  let meta = TrashMeta {
+    id: id.to_owned(),
+    trashed_at: time.timestamp().max(0) as u64,
+    record: record.to_owned(),
+  };
+  fs::write(path.join(META_FILE), serde_json::to_vec(&meta)?).await?;
+  Ok(path)
+}
+
+pub async fn read_meta(entry: &Path) -> std::io::Result<TrashMeta> {
+  Ok(serde_json::from_slice(
+    &fs::read(entry.join(META_FILE)).await?,
+  )?)
+}
+
+// 先尝试重命名，游戏和数据目录不在同一个盘上时退回复制后删除
+pub async fn move_file(
+  from: &Path,
+  to: &Path,
+) -> std::io::Result<()> {
+  if let Some(parent) = to.parent() {
+    fs::create_dir_all(parent).await?;
+  }
+  if fs::rename(from, to).await.is_ok() {
+    return Ok(());
+  }
+  fs::copy(from, to).await?;
+  fs::remove_file(from).await
+}
+
+// 不跟随符号链接，读不到的部分不计
+pub async fn dir_size(path: &Path) -> u64 {
+  let mut size = 0;
+  let mut stack = vec![path.to_path_buf()];
+  while let Some(dir) = stack.pop() {
+    let Ok(mut read_dir) = fs::read_dir(&dir).await else {
+      continue;
+    };
+    while let Ok(Some(entry)) = read_dir.next_entry().await {
+      let Ok(metadata) = entry.metadata().await else {
+        continue;
+      };
+      if metadata.is_dir() {
+        stack.push(entry.path());
+      } else {
+        size += metadata.len();
+      }
+    }
+  }
+  size
+}
+
+// 新卸载的在前。目录不存在时视为回收站为空，
+// 缺少记录的目录不是回收站的项，不列出
+pub async fn list(dir: &Path) -> Vec<TrashEntry> {
+  let mut entries = Vec::new();
+  let Ok(mut read_dir) = fs::read_dir(dir).await else {
+    return entries;
+  };
+  while let Ok(Some(entry)) = read_dir.next_entry().await {
+    let path = entry.path();
+    let Ok(meta) = read_meta(&path).await else {
+      continue;
+    };
+    entries.push(TrashEntry {
+      size: dir_size(&path).await,
+      id: meta.id,
+      version: meta.record.version,
+      trashed_at: meta.trashed_at,
+      path,
+    });
+  }
+  entries.sort_by(|a, b| {
+    b.trashed_at.cmp(&a.trashed_at).then(b.path.cmp(&a.path))
+  });
+  entries
+}
+
+// 删除卸载时间早于`now - max_age`的项，返回删除的数量
+pub async fn purge_older_than(
+  dir: &Path,
+  now: u64,
+  max_age: Duration,
+) -> std::io::Result<usize> {
+  let deadline = now.saturating_sub(max_age.as_secs());
+  let mut count = 0;
+  for entry in list(dir).await {
+    if entry.trashed_at < deadline {
+      fs::remove_dir_all(&entry.path).await?;
+      count += 1;
+    }
+  }
+  Ok(count)
+}
+
+// 清空整个回收站，返回腾出的字节数
+pub async fn empty(dir: &Path) -> std::io::Result<u64> {
+  let size = dir_size(dir).await;
+  match fs::remove_dir_all(dir).await {
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+    result => result.map(|()| size),
+  }
+}
@@ -0,0 +1,207 @@
+// 命令行入口的集成测试：临时的游戏目录加上本地的模拟registry
+mod common;
+
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  process::Output,
+};
+
+use common::{MockResponse, mock_server, zip};
+use tokio::process::Command;
+
+struct Fixture {
+  root: PathBuf,
+  registry: String,
+}
+
+impl Fixture {
+  // 每个测试使用独立的目录，配置和缓存也放在里面
+  async fn new(name: &str) -> Self {
+    let root = std::env::temp_dir().join(format!(
+      "kmm-cli-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    tokio::fs::create_dir_all(root.join("game/bin/1000/res_mods"))
+      .await
+      .unwrap();
+    let registry = serde_json::json!({
+      "example": {
+        "id": "example",
+        "version": "2.0",
+        "url": "example-2.0.zip",
+        "image_url": "",
+        "name": "Example",
+        "versions": [
+          { "version": "1.0", "url": "example-1.0.zip" },
+        ],
+      },
+      "broken": {
+        "id": "broken",
+        "version": "1.0",
+        "url": "missing.zip",
+        "image_url": "",
+        "name": "Broken",
+      },
+    });
+    let routes = HashMap::from([
+      ("/registry".to_string(), registry.to_string().into_bytes()),
+      (
+        "/example-1.0.zip".to_string(),
+        zip(&[("example.txt", "1.0")]).await,
+      ),
+      (
+        "/example-2.0.zip".to_string(),
+        zip(&[("example.txt", "2.0")]).await,
+      ),
+    ]);
+    Self {
+      root,
+      registry: format!("{}/registry", serve(routes).await),
+    }
+  }
+
+  fn res_mods(&self) -> PathBuf {
+    self.root.join("game/bin/1000/res_mods")
+  }
+
+  async fn run(&self, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_kmm"));
+    command
+      .args(args)
+      .arg("--game-dir")
+      .arg(self.root.join("game"))
+      .arg("--registry")
+      .arg(&self.registry);
+    isolate(&mut command, &self.root);
+    command.output().await.unwrap()
+  }
+}
+
+impl Drop for Fixture {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.root);
+  }
+}
+
+// 不读写用户真实的配置和缓存
+fn isolate(command: &mut Command, root: &Path) {
+  command
+    .env("HOME", root)
+    .env("XDG_CONFIG_HOME", root.join("config"))
+    .env("XDG_CACHE_HOME", root.join("cache"))
+    .env("XDG_DATA_HOME", root.join("data"));
+}
+
+// 未知路径返回404
+async fn serve(routes: HashMap<String, Vec<u8>>) -> String {
+  let (url, _) =
+    mock_server(move |request| match routes.get(&request.path) {
+      Some(body) => MockResponse::ok(body.to_owned()),
+      None => MockResponse::status(404),
+    })
+    .await;
+  url
+}
+
+fn list(output: &Output) -> serde_json::Value {
+  serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn entry<'a>(
+  list: &'a serde_json::Value,
+  id: &str,
+) -> &'a serde_json::Value {
+  list
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|entry| entry["id"] == id)
+    .unwrap()
+}
+
+#[tokio::test]
+async fn list_shows_registry_mods() {
+  let fixture = Fixture::new("list").await;
+  let output = fixture.run(&["list", "--json"]).await;
+  assert!(output.status.success());
+  let list = list(&output);
+  let example = entry(&list, "example");
+  assert_eq!(example["available"], "2.0");
+  assert!(example["installed"].is_null());
+  assert_eq!(example["update"], false);
+}
+
+#[tokio::test]
+async fn install_and_uninstall() {
+  let fixture = Fixture::new("install").await;
+  let output = fixture.run(&["install", "example"]).await;
+  assert!(output.status.success());
+  let file = fixture.res_mods().join("example.txt");
+  assert_eq!(tokio::fs::read_to_string(&file).await.unwrap(), "2.0");
+
+  let output = fixture.run(&["uninstall", "example"]).await;
+  assert!(output.status.success());
+  assert!(!file.exists());
+
+  let output = fixture.run(&["uninstall", "example"]).await;
+  assert_eq!(output.status.code(), Some(1));
+}
+
+#[tokio::test]
+async fn update_installed_mods() {
+  let fixture = Fixture::new("update").await;
+  let output = fixture.run(&["install", "example@1.0"]).await;
+  assert!(output.status.success());
+  let output = fixture.run(&["list", "--json"]).await;
+  let list = list(&output);
+  let example = entry(&list, "example");
+  assert_eq!(example["installed"], "1.0");
+  assert_eq!(example["update"], true);
+
+  let output = fixture.run(&["update"]).await;
+  assert!(output.status.success());
+  let file = fixture.res_mods().join("example.txt");
+  assert_eq!(tokio::fs::read_to_string(&file).await.unwrap(), "2.0");
+}
+
+#[tokio::test]
+async fn partial_failure_exit_code() {
+  let fixture = Fixture::new("partial").await;
+  let output = fixture
+    .run(&["install", "example", "broken", "unknown"])
+    .await;
+  assert_eq!(output.status.code(), Some(1));
+  assert!(fixture.res_mods().join("example.txt").is_file());
+}
+
+// 配置文件损坏时只做提示，照常使用默认配置
+#[tokio::test]
+async fn corrupt_settings_are_not_fatal() {
+  let fixture = Fixture::new("settings").await;
+  let settings = fixture
+    .root
+    .join("config/korabli-mod-manager/settings.toml");
+  tokio::fs::create_dir_all(settings.parent().unwrap())
+    .await
+    .unwrap();
+  tokio::fs::write(&settings, "game_dir = [").await.unwrap();
+  let output = fixture.run(&["list", "--json"]).await;
+  assert!(output.status.success());
+  assert!(!output.stderr.is_empty());
+  assert_eq!(entry(&list(&output), "example")["available"], "2.0");
+}
+
+#[tokio::test]
+async fn missing_game_dir_is_fatal() {
+  let fixture = Fixture::new("fatal").await;
+  let mut command = Command::new(env!("CARGO_BIN_EXE_kmm"));
+  command
+    .args(["list", "--game-dir"])
+    .arg(fixture.root.join("nowhere"));
+  isolate(&mut command, &fixture.root);
+  let output = command.output().await.unwrap();
+  assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,306 @@
+// 集成测试共用的夹具：临时目录中的游戏目录和程序生成的压缩包
+#![allow(dead_code)]
+use std::{
+  path::{Path, PathBuf},
+  sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+  },
+  time::Duration,
+};
+
+use async_zip::{
+  Compression, StringEncoding, ZipEntryBuilder, ZipString,
+  base::write::ZipFileWriter,
+};
+use korabli_mod_manager::{mod_manager::ModManager, paths};
+use tokio::{
+  io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+  net::TcpListener,
+};
+use tokio_rustls::{
+  TlsAcceptor,
+  rustls::{
+    self,
+    pki_types::{CertificateDer, PrivatePkcs8KeyDer},
+  },
+};
+
+// GBK编码的“中文.txt”，不带UTF-8标记写入压缩包
+pub const GBK_NAME: &[u8] = b"\xd6\xd0\xce\xc4.txt";
+
+// 每个测试使用独立的目录，结束时删除
+pub struct FakeGame {
+  pub root: PathBuf,
+}
+
+impl FakeGame {
+  // 带有`bin/1000/res_mods`的游戏目录
+  pub fn new(name: &str) -> Self {
+    let game = Self::with_builds(name, &[]);
+    std::fs::create_dir_all(game.res_mods()).unwrap();
+    game
+  }
+
+  // 只在bin下创建给定的目录
+  pub fn with_builds(name: &str, builds: &[&str]) -> Self {
+    let root = std::env::temp_dir().join(format!(
+      "kmm-test-{}-{}",
+      std::process::id(),
+      name
+    ));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("bin")).unwrap();
+    for build in builds {
+      std::fs::create_dir_all(root.join("bin").join(build)).unwrap();
+    }
+    // 和ModManager一样用真实位置，临时目录本身可能是链接
+    let root = paths::canonicalize(&root).unwrap();
+    Self { root }
+  }
+
+  pub fn res_mods(&self) -> PathBuf {
+    self.root.join("bin/1000/res_mods")
+  }
+
+  pub async fn mod_manager(&self) -> ModManager {
+    let mut mod_manager =
+      ModManager::try_from_game_dir(&self.root).await.unwrap();
+    mod_manager.ensure_records().await.unwrap();
+    mod_manager
+  }
+
+  // 在游戏目录外写入压缩包，返回路径
+  pub async fn archive(
+    &self,
+    name: &str,
+    entries: &[(&str, &str)],
+  ) -> PathBuf {
+    let path = self.root.join("archives").join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, zip(entries).await).unwrap();
+    path
+  }
+
+  pub fn read(&self, path: &str) -> String {
+    std::fs::read_to_string(self.res_mods().join(path)).unwrap()
+  }
+
+  // res_mods下所有文件的相对路径，按字典序排列
+  pub fn files(&self) -> Vec<String> {
+    let mut files = Vec::new();
+    collect_files(&self.res_mods(), &self.res_mods(), &mut files);
+    files.sort();
+    files
+  }
+}
+
+impl Drop for FakeGame {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.root);
+  }
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) {
+  for entry in std::fs::read_dir(dir).unwrap() {
+    let path = entry.unwrap().path();
+    if path.is_dir() {
+      collect_files(root, &path, files);
+    } else {
+      let relative = path.strip_prefix(root).unwrap();
+      files.push(relative.to_string_lossy().replace('\\', "/"));
+    }
+  }
+}
+
+// 以UTF-8文件名生成压缩包，以`/`结尾的条目是目录
+pub async fn zip(entries: &[(&str, &str)]) -> Vec<u8> {
+  let entries = entries
+    .iter()
+    .map(|(name, content)| {
+      (
+        ZipString::new(
+          name.as_bytes().to_vec(),
+          StringEncoding::Utf8,
+        ),
+        content.as_bytes(),
+      )
+    })
+    .collect::<Vec<_>>();
+  write_zip(entries).await
+}
+
+// 文件名按原样的字节写入，不声明编码
+pub async fn raw_zip(entries: &[(&[u8], &str)]) -> Vec<u8> {
+  let entries = entries
+    .iter()
+    .map(|(name, content)| {
+      (
+        ZipString::new(name.to_vec(), StringEncoding::Raw),
+        content.as_bytes(),
+      )
+    })
+    .collect::<Vec<_>>();
+  write_zip(entries).await
+}
+
+async fn write_zip(entries: Vec<(ZipString, &[u8])>) -> Vec<u8> {
+  let mut writer = ZipFileWriter::new(Vec::new());
+  for (name, content) in entries {
+    let entry = ZipEntryBuilder::new(name, Compression::Stored);
+    writer.write_entry_whole(entry, content).await.unwrap();
+  }
+  writer.close().await.unwrap()
+}
+
+pub struct MockRequest {
+  pub path: String,
+  // Range请求的起始位置
+  pub range: Option<u64>,
+}
+
+pub struct MockResponse {
+  pub status: u16,
+  pub headers: Vec<(String, String)>,
+  pub body: Vec<u8>,
+  // 为false时不发送Content-Length，发送完内容后关闭连接
+  pub content_length: bool,
+  // 发送这么多字节后断开连接，模拟传输中断
+  pub truncate: Option<usize>,
+  // 发送完后不关闭连接也不再发送，模拟卡住的服务器
+  pub stall: bool,
+}
+
+impl MockResponse {
+  pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+    Self {
+      status: 200,
+      headers: Vec::new(),
+      body: body.into(),
+      content_length: true,
+      truncate: None,
+      stall: false,
+    }
+  }
+
+  pub fn status(status: u16) -> Self {
+    Self {
+      status,
+      ..Self::ok(Vec::new())
+    }
+  }
+}
+
+// 最简单的HTTP服务器，每个连接只处理一个请求；
+// 返回地址和收到的请求数
+pub async fn mock_server(
+  handler: impl Fn(MockRequest) -> MockResponse + Send + Sync + 'static,
+) -> (String, Arc<AtomicUsize>) {
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let handler = Arc::new(handler);
+  let hits = Arc::new(AtomicUsize::new(0));
+  let counter = hits.to_owned();
+  tokio::spawn(async move {
+    while let Ok((stream, _)) = listener.accept().await {
+      tokio::spawn(respond(
+        stream,
+        handler.to_owned(),
+        counter.to_owned(),
+      ));
+    }
+  });
+  (format!("http://{}", addr), hits)
+}
+
+// 同上，用tests/fixtures/tls中的自签名证书提供HTTPS，
+// 客户端需要跳过证书校验
+pub async fn mock_tls_server(
+  handler: impl Fn(MockRequest) -> MockResponse + Send + Sync + 'static,
+) -> (String, Arc<AtomicUsize>) {
+  let fixtures =
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls");
+  let cert = std::fs::read(fixtures.join("cert.der")).unwrap();
+  let key = std::fs::read(fixtures.join("key.der")).unwrap();
+  let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+    rustls::crypto::ring::default_provider(),
+  ))
+  .with_safe_default_protocol_versions()
+  .unwrap()
+  .with_no_client_auth()
+  .with_single_cert(
+    vec![CertificateDer::from(cert)],
+    PrivatePkcs8KeyDer::from(key).into(),
+  )
+  .unwrap();
+  let acceptor = TlsAcceptor::from(Arc::new(config));
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let handler = Arc::new(handler);
+  let hits = Arc::new(AtomicUsize::new(0));
+  let counter = hits.to_owned();
+  tokio::spawn(async move {
+    while let Ok((stream, _)) = listener.accept().await {
+      let acceptor = acceptor.to_owned();
+      let handler = handler.to_owned();
+      let counter = counter.to_owned();
+      tokio::spawn(async move {
+        if let Ok(stream) = acceptor.accept(stream).await {
+          respond(stream, handler, counter).await;
+        }
+      });
+    }
+  });
+  (format!("https://{}", addr), hits)
+}
+
+async fn respond(
+  mut stream: impl AsyncRead + AsyncWrite + Unpin,
+  handler: Arc<impl Fn(MockRequest) -> MockResponse>,
+  counter: Arc<AtomicUsize>,
+) {
+  let mut request = Vec::new();
+  let mut buf = [0; 1024];
+  while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+    match stream.read(&mut buf).await {
+      Ok(0) | Err(_) => return,
+      Ok(len) => request.extend_from_slice(&buf[..len]),
+    }
+  }
+  counter.fetch_add(1, Ordering::SeqCst);
+  let request = String::from_utf8_lossy(&request);
+  let path = request.split(' ').nth(1).unwrap_or_default();
+  let range = request
+    .lines()
+    .find_map(|line| {
+      line
+        .to_ascii_lowercase()
+        .strip_prefix("range: bytes=")
+        .map(str::to_string)
+    })
+    .and_then(|range| range.trim_end_matches('-').parse().ok());
+  let response = handler(MockRequest {
+    path: path.to_string(),
+    range,
+  });
+  let mut head = format!("HTTP/1.1 {} Mock\r\n", response.status);
+  if response.content_length {
+    head += &format!("Content-Length: {}\r\n", response.body.len());
+  }
+  for (name, value) in response.headers {
+    head += &format!("{}: {}\r\n", name, value);
+  }
+  head += "Connection: close\r\n\r\n";
+  let body = match response.truncate {
+    Some(len) => &response.body[..len],
+    None => &response.body[..],
+  };
+  let _ = stream.write_all(head.as_bytes()).await;
+  let _ = stream.write_all(body).await;
+  let _ = stream.flush().await;
+  if response.stall {
+    tokio::time::sleep(Duration::from_secs(3600)).await;
+  }
+  // TLS需要先发送close_notify
+  let _ = stream.shutdown().await;
+}
@@ -0,0 +1,196 @@
+// 控制接口的协议测试，由不带界面的核心处理请求
+#![cfg(feature = "control")]
+mod common;
+
+use std::path::PathBuf;
+
+use common::FakeGame;
+use korabli_mod_manager::{
+  control::{Call, Method, Response, load_or_create_token, serve},
+  mod_manager::ModManager,
+};
+use serde_json::{Value, json};
+use tokio::{
+  io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+  net::{
+    TcpListener, TcpStream,
+    tcp::{OwnedReadHalf, OwnedWriteHalf},
+  },
+  sync::mpsc,
+};
+
+const TOKEN: &str = "secret";
+
+struct Client {
+  lines: Lines<BufReader<OwnedReadHalf>>,
+  writer: OwnedWriteHalf,
+}
+
+impl Client {
+  async fn send(&mut self, request: Value) -> Response {
+    let mut line = request.to_string().into_bytes();
+    line.push(b'\n');
+    self.writer.write_all(&line).await.unwrap();
+    let line = self.lines.next_line().await.unwrap().unwrap();
+    serde_json::from_str(&line).unwrap()
+  }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!(
+    "kmm-control-{}-{}",
+    std::process::id(),
+    name
+  ));
+  let _ = std::fs::remove_dir_all(&dir);
+  dir
+}
+
+// 启动服务器和处理请求的核心，返回连上的客户端
+async fn start(game: &FakeGame) -> Client {
+  let archive = game
+    .archive("example.zip", &[("example.txt", "example")])
+    .await;
+  let mod_manager = game.mod_manager().await;
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let (calls, receiver) = mpsc::unbounded_channel();
+  tokio::spawn(serve(listener, TOKEN.to_string(), calls));
+  tokio::spawn(core(mod_manager, archive, receiver));
+
+  let (reader, writer) =
+    TcpStream::connect(addr).await.unwrap().into_split();
+  Client {
+    lines: BufReader::new(reader).lines(),
+    writer,
+  }
+}
+
+async fn core(
+  mut mod_manager: ModManager,
+  archive: PathBuf,
+  mut calls: mpsc::UnboundedReceiver<Call>,
+) {
+  while let Some(call) = calls.recv().await {
+    let result = match call.method.to_owned() {
+      Method::ListInstalled => {
+        let records = mod_manager.records().await.unwrap();
+        Ok(json!(records.records.keys().collect::<Vec<_>>()))
+      }
+      Method::ListAvailable => Ok(json!(["example"])),
+      Method::Install { id } => mod_manager
+        .install_zip_mod(&archive, &id, "1.0")
+        .await
+        .map(|files| json!({ "files": files }))
+        .map_err(|err| err.to_string()),
+      Method::Uninstall { id } => {
+        match mod_manager.uninstall_mod(&id).await {
+          Ok(true) => Ok(json!({ "uninstalled": id })),
+          Ok(false) => Err(format!("{} is not installed", id)),
+          Err(err) => Err(err.to_string()),
+        }
+      }
+      Method::Status => Ok(json!({ "ok": true })),
+    };
+    call.reply(result);
+  }
+}
+
+#[tokio::test]
+async fn install_list_uninstall() {
+  let game = FakeGame::new("control-cycle");
+  let mut client = start(&game).await;
+
+  let response = client
+    .send(json!({
+      "id": 1,
+      "token": TOKEN,
+      "method": "install",
+      "params": { "id": "example" },
+    }))
+    .await;
+  assert_eq!(response.id, 1);
+  assert_eq!(response.result, Some(json!({ "files": 1 })));
+  assert!(game.res_mods().join("example.txt").is_file());
+
+  let response = client
+    .send(
+      json!({ "id": 2, "token": TOKEN, "method": "list_installed" }),
+    )
+    .await;
+  assert_eq!(response.result, Some(json!(["example"])));
+
+  let response = client
+    .send(json!({
+      "id": 3,
+      "token": TOKEN,
+      "method": "uninstall",
+      "params": { "id": "example" },
+    }))
+    .await;
+  assert!(response.error.is_none());
+  let response = client
+    .send(json!({
+      "id": 4,
+      "token": TOKEN,
+      "method": "uninstall",
+      "params": { "id": "example" },
+    }))
+    .await;
+  assert_eq!(response.id, 4);
+  assert!(response.result.is_none());
+  assert!(response.error.is_some());
+
+  let response = client
+    .send(
+      json!({ "id": "status", "token": TOKEN, "method": "status" }),
+    )
+    .await;
+  assert_eq!(response.id, "status");
+  assert_eq!(response.result, Some(json!({ "ok": true })));
+}
+
+#[tokio::test]
+async fn requests_need_token() {
+  let game = FakeGame::new("control-token");
+  let mut client = start(&game).await;
+
+  let response = client
+    .send(json!({
+      "id": 1,
+      "token": "wrong",
+      "method": "install",
+      "params": { "id": "example" },
+    }))
+    .await;
+  assert_eq!(response.error.as_deref(), Some("unauthorized"));
+  assert!(!game.res_mods().join("example.txt").exists());
+
+  // 格式错误的请求不会断开连接
+  let response =
+    client.send(json!({ "id": 2, "method": "status" })).await;
+  assert!(response.error.is_some());
+  let response = client
+    .send(
+      json!({ "id": 3, "token": TOKEN, "method": "list_available" }),
+    )
+    .await;
+  assert_eq!(response.result, Some(json!(["example"])));
+}
+
+#[tokio::test]
+async fn token_is_created_once() {
+  let dir = temp_dir("token-file");
+  let path = dir.join("control_token");
+  let token = load_or_create_token(&path).await.unwrap();
+  assert_eq!(token.len(), 64);
+  assert_eq!(load_or_create_token(&path).await.unwrap(), token);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+  }
+  std::fs::remove_dir_all(dir).unwrap();
+}
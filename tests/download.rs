@@ -0,0 +1,591 @@
+// 下载流程对着本地的模拟服务器测试：进度、错误、重试和断点续传
+mod common;
+
+use std::{
+  path::PathBuf,
+  sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+  },
+  time::Duration,
+};
+
+use common::{MockResponse, mock_server, mock_tls_server};
+use korabli_mod_manager::{
+  data::progress::Progress,
+  error::Error,
+  http::{HttpOptions, RedirectError, client_builder},
+  paths::downloads_dir,
+  tasks::{
+    TaskEvent,
+    download::{
+      self, DownloadRequest, DownloadTimeouts, RetryPolicy,
+      download_with_client,
+    },
+  },
+};
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+fn body() -> Vec<u8> {
+  (0..64 * 1024).map(|i| (i % 251) as u8).collect()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+  let path = std::env::temp_dir().join(format!(
+    "kmm-download-{}-{}",
+    std::process::id(),
+    name
+  ));
+  let _ = std::fs::remove_file(&path);
+  path
+}
+
+fn request(base: &str, path: PathBuf) -> DownloadRequest {
+  DownloadRequest {
+    url: Url::parse(&format!("{}/mod.zip", base)).unwrap(),
+    path,
+    expected_size: None,
+    sha256: None,
+    // 重试不用等太久
+    retry_policy: RetryPolicy {
+      max_retries: 2,
+      base_delay: Duration::from_millis(10),
+      max_delay: Duration::from_millis(10),
+    },
+    timeouts: DownloadTimeouts::default(),
+    resume_from: 0,
+  }
+}
+
+// 下载并收集所有进度
+async fn run(
+  request: DownloadRequest,
+) -> (Result<PathBuf, Error>, Vec<Progress>) {
+  let mut progress = Vec::new();
+  let res = download_with_client(&Client::new(), request, |event| {
+    if let TaskEvent::Progress(event) = event {
+      progress.push(event);
+    }
+  })
+  .await
+  .map(|file| file.path);
+  (res, progress)
+}
+
+fn assert_monotonic(progress: &[Progress], size: u64) {
+  assert!(
+    progress
+      .windows(2)
+      .all(|pair| pair[0].current <= pair[1].current)
+  );
+  assert_eq!(progress.last().unwrap().current, size);
+}
+
+#[tokio::test]
+async fn with_content_length() {
+  let (base, _) = mock_server(|_| MockResponse::ok(body())).await;
+  let path = temp_path("length");
+  let (res, progress) = run(request(&base, path.to_owned())).await;
+  assert_eq!(res.unwrap(), path);
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  let size = body().len() as u64;
+  assert_monotonic(&progress, size);
+  assert!(progress.iter().all(|progress| progress.max == Some(size)));
+  std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn without_content_length() {
+  let (base, _) = mock_server(|_| MockResponse {
+    content_length: false,
+    ..MockResponse::ok(body())
+  })
+  .await;
+  let path = temp_path("no-length");
+  let (res, progress) = run(request(&base, path.to_owned())).await;
+  res.unwrap();
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  assert_monotonic(&progress, body().len() as u64);
+  assert!(progress.iter().all(|progress| progress.max.is_none()));
+  std::fs::remove_file(path).unwrap();
+}
+
+// 4xx不是暂时性的错误，不重试
+#[tokio::test]
+async fn not_found() {
+  let (base, hits) = mock_server(|_| MockResponse::status(404)).await;
+  let path = temp_path("not-found");
+  let (res, _) = run(request(&base, path.to_owned())).await;
+  assert!(matches!(
+    res.unwrap_err(),
+    Error::Download(download::Error::Http { status, .. })
+      if status.as_u16() == 404
+  ));
+  assert_eq!(hits.load(Ordering::SeqCst), 1);
+  let _ = std::fs::remove_file(path);
+}
+
+// 传输中断后重新下载
+#[tokio::test]
+async fn disconnect_is_retried() {
+  let (base, hits) = mock_server({
+    let attempts = std::sync::atomic::AtomicUsize::new(0);
+    move |_| {
+      let truncate = (attempts.fetch_add(1, Ordering::SeqCst) == 0)
+        .then_some(1000);
+      MockResponse {
+        truncate,
+        ..MockResponse::ok(body())
+      }
+    }
+  })
+  .await;
+  let path = temp_path("disconnect");
+  let (res, progress) = run(request(&base, path.to_owned())).await;
+  res.unwrap();
+  assert_eq!(hits.load(Ordering::SeqCst), 2);
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  assert_eq!(progress.last().unwrap().current, body().len() as u64);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn disconnect_without_retries() {
+  let (base, _) = mock_server(|_| MockResponse {
+    truncate: Some(1000),
+    ..MockResponse::ok(body())
+  })
+  .await;
+  let path = temp_path("disconnect-fatal");
+  let mut request = request(&base, path.to_owned());
+  request.retry_policy.max_retries = 0;
+  let (res, _) = run(request).await;
+  assert!(matches!(
+    res.unwrap_err(),
+    Error::Download(download::Error::Reqwest(_))
+  ));
+  let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn range_resume() {
+  let (base, _) = mock_server(|request| {
+    let start = request.range.unwrap_or_default() as usize;
+    let body = body();
+    MockResponse {
+      status: if start > 0 { 206 } else { 200 },
+      ..MockResponse::ok(body[start..].to_vec())
+    }
+  })
+  .await;
+  let path = temp_path("resume");
+  std::fs::write(&path, &body()[..1000]).unwrap();
+  let mut request = request(&base, path.to_owned());
+  request.resume_from = 1000;
+  let (res, progress) = run(request).await;
+  res.unwrap();
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  assert_eq!(progress.first().unwrap().current, 1000);
+  assert_monotonic(&progress, body().len() as u64);
+  std::fs::remove_file(path).unwrap();
+}
+
+// 服务器不支持Range时从头下载，已有的部分被覆盖
+#[tokio::test]
+async fn range_ignored() {
+  let (base, _) = mock_server(|_| MockResponse::ok(body())).await;
+  let path = temp_path("range-ignored");
+  std::fs::write(&path, b"stale").unwrap();
+  let mut request = request(&base, path.to_owned());
+  request.resume_from = 5;
+  let (res, progress) = run(request).await;
+  res.unwrap();
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  assert_eq!(progress.first().unwrap().current, 0);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn checksum() {
+  let (base, hits) = mock_server(|_| MockResponse::ok(body())).await;
+  let path = temp_path("checksum");
+  let mut request = request(&base, path.to_owned());
+  request.sha256 = Some(hex::encode(Sha256::digest(body())));
+  let (res, _) = run(request.to_owned()).await;
+  res.unwrap();
+
+  // 缓存中的文件校验通过时不再请求
+  let (res, _) = run(request.to_owned()).await;
+  res.unwrap();
+  assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+  std::fs::remove_file(&path).unwrap();
+  request.sha256 = Some("0".repeat(64));
+  let (res, _) = run(request).await;
+  assert!(matches!(
+    res.unwrap_err(),
+    Error::Download(download::Error::ChecksumMismatch { .. })
+  ));
+  let _ = std::fs::remove_file(path);
+}
+
+// 缓存中已有校验通过的文件时一次请求都不发
+#[tokio::test]
+async fn cache_hit_skips_request() {
+  let (base, hits) = mock_server(|_| MockResponse::ok(body())).await;
+  let path = temp_path("cache-hit");
+  std::fs::write(&path, body()).unwrap();
+  let mut request = request(&base, path.to_owned());
+  request.sha256 = Some(hex::encode(Sha256::digest(body())));
+  let (res, progress) = run(request).await;
+  assert_eq!(res.unwrap(), path);
+  assert!(progress.is_empty());
+  assert_eq!(hits.load(Ordering::SeqCst), 0);
+  std::fs::remove_file(path).unwrap();
+}
+
+// 缓存中的文件和sha256不符时重新下载并覆盖
+#[tokio::test]
+async fn cache_mismatch_is_refetched() {
+  let (base, hits) = mock_server(|_| MockResponse::ok(body())).await;
+  let path = temp_path("cache-mismatch");
+  std::fs::write(&path, b"stale archive").unwrap();
+  let mut request = request(&base, path.to_owned());
+  request.sha256 = Some(hex::encode(Sha256::digest(body())));
+  let (res, _) = run(request).await;
+  res.unwrap();
+  assert_eq!(hits.load(Ordering::SeqCst), 1);
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  std::fs::remove_file(path).unwrap();
+}
+
+// id和版本中不能用在文件名里的字符被去掉，不会跑出下载目录
+#[test]
+fn cached_path_is_sanitized() {
+  let url =
+    Url::parse("https://example.com/files/mod.zip?x=1").unwrap();
+  let path = download::cached_download_path("../a/b:c", "1.0?", &url);
+  assert_eq!(path.parent().unwrap(), downloads_dir());
+  let name = path.file_name().unwrap().to_string_lossy();
+  assert!(name.ends_with(".zip"), "{}", name);
+  assert!(!name.contains(['/', '\\', ':', '?']), "{}", name);
+  // 同一模组的同一版本总是同一个位置
+  assert_eq!(
+    download::cached_download_path("../a/b:c", "1.0?", &url),
+    path
+  );
+  assert_ne!(
+    download::cached_download_path("../a/b:c", "2.0", &url),
+    path
+  );
+}
+
+// 5xx是暂时性的，重试后成功
+#[tokio::test]
+async fn server_error_is_retried() {
+  let (base, hits) = mock_server({
+    let attempts = std::sync::atomic::AtomicUsize::new(0);
+    move |_| match attempts.fetch_add(1, Ordering::SeqCst) {
+      0 => MockResponse::status(503),
+      _ => MockResponse::ok(body()),
+    }
+  })
+  .await;
+  let path = temp_path("server-error");
+  let (res, progress) = run(request(&base, path.to_owned())).await;
+  res.unwrap();
+  assert_eq!(hits.load(Ordering::SeqCst), 2);
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  assert_monotonic(&progress[1..], body().len() as u64);
+  std::fs::remove_file(path).unwrap();
+}
+
+// 用完重试次数后报告最后一次的错误
+#[tokio::test]
+async fn server_error_exhausts_retries() {
+  let (base, hits) = mock_server(|_| MockResponse::status(502)).await;
+  let path = temp_path("server-error-fatal");
+  let (res, _) = run(request(&base, path.to_owned())).await;
+  assert!(matches!(
+    res.unwrap_err(),
+    Error::Download(download::Error::Http { status, .. })
+      if status.as_u16() == 502
+  ));
+  // 第一次加上两次重试
+  assert_eq!(hits.load(Ordering::SeqCst), 3);
+  let _ = std::fs::remove_file(path);
+}
+
+// 内容不对时重新下载也没有用
+#[tokio::test]
+async fn checksum_mismatch_is_not_retried() {
+  let (base, hits) = mock_server(|_| MockResponse::ok(body())).await;
+  let path = temp_path("checksum-fatal");
+  let mut request = request(&base, path.to_owned());
+  request.sha256 = Some("0".repeat(64));
+  let (res, _) = run(request).await;
+  let err = res.unwrap_err();
+  assert!(
+    matches!(
+      &err,
+      Error::Download(download::Error::ChecksumMismatch { actual, .. })
+        if *actual == hex::encode(Sha256::digest(body()))
+    ),
+    "{}",
+    err
+  );
+  assert_eq!(hits.load(Ordering::SeqCst), 1);
+  let _ = std::fs::remove_file(path);
+}
+
+// 续传得到的文件按完整内容校验
+#[tokio::test]
+async fn range_resume_verifies_whole_file() {
+  let (base, _) = mock_server(|request| {
+    let start = request.range.unwrap_or_default() as usize;
+    MockResponse {
+      status: if start > 0 { 206 } else { 200 },
+      ..MockResponse::ok(body()[start..].to_vec())
+    }
+  })
+  .await;
+  let path = temp_path("resume-checksum");
+  std::fs::write(&path, &body()[..4096]).unwrap();
+  let mut request = request(&base, path.to_owned());
+  request.resume_from = 4096;
+  request.sha256 = Some(hex::encode(Sha256::digest(body())));
+  let (res, _) = run(request.to_owned()).await;
+  res.unwrap();
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+
+  // 已有的部分是坏的，续传后校验失败
+  let mut corrupted = body()[..4096].to_vec();
+  corrupted[0] ^= 0xFF;
+  std::fs::write(&path, corrupted).unwrap();
+  let (res, _) = run(request).await;
+  assert!(matches!(
+    res.unwrap_err(),
+    Error::Download(download::Error::ChecksumMismatch { .. })
+  ));
+  let _ = std::fs::remove_file(path);
+}
+
+// file://地址直接复制，同样支持续传和校验
+#[tokio::test]
+async fn file_url() {
+  let source = temp_path("fixture.zip");
+  std::fs::write(&source, body()).unwrap();
+  let path = temp_path("from-file");
+  let mut request = DownloadRequest {
+    url: Url::from_file_path(&source).unwrap(),
+    sha256: Some(hex::encode(Sha256::digest(body()))),
+    ..request("http://unused", path.to_owned())
+  };
+  let (res, progress) = run(request.to_owned()).await;
+  res.unwrap();
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  assert_monotonic(&progress, body().len() as u64);
+
+  std::fs::write(&path, &body()[..100]).unwrap();
+  request.resume_from = 100;
+  let (res, progress) = run(request.to_owned()).await;
+  res.unwrap();
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  assert_eq!(progress.first().unwrap().current, 100);
+
+  std::fs::remove_file(&path).unwrap();
+  request.resume_from = 0;
+  request.sha256 = Some("0".repeat(64));
+  let (res, _) = run(request).await;
+  assert!(matches!(
+    res.unwrap_err(),
+    Error::Download(download::Error::ChecksumMismatch { .. })
+  ));
+  std::fs::remove_file(source).unwrap();
+  let _ = std::fs::remove_file(path);
+}
+
+// 本地文件不存在不是暂时性的错误
+#[tokio::test]
+async fn missing_file_url() {
+  let source = temp_path("missing-fixture.zip");
+  let path = temp_path("from-missing-file");
+  let request = DownloadRequest {
+    url: Url::from_file_path(&source).unwrap(),
+    ..request("http://unused", path.to_owned())
+  };
+  let (res, _) = run(request).await;
+  let err = res.unwrap_err();
+  assert!(
+    matches!(&err, Error::Download(download::Error::Io(err)) if err.kind() == std::io::ErrorKind::NotFound),
+    "{}",
+    err
+  );
+  let _ = std::fs::remove_file(path);
+}
+
+// 发了一部分就不再发送的服务器
+fn stalled() -> MockResponse {
+  MockResponse {
+    truncate: Some(1000),
+    stall: true,
+    ..MockResponse::ok(body())
+  }
+}
+
+// 连续一段时间没有数据时报告超时，不会一直卡住
+#[tokio::test]
+async fn stalled_stream_times_out() {
+  let (base, hits) = mock_server(|_| stalled()).await;
+  let path = temp_path("stalled");
+  let mut request = request(&base, path.to_owned());
+  request.retry_policy.max_retries = 0;
+  request.timeouts.idle = Duration::from_millis(200);
+  let (res, progress) =
+    tokio::time::timeout(Duration::from_secs(10), run(request))
+      .await
+      .expect("download should give up on its own");
+  let err = res.unwrap_err();
+  assert!(
+    matches!(
+      &err,
+      Error::Download(download::Error::Timeout { after })
+        if *after == Duration::from_millis(200)
+    ),
+    "{}",
+    err
+  );
+  assert_eq!(hits.load(Ordering::SeqCst), 1);
+  assert_eq!(progress.last().unwrap().current, 1000);
+  let _ = std::fs::remove_file(path);
+}
+
+// 超时是暂时性的，重试时从头下载
+#[tokio::test]
+async fn stalled_stream_is_retried() {
+  let (base, hits) = mock_server({
+    let attempts = std::sync::atomic::AtomicUsize::new(0);
+    move |_| match attempts.fetch_add(1, Ordering::SeqCst) {
+      0 => stalled(),
+      _ => MockResponse::ok(body()),
+    }
+  })
+  .await;
+  let path = temp_path("stalled-retry");
+  let mut request = request(&base, path.to_owned());
+  request.timeouts.idle = Duration::from_millis(200);
+  let (res, _) =
+    tokio::time::timeout(Duration::from_secs(10), run(request))
+      .await
+      .unwrap();
+  res.unwrap();
+  assert_eq!(hits.load(Ordering::SeqCst), 2);
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  std::fs::remove_file(path).unwrap();
+}
+
+// 和正式使用的客户端相同的配置，只是信任测试用的证书
+fn redirect_client(allow_insecure_redirects: bool) -> Client {
+  client_builder(&HttpOptions {
+    allow_insecure_redirects,
+    ..HttpOptions::default()
+  })
+  .danger_accept_invalid_certs(true)
+  .build()
+  .unwrap()
+}
+
+// HTTPS服务器重定向到HTTP服务器上的文件
+async fn downgrade_servers() -> (String, String, Arc<AtomicUsize>) {
+  let (http, hits) = mock_server(|_| MockResponse::ok(body())).await;
+  let target = format!("{}/mirror/mod.zip", http);
+  let (https, _) = mock_tls_server({
+    let target = target.to_owned();
+    move |_| MockResponse {
+      status: 302,
+      headers: vec![("Location".to_string(), target.to_owned())],
+      ..MockResponse::ok(Vec::new())
+    }
+  })
+  .await;
+  (https, target, hits)
+}
+
+#[tokio::test]
+async fn insecure_redirect_is_refused() {
+  let (https, target, hits) = downgrade_servers().await;
+  let path = temp_path("insecure-redirect");
+  let res = download_with_client(
+    &redirect_client(false),
+    request(&https, path.to_owned()),
+    |_| {},
+  )
+  .await;
+  let err = res.unwrap_err();
+  assert!(
+    matches!(
+      &err,
+      Error::Download(download::Error::Redirect(
+        RedirectError::InsecureRedirect { url }
+      )) if url.as_str() == target
+    ),
+    "{}",
+    err
+  );
+  // 提示中要能看到被拒绝的地址
+  assert!(err.to_string().contains(&target), "{}", err);
+  // 被拒绝的重定向不重试，也不会去请求HTTP地址
+  assert_eq!(hits.load(Ordering::SeqCst), 0);
+  assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn insecure_redirect_allowed_by_setting() {
+  let (https, _, hits) = downgrade_servers().await;
+  let path = temp_path("insecure-redirect-allowed");
+  download_with_client(
+    &redirect_client(true),
+    request(&https, path.to_owned()),
+    |_| {},
+  )
+  .await
+  .unwrap();
+  assert_eq!(hits.load(Ordering::SeqCst), 1);
+  assert_eq!(std::fs::read(&path).unwrap(), body());
+  std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn redirect_loop_is_cut_off() {
+  let (base, hits) = mock_server(|request| MockResponse {
+    status: 302,
+    headers: vec![(
+      "Location".to_string(),
+      format!("{}x", request.path),
+    )],
+    ..MockResponse::ok(Vec::new())
+  })
+  .await;
+  let path = temp_path("redirect-loop");
+  let res = download_with_client(
+    &redirect_client(false),
+    request(&base, path.to_owned()),
+    |_| {},
+  )
+  .await;
+  let err = res.unwrap_err();
+  assert!(
+    matches!(
+      &err,
+      Error::Download(download::Error::Redirect(
+        RedirectError::TooManyRedirects { url }
+      )) if url.path().ends_with("xxxxxxxxxx")
+    ),
+    "{}",
+    err
+  );
+  // 第一次请求加上10次重定向
+  assert_eq!(hits.load(Ordering::SeqCst), 11);
+}
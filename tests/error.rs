@@ -0,0 +1,99 @@
+// 用户看到的错误文字要保持稳定，这里固定使用英文检查
+use std::path::PathBuf;
+
+use korabli_mod_manager::{
+  error::{Error, Operation, ResultExt},
+  i18n::{Locale, set_locale},
+  mod_manager,
+  tasks::{download, install},
+};
+
+fn english() {
+  set_locale(Locale::En);
+}
+
+#[test]
+fn converts_module_errors() {
+  english();
+  let err = Error::from(mod_manager::Error::ResModsDirNotFound {
+    game_dir_path: PathBuf::from("/games/korabli"),
+  });
+  assert!(matches!(err, Error::ModManager(_)));
+  assert_eq!(
+    err.to_string(),
+    "Failed to change game files: ResModsDirNotFound: /games/korabli"
+  );
+
+  let err = Error::from(std::io::Error::other("disk full"));
+  assert!(matches!(err, Error::Io(_)));
+  assert_eq!(err.to_string(), "File access failed: disk full");
+
+  let err = Error::from(url::Url::parse("registry").unwrap_err());
+  assert_eq!(
+    err.to_string(),
+    "Invalid URL: relative URL without a base"
+  );
+}
+
+#[test]
+fn task_errors() {
+  english();
+  let err = Error::from(download::Error::ChecksumMismatch {
+    expected: "aa".to_string(),
+    actual: "bb".to_string(),
+  });
+  assert_eq!(
+    err.to_string(),
+    "Download failed: ChecksumMismatch: expected aa, got bb"
+  );
+
+  let err = Error::from(install::Error::UnknownArchive(
+    PathBuf::from("example.bin"),
+  ));
+  assert_eq!(
+    err.to_string(),
+    "Install failed: UnknownArchive: example.bin"
+  );
+
+  let err =
+    Error::from(install::Error::InvalidArchive { size: 2048 });
+  assert_eq!(
+    err.to_string(),
+    "Install failed: the downloaded file is not a valid archive (the download may be incomplete or the link broken), size: 2.0 KB"
+  );
+}
+
+#[test]
+fn context_names_operation_and_mod() {
+  english();
+  let err = Error::NotFound("example".to_string())
+    .context(Operation::Install, "example");
+  assert_eq!(
+    err.to_string(),
+    "Install example: Mod example was not found"
+  );
+  assert!(matches!(err.root(), Error::NotFound(_)));
+  // 里面的错误已经在文字中，不再重复作为source
+  assert!(std::error::Error::source(&err).is_none());
+
+  let res: Result<(), _> = Err(mod_manager::Error::FileConflict {
+    file: PathBuf::from("gui/example.xml"),
+  });
+  let err = res.context(Operation::Update, "example").unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    "Update example: Failed to change game files: FileCoflict: files: gui/example.xml"
+  );
+  assert!(matches!(err.root(), Error::ModManager(_)));
+}
+
+#[test]
+fn not_installed() {
+  english();
+  let err = Error::NotInstalled("example".to_string())
+    .context(Operation::Uninstall, "example");
+  assert_eq!(
+    err.to_string(),
+    "Uninstall example: Mod example is not installed"
+  );
+}
@@ -0,0 +1,8 @@
+[Setup]
+Lang=en
+Dir=C:\Games\Korabli
+Group=(Default)
+NoIcons=0
+SetupType=custom
+Components=crosshairs,crosshairs\Modern_Crosshair,!minimap,sounds\Modern Sound Mod,sounds\Modern Sound Mod,ports\Custom_Port_Skin,*Damage_Meter,!Unknown_Flag
+Tasks=
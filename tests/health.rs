@@ -0,0 +1,104 @@
+// 健康检查：由安装记录和res_mods的遍历结果生成报告
+use std::{collections::HashMap, path::PathBuf};
+
+use korabli_mod_manager::{
+  data::health::{HealthReport, ModHealth},
+  mod_manager::{Record, Records},
+};
+
+fn paths(paths: &[&str]) -> Vec<PathBuf> {
+  paths.iter().map(PathBuf::from).collect()
+}
+
+fn records(mods: &[(&str, &[&str])]) -> Records {
+  Records {
+    records: mods
+      .iter()
+      .map(|(id, files)| {
+        (
+          id.to_string(),
+          Record {
+            metadata: None,
+            update_time: 0,
+            version: "1.0".into(),
+            files: paths(files),
+          },
+        )
+      })
+      .collect::<HashMap<_, _>>(),
+    ..Default::default()
+  }
+}
+
+#[test]
+fn intact_install_is_healthy() {
+  let records =
+    records(&[("a", &["gui", "gui/a.png"]), ("empty", &[])]);
+  let report = HealthReport::build(
+    &records,
+    &paths(&["gui/a.png", ".kmmgr.json"]),
+    &paths(&["gui"]),
+  );
+  assert!(report.is_healthy());
+  assert_eq!(report.mods.len(), 2);
+  assert_eq!(report.broken_mods().count(), 0);
+}
+
+#[test]
+fn missing_files_are_reported_per_mod() {
+  let records = records(&[
+    ("partial", &["gui", "gui/a.png", "gui/b.png"]),
+    ("gone", &["banks/c.bnk", "banks/d.bnk"]),
+    ("ok", &["e.txt"]),
+  ]);
+  let report = HealthReport::build(
+    &records,
+    &paths(&["gui/b.png", "e.txt"]),
+    &paths(&["gui", "banks"]),
+  );
+  assert!(!report.is_healthy());
+  let health = report
+    .broken_mods()
+    .map(|mod_report| {
+      (mod_report.id.as_str(), mod_report.health.to_owned())
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(
+    health,
+    [
+      ("gone", ModHealth::AllMissing),
+      ("partial", ModHealth::Missing(paths(&["gui/a.png"]))),
+    ]
+  );
+  assert!(report.orphans.is_empty());
+}
+
+#[test]
+fn unrecorded_files_are_classified() {
+  let records = records(&[("a", &["gui/a.png"])]);
+  let report = HealthReport::build(
+    &records,
+    &paths(&[
+      "gui/a.png",
+      "gui/manual.png",
+      "gui/a.png.bak",
+      "gui/a.png~",
+      "config.XML.Orig",
+      "download.part",
+      "gui/~$lock.tmp",
+      ".kmmgr.json",
+    ]),
+    &paths(&["gui", "empty_dir"]),
+  );
+  assert_eq!(report.broken_mods().count(), 0);
+  assert_eq!(report.orphans, paths(&["gui/manual.png"]));
+  assert_eq!(
+    report.backups,
+    paths(&["config.XML.Orig", "gui/a.png.bak", "gui/a.png~"])
+  );
+  assert_eq!(
+    report.temp_files,
+    paths(&["download.part", "gui/~$lock.tmp"])
+  );
+  assert!(!report.is_healthy());
+}
@@ -0,0 +1,102 @@
+// 其他安装器选择文件的解析和匹配
+use korabli_mod_manager::data::import::{
+  SelectionMatch, match_selection, parse_selection,
+};
+
+const MODS: [(&str, &str); 5] = [
+  ("modern_crosshair", "Modern Crosshair"),
+  ("modern_sound", "Modern Sound Mod"),
+  ("damage_meter", "Damage Meter"),
+  ("port_skins", "Custom Port Skins"),
+  ("minimap", "Minimap"),
+];
+
+#[test]
+fn parses_aslain_options() {
+  let entries = parse_selection(include_str!(
+    "fixtures/import/aslain_options.inl"
+  ));
+  assert_eq!(
+    entries,
+    vec![
+      "crosshairs",
+      "Modern_Crosshair",
+      "Modern Sound Mod",
+      "Custom_Port_Skin",
+      "Damage_Meter",
+    ]
+  );
+}
+
+#[test]
+fn parses_plain_list() {
+  let entries =
+    parse_selection(include_str!("fixtures/import/list.txt"));
+  assert_eq!(
+    entries,
+    vec![
+      "Modern Crosshair",
+      "modern_sound_mod",
+      "Damage-Meter",
+      "Totally Unknown Mod",
+    ]
+  );
+}
+
+#[test]
+fn matches_aslain_options() {
+  let entries = parse_selection(include_str!(
+    "fixtures/import/aslain_options.inl"
+  ));
+  assert_eq!(
+    match_selection(&entries, MODS),
+    SelectionMatch {
+      ids: vec![
+        "modern_crosshair".to_string(),
+        "modern_sound".to_string(),
+        "port_skins".to_string(),
+        "damage_meter".to_string(),
+      ],
+      unmatched: vec!["crosshairs".to_string()],
+    }
+  );
+}
+
+#[test]
+fn matches_plain_list() {
+  let entries =
+    parse_selection(include_str!("fixtures/import/list.txt"));
+  let result = match_selection(&entries, MODS);
+  assert_eq!(
+    result.ids,
+    vec!["modern_crosshair", "modern_sound", "damage_meter"]
+  );
+  assert_eq!(result.unmatched, vec!["Totally Unknown Mod"]);
+}
+
+#[test]
+fn tolerates_typos_but_not_short_fragments() {
+  let entries = ["Modern Crosshiar".to_string(), "mod".to_string()];
+  let result = match_selection(&entries, MODS);
+  assert_eq!(result.ids, vec!["modern_crosshair"]);
+  assert_eq!(result.unmatched, vec!["mod"]);
+}
+
+// 同一个模组只选一次
+#[test]
+fn duplicate_matches_are_merged() {
+  let entries = [
+    "minimap".to_string(),
+    "Minimap".to_string(),
+    "Mini-Map".to_string(),
+  ];
+  let result = match_selection(&entries, MODS);
+  assert_eq!(result.ids, vec!["minimap"]);
+  assert!(result.unmatched.is_empty());
+}
+
+#[test]
+fn empty_selection() {
+  assert!(parse_selection("\u{feff}\n# nothing\n\n").is_empty());
+  assert!(parse_selection("[Setup]\nComponents=\n").is_empty());
+}
@@ -0,0 +1,387 @@
+// 在临时的游戏目录中完整地走一遍安装、升级、卸载，检查磁盘和安装记录
+mod common;
+
+use std::{
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use chrono::{Local, TimeZone};
+use common::{FakeGame, GBK_NAME, raw_zip, zip};
+use korabli_mod_manager::{
+  data::archive::ArchiveType,
+  install_log::{latest_log, log_name, rotate},
+  mod_manager::Error,
+  tasks::install::{self, verify_archive},
+};
+
+#[tokio::test]
+async fn install() {
+  let game = FakeGame::new("install");
+  let archive = game
+    .archive(
+      "example.zip",
+      &[("example/", ""), ("example/a.txt", "a"), ("b.txt", "b")],
+    )
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  let count = mod_manager
+    .install_zip_mod(&archive, "example", "1.0")
+    .await
+    .unwrap();
+  assert_eq!(count, 3);
+  assert_eq!(game.files(), [".kmmgr.json", "b.txt", "example/a.txt"]);
+  assert_eq!(game.read("example/a.txt"), "a");
+
+  let records = mod_manager.records().await.unwrap();
+  let record = &records.records["example"];
+  assert_eq!(record.version, "1.0");
+  assert_eq!(
+    record.files,
+    [
+      PathBuf::from("example"),
+      PathBuf::from("example/a.txt"),
+      PathBuf::from("b.txt"),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn upgrade() {
+  let game = FakeGame::new("upgrade");
+  let old = game
+    .archive("example-1.0.zip", &[("a.txt", "1.0"), ("b.txt", "1.0")])
+    .await;
+  let new = game
+    .archive("example-2.0.zip", &[("a.txt", "2.0"), ("c.txt", "2.0")])
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&old, "example", "1.0")
+    .await
+    .unwrap();
+  mod_manager.set_note("example", "keep me").await.unwrap();
+
+  // 覆盖自己旧版本的文件不算冲突，新版本不再包含的文件被删除
+  mod_manager
+    .install_zip_mod(&new, "example", "2.0")
+    .await
+    .unwrap();
+  assert_eq!(game.files(), [".kmmgr.json", "a.txt", "c.txt"]);
+  assert_eq!(game.read("a.txt"), "2.0");
+
+  let records = mod_manager.records().await.unwrap();
+  let record = &records.records["example"];
+  assert_eq!(record.version, "2.0");
+  assert_eq!(record.note(), "keep me");
+}
+
+#[tokio::test]
+async fn uninstall() {
+  let game = FakeGame::new("uninstall");
+  let archive = game
+    .archive("example.zip", &[("dir/", ""), ("dir/a.txt", "a")])
+    .await;
+  let other = game.archive("other.zip", &[("other.txt", "o")]).await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&archive, "example", "1.0")
+    .await
+    .unwrap();
+  mod_manager
+    .install_zip_mod(&other, "other", "1.0")
+    .await
+    .unwrap();
+  std::fs::write(game.res_mods().join("user.txt"), "mine").unwrap();
+
+  assert!(mod_manager.uninstall_mod("example").await.unwrap());
+  // 其他模组和用户自己的文件不受影响
+  assert_eq!(game.files(), [".kmmgr.json", "other.txt", "user.txt"]);
+  let records = mod_manager.records().await.unwrap();
+  assert!(!records.records.contains_key("example"));
+  assert!(records.records.contains_key("other"));
+
+  assert!(!mod_manager.uninstall_mod("example").await.unwrap());
+}
+
+#[tokio::test]
+async fn conflict() {
+  let game = FakeGame::new("conflict");
+  let first =
+    game.archive("first.zip", &[("shared.txt", "first")]).await;
+  let second = game
+    .archive(
+      "second.zip",
+      &[("own.txt", "second"), ("shared.txt", "second")],
+    )
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&first, "first", "1.0")
+    .await
+    .unwrap();
+
+  let err = mod_manager
+    .install_zip_mod(&second, "second", "1.0")
+    .await
+    .unwrap_err();
+  assert!(matches!(
+    err,
+    Error::FileConflict { file } if file == Path::new("shared.txt")
+  ));
+  // 冲突时什么都不写入
+  assert_eq!(game.files(), [".kmmgr.json", "shared.txt"]);
+  assert_eq!(game.read("shared.txt"), "first");
+  let records = mod_manager.records().await.unwrap();
+  assert!(!records.records.contains_key("second"));
+}
+
+#[tokio::test]
+async fn user_files_conflict() {
+  let game = FakeGame::new("user-conflict");
+  std::fs::write(game.res_mods().join("user.txt"), "mine").unwrap();
+  let archive =
+    game.archive("example.zip", &[("user.txt", "mod")]).await;
+  let mut mod_manager = game.mod_manager().await;
+  let res = mod_manager
+    .install_zip_mod(&archive, "example", "1.0")
+    .await;
+  assert!(matches!(res, Err(Error::FileConflict { .. })));
+  assert_eq!(game.read("user.txt"), "mine");
+}
+
+// 试图写到res_mods之外的路径被限制在res_mods之内
+#[tokio::test]
+async fn traversal() {
+  let game = FakeGame::new("traversal");
+  let archive = game
+    .archive(
+      "evil.zip",
+      &[("../escaped.txt", "evil"), ("..\\..\\escaped2.txt", "evil")],
+    )
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&archive, "evil", "1.0")
+    .await
+    .unwrap();
+  assert!(!game.root.join("bin/1000/escaped.txt").exists());
+  assert!(!game.root.join("bin/escaped2.txt").exists());
+  assert_eq!(
+    game.files(),
+    [".kmmgr.json", "escaped.txt", "escaped2.txt"]
+  );
+
+  assert!(mod_manager.uninstall_mod("evil").await.unwrap());
+  assert_eq!(game.files(), [".kmmgr.json"]);
+}
+
+// 没有UTF-8标记的非ASCII文件名目前无法解析，安装失败且不写入任何文件
+#[tokio::test]
+async fn gbk_names() {
+  let game = FakeGame::new("gbk");
+  let archive = game.root.join("gbk.zip");
+  std::fs::write(
+    &archive,
+    raw_zip(&[(b"readme.txt", "ascii"), (GBK_NAME, "gbk")]).await,
+  )
+  .unwrap();
+  let mut mod_manager = game.mod_manager().await;
+  let res = mod_manager.install_zip_mod(&archive, "gbk", "1.0").await;
+  assert!(matches!(res, Err(Error::AsyncZip(_))));
+  assert_eq!(game.files(), [".kmmgr.json"]);
+  let records = mod_manager.records().await.unwrap();
+  assert!(records.records.is_empty());
+}
+
+// 单个很大的条目在写入过程中也有进度，按缓冲区大小分批写入
+#[tokio::test]
+async fn large_entry_progress() {
+  let game = FakeGame::new("large-entry");
+  let large = "x".repeat(16 * 1024 * 1024);
+  let archive = game
+    .archive(
+      "large.zip",
+      &[("small.txt", "s"), ("large.bin", &large)],
+    )
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager.set_extract_buffer_size(64 * 1024);
+  let mut events = Vec::new();
+  mod_manager
+    .install_zip_mod_with_progress(
+      &archive,
+      "large",
+      "1.0",
+      |progress| events.push(progress),
+    )
+    .await
+    .unwrap();
+
+  let total = large.len() as u64 + 1;
+  assert!(events.iter().all(|progress| progress.max == Some(total)));
+  assert!(
+    events
+      .windows(2)
+      .all(|pair| pair[0].current <= pair[1].current)
+  );
+  // 第一个事件是小文件，之后都在大文件中途
+  let during = events
+    .iter()
+    .filter(|progress| {
+      progress.current > 1 && progress.current < total
+    })
+    .count();
+  assert!(during >= 100, "only {} events during the entry", during);
+  assert_eq!(events.last().unwrap().current, total);
+  assert_eq!(
+    std::fs::metadata(game.res_mods().join("large.bin"))
+      .unwrap()
+      .len(),
+    large.len() as u64
+  );
+}
+
+// 安装和卸载各留下一份日志，写入和删除的文件都列在里面
+#[tokio::test]
+async fn install_log() {
+  let game = FakeGame::new("install-log");
+  let archive = game
+    .archive(
+      "example.zip",
+      &[("example/", ""), ("example/a.txt", "a"), ("b.txt", "b")],
+    )
+    .await;
+  let logs = game.root.join("logs");
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager.set_log_dir(logs.to_owned());
+  mod_manager
+    .install_zip_mod(&archive, "example", "1.0")
+    .await
+    .unwrap();
+
+  let log = latest_log(&logs, "example").await.unwrap();
+  let name = log.file_name().unwrap().to_string_lossy().to_string();
+  assert!(name.starts_with("install-example-"), "{}", name);
+  let content = std::fs::read_to_string(&log).unwrap();
+  assert!(content.contains("版本: 1.0"));
+  assert!(content.contains("目录: example"));
+  for file in [Path::new("example").join("a.txt"), "b.txt".into()] {
+    assert!(
+      content.contains(&format!("写入: {}", file.display())),
+      "{}",
+      content
+    );
+  }
+  assert!(content.contains("结果: 成功，写入了3个文件"));
+
+  // 时间戳精确到毫秒，隔开一点保证先后
+  tokio::time::sleep(Duration::from_millis(5)).await;
+  mod_manager.uninstall_mod("example").await.unwrap();
+  let log = latest_log(&logs, "example").await.unwrap();
+  let name = log.file_name().unwrap().to_string_lossy().to_string();
+  assert!(name.starts_with("uninstall-example-"), "{}", name);
+  let content = std::fs::read_to_string(&log).unwrap();
+  assert!(content.contains("删除: b.txt"));
+  assert!(content.contains("结果: 成功"));
+  assert_eq!(std::fs::read_dir(&logs).unwrap().count(), 2);
+
+  // 没有记录的模组什么都不做，也不留日志
+  assert!(!mod_manager.uninstall_mod("example").await.unwrap());
+  assert_eq!(std::fs::read_dir(&logs).unwrap().count(), 2);
+  assert!(latest_log(&logs, "other").await.is_none());
+}
+
+#[tokio::test]
+async fn install_log_records_conflicts() {
+  let game = FakeGame::new("install-log-conflict");
+  let first = game.archive("first.zip", &[("shared.txt", "1")]).await;
+  let second =
+    game.archive("second.zip", &[("shared.txt", "2")]).await;
+  let logs = game.root.join("logs");
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager.set_log_dir(logs.to_owned());
+  mod_manager
+    .install_zip_mod(&first, "first", "1.0")
+    .await
+    .unwrap();
+  let res =
+    mod_manager.install_zip_mod(&second, "second", "1.0").await;
+  assert!(matches!(res, Err(Error::FileConflict { .. })));
+
+  let log = latest_log(&logs, "second").await.unwrap();
+  let content = std::fs::read_to_string(&log).unwrap();
+  assert!(
+    content.contains("冲突: shared.txt (first)"),
+    "{}",
+    content
+  );
+  assert!(content.contains("结果: 失败"));
+  assert!(!content.contains("写入:"));
+}
+
+// 只保留最新的几份，导出的任务日志等其他文件不受影响
+#[tokio::test]
+async fn install_logs_are_capped() {
+  let game = FakeGame::new("install-log-rotate");
+  let logs = game.root.join("logs");
+  std::fs::create_dir_all(&logs).unwrap();
+  let names = (0..3)
+    .map(|minute| {
+      let time =
+        Local.with_ymd_and_hms(2024, 1, 1, 12, minute, 0).unwrap();
+      let name = log_name("install", "example", time);
+      std::fs::write(logs.join(&name), "").unwrap();
+      name
+    })
+    .collect::<Vec<_>>();
+  std::fs::write(logs.join("example-0.log"), "").unwrap();
+
+  rotate(&logs, 2).await.unwrap();
+  assert!(!logs.join(&names[0]).exists());
+  assert!(logs.join(&names[1]).exists());
+  assert!(logs.join(&names[2]).exists());
+  assert!(logs.join("example-0.log").exists());
+  assert_eq!(
+    latest_log(&logs, "example").await.unwrap(),
+    logs.join(&names[2])
+  );
+}
+
+// 错误页面和没下载完的zip在解压前就被认出来，报告文件大小
+#[tokio::test]
+async fn verify_rejects_invalid_archives() {
+  let game = FakeGame::new("verify");
+  let dir = game.root.join("archives");
+  std::fs::create_dir_all(&dir).unwrap();
+  let html = dir.join("error.zip");
+  let page = "<!DOCTYPE html><html><body>404</body></html>";
+  std::fs::write(&html, page).unwrap();
+  assert!(matches!(
+    verify_archive(&html, Some(ArchiveType::Zip)).await,
+    Err(install::Error::InvalidArchive { size }) if size == page.len() as u64
+  ));
+  assert!(matches!(
+    verify_archive(&html, None).await,
+    Err(install::Error::UnknownArchive(_))
+  ));
+
+  let bytes = zip(&[("a.txt", &"a".repeat(1000))]).await;
+  let complete = dir.join("complete.zip");
+  std::fs::write(&complete, &bytes).unwrap();
+  assert_eq!(
+    verify_archive(&complete, None).await.unwrap(),
+    ArchiveType::Zip
+  );
+  // 在中央目录结尾记录之前截断
+  let eocd = bytes
+    .windows(4)
+    .rposition(|window| window == b"PK\x05\x06")
+    .unwrap();
+  let truncated = dir.join("truncated.zip");
+  std::fs::write(&truncated, &bytes[..eocd]).unwrap();
+  assert!(matches!(
+    verify_archive(&truncated, None).await,
+    Err(install::Error::InvalidArchive { size }) if size == eocd as u64
+  ));
+}
@@ -0,0 +1,369 @@
+// ModManager对游戏目录的识别，以及安装和卸载时发出的事件
+mod common;
+
+use std::path::Path;
+
+use common::FakeGame;
+use korabli_mod_manager::{
+  data::{
+    archive::sha256_file,
+    health::{ADOPTED_ID, ModHealth},
+  },
+  mod_manager::{
+    Conflict, Error, ModEvent, ModManager, find_conflicts,
+    list_zip_entries,
+  },
+};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn picks_highest_numeric_build() {
+  let game =
+    FakeGame::with_builds("normal", &["999", "1000", "latest"]);
+  // 数字命名的文件不是构建目录
+  std::fs::write(game.root.join("bin/2000"), "").unwrap();
+  let mod_manager =
+    ModManager::try_from_game_dir(&game.root).await.unwrap();
+  assert_eq!(mod_manager.game_version().await, "1000");
+}
+
+#[tokio::test]
+async fn empty_bin_is_not_a_game_dir() {
+  let game = FakeGame::with_builds("empty", &[]);
+  let res = ModManager::try_from_game_dir(&game.root).await;
+  assert!(matches!(res, Err(Error::ResModsDirNotFound { .. })));
+}
+
+#[tokio::test]
+async fn non_numeric_builds_are_ignored() {
+  let game =
+    FakeGame::with_builds("non-numeric", &["latest", "1000a"]);
+  let res = ModManager::try_from_game_dir(&game.root).await;
+  assert!(matches!(res, Err(Error::ResModsDirNotFound { .. })));
+}
+
+#[tokio::test]
+async fn missing_bin_is_io_error() {
+  let game = FakeGame::with_builds("missing", &[]);
+  std::fs::remove_dir(game.root.join("bin")).unwrap();
+  let res = ModManager::try_from_game_dir(&game.root).await;
+  assert!(matches!(res, Err(Error::Io(_))));
+}
+
+#[cfg(unix)]
+fn link_dir(target: &Path, link: &Path) {
+  std::os::unix::fs::symlink(target, link).unwrap();
+}
+
+// 玩家一般用目录联接挪走res_mods，不需要管理员权限
+#[cfg(windows)]
+fn link_dir(target: &Path, link: &Path) {
+  let status = std::process::Command::new("cmd")
+    .args(["/C", "mklink", "/J"])
+    .arg(link)
+    .arg(target)
+    .status()
+    .unwrap();
+  assert!(status.success());
+}
+
+// 链接到游戏目录中的其他位置不需要额外的设置，保存的是真实位置
+#[tokio::test]
+async fn res_mods_link_inside_game_dir() {
+  let game = FakeGame::with_builds("link-inside", &["1000"]);
+  let target = game.root.join("mods");
+  std::fs::create_dir_all(&target).unwrap();
+  link_dir(&target, &game.res_mods());
+
+  let mut mod_manager =
+    ModManager::try_from_game_dir(&game.root).await.unwrap();
+  assert_eq!(mod_manager.res_mods_path(), target);
+  mod_manager.ensure_records().await.unwrap();
+  assert!(target.join(".kmmgr.json").is_file());
+}
+
+#[tokio::test]
+async fn res_mods_link_outside_game_dir() {
+  let game = FakeGame::with_builds("link-outside", &["1000"]);
+  let elsewhere = FakeGame::with_builds("link-outside-target", &[]);
+  let target = elsewhere.root.join("res_mods");
+  std::fs::create_dir_all(&target).unwrap();
+  link_dir(&target, &game.res_mods());
+
+  let res = ModManager::try_from_game_dir(&game.root).await;
+  assert!(matches!(
+    res,
+    Err(Error::ResModsOutsideGameDir { res_mods_path })
+      if res_mods_path == target
+  ));
+  // 打开设置后允许这一层链接
+  let mod_manager =
+    ModManager::try_from_game_dir_with(&game.root, true)
+      .await
+      .unwrap();
+  assert_eq!(mod_manager.res_mods_path(), target);
+}
+
+// 链接的目标本身又是链接时，即使打开设置也不接受
+#[tokio::test]
+async fn res_mods_link_chain_is_refused() {
+  let game = FakeGame::with_builds("link-chain", &["1000"]);
+  let elsewhere = FakeGame::with_builds("link-chain-target", &[]);
+  let target = elsewhere.root.join("res_mods");
+  std::fs::create_dir_all(&target).unwrap();
+  let hop = elsewhere.root.join("hop");
+  link_dir(&target, &hop);
+  link_dir(&hop, &game.res_mods());
+
+  let res =
+    ModManager::try_from_game_dir_with(&game.root, true).await;
+  assert!(matches!(res, Err(Error::ResModsOutsideGameDir { .. })));
+}
+
+#[tokio::test]
+async fn install_and_uninstall_events() {
+  let game = FakeGame::new("events");
+  let res_mods = game.res_mods();
+  let archive = game
+    .archive("example.zip", &[("a.txt", "a"), ("b.txt", "b")])
+    .await;
+
+  let (sender, mut receiver) = mpsc::unbounded_channel();
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager.set_event_sink(sender);
+  mod_manager
+    .install_zip_mod(&archive, "example", "1.0")
+    .await
+    .unwrap();
+  assert!(mod_manager.uninstall_mod("example").await.unwrap());
+  drop(mod_manager);
+
+  let mut events = Vec::new();
+  while let Some(event) = receiver.recv().await {
+    events.push(event);
+  }
+  let id = "example".to_string();
+  let records = res_mods.join(".kmmgr.json");
+  assert_eq!(
+    events,
+    vec![
+      ModEvent::InstallStarted {
+        id: id.to_owned(),
+        version: "1.0".to_string(),
+      },
+      ModEvent::FileWritten {
+        id: id.to_owned(),
+        path: res_mods.join("a.txt"),
+      },
+      ModEvent::FileWritten {
+        id: id.to_owned(),
+        path: res_mods.join("b.txt"),
+      },
+      ModEvent::RecordsWritten {
+        path: records.to_owned(),
+      },
+      ModEvent::InstallFinished {
+        id: id.to_owned(),
+        files: 2,
+      },
+      ModEvent::RecordsWritten { path: records },
+      ModEvent::UninstallFinished { id },
+    ]
+  );
+}
+
+#[tokio::test]
+async fn records_archive_sha256() {
+  let game = FakeGame::new("sha256");
+  let archive = game.archive("example.zip", &[("a.txt", "a")]).await;
+  let expected = sha256_file(&archive).await.unwrap();
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&archive, "example", "1.0")
+    .await
+    .unwrap();
+  mod_manager.set_note("example", "keep").await.unwrap();
+
+  // 升级时换成新压缩包的，备注保留
+  let archive = game.archive("example.zip", &[("a.txt", "b")]).await;
+  let upgraded = sha256_file(&archive).await.unwrap();
+  assert_ne!(upgraded, expected);
+  mod_manager
+    .install_zip_mod(&archive, "example", "2.0")
+    .await
+    .unwrap();
+  let records = mod_manager.records().await.unwrap();
+  let record = &records.records["example"];
+  assert_eq!(record.archive_sha256(), Some(upgraded.as_str()));
+  assert_eq!(record.note(), "keep");
+}
+
+// 早先写入的记录没有sha256
+#[tokio::test]
+async fn old_records_load() {
+  let game = FakeGame::new("old-records");
+  std::fs::write(
+    game.res_mods().join(".kmmgr.json"),
+    r#"{
+      "noted": {
+        "metadata": { "note": "hi" },
+        "update_time": 0,
+        "version": "1.0",
+        "files": []
+      },
+      "bare": {
+        "metadata": null,
+        "update_time": 0,
+        "version": "1.0",
+        "files": []
+      }
+    }"#,
+  )
+  .unwrap();
+  let mut mod_manager = game.mod_manager().await;
+  let records = mod_manager.records().await.unwrap();
+  assert_eq!(records.records["noted"].note(), "hi");
+  assert_eq!(records.records["noted"].archive_sha256(), None);
+  assert_eq!(records.records["bare"].archive_sha256(), None);
+  // 没有构建号时不算不一致，下次写入时补上
+  assert_eq!(records.build, None);
+  assert_eq!(records.build_mismatch(1000), None);
+  mod_manager.set_note("bare", "new").await.unwrap();
+  let records = mod_manager.records().await.unwrap();
+  assert_eq!(records.build, Some(1000));
+  assert_eq!(records.records.len(), 2);
+}
+
+#[tokio::test]
+async fn records_remember_build() {
+  let game = FakeGame::new("records-build");
+  let mut mod_manager = game.mod_manager().await;
+  let records = mod_manager.records().await.unwrap();
+  assert_eq!(records.build, Some(mod_manager.build()));
+  assert_eq!(records.build_mismatch(mod_manager.build()), None);
+
+  // 旧构建的res_mods整个复制过来
+  std::fs::write(
+    game.res_mods().join(".kmmgr.json"),
+    r#"{ "$build": 900, "a": {
+      "metadata": null, "update_time": 0,
+      "version": "1.0", "files": [] } }"#,
+  )
+  .unwrap();
+  let records = mod_manager.records().await.unwrap();
+  assert_eq!(records.build_mismatch(1000), Some(900));
+  assert!(records.records.contains_key("a"));
+
+  mod_manager.stamp_build().await.unwrap();
+  let records = mod_manager.records().await.unwrap();
+  assert_eq!(records.build_mismatch(1000), None);
+  assert!(records.records.contains_key("a"));
+}
+
+// 预演和实际安装使用同一份条目列表和冲突检查
+#[tokio::test]
+async fn dry_run_matches_install() {
+  let game = FakeGame::new("dry-run");
+  std::fs::write(game.res_mods().join("game.txt"), "game").unwrap();
+  let archive = game
+    .archive("owner.zip", &[("shared.txt", "a"), ("own.txt", "o")])
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&archive, "owner", "1.0")
+    .await
+    .unwrap();
+
+  let archive = game
+    .archive(
+      "other.zip",
+      &[
+        ("shared.txt", "b"),
+        ("game.txt", "b"),
+        ("../escape/run.exe", "b"),
+      ],
+    )
+    .await;
+  let entries = list_zip_entries(&archive).await.unwrap();
+  let escaped = &entries[2];
+  assert_eq!(escaped.path, std::path::Path::new("escape/run.exe"));
+  assert!(escaped.escapes());
+  assert!(escaped.is_executable());
+  assert!(!entries[0].escapes());
+
+  let records = mod_manager.records().await.unwrap();
+  let conflicts =
+    find_conflicts(&game.res_mods(), &records, "other", &entries);
+  assert_eq!(
+    conflicts,
+    [
+      Conflict {
+        file: "shared.txt".into(),
+        owner: Some("owner".to_string()),
+      },
+      Conflict {
+        file: "game.txt".into(),
+        owner: None,
+      },
+    ]
+  );
+  let res =
+    mod_manager.install_zip_mod(&archive, "other", "1.0").await;
+  assert!(matches!(res, Err(Error::FileConflict { file })
+    if file == std::path::Path::new("shared.txt")));
+  assert!(!game.res_mods().join("escape").exists());
+
+  // 升级时自身的文件不算冲突
+  let entries = list_zip_entries(
+    &game.archive("owner-2.zip", &[("shared.txt", "c")]).await,
+  )
+  .await
+  .unwrap();
+  assert!(
+    find_conflicts(&game.res_mods(), &records, "owner", &entries)
+      .is_empty()
+  );
+}
+
+#[tokio::test]
+async fn health_check_and_adopt() {
+  let game = FakeGame::new("health");
+  let archive = game
+    .archive("a.zip", &[("a.txt", "a"), ("b.txt", "b")])
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&archive, "a", "1.0")
+    .await
+    .unwrap();
+  std::fs::remove_file(game.res_mods().join("b.txt")).unwrap();
+  std::fs::create_dir_all(game.res_mods().join("gui")).unwrap();
+  std::fs::write(game.res_mods().join("gui/manual.png"), "m")
+    .unwrap();
+
+  let report = mod_manager.health_check().await.unwrap();
+  assert_eq!(
+    report.mods[0].health,
+    ModHealth::Missing(vec!["b.txt".into()])
+  );
+  assert_eq!(
+    report.orphans,
+    [std::path::Path::new("gui/manual.png")]
+  );
+
+  mod_manager
+    .adopt_files(ADOPTED_ID, &report.orphans)
+    .await
+    .unwrap();
+  // 重复收编不会重复记录
+  mod_manager
+    .adopt_files(ADOPTED_ID, &report.orphans)
+    .await
+    .unwrap();
+  let report = mod_manager.health_check().await.unwrap();
+  assert!(report.orphans.is_empty());
+  let records = mod_manager.records().await.unwrap();
+  assert_eq!(records.records[ADOPTED_ID].files.len(), 1);
+  assert!(mod_manager.uninstall_mod(ADOPTED_ID).await.unwrap());
+  assert!(!game.res_mods().join("gui/manual.png").exists());
+}
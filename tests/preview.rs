@@ -0,0 +1,108 @@
+// 压缩包内容预览的树形结构和标记
+use std::{collections::HashSet, path::PathBuf};
+
+use korabli_mod_manager::{
+  data::preview::{ArchiveTree, Flag},
+  mod_manager::{ArchiveEntry, Conflict},
+};
+
+fn entry(name: &str, path: &str, size: u64) -> ArchiveEntry {
+  ArchiveEntry {
+    name: name.to_string(),
+    path: PathBuf::from(path),
+    dir: name.ends_with('/'),
+    size,
+  }
+}
+
+fn entries() -> Vec<ArchiveEntry> {
+  vec![
+    entry("readme.txt", "readme.txt", 1),
+    entry("gui/", "gui", 0),
+    entry("gui/flags/a.png", "gui/flags/a.png", 10),
+    entry("gui/b.png", "gui/b.png", 20),
+    entry("../tools/run.exe", "tools/run.exe", 5),
+    entry("banks/mod.bnk", "banks/mod.bnk", 100),
+  ]
+}
+
+#[test]
+fn tree_sums_sizes_and_lists_dirs_first() {
+  let tree = ArchiveTree::build(&entries(), &[]);
+  assert_eq!(tree.files, 5);
+  assert_eq!(tree.size, 136);
+  let names = tree
+    .nodes
+    .iter()
+    .map(|node| (node.name.as_str(), node.dir, node.size))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    names,
+    [
+      ("banks", true, 100),
+      ("gui", true, 30),
+      ("tools", true, 5),
+      ("readme.txt", false, 1),
+    ]
+  );
+  let gui = &tree.nodes[1];
+  assert_eq!(gui.children[0].path, PathBuf::from("gui/flags"));
+  assert_eq!(gui.children[1].name, "b.png");
+}
+
+#[test]
+fn suspicious_and_conflicting_entries_are_flagged() {
+  let conflicts = [Conflict {
+    file: PathBuf::from("gui/b.png"),
+    owner: Some("other".to_string()),
+  }];
+  let tree = ArchiveTree::build(&entries(), &conflicts);
+  assert_eq!(tree.conflicts, 1);
+  assert_eq!(tree.suspicious, 1);
+  let run = &tree.nodes[2].children[0];
+  assert_eq!(
+    run.flags,
+    [
+      Flag::Escapes("../tools/run.exe".to_string()),
+      Flag::Executable
+    ]
+  );
+  let gui = &tree.nodes[1];
+  assert!(gui.flagged);
+  assert!(!gui.children[0].flagged);
+  assert_eq!(
+    gui.children[1].flags,
+    [Flag::Conflict(Some("other".to_string()))]
+  );
+  assert!(!tree.nodes[0].flagged);
+  assert_eq!(
+    tree.flagged_dirs(),
+    HashSet::from([PathBuf::from("gui"), PathBuf::from("tools")])
+  );
+}
+
+#[test]
+fn visible_follows_expansion() {
+  let tree = ArchiveTree::build(&entries(), &[]);
+  let visible = |expanded: &[&str]| {
+    let expanded =
+      expanded.iter().map(PathBuf::from).collect::<HashSet<_>>();
+    tree
+      .visible(&expanded)
+      .into_iter()
+      .map(|(depth, node)| (depth, node.name.to_owned()))
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(visible(&[]).len(), 4);
+  // 上级折叠时下级即使展开也看不到
+  assert_eq!(visible(&["gui/flags"]).len(), 4);
+  assert_eq!(
+    visible(&["gui", "gui/flags"])[1..5],
+    [
+      (0, "gui".to_string()),
+      (1, "flags".to_string()),
+      (2, "a.png".to_string()),
+      (1, "b.png".to_string()),
+    ]
+  );
+}
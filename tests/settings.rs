@@ -0,0 +1,249 @@
+// 设置的保存格式和设置界面的编辑
+use std::{path::PathBuf, time::Duration};
+
+use korabli_mod_manager::{
+  data::{
+    settings::{Error, GameInstall, Settings},
+    settings_form::{SettingsEdit, SettingsForm, UrlError},
+  },
+  http::HttpOptions,
+};
+use url::Url;
+
+fn temp_path(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!(
+    "kmm-settings-{}-{}",
+    std::process::id(),
+    name
+  ));
+  let _ = std::fs::remove_dir_all(&dir);
+  dir.join("settings.toml")
+}
+
+#[tokio::test]
+async fn save_and_load() {
+  let path = temp_path("round-trip");
+  let settings = Settings {
+    game_dir: Some(PathBuf::from("/games/korabli")),
+    registry_urls: vec!["https://example.com/registry".to_string()],
+    download_retries: 5,
+    ..Settings::default()
+  };
+  // 目录不存在时一起创建
+  settings.save(&path).await.unwrap();
+  assert_eq!(Settings::load(&path).unwrap(), settings);
+  let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn missing_file_is_default() {
+  let path = temp_path("missing");
+  assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+}
+
+// 缺少的字段取默认值，写错的文件返回错误
+#[test]
+fn load_fills_missing_fields() {
+  let path = temp_path("partial");
+  std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+  std::fs::write(&path, "download_retries = 1").unwrap();
+  let settings = Settings::load(&path).unwrap();
+  assert_eq!(settings.download_retries, 1);
+  assert_eq!(settings.idle_timeout_secs, 60);
+
+  std::fs::write(&path, "download_retries = \"many\"").unwrap();
+  assert!(matches!(Settings::load(&path), Err(Error::TomlDe(_))));
+  let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn old_settings_have_no_games() {
+  let settings: Settings =
+    toml::from_str("game_dir = \"/games/korabli\"").unwrap();
+  assert!(settings.games.is_empty());
+  let saved = toml::to_string(&settings).unwrap();
+  assert!(!saved.contains("[[games]]"));
+}
+
+#[test]
+fn games_round_trip() {
+  let settings = Settings {
+    games: vec![
+      GameInstall::new("Live", PathBuf::from("/games/korabli")),
+      GameInstall::new("", PathBuf::from("/games/korabli_pt")),
+    ],
+    ..Settings::default()
+  };
+  assert_eq!(settings.games[1].name, "korabli_pt");
+  let loaded: Settings =
+    toml::from_str(&toml::to_string(&settings).unwrap()).unwrap();
+  assert_eq!(loaded, settings);
+  assert_eq!(
+    loaded
+      .game_install(&PathBuf::from("/games/korabli"))
+      .map(|game| game.name.as_str()),
+    Some("Live")
+  );
+}
+
+#[test]
+fn form_edits_games() {
+  let base = Settings {
+    game_dir: Some(PathBuf::from("/games/korabli")),
+    ..Settings::default()
+  };
+  let mut form = SettingsForm::from_settings(&base);
+  form.update(SettingsEdit::AddGame(PathBuf::from("/games/korabli")));
+  form.update(SettingsEdit::AddGame(PathBuf::from("/games/pt")));
+  form.update(SettingsEdit::AddGame(PathBuf::from("/games/old")));
+  form.update(SettingsEdit::GameName(0, " Live ".to_string()));
+  form.update(SettingsEdit::GameName(1, String::new()));
+  form.update(SettingsEdit::GamePath(1, " /games/pt2 ".to_string()));
+  form.update(SettingsEdit::GamePath(2, String::new()));
+  let settings = form.apply(&base).unwrap();
+  assert_eq!(
+    settings.games,
+    [
+      GameInstall::new("Live", PathBuf::from("/games/korabli")),
+      GameInstall::new("pt2", PathBuf::from("/games/pt2")),
+    ]
+  );
+  assert_eq!(settings.game_dir, base.game_dir);
+
+  form.update(SettingsEdit::RemoveGame(0));
+  form.update(SettingsEdit::RemoveGame(5));
+  assert_eq!(form.apply(&base).unwrap().games.len(), 1);
+}
+
+// 地址有误时不应用任何改动，表单中的编辑原样保留，
+// 改正后再应用
+#[test]
+fn invalid_url_keeps_edits() {
+  let base = Settings::default();
+  let mut form = SettingsForm::from_settings(&base);
+  for _ in 0..4 {
+    form.update(SettingsEdit::AddRegistryUrl);
+  }
+  let urls = [
+    "https://example.com/registry",
+    "not a url",
+    " ",
+    "ftp://example.com/registry",
+  ];
+  for (index, url) in urls.iter().enumerate() {
+    form.update(SettingsEdit::RegistryUrl(index, url.to_string()));
+  }
+  form.update(SettingsEdit::AutoUpdate(true));
+  form.update(SettingsEdit::DownloadRetries(7));
+  form.update(SettingsEdit::AddGame(PathBuf::from("/games/pt")));
+  let edited = form.to_owned();
+
+  let errors = form.apply(&base).unwrap_err();
+  assert_eq!(errors.keys().copied().collect::<Vec<_>>(), [1, 3]);
+  assert!(matches!(errors[&1], UrlError::Invalid(_)));
+  assert_eq!(
+    errors[&3],
+    UrlError::UnsupportedScheme("ftp".to_string())
+  );
+  assert_eq!(form, edited);
+
+  form.update(SettingsEdit::RemoveRegistryUrl(3));
+  form.update(SettingsEdit::RegistryUrl(
+    1,
+    "file:///srv/registry.json".to_string(),
+  ));
+  let settings = form.apply(&base).unwrap();
+  assert_eq!(
+    settings.registry_urls,
+    ["https://example.com/registry", "file:///srv/registry.json"]
+  );
+  assert!(settings.auto_update);
+  assert_eq!(settings.download_retries, 7);
+  assert_eq!(settings.games.len(), 1);
+}
+
+// 旧的配置文件默认直接删除
+#[test]
+fn safe_delete_is_opt_in() {
+  let settings: Settings =
+    toml::from_str("game_dir = \"/games/korabli\"").unwrap();
+  assert!(!settings.safe_delete);
+  assert_eq!(settings.trash_retention_days, 7);
+
+  let mut form = SettingsForm::from_settings(&settings);
+  form.update(SettingsEdit::SafeDelete(true));
+  form.update(SettingsEdit::TrashRetentionDays(0));
+  let applied = form.apply(&settings).unwrap();
+  assert!(applied.safe_delete);
+  assert_eq!(applied.trash_retention_days, 1);
+}
+
+// 旧的配置文件取默认的网络设置，空字符串视为没有设置
+#[test]
+fn http_options_from_settings() {
+  let settings: Settings =
+    toml::from_str("cache_archives = true").unwrap();
+  assert_eq!(settings.http_options(), HttpOptions::default());
+
+  let settings: Settings = toml::from_str(
+    "proxy = \"http://127.0.0.1:7890\"\nuser_agent = \"\"\nconnect_timeout_secs = 5",
+  )
+  .unwrap();
+  let options = settings.http_options();
+  assert_eq!(options.proxy.as_deref(), Some("http://127.0.0.1:7890"));
+  assert_eq!(options.user_agent, None);
+  assert_eq!(options.connect_timeout, Duration::from_secs(5));
+  assert!(!options.allow_insecure_redirects);
+
+  let base = Settings::default();
+  let mut form = SettingsForm::from_settings(&base);
+  form.update(SettingsEdit::AllowInsecureRedirects(true));
+  let settings = form.apply(&base).unwrap();
+  assert!(settings.http_options().allow_insecure_redirects);
+}
+
+#[test]
+fn download_options_from_settings() {
+  let settings = Settings::default();
+  assert_eq!(
+    settings.download_timeouts().idle,
+    Duration::from_secs(60)
+  );
+  assert_eq!(settings.retry_policy().max_retries, 3);
+
+  let base = Settings::default();
+  let mut form = SettingsForm::from_settings(&base);
+  form.update(SettingsEdit::IdleTimeout(0));
+  form.update(SettingsEdit::DownloadRetries(0));
+  form.update(SettingsEdit::ConnectTimeout(30));
+  let settings = form.apply(&base).unwrap();
+  // 超时至少一秒，重试可以关掉
+  assert_eq!(
+    settings.download_timeouts().idle,
+    Duration::from_secs(1)
+  );
+  assert_eq!(settings.retry_policy().max_retries, 0);
+  assert_eq!(
+    settings.http_options().connect_timeout,
+    Duration::from_secs(30)
+  );
+}
+
+// 别名按解析后的地址匹配，没有别名时显示主机名，没有主机时显示协议名
+#[test]
+fn registry_labels() {
+  let mut settings = Settings::default();
+  settings.registry_aliases.insert(
+    "HTTPS://Example.com/registry".to_string(),
+    "Official".to_string(),
+  );
+  settings
+    .registry_aliases
+    .insert("not a url".to_string(), "Broken".to_string());
+  let label =
+    |url: &str| settings.registry_label(&Url::parse(url).unwrap());
+  assert_eq!(label("https://example.com/registry"), "Official");
+  assert_eq!(label("https://example.com/other"), "example.com");
+  assert_eq!(label("http://127.0.0.1:8080/registry"), "127.0.0.1");
+  assert_eq!(label("data:application/json,{}"), "data");
+}
@@ -0,0 +1,135 @@
+// 分享链接：导出的registry经过data:链接加载后应当得到同样的模组
+use korabli_mod_manager::{
+  data::{
+    registry::{Registry, load_from_url},
+    share::{share_link, share_registry},
+  },
+  http::client,
+};
+use url::Url;
+
+fn registry(base: &str, mods: serde_json::Value) -> Registry {
+  serde_json::from_value::<Registry>(mods)
+    .unwrap()
+    .with_base_url(Url::parse(base).unwrap())
+}
+
+fn registries() -> Vec<Registry> {
+  vec![
+    registry(
+      "https://example.com/main/registry.json",
+      serde_json::json!({
+        "example": {
+          "id": "example",
+          "version": "2.0",
+          "url": "example-2.0.zip",
+          "sha256": "aa",
+          "size": 100,
+          "image_url": "example.png",
+          "name": "Example",
+          "versions": [
+            { "version": "1.0", "url": "old/example-1.0.zip", "sha256": "bb" },
+          ],
+        },
+        "current": {
+          "id": "current",
+          "version": "1.0",
+          "url": "https://cdn.example.com/current.zip",
+          "size": 50,
+          "image_url": "",
+          "name": "Current",
+        },
+        "unused": {
+          "id": "unused",
+          "version": "1.0",
+          "url": "unused.zip",
+          "image_url": "",
+          "name": "Unused",
+        },
+      }),
+    ),
+    registry(
+      "https://mirror.example.com/registry.json",
+      serde_json::json!({
+        "example": {
+          "id": "example",
+          "version": "1.0",
+          "url": "mirror-example.zip",
+          "image_url": "",
+          "name": "Mirror Example",
+        },
+        "mirrored": {
+          "id": "mirrored",
+          "version": "3.0",
+          "url": "mirrored.zip",
+          "image_url": "",
+          "name": "Mirrored",
+        },
+      }),
+    ),
+  ]
+}
+
+const INSTALLED: [(&str, &str, Option<&str>); 5] = [
+  ("example", "1.0", None),
+  ("current", "1.0", Some("dd")),
+  ("mirrored", "3.0", None),
+  ("gone", "1.0", None),
+  ("unused", "0.9", None),
+];
+
+#[test]
+fn pins_installed_versions() {
+  let (shared, skipped) = share_registry(&registries(), INSTALLED);
+  assert_eq!(skipped, vec!["gone", "unused"]);
+  // 靠前的registry优先，旧版本的地址按来源解析
+  let example = &shared.mods["example"];
+  assert_eq!(example.name, "Example");
+  assert_eq!(example.version, "1.0");
+  assert_eq!(
+    example.url,
+    "https://example.com/main/old/example-1.0.zip"
+  );
+  assert_eq!(example.sha256.as_deref(), Some("bb"));
+  assert_eq!(example.size, None);
+  assert_eq!(
+    example.image_url,
+    "https://example.com/main/example.png"
+  );
+  assert!(example.versions.is_empty());
+  // 带上实际安装的压缩包的sha256
+  let current = &shared.mods["current"];
+  assert_eq!(current.sha256.as_deref(), Some("dd"));
+  assert_eq!(current.url, "https://cdn.example.com/current.zip");
+  assert_eq!(current.size, Some(50));
+  assert_eq!(current.image_url, "");
+  assert_eq!(
+    shared.mods["mirrored"].url,
+    "https://mirror.example.com/mirrored.zip"
+  );
+}
+
+#[tokio::test]
+async fn link_round_trip() {
+  let (shared, _) = share_registry(&registries(), INSTALLED);
+  let link = share_link(&shared).unwrap();
+  assert!(link.starts_with("data:application/json;base64,"));
+  let url = Url::parse(&link).unwrap();
+  let loaded = load_from_url(&url, client()).await.unwrap();
+  assert_eq!(loaded.source, Some(url));
+  let mut ids = loaded.mods.keys().collect::<Vec<_>>();
+  ids.sort();
+  assert_eq!(ids, vec!["current", "example", "mirrored"]);
+  for (id, modr) in &shared.mods {
+    let loaded = &loaded.mods[id];
+    assert_eq!(loaded.version, modr.version);
+    assert_eq!(loaded.url, modr.url);
+    assert_eq!(loaded.sha256, modr.sha256);
+    assert_eq!(loaded.current_release(), modr.current_release());
+    // 地址都是绝对的，不依赖registry自身的位置
+    assert_eq!(
+      loaded.current_release().url,
+      loaded.url.parse::<Url>().unwrap().to_string()
+    );
+  }
+}
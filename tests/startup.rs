@@ -0,0 +1,106 @@
+// 启动时根据设置决定首批任务的输入：游戏目录和要加载的registry
+use std::path::PathBuf;
+
+use korabli_mod_manager::data::settings::{
+  Settings, resolve_registry_urls,
+};
+
+#[test]
+fn default_settings_load_default_registry() {
+  let settings = Settings::default();
+  let (urls, invalid) = settings.parsed_registry_urls();
+  assert_eq!(urls.len(), 1);
+  assert!(invalid.is_empty());
+  assert_eq!(
+    settings.game_dir_path(),
+    std::env::current_dir().unwrap()
+  );
+}
+
+#[test]
+fn invalid_registry_urls_are_reported_in_order() {
+  let settings = Settings {
+    game_dir: Some(PathBuf::from("/games/korabli")),
+    registry_urls: vec![
+      "https://a.example/registry".to_string(),
+      "not a url".to_string(),
+      "file:///tmp/registry.json".to_string(),
+      "".to_string(),
+    ],
+    ..Settings::default()
+  };
+  let (urls, invalid) = settings.parsed_registry_urls();
+  assert_eq!(
+    urls.iter().map(|url| url.as_str()).collect::<Vec<_>>(),
+    ["https://a.example/registry", "file:///tmp/registry.json"]
+  );
+  assert_eq!(invalid, ["not a url", ""]);
+  assert_eq!(
+    settings.game_dir_path(),
+    PathBuf::from("/games/korabli")
+  );
+}
+
+fn resolve(
+  settings: &[&str],
+  env: Option<&str>,
+  default: &str,
+) -> (Vec<String>, Vec<String>) {
+  let settings = settings
+    .iter()
+    .map(|url| url.to_string())
+    .collect::<Vec<_>>();
+  let (urls, invalid) =
+    resolve_registry_urls(&settings, env, default);
+  (urls.into_iter().map(String::from).collect(), invalid)
+}
+
+#[test]
+fn settings_take_precedence() {
+  let (urls, _) = resolve(
+    &["https://settings.example/"],
+    Some("https://env.example/"),
+    "https://default.example/",
+  );
+  assert_eq!(urls, ["https://settings.example/"]);
+}
+
+#[test]
+fn env_overrides_default() {
+  let (urls, invalid) = resolve(
+    &[],
+    Some(" https://a.example/ , ,https://b.example/"),
+    "https://default.example/",
+  );
+  assert_eq!(urls, ["https://a.example/", "https://b.example/"]);
+  assert!(invalid.is_empty());
+}
+
+#[test]
+fn default_is_last_resort() {
+  let default = "https://default.example/,https://mirror.example/";
+  for env in [None, Some(""), Some(" , ")] {
+    let (urls, _) = resolve(&[], env, default);
+    assert_eq!(
+      urls,
+      ["https://default.example/", "https://mirror.example/"]
+    );
+  }
+}
+
+// 来源中有地址就使用该来源，无效的地址只做提示，不会退回下一个来源
+#[test]
+fn invalid_entries_are_reported() {
+  let (urls, invalid) = resolve(
+    &[],
+    Some("not a url,https://env.example/"),
+    "https://default.example/",
+  );
+  assert_eq!(urls, ["https://env.example/"]);
+  assert_eq!(invalid, ["not a url"]);
+
+  let (urls, invalid) =
+    resolve(&[], None, "https://default.example/,broken");
+  assert_eq!(urls, ["https://default.example/"]);
+  assert_eq!(invalid, ["broken"]);
+}
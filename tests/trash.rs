@@ -0,0 +1,148 @@
+// 安全删除：卸载的文件移到回收站，可以恢复，过期后清理
+mod common;
+
+use std::{path::Path, time::Duration};
+
+use common::FakeGame;
+use korabli_mod_manager::{
+  mod_manager::{Error, ModManager},
+  trash::{self, META_FILE},
+};
+
+async fn install_example(game: &FakeGame) -> ModManager {
+  let archive = game
+    .archive("example.zip", &[("dir/", ""), ("dir/a.txt", "a")])
+    .await;
+  let mut mod_manager = game.mod_manager().await;
+  mod_manager
+    .install_zip_mod(&archive, "example", "1.0")
+    .await
+    .unwrap();
+  mod_manager
+}
+
+// 默认直接删除，不会创建回收站
+#[tokio::test]
+async fn deletes_directly_by_default() {
+  let game = FakeGame::new("trash-default");
+  let trash_dir = game.root.join("trash");
+  let mut mod_manager = install_example(&game).await;
+
+  assert!(mod_manager.uninstall_mod("example").await.unwrap());
+  assert_eq!(game.files(), [".kmmgr.json"]);
+  assert!(!trash_dir.exists());
+  assert!(trash::list(&trash_dir).await.is_empty());
+}
+
+#[tokio::test]
+async fn safe_delete_moves_and_restores() {
+  let game = FakeGame::new("trash-restore");
+  let trash_dir = game.root.join("trash");
+  let mut mod_manager = install_example(&game).await;
+  mod_manager.set_trash_dir(Some(trash_dir.to_owned()));
+
+  assert!(mod_manager.uninstall_mod("example").await.unwrap());
+  assert_eq!(game.files(), [".kmmgr.json"]);
+  assert!(mod_manager.record("example").await.unwrap().is_none());
+  let entries = trash::list(&trash_dir).await;
+  assert_eq!(entries.len(), 1);
+  let entry = &entries[0];
+  assert_eq!(entry.id, "example");
+  assert_eq!(entry.version, "1.0");
+  assert!(entry.size > 0);
+  // 保留在res_mods中的相对路径
+  assert_eq!(
+    std::fs::read_to_string(entry.path.join("dir/a.txt")).unwrap(),
+    "a"
+  );
+  assert!(entry.path.join(META_FILE).is_file());
+
+  let id = mod_manager.restore_trashed(&entry.path).await.unwrap();
+  assert_eq!(id, "example");
+  assert_eq!(game.files(), [".kmmgr.json", "dir/a.txt"]);
+  assert_eq!(game.read("dir/a.txt"), "a");
+  let record = mod_manager.record("example").await.unwrap().unwrap();
+  assert_eq!(record.version, "1.0");
+  assert!(!entry.path.exists());
+  assert!(trash::list(&trash_dir).await.is_empty());
+
+  // 恢复的记录照常可以卸载
+  mod_manager.set_trash_dir(None);
+  assert!(mod_manager.uninstall_mod("example").await.unwrap());
+  assert_eq!(game.files(), [".kmmgr.json"]);
+  assert!(trash::list(&trash_dir).await.is_empty());
+}
+
+// 文件已经存在或模组已经重新安装时不做改动
+#[tokio::test]
+async fn restore_refuses_conflicts() {
+  let game = FakeGame::new("trash-conflict");
+  let trash_dir = game.root.join("trash");
+  let mut mod_manager = install_example(&game).await;
+  mod_manager.set_trash_dir(Some(trash_dir.to_owned()));
+  mod_manager.uninstall_mod("example").await.unwrap();
+  let entry = trash::list(&trash_dir).await.remove(0);
+
+  std::fs::create_dir_all(game.res_mods().join("dir")).unwrap();
+  std::fs::write(game.res_mods().join("dir/a.txt"), "mine").unwrap();
+  let err =
+    mod_manager.restore_trashed(&entry.path).await.unwrap_err();
+  assert!(
+    matches!(&err, Error::FileConflict { file } if file == Path::new("dir/a.txt")),
+    "{}",
+    err
+  );
+  assert_eq!(game.read("dir/a.txt"), "mine");
+  assert!(mod_manager.record("example").await.unwrap().is_none());
+  assert!(entry.path.is_dir());
+
+  std::fs::remove_file(game.res_mods().join("dir/a.txt")).unwrap();
+  let archive = game.archive("new.zip", &[("new.txt", "n")]).await;
+  mod_manager
+    .install_zip_mod(&archive, "example", "2.0")
+    .await
+    .unwrap();
+  let err =
+    mod_manager.restore_trashed(&entry.path).await.unwrap_err();
+  assert!(matches!(err, Error::RecordExists { .. }), "{}", err);
+  let record = mod_manager.record("example").await.unwrap().unwrap();
+  assert_eq!(record.version, "2.0");
+  assert!(entry.path.is_dir());
+}
+
+#[tokio::test]
+async fn purge_and_empty() {
+  let game = FakeGame::new("trash-purge");
+  let trash_dir = game.root.join("trash");
+  let mut mod_manager = install_example(&game).await;
+  mod_manager.set_trash_dir(Some(trash_dir.to_owned()));
+  mod_manager.uninstall_mod("example").await.unwrap();
+  let entry = trash::list(&trash_dir).await.remove(0);
+  let day = Duration::from_secs(24 * 60 * 60);
+
+  // 还没过期的保留
+  let count =
+    trash::purge_older_than(&trash_dir, entry.trashed_at, day)
+      .await
+      .unwrap();
+  assert_eq!(count, 0);
+  assert!(entry.path.is_dir());
+  let later = entry.trashed_at + 2 * day.as_secs();
+  let count = trash::purge_older_than(&trash_dir, later, day)
+    .await
+    .unwrap();
+  assert_eq!(count, 1);
+  assert!(trash::list(&trash_dir).await.is_empty());
+
+  let archive = game.archive("again.zip", &[("b.txt", "bb")]).await;
+  mod_manager
+    .install_zip_mod(&archive, "again", "1.0")
+    .await
+    .unwrap();
+  mod_manager.uninstall_mod("again").await.unwrap();
+  let size = trash::list(&trash_dir).await[0].size;
+  assert_eq!(trash::empty(&trash_dir).await.unwrap(), size);
+  assert!(!trash_dir.exists());
+  // 回收站不存在时什么都不做
+  assert_eq!(trash::empty(&trash_dir).await.unwrap(), 0);
+}